@@ -11,13 +11,101 @@
 //!   - Any additional types required to define either the query or the response.
 //!
 //! The supported endpoints are:
+//!  - `GET` `api/health`. Returns the health of the LEAP, including whether the manifest being
+//!    served may be outdated because the last manifest fetch failed, and whether the downloads are
+//!    paused.
 //!  - `POST` `api/manifest/fetch`. Triggers an immediate fetch of the manifest, causing the LEAP to
 //!    update its cached content.
+//!  - `POST` `api/downloader/pause`. Stops starting new downloads until the downloader is resumed.
+//!    Downloads already in progress are completed.
+//!  - `POST` `api/downloader/resume`. Resumes the downloads paused with `api/downloader/pause`.
 //!  - `GET` `api/manifest/latest`. Returns the latest manifest that is in use by the LEAP.
+//!    Responds with `204 No Content` while there is no manifest.
+//!  - `GET` `api/manifest/export`. Returns the latest manifest with the URIs of its videos pointing
+//!    at the `GET` `api/content/{id}` endpoint of the LEAP. Other LEAPs can mirror this one by
+//!    using the URL of this endpoint as their `remote_server`. Responds with `404 Not Found` while
+//!    there is no manifest.
 //!  - `GET` `api/content/meta`. Returns a list of the content metadata in the local server (LEAP).
-//!  - `GET` `api/content/meta/{id}`. Returns the metadata of the requested id.
+//!    The list is paginated with the `limit` and `offset` query parameters. Without a `limit`, the
+//!    first `default_page_size` videos configured in the server are returned, and larger limits
+//!    are clamped to its `max_page_size`.
+//!  - `GET` `api/content/meta/{id}`. Returns the metadata of the requested id. Responds with
+//!    `400 Bad Request` if the id is not a valid UUID.
+//!  - `GET` `api/content/summary`. Returns the number of videos in each download status, the
+//!    disk space used by the content, and the time it took to cache the current manifest.
+//!  - `GET` `api/content/sections`. Returns the download completion of each section.
+//!  - `GET` `api/content/sections/{name}/status`. Returns the videos of the first section with the
+//!    given name, with their download status, and the first of them that can be played. Responds
+//!    with `404 Not Found` if there is no such section.
+//!  - `GET` `api/content/{id}/related`. Returns the other videos of the section of the requested
+//!    video, ordered as in the manifest.
 //!  - `GET` `api/content/{id}`. Obtains the requested content from the server. The path indicates
-//!    the resource ID.
+//!    the resource ID. Responds with `409 Conflict` if the content is not downloaded yet, and with
+//!    `429 Too Many Requests` if the client already has too many content requests in flight. If
+//!    the server is configured to serve partial content, content being downloaded is streamed as
+//!    it is downloaded instead.
+//!  - `GET` `api/content/{id}/available`. Returns whether `GET` `api/content/{id}` can serve the
+//!    requested content, along with its download status, without reading the content.
+//!  - `GET` `api/content/events`. Streams changes in the download status of the content as
+//!    server-sent events. A `resync` event indicates that some events were lost, and that the
+//!    client should fetch the content metadata again.
+//!  - `GET` `api/content/{id}/progress/stream`. Streams the download status of a single video as
+//!    server-sent events, starting with its current status. The stream ends after the video is
+//!    downloaded or its download fails.
+//!  - `GET` `api/content/{id}/events`. Returns the download history of a video: when it was
+//!    queued, when its downloads started, failed and completed, and the progress milestones in
+//!    between. Only the latest events are kept. Responds with `404 Not Found` if the video is
+//!    unknown.
+//!  - `GET` `api/content/export`. Exports the metadata of all the content in the local server as
+//!    newline-delimited JSON.
+//!  - `POST` `api/content/local/redownload`. Deletes the local copy of the content with the ID given
+//!    in the query and downloads it again.
+//!  - `PUT` `api/content/local/pin`. Pins or unpins the content with the ID given in the query.
+//!    Pinned content is listed first in its section, and is kept even if it is removed from the
+//!    manifest.
+//!
+//! The admin endpoints require the admin API key of the server as a bearer token:
+//!  - `GET` `api/admin/config`. Returns the configuration loaded by the server, with its secrets
+//!    redacted.
+//!  - `GET` `api/admin/orphans`. Lists the video files that don't belong to any video, such as
+//!    leftovers of interrupted downloads.
+//!  - `DELETE` `api/admin/orphans`. Deletes the video files listed by `GET` `api/admin/orphans`.
+//!    Files of videos being downloaded are never deleted.
+//!  - `POST` `api/admin/reconcile`. Reconciles the database with the current manifest, creating the
+//!    missing videos and deleting the ones no longer in the manifest, as done when a new manifest
+//!    is adopted. Responds with `404 Not Found` while there is no manifest.
+//!  - `POST` `api/admin/import`. Copies the files of the directory given in the query into the
+//!    content of the LEAP, as the content of the videos of the current manifest that are not
+//!    downloaded yet and whose checksum they match. Files matching no video are reported and left
+//!    alone. Responds with `404 Not Found` while there is no manifest.
+//!
+//! The `api/content`, `api/admin/orphans`, `api/admin/reconcile` and `api/admin/import` endpoints
+//! accept a `channel` query parameter to select one of the additional channels configured in the
+//! LEAP, each with its own manifest and content. The default channel is used if missing, and
+//! unknown channels respond with `404 Not Found`. The manifest, health and downloader endpoints
+//! always refer to the default channel.
+//!
+//! Requests whose query parameters or path can't be parsed respond with `400 Bad Request` and an
+//! [`types::ApiError`] JSON body describing the problem.
+//!
+//! Both `leap-server` and `leap-site` use these namespaces, so renaming them breaks both:
+//!
+//! ```
+//! use leap_api::api::content::{local, meta};
+//!
+//! let list = meta::get::Query {
+//!     limit: Some(10),
+//!     ..Default::default()
+//! };
+//! let single = meta::id::get::Query::default();
+//! let redownload = local::redownload::post::Query {
+//!     id: "id".to_string(),
+//!     channel: None,
+//! };
+//! let _: Option<meta::get::Response> = None;
+//! let _: Option<meta::id::get::Response> = None;
+//! # let _ = (list, single, redownload);
+//! ```
 
 pub mod types;
 
@@ -31,27 +119,420 @@ pub mod api {
         }
     }
 
+    pub mod health {
+        pub mod get {
+            pub use crate::types::HealthStatus;
+
+            /// The response to the `GET` `api/health` request
+            pub type Response = HealthStatus;
+        }
+    }
+
+    pub mod logfile {
+        pub mod get {
+            /// Query parameters of the `GET` `api/logfile` request. The response has the matching
+            /// lines of the logfile, each a JSON log entry. The whole logfile is returned if no
+            /// parameter is set.
+            #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+            pub struct Query {
+                /// Maximum number of lines returned, the last ones of the logfile.
+                pub tail: Option<usize>,
+
+                /// Minimum level of the returned entries, either by name (`trace`, `debug`,
+                /// `info`, `warn`, `error` or `fatal`) or by its numeric value (`10` to `60`).
+                pub level: Option<String>,
+
+                /// Text that the returned lines contain.
+                pub contains: Option<String>,
+            }
+        }
+    }
+
     pub mod content {
         pub mod meta {
             pub mod get {
-                pub use crate::types::{GroupedSection, LocalVideoMeta, Progress, VideoStatus};
+                pub use crate::types::{
+                    FailureKind, GroupedSection, LocalVideoMeta, Progress, VideoStatus,
+                };
+
+                /// Query parameters of the `GET` `api/content/meta` request. Pages are taken from
+                /// the list of videos of all sections, in the order displayed.
+                #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Query {
+                    /// Maximum number of videos returned. The server applies a default limit if
+                    /// missing, and clamps larger limits to its maximum page size. Use `total` in
+                    /// the response to fetch the following pages.
+                    pub limit: Option<usize>,
+
+                    /// Number of videos skipped from the start of the list.
+                    pub offset: Option<usize>,
+
+                    /// Channel whose videos are listed. The default channel is used if missing.
+                    pub channel: Option<String>,
+                }
 
                 /// The response to the `GET` `api/content/meta` request
                 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
                 pub struct Response {
+                    /// Videos in the requested page, grouped by section. When paginating, sections
+                    /// without videos in the page are omitted.
                     pub videos: Vec<GroupedSection>,
+
+                    /// Total number of videos, regardless of the pagination.
+                    #[serde(default)]
+                    pub total: usize,
+
+                    /// The limit applied to the request, after the server defaults and clamping.
+                    #[serde(default)]
+                    pub limit: Option<usize>,
+
+                    /// The `offset` of the request.
+                    #[serde(default)]
+                    pub offset: usize,
                 }
             }
 
             pub mod id {
                 pub mod get {
-                    pub use crate::types::{LocalVideoMeta, Progress, VideoStatus};
+                    pub use crate::types::{FailureKind, LocalVideoMeta, Progress, VideoStatus};
+
+                    /// Query parameters of the `GET` `api/content/meta/{id}` request.
+                    #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Query {
+                        /// Channel of the video. The default channel is used if missing.
+                        pub channel: Option<String>,
+                    }
 
                     /// The response to the `GET` `api/content/meta/{id}` request
                     #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
                     pub struct Response {
                         pub meta: Option<LocalVideoMeta>,
                     }
+
+                    /// The JSON body of the `400 Bad Request` response to the
+                    /// `GET` `api/content/meta/{id}` request, returned when the id is not a valid
+                    /// UUID.
+                    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct InvalidId {
+                        /// The requested id
+                        pub id: String,
+                        /// Why the id is not valid
+                        pub message: String,
+                    }
+                }
+            }
+        }
+
+        pub mod id {
+            pub mod get {
+                pub use crate::types::{FailureKind, Progress, VideoStatus};
+
+                /// Query parameters of the `GET` `api/content/{id}` request.
+                #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Query {
+                    /// Channel of the content. The default channel is used if missing.
+                    pub channel: Option<String>,
+                }
+
+                /// The JSON body of the `409 Conflict` response to the `GET` `api/content/{id}`
+                /// request, returned when the content is known but not downloaded yet.
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Unavailable {
+                    pub status: VideoStatus,
+                }
+            }
+
+            pub mod view {
+                pub mod post {
+                    pub use crate::types::ChannelQuery;
+
+                    /// Query parameters of the `POST` `api/content/{id}/view` request
+                    pub type Query = ChannelQuery;
+
+                    /// The response to the `POST` `api/content/{id}/view` request
+                    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Response {
+                        /// Number of views of the video, including this one
+                        pub view_count: u64,
+                    }
+                }
+            }
+
+            pub mod available {
+                pub mod get {
+                    pub use crate::types::{FailureKind, Progress, VideoStatus};
+
+                    /// Query parameters of the `GET` `api/content/{id}/available` request.
+                    #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Query {
+                        /// Channel of the content. The default channel is used if missing.
+                        pub channel: Option<String>,
+                    }
+
+                    /// The response to the `GET` `api/content/{id}/available` request
+                    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Response {
+                        /// Whether the content can be served by `GET` `api/content/{id}`
+                        pub available: bool,
+                        /// The download status of the content
+                        pub status: VideoStatus,
+                    }
+                }
+            }
+
+            pub mod related {
+                pub mod get {
+                    pub use crate::types::{FailureKind, LocalVideoMeta, Progress, VideoStatus};
+
+                    /// Query parameters of the `GET` `api/content/{id}/related` request.
+                    #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Query {
+                        /// Channel of the video. The default channel is used if missing.
+                        pub channel: Option<String>,
+                    }
+
+                    /// The response to the `GET` `api/content/{id}/related` request
+                    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Response {
+                        /// The other videos of the section containing the video, ordered as in
+                        /// the manifest. Empty if the video isn't in any section.
+                        pub videos: Vec<LocalVideoMeta>,
+                    }
+                }
+            }
+
+            pub mod events {
+                pub mod get {
+                    pub use crate::types::{VideoEvent, VideoEventKind};
+
+                    /// Query parameters of the `GET` `api/content/{id}/events` request.
+                    #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Query {
+                        /// Channel of the video. The default channel is used if missing.
+                        pub channel: Option<String>,
+                    }
+
+                    /// The response to the `GET` `api/content/{id}/events` request
+                    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Response {
+                        /// The download history of the video, oldest event first. Only the latest
+                        /// events of each video are kept.
+                        pub events: Vec<VideoEvent>,
+                    }
+                }
+            }
+
+            pub mod progress {
+                pub mod stream {
+                    pub mod get {
+                        pub use crate::types::{
+                            ChannelQuery, ContentEvent, FailureKind, Progress, VideoStatus,
+                        };
+
+                        /// Query parameters of the `GET` `api/content/{id}/progress/stream`
+                        /// request
+                        pub type Query = ChannelQuery;
+
+                        /// The data of the events streamed by the `GET`
+                        /// `api/content/{id}/progress/stream` request
+                        pub type Event = ContentEvent;
+                    }
+                }
+            }
+        }
+
+        pub mod events {
+            pub mod get {
+                pub use crate::types::{
+                    ChannelQuery, ContentEvent, FailureKind, Progress, VideoStatus,
+                };
+
+                /// Query parameters of the `GET` `api/content/events` request
+                pub type Query = ChannelQuery;
+
+                /// The data of the events streamed by the `GET` `api/content/events` request
+                pub type Event = ContentEvent;
+
+                /// Name of the event sent when the client missed some events and has to fetch the
+                /// content metadata again
+                pub const RESYNC_EVENT: &str = "resync";
+            }
+        }
+
+        pub mod export {
+            pub mod get {
+                pub use crate::types::{
+                    ChannelQuery, FailureKind, LocalVideoMeta, Progress, VideoStatus,
+                };
+
+                /// Query parameters of the `GET` `api/content/export` request
+                pub type Query = ChannelQuery;
+
+                /// Each of the lines of the `GET` `api/content/export` response
+                pub type Line = LocalVideoMeta;
+            }
+        }
+
+        pub mod local {
+            pub mod redownload {
+                pub mod post {
+                    /// Query parameters of the `POST` `api/content/local/redownload` request
+                    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Query {
+                        /// ID of the content to download again
+                        pub id: String,
+
+                        /// Channel of the content. The default channel is used if missing.
+                        pub channel: Option<String>,
+                    }
+                }
+            }
+
+            pub mod pin {
+                pub mod put {
+                    /// Query parameters of the `PUT` `api/content/local/pin` request
+                    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                    pub struct Query {
+                        /// ID of the content to pin or unpin
+                        pub id: String,
+                        /// Whether the content is pinned. Pinned content is listed first, and is
+                        /// kept even if it is removed from the manifest.
+                        pub pinned: bool,
+                        /// Channel of the content. The default channel is used if missing.
+                        pub channel: Option<String>,
+                    }
+                }
+            }
+        }
+
+        pub mod summary {
+            pub mod get {
+                pub use crate::types::{ChannelQuery, ContentSummary};
+
+                /// Query parameters of the `GET` `api/content/summary` request
+                pub type Query = ChannelQuery;
+
+                /// The response to the `GET` `api/content/summary` request
+                pub type Response = ContentSummary;
+            }
+        }
+
+        pub mod sections {
+            pub mod get {
+                pub use crate::types::{ChannelQuery, SectionStatus};
+
+                /// Query parameters of the `GET` `api/content/sections` request
+                pub type Query = ChannelQuery;
+
+                /// The response to the `GET` `api/content/sections` request
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Response {
+                    pub sections: Vec<SectionStatus>,
+                }
+            }
+
+            pub mod name {
+                pub mod status {
+                    pub mod get {
+                        pub use crate::types::{
+                            ChannelQuery, FailureKind, LocalVideoMeta, Progress, VideoStatus,
+                        };
+
+                        /// Query parameters of the `GET` `api/content/sections/{name}/status`
+                        /// request
+                        pub type Query = ChannelQuery;
+
+                        /// The response to the `GET` `api/content/sections/{name}/status` request
+                        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                        pub struct Response {
+                            /// The videos of the section, ordered as in the manifest
+                            pub videos: Vec<LocalVideoMeta>,
+                            /// ID of the first downloaded video of the section, if any
+                            pub first_playable_id: Option<String>,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub mod admin {
+        pub mod orphans {
+            pub mod get {
+                pub use crate::types::{ChannelQuery, OrphanFile};
+
+                /// Query parameters of the `GET` `api/admin/orphans` request
+                pub type Query = ChannelQuery;
+
+                /// The response to the `GET` `api/admin/orphans` request
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Response {
+                    pub files: Vec<OrphanFile>,
+                }
+            }
+
+            pub mod delete {
+                pub use crate::types::{ChannelQuery, OrphanFile};
+
+                /// Query parameters of the `DELETE` `api/admin/orphans` request
+                pub type Query = ChannelQuery;
+
+                /// The response to the `DELETE` `api/admin/orphans` request
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Response {
+                    /// The files that were deleted
+                    pub removed: Vec<OrphanFile>,
+                }
+            }
+        }
+
+        pub mod import {
+            pub mod post {
+                /// Query parameters of the `POST` `api/admin/import` request
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Query {
+                    /// Directory of the LEAP whose files are imported, such as a mounted USB drive
+                    pub path: String,
+
+                    /// Channel whose content is imported. The default channel is used if missing.
+                    pub channel: Option<String>,
+                }
+
+                /// A file imported as the content of a video
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct ImportedFile {
+                    /// Path of the imported file
+                    pub path: String,
+                    /// ID of the video whose content it is
+                    pub id: String,
+                }
+
+                /// The response to the `POST` `api/admin/import` request
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Response {
+                    /// Files imported, once per video whose content they are
+                    pub imported: Vec<ImportedFile>,
+                    /// Files that don't match any video missing its content, which were not
+                    /// imported
+                    pub unmatched: Vec<String>,
+                }
+            }
+        }
+
+        pub mod reconcile {
+            pub mod post {
+                pub use crate::types::ChannelQuery;
+
+                /// Query parameters of the `POST` `api/admin/reconcile` request
+                pub type Query = ChannelQuery;
+
+                /// The response to the `POST` `api/admin/reconcile` request
+                #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+                pub struct Response {
+                    /// Number of videos of the manifest that were missing from the database
+                    pub inserted: usize,
+                    /// Number of videos no longer in the manifest that were deleted
+                    pub deleted: usize,
                 }
             }
         }
@@ -54,8 +54,62 @@ where
 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
 pub struct Progress(pub f64);
 
+/// Cause of a failed video download
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy)]
+pub enum FailureKind {
+    /// The cause is not known, e.g. for failures reported by older servers
+    #[default]
+    Unknown,
+    /// The content could not be fetched from the remote server
+    Network,
+    /// The fetched content does not match the checksum of the manifest
+    Checksum,
+    /// The content could not be stored in the local server
+    Storage,
+    /// The download was interrupted, e.g. by a restart of the local server
+    Interrupted,
+}
+
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FailureKind::Unknown => "unknown error",
+            FailureKind::Network => "network error",
+            FailureKind::Checksum => "checksum mismatch",
+            FailureKind::Storage => "storage error",
+            FailureKind::Interrupted => "interrupted",
+        })
+    }
+}
+
+/// Kind of an event in the download history of a video
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy)]
+pub enum VideoEventKind {
+    /// The video is waiting to be downloaded, either for the first time or after a reset
+    Queued,
+    /// A download of the video started
+    Started,
+    /// The download went past a quarter of the video, given in the message as a percentage
+    Progress,
+    /// The download failed, with the error in the message
+    Failed,
+    /// The video was downloaded
+    Completed,
+}
+
+/// An event in the download history of a video
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct VideoEvent {
+    /// When the event happened, as milliseconds since the Unix epoch
+    pub at_millis: u64,
+    pub kind: VideoEventKind,
+    /// Details of the event, empty if there are none
+    pub message: String,
+}
+
 /// The status of the video download
 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+#[serde(from = "VideoStatusRepr")]
 pub enum VideoStatus {
     /// The video download has not started
     Pending,
@@ -64,7 +118,61 @@ pub enum VideoStatus {
     /// The video download is completed
     Downloaded,
     /// The video download failed
-    Failed(String),
+    Failed {
+        /// Error message of the last failure
+        message: String,
+        /// Cause of the last failure
+        kind: FailureKind,
+        /// Number of failed download attempts
+        attempts: u32,
+    },
+}
+
+/// Accepted representations of [`VideoStatus`]. Older servers reported failures only with their
+/// error message.
+#[derive(serde::Deserialize)]
+enum VideoStatusRepr {
+    Pending,
+    Downloading(Progress),
+    Downloaded,
+    Failed(FailureRepr),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum FailureRepr {
+    Message(String),
+    Details {
+        message: String,
+        #[serde(default)]
+        kind: FailureKind,
+        #[serde(default)]
+        attempts: u32,
+    },
+}
+
+impl From<VideoStatusRepr> for VideoStatus {
+    fn from(value: VideoStatusRepr) -> Self {
+        match value {
+            VideoStatusRepr::Pending => VideoStatus::Pending,
+            VideoStatusRepr::Downloading(progress) => VideoStatus::Downloading(progress),
+            VideoStatusRepr::Downloaded => VideoStatus::Downloaded,
+            VideoStatusRepr::Failed(FailureRepr::Message(message)) => VideoStatus::Failed {
+                message,
+                kind: FailureKind::default(),
+                attempts: 0,
+            },
+            VideoStatusRepr::Failed(FailureRepr::Details {
+                message,
+                kind,
+                attempts,
+            }) => VideoStatus::Failed {
+                message,
+                kind,
+                attempts,
+            },
+        }
+    }
 }
 
 /// Metadata of a single video of the local server.
@@ -74,12 +182,27 @@ pub struct LocalVideoMeta {
     pub id: String,
     /// Human-readable name of the video
     pub name: String,
-    /// Size of the video in bytes
-    pub size: usize,
+    /// Size of the video in bytes, as declared by the manifest
+    #[serde(alias = "size")]
+    pub declared_size: usize,
+    /// Size in bytes of the downloaded file on disk. Only known for downloaded videos, and may
+    /// differ from `declared_size` if the download was truncated or the manifest is wrong.
+    #[serde(default)]
+    pub actual_size: Option<usize>,
     /// Download status
     pub status: VideoStatus,
     /// Total views of the video
     pub view_count: u64,
+    /// Duration of the video in whole seconds, if known
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+    /// Resolution of the video as `<width>x<height>`, if known
+    #[serde(default)]
+    pub resolution: Option<String>,
+    /// Whether the video was pinned, so that it is listed first and kept when removed from the
+    /// manifest
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 /// Grouped section of video content
@@ -88,10 +211,119 @@ pub struct GroupedSection {
     /// Name of the section
     pub name: String,
 
+    /// Optional description of the section
+    #[serde(default)]
+    pub description: Option<String>,
+
     /// Content within the section. Ordered as displayed
     pub content: Vec<LocalVideoMeta>,
 }
 
+/// Summary of the download status of all the content in the local server.
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy)]
+pub struct ContentSummary {
+    /// Total number of videos
+    pub total: u64,
+    /// Number of videos available locally
+    pub downloaded: u64,
+    /// Number of videos being downloaded
+    pub downloading: u64,
+    /// Number of videos whose last download attempt failed
+    pub failed: u64,
+    /// Number of videos whose download has not started
+    pub pending: u64,
+    /// True if there is content and all of it is available locally
+    pub fully_cached: bool,
+    /// Bytes used on disk by the content, including partial downloads and files that are no
+    /// longer in use. Unknown if the content directory can't be read.
+    #[serde(default)]
+    pub disk_bytes_used: Option<u64>,
+    /// Seconds it took to cache all the content of the current manifest after it was published.
+    /// Unknown until all the content is cached.
+    #[serde(default)]
+    pub time_to_cache_secs: Option<u64>,
+}
+
+/// Health of the local server.
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone)]
+pub struct HealthStatus {
+    /// Name of the manifest being served, if any
+    pub manifest: Option<String>,
+    /// True if the last manifest fetch failed, so the manifest being served may be outdated. The
+    /// local content keeps being served meanwhile.
+    pub stale_manifest: bool,
+    /// Error of the last manifest fetch, if it failed
+    pub upstream_error: Option<String>,
+    /// Version of the manifest being served, if the server doesn't support it. Such manifests are
+    /// served on a best-effort basis.
+    #[serde(default)]
+    pub unsupported_manifest_version: Option<String>,
+    /// True if an operator paused the downloads of new content
+    #[serde(default)]
+    pub downloads_paused: bool,
+    /// True if the downloads are paused because the storage is running out of free space. The
+    /// content already downloaded keeps being served.
+    #[serde(default)]
+    pub low_disk_space: bool,
+    /// Why the downloads are paused due to the conditions of the device, such as its
+    /// temperature, if they are
+    #[serde(default)]
+    pub throttled: Option<String>,
+    /// Number of videos being streamed. The downloads may be limited while videos are streamed.
+    #[serde(default)]
+    pub active_streams: usize,
+    /// Why the newest manifest of the upstream was not adopted, such as requiring a newer version
+    /// of the server, if it wasn't. The current manifest keeps being served meanwhile.
+    #[serde(default)]
+    pub rejected_manifest: Option<String>,
+}
+
+/// The JSON body of the `400 Bad Request` responses to requests whose query parameters or path
+/// can't be parsed.
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone)]
+pub struct ApiError {
+    /// What was wrong with the request
+    pub message: String,
+}
+
+/// A video file in the download directories of the local server that doesn't belong to any video.
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone)]
+pub struct OrphanFile {
+    /// Path of the file in the local server
+    pub path: String,
+    /// Size of the file in bytes
+    pub size: u64,
+}
+
+/// Download completion of a section of video content
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone)]
+pub struct SectionStatus {
+    /// Name of the section
+    pub name: String,
+    /// Number of videos of the section available locally
+    pub downloaded: u64,
+    /// Total number of videos in the section
+    pub total: u64,
+    /// True if all the videos of the section are available locally
+    pub ready: bool,
+}
+
+/// Query parameters of the endpoints whose only parameter is the channel
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone)]
+pub struct ChannelQuery {
+    /// Channel of the request. The default channel is used if missing.
+    pub channel: Option<String>,
+}
+
+/// Change in the download status of a video
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct ContentEvent {
+    /// Unique identifier of the video
+    pub id: String,
+    /// New download status of the video
+    pub status: VideoStatus,
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
 pub struct BuildInfo {
     pub name: String,
@@ -104,6 +336,19 @@ pub struct BuildInfo {
     pub profile: String,
     pub rustc_version: String,
     pub features: String,
+    /// Major versions of the manifest supported by the server
+    #[serde(default)]
+    pub supported_manifest_versions: Option<VersionRange>,
+    /// Name telling the server apart from the others of a fleet
+    #[serde(default)]
+    pub instance_name: Option<String>,
+}
+
+/// Inclusive range of major versions
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy)]
+pub struct VersionRange {
+    pub min_major: u32,
+    pub max_major: u32,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -0,0 +1,2 @@
+ALTER TABLE videos DROP COLUMN resolution;
+ALTER TABLE videos DROP COLUMN duration_secs;
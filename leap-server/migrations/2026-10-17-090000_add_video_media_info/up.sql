@@ -0,0 +1,2 @@
+ALTER TABLE videos ADD COLUMN duration_secs BIG INT;
+ALTER TABLE videos ADD COLUMN resolution VARCHAR;
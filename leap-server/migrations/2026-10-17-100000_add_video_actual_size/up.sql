@@ -0,0 +1 @@
+ALTER TABLE videos ADD COLUMN actual_size BIG INT;
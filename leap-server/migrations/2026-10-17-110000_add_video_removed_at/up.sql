@@ -0,0 +1 @@
+ALTER TABLE videos ADD COLUMN removed_at BIG INT;
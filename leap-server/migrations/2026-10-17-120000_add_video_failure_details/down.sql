@@ -0,0 +1,2 @@
+ALTER TABLE videos DROP COLUMN failure_kind;
+ALTER TABLE videos DROP COLUMN failed_attempts;
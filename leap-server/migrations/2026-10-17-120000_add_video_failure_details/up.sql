@@ -0,0 +1,2 @@
+ALTER TABLE videos ADD COLUMN failure_kind BIG INT NOT NULL DEFAULT 0;
+ALTER TABLE videos ADD COLUMN failed_attempts BIG INT NOT NULL DEFAULT 0;
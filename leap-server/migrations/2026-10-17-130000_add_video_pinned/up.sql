@@ -0,0 +1 @@
+ALTER TABLE videos ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE videos ADD COLUMN retry_at BIG INT;
@@ -0,0 +1,12 @@
+-- Append-only history of the downloads of each video. Only the latest events of each video are
+-- kept, see `MAX_EVENTS_PER_VIDEO`.
+CREATE TABLE video_events (
+    seq INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    video_id VARCHAR NOT NULL,
+    -- Unix time in milliseconds
+    at BIG INT NOT NULL,
+    kind BIG INT NOT NULL,
+    message VARCHAR NOT NULL DEFAULT ''
+);
+
+CREATE INDEX video_events_by_video ON video_events (video_id, seq);
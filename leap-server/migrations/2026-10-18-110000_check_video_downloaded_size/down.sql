@@ -0,0 +1,2 @@
+DROP TRIGGER videos_downloaded_size_update;
+DROP TRIGGER videos_downloaded_size_insert;
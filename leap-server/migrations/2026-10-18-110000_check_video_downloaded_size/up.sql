@@ -0,0 +1,15 @@
+-- SQLite can't add a CHECK constraint to an existing table without rebuilding it, so the
+-- constraint is enforced by triggers instead.
+UPDATE videos SET downloaded_size = file_size WHERE downloaded_size > file_size;
+
+CREATE TRIGGER videos_downloaded_size_insert BEFORE INSERT ON videos
+WHEN NEW.downloaded_size > NEW.file_size
+BEGIN
+    SELECT RAISE(ABORT, 'downloaded_size exceeds file_size');
+END;
+
+CREATE TRIGGER videos_downloaded_size_update BEFORE UPDATE OF downloaded_size, file_size ON videos
+WHEN NEW.downloaded_size > NEW.file_size
+BEGIN
+    SELECT RAISE(ABORT, 'downloaded_size exceeds file_size');
+END;
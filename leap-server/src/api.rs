@@ -1,33 +1,159 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use crate::provision::DynProvision;
-use crate::{cfg::LeapConfig, db::Database, downloader::UserCommand};
+use crate::{
+    cfg::{DownloaderConfig, HttpServerConfig, LeapConfig},
+    db::Database,
+    downloader::{
+        ContentHandles, DownloaderStatus, EventSender, HashingPool, UserCommand,
+        partial::PartialWrites, streams::ActiveStreams,
+    },
+    store::ContentStore,
+};
 
-use actix_web::web;
+use actix_web::{http::header, middleware::Condition, web};
 use tokio::sync::mpsc::UnboundedSender;
 
+mod admin;
+mod connections;
 mod provision;
 mod user;
 
+pub use connections::register_connection;
+
 /// Shared resources used in HTTP handlers
 pub struct ApiData {
     config: LeapConfig,
     db: Arc<Database>,
+    status: Arc<DownloaderStatus>,
+    store: Arc<dyn ContentStore>,
+    cmd_sender: UnboundedSender<UserCommand>,
+    events: EventSender,
+    /// Time of the last user-triggered manifest fetch, used to debounce them.
+    last_manual_fetch: std::sync::Mutex<Option<std::time::Instant>>,
+    /// Disk usage of the content of the default channel.
+    disk_usage: DiskUsageCache,
+    /// In-flight content requests of each client.
+    content_connections: connections::ConnectionLimiter,
+    /// The additional channels, by name.
+    channels: BTreeMap<String, Channel>,
+    /// Pool computing the checksums of the imported files.
+    hashing: HashingPool,
+    /// Offsets up to which the videos being downloaded can be streamed.
+    partial_writes: PartialWrites,
+    /// Content streams being served, to which the downloads yield.
+    active_streams: ActiveStreams,
+}
+
+/// Disk usage of a content directory and the time it was computed, to avoid walking the directory
+/// on every request.
+type DiskUsageCache = tokio::sync::Mutex<Option<(std::time::Instant, u64)>>;
+
+/// An additional manifest channel, with its own database, downloader and content directory.
+pub struct Channel {
+    db: Arc<Database>,
+    status: Arc<DownloaderStatus>,
     cmd_sender: UnboundedSender<UserCommand>,
+    events: EventSender,
+    downloader_config: DownloaderConfig,
+    disk_usage: DiskUsageCache,
+}
+
+impl Channel {
+    pub fn new(
+        db: Arc<Database>,
+        status: Arc<DownloaderStatus>,
+        cmd_sender: UnboundedSender<UserCommand>,
+        events: EventSender,
+        downloader_config: DownloaderConfig,
+    ) -> Self {
+        Self {
+            db,
+            status,
+            cmd_sender,
+            events,
+            downloader_config,
+            disk_usage: Default::default(),
+        }
+    }
+}
+
+/// The resources of the channel selected by a request, either the default channel or an
+/// additional one. The content store is shared by all the channels.
+struct ChannelRef<'a> {
+    db: &'a Arc<Database>,
+    status: &'a Arc<DownloaderStatus>,
+    store: &'a Arc<dyn ContentStore>,
+    cmd_sender: &'a UnboundedSender<UserCommand>,
+    events: &'a EventSender,
+    downloader_config: &'a DownloaderConfig,
+    disk_usage: &'a DiskUsageCache,
 }
 
 impl ApiData {
     pub fn new(
         config: LeapConfig,
         db: Arc<Database>,
+        status: Arc<DownloaderStatus>,
+        content: ContentHandles,
         cmd_sender: UnboundedSender<UserCommand>,
+        channels: BTreeMap<String, Channel>,
     ) -> Self {
+        let ContentHandles {
+            store,
+            events,
+            partial_writes,
+            active_streams,
+            hashing,
+        } = content;
         Self {
             config,
             db,
+            status,
+            store,
             cmd_sender,
+            events,
+            last_manual_fetch: std::sync::Mutex::new(None),
+            disk_usage: Default::default(),
+            content_connections: Default::default(),
+            channels,
+            hashing,
+            partial_writes,
+            active_streams,
         }
     }
+
+    /// The resources of the given channel, or of the default channel if `None`. Returns `None` if
+    /// the channel doesn't exist.
+    fn channel(&self, name: Option<&str>) -> Option<ChannelRef<'_>> {
+        let Some(name) = name else {
+            return Some(ChannelRef {
+                db: &self.db,
+                status: &self.status,
+                store: &self.store,
+                cmd_sender: &self.cmd_sender,
+                events: &self.events,
+                downloader_config: &self.config.downloader_config,
+                disk_usage: &self.disk_usage,
+            });
+        };
+        self.channels.get(name).map(|channel| ChannelRef {
+            db: &channel.db,
+            status: &channel.status,
+            store: &self.store,
+            cmd_sender: &channel.cmd_sender,
+            events: &channel.events,
+            downloader_config: &channel.downloader_config,
+            disk_usage: &channel.disk_usage,
+        })
+    }
+}
+
+/// Responds to requests for channels that are not configured.
+fn unknown_channel(channel: Option<&str>) -> actix_web::HttpResponse {
+    let msg = format!("Unknown channel {}", channel.unwrap_or_default());
+    tracing::info!(msg);
+    actix_web::HttpResponse::NotFound().body(msg)
 }
 
 /// Shared resources used in HTTP handlers
@@ -44,32 +170,485 @@ impl ProvisionApiData {
     }
 }
 
+/// Maximum size of the request bodies accepted by the provisioning API, in bytes. It only receives
+/// small configuration documents, so it is stricter than the LEAP API.
+const PROVISION_MAX_PAYLOAD_BYTES: usize = 16 * 1024;
+
+/// Limits the size of the request bodies accepted by the handlers of `scope`. Larger bodies are
+/// rejected with `413 Payload Too Large`.
+fn limit_payload(scope: actix_web::Scope, limit: usize) -> actix_web::Scope {
+    scope
+        .app_data(web::PayloadConfig::new(limit))
+        .app_data(web::JsonConfig::default().limit(limit))
+}
+
+/// Responds to the requests whose query or path can't be parsed with a JSON [`ApiError`], which
+/// clients can show, instead of a plain-text error.
+///
+/// [`ApiError`]: leap_api::types::ApiError
+fn handle_extractor_errors(scope: actix_web::Scope) -> actix_web::Scope {
+    fn bad_request<E>(err: E, message: String) -> actix_web::Error
+    where
+        E: std::fmt::Debug + std::fmt::Display + 'static,
+    {
+        tracing::info!("{message}");
+        let response =
+            actix_web::HttpResponse::BadRequest().json(leap_api::types::ApiError { message });
+        actix_web::error::InternalError::from_response(err, response).into()
+    }
+
+    scope
+        .app_data(web::QueryConfig::default().error_handler(|err, req| {
+            let message = format!("Invalid query `{}`: {err}", req.query_string());
+            bad_request(err, message)
+        }))
+        .app_data(web::PathConfig::default().error_handler(|err, req| {
+            let message = format!("Invalid path `{}`: {err}", req.path());
+            bad_request(err, message)
+        }))
+}
+
+/// Maximum size of the request bodies accepted by the admin API, in bytes. It only receives small
+/// commands, so it is stricter than the rest of the LEAP API.
+const ADMIN_MAX_PAYLOAD_BYTES: usize = 16 * 1024;
+
+/// Handlers of the admin API, which require the admin API key. Their request bodies are limited to
+/// `max_payload_bytes`, and never to more than [`ADMIN_MAX_PAYLOAD_BYTES`].
+fn admin_handlers(max_payload_bytes: usize) -> actix_web::Scope {
+    limit_payload(
+        web::scope("admin"),
+        max_payload_bytes.min(ADMIN_MAX_PAYLOAD_BYTES),
+    )
+    .service(admin::get_config)
+    .service(admin::list_orphans)
+    .service(admin::remove_orphans)
+    .service(admin::reconcile)
+    .service(admin::import)
+}
+
 fn common_api_handlers() -> actix_web::Scope {
     web::scope("api").service(user::get_version)
 }
 
-pub fn register_handlers(app: &mut web::ServiceConfig) {
-    app.service(
-        common_api_handlers()
-            .service(user::list_content_metadata)
-            .service(user::content_metadata_for_id)
-            .service(user::get_content)
-            .service(user::increment_view_cnt)
-            .service(user::fetch_manifest)
-            .service(user::get_manifest)
-            .service(user::log_file),
+/// Builds the CORS policy of the API from the list of allowed origins. Requests from the same
+/// origin as the server are always allowed, so that the bundled site keeps working.
+fn cors_policy(allowed_origins: &[String]) -> actix_cors::Cors {
+    let allowed_origins = allowed_origins.to_vec();
+    actix_cors::Cors::default()
+        .allowed_origin_fn(move |origin, req_head| {
+            let same_origin = req_head
+                .headers()
+                .get(header::HOST)
+                .zip(origin.to_str().ok())
+                .is_some_and(|(host, origin)| {
+                    origin
+                        .split_once("://")
+                        .is_some_and(|(_, origin_host)| host.as_bytes() == origin_host.as_bytes())
+                });
+            same_origin
+                || allowed_origins
+                    .iter()
+                    .any(|o| o.as_bytes() == origin.as_bytes())
+        })
+        .allowed_methods(["GET", "POST", "PUT", "DELETE"])
+        .allow_any_header()
+        .expose_headers([header::CONTENT_RANGE])
+        .max_age(3600)
+}
+
+/// Returns a function registering the handlers of the LEAP API. CORS headers are only added when
+/// the configuration allows cross-origin requests.
+pub fn register_handlers(config: &HttpServerConfig) -> impl FnOnce(&mut web::ServiceConfig) {
+    let cors = Condition::new(
+        !config.cors_allowed_origins.is_empty(),
+        cors_policy(&config.cors_allowed_origins),
     );
+
+    let max_payload_bytes = config.max_payload_bytes();
+
+    move |app| {
+        app.service(
+            handle_extractor_errors(limit_payload(common_api_handlers(), max_payload_bytes))
+                .service(user::get_health)
+                .service(user::list_content_metadata)
+                .service(user::content_metadata_for_id)
+                .service(user::related_content)
+                .service(user::video_events)
+                .service(user::content_summary)
+                .service(user::content_sections)
+                .service(user::section_videos_status)
+                .service(user::content_events)
+                .service(user::video_progress)
+                .service(user::export_content)
+                .service(user::get_content)
+                .service(user::content_availability)
+                .service(user::increment_view_cnt)
+                .service(user::redownload_content)
+                .service(user::pin_content)
+                .service(user::fetch_manifest)
+                .service(user::pause_downloader)
+                .service(user::resume_downloader)
+                .service(user::get_manifest)
+                .service(user::export_manifest)
+                .service(user::log_file)
+                .service(admin_handlers(max_payload_bytes))
+                .wrap(cors),
+        );
+    }
 }
 
 pub fn register_provisioning_handlers(app: &mut web::ServiceConfig) {
     app.service(common_api_handlers());
     app.service(
-        web::scope("provision")
-            .service(provision::set_network_config)
-            .service(provision::get_storage_devs)
-            .service(provision::format_storage)
-            .service(provision::set_configuration)
-            .service(provision::complete_provisioning)
-            .service(provision::status),
+        handle_extractor_errors(limit_payload(
+            web::scope("provision"),
+            PROVISION_MAX_PAYLOAD_BYTES,
+        ))
+        .service(provision::set_network_config)
+        .service(provision::get_storage_devs)
+        .service(provision::format_storage)
+        .service(provision::set_configuration)
+        .service(provision::complete_provisioning)
+        .service(provision::status),
     );
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    use actix_web::{App, HttpResponse, http::StatusCode, test};
+    use googletest::prelude::*;
+
+    /// Creates the API data for tests, with an empty database stored in `runtime_path`.
+    pub async fn create_api_data(runtime_path: &std::path::Path) -> web::Data<ApiData> {
+        create_api_data_with_config(test_config(runtime_path)).await
+    }
+
+    /// Returns a configuration for tests storing all the data in `runtime_path`.
+    pub fn test_config(runtime_path: &std::path::Path) -> LeapConfig {
+        LeapConfig {
+            debug: false,
+            log_max_bytes: crate::cfg::DEFAULT_LOG_MAX_BYTES,
+            log_max_files: crate::cfg::DEFAULT_LOG_MAX_FILES,
+            instance_name: Some("test-instance".to_string()),
+            downloader_config: crate::cfg::DownloaderConfig {
+                concurrent_downloads: 1,
+                solo_download_bytes: None,
+                streaming_concurrent_downloads: None,
+                yield_to_streams: crate::cfg::DEFAULT_YIELD_TO_STREAMS,
+                content_path: runtime_path.join("content"),
+                temp_download_path: None,
+                remote_server: "/invalid".try_into().unwrap(),
+                update_interval: std::time::Duration::from_secs(300),
+                retry_params: crate::cfg::RetryParams {
+                    initial_backoff: std::time::Duration::from_secs(1),
+                    backoff_factor: 2.0,
+                    max_backoff: std::time::Duration::from_secs(10),
+                    backoff_jitter_fraction: 0.0,
+                },
+                max_manifest_bytes: crate::cfg::DEFAULT_MAX_MANIFEST_BYTES,
+                manual_fetch_cooldown: crate::cfg::DEFAULT_MANUAL_FETCH_COOLDOWN,
+                manifest_retry_interval: crate::cfg::DEFAULT_MANIFEST_RETRY_INTERVAL,
+                unreachable_after_failures: crate::cfg::DEFAULT_UNREACHABLE_AFTER_FAILURES,
+                max_update_interval: crate::cfg::DEFAULT_MAX_UPDATE_INTERVAL,
+                retain_removed_for: None,
+                completion_webhook_url: None,
+                existing_file_check: crate::cfg::ExistingFileCheck::default(),
+                reconcile_interval: crate::cfg::DEFAULT_RECONCILE_INTERVAL,
+                progress_event_interval: crate::cfg::DEFAULT_PROGRESS_EVENT_INTERVAL,
+                error_log_interval: crate::cfg::DEFAULT_ERROR_LOG_INTERVAL,
+                allowed_uri_schemes: None,
+                min_free_bytes: 0,
+                strict_manifest: false,
+                verify_manifest_content_type: true,
+                resource_guard: Default::default(),
+                io_chunk_bytes: crate::cfg::DEFAULT_IO_CHUNK_BYTES,
+                hashing_threads: crate::cfg::DEFAULT_HASHING_THREADS,
+                request_headers: Default::default(),
+                channels: Default::default(),
+            },
+            db_config: crate::cfg::DbConfig {
+                busy_timeout: std::time::Duration::from_secs(2),
+                pool_size: 4,
+                runtime_path: runtime_path.to_path_buf(),
+            },
+            s3_config: crate::cfg::S3Config {
+                endpoint_url: None,
+                force_path_style: false,
+                access_key_id: None,
+                secret_access_key: None,
+                region: "us-east-1".to_string(),
+            },
+            http_config: HttpServerConfig::default(),
+        }
+    }
+
+    /// Creates the API data for tests from the given configuration, with an empty database.
+    pub async fn create_api_data_with_config(config: LeapConfig) -> web::Data<ApiData> {
+        create_api_data_with_receiver(config).await.0
+    }
+
+    /// Same as [`create_api_data_with_config`], also returning the receiver of the commands sent
+    /// to the downloader.
+    pub async fn create_api_data_with_receiver(
+        config: LeapConfig,
+    ) -> (
+        web::Data<ApiData>,
+        tokio::sync::mpsc::UnboundedReceiver<UserCommand>,
+    ) {
+        let database = Arc::new(Database::open(config.db_config.clone()).await.unwrap());
+        database.apply_pending_migrations().await.unwrap();
+        let (cmd_sender, cmd_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let content = ContentHandles::new(
+            crate::store::content_store(&config.http_config),
+            config.downloader_config.hashing_threads,
+        );
+        let api_data = web::Data::new(ApiData::new(
+            config,
+            database,
+            Default::default(),
+            content,
+            cmd_sender,
+            Default::default(),
+        ));
+        (api_data, cmd_receiver)
+    }
+
+    fn http_config(origins: &[&str]) -> HttpServerConfig {
+        HttpServerConfig {
+            cors_allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_cors_allowed_origin() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let config = http_config(&["http://allowed.example"]);
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(register_handlers(&config)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/version")
+            .insert_header((header::ORIGIN, "http://allowed.example"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_true!(resp.status().is_success());
+        expect_that!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .and_then(|v| v.to_str().ok()),
+            some(eq("http://allowed.example"))
+        );
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_cors_preflight() -> googletest::Result<()> {
+        let config = http_config(&["http://allowed.example"]);
+        let app = test::init_service(App::new().configure(register_handlers(&config))).await;
+
+        let req = test::TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/api/manifest/fetch")
+            .insert_header((header::ORIGIN, "http://allowed.example"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_true!(resp.status().is_success());
+        expect_that!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .and_then(|v| v.to_str().ok()),
+            some(eq("http://allowed.example"))
+        );
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_cors_preflight_put_and_delete() -> googletest::Result<()> {
+        let config = http_config(&["http://allowed.example"]);
+        let app = test::init_service(App::new().configure(register_handlers(&config))).await;
+
+        for (uri, method) in [
+            ("/api/content/local/pin", "PUT"),
+            ("/api/admin/orphans", "DELETE"),
+        ] {
+            let req = test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri(uri)
+                .insert_header((header::ORIGIN, "http://allowed.example"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, method))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            expect_true!(resp.status().is_success());
+            expect_that!(
+                resp.headers()
+                    .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+                    .and_then(|v| v.to_str().ok()),
+                some(contains_substring(method))
+            );
+        }
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_cors_disallowed_origin() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        for config in [http_config(&["http://allowed.example"]), http_config(&[])] {
+            let app = test::init_service(
+                App::new()
+                    .app_data(api_data.clone())
+                    .configure(register_handlers(&config)),
+            )
+            .await;
+
+            let req = test::TestRequest::get()
+                .uri("/api/version")
+                .insert_header((header::ORIGIN, "http://evil.example"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            expect_that!(
+                resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+                none()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_limit_payload() -> googletest::Result<()> {
+        let app = test::init_service(
+            App::new().service(
+                limit_payload(web::scope("test"), 16)
+                    .route(
+                        "json",
+                        web::post().to(|_: web::Json<serde_json::Value>| async {
+                            HttpResponse::Ok().finish()
+                        }),
+                    )
+                    .route(
+                        "bytes",
+                        web::post().to(|_: web::Bytes| async { HttpResponse::Ok().finish() }),
+                    ),
+            ),
+        )
+        .await;
+
+        let oversized = serde_json::json!({ "data": "x".repeat(64) });
+        let req = test::TestRequest::post()
+            .uri("/test/json")
+            .set_json(&oversized)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::PAYLOAD_TOO_LARGE));
+
+        let req = test::TestRequest::post()
+            .uri("/test/bytes")
+            .set_payload(vec![0u8; 64])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::PAYLOAD_TOO_LARGE));
+
+        let req = test::TestRequest::post()
+            .uri("/test/json")
+            .set_json(serde_json::json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_admin_limit_payload() -> googletest::Result<()> {
+        // The admin API is nested in the LEAP API, which accepts larger bodies
+        let max_payload_bytes = 4 * ADMIN_MAX_PAYLOAD_BYTES;
+        let app = test::init_service(App::new().service(
+            limit_payload(web::scope("api"), max_payload_bytes).service(
+                admin_handlers(max_payload_bytes).route(
+                    "bytes",
+                    web::post().to(|_: web::Bytes| async { HttpResponse::Ok().finish() }),
+                ),
+            ),
+        ))
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/bytes")
+            .set_payload(vec![0u8; ADMIN_MAX_PAYLOAD_BYTES + 1])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::PAYLOAD_TOO_LARGE));
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/bytes")
+            .set_payload(vec![0u8; ADMIN_MAX_PAYLOAD_BYTES])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_extractor_errors() -> googletest::Result<()> {
+        let app = test::init_service(
+            App::new().service(
+                handle_extractor_errors(web::scope("test"))
+                    .route(
+                        "query",
+                        web::get().to(
+                            |_: web::Query<leap_api::api::content::meta::get::Query>| async {
+                                HttpResponse::Ok().finish()
+                            },
+                        ),
+                    )
+                    .route(
+                        "path/{id}",
+                        web::get().to(|_: web::Path<u32>| async { HttpResponse::Ok().finish() }),
+                    ),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/test/query?limit=abc")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::BAD_REQUEST));
+        let body: leap_api::types::ApiError = test::read_body_json(resp).await;
+        expect_that!(body.message, contains_substring("limit=abc"));
+
+        let req = test::TestRequest::get().uri("/test/path/abc").to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::BAD_REQUEST));
+        let body: leap_api::types::ApiError = test::read_body_json(resp).await;
+        expect_that!(body.message, contains_substring("/test/path/abc"));
+
+        let req = test::TestRequest::get()
+            .uri("/test/query?limit=10")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        Ok(())
+    }
+}
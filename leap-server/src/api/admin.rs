@@ -0,0 +1,651 @@
+use std::future::{Ready, ready};
+
+use actix_web::{
+    FromRequest, HttpRequest, HttpResponse, Responder, delete, get, http::header, post, web,
+};
+use secrecy::ExposeSecret;
+
+use crate::api::{ApiData, unknown_channel};
+
+/// Extractor authorizing requests to the admin API. Requests must carry the configured admin API
+/// key as a bearer token in the `Authorization` header. When no key is configured, the admin API
+/// is disabled and every request is rejected with `403 Forbidden`.
+pub struct Admin;
+
+impl FromRequest for Admin {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let Some(api_data) = req.app_data::<web::Data<ApiData>>() else {
+            return ready(Err(actix_web::error::ErrorInternalServerError(
+                "Missing API data",
+            )));
+        };
+        let Some(admin_api_key) = &api_data.config.http_config.admin_api_key else {
+            return ready(Err(actix_web::error::ErrorForbidden(
+                "The admin API is disabled",
+            )));
+        };
+
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if token.is_some_and(|token| keys_match(token, admin_api_key.expose_secret())) {
+            ready(Ok(Admin))
+        } else {
+            tracing::warn!("Rejecting unauthorized admin API request");
+            ready(Err(actix_web::error::ErrorUnauthorized(
+                "Invalid admin API key",
+            )))
+        }
+    }
+}
+
+/// Compares two keys in constant time (for keys of the same length), so that the comparison does
+/// not leak how many leading characters of the key were guessed right.
+fn keys_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Returns the configuration loaded by the server, with its secrets redacted.
+#[tracing::instrument(
+    skip(api_data, _admin)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/config")]
+async fn get_config(api_data: web::Data<ApiData>, _admin: Admin) -> impl Responder {
+    HttpResponse::Ok().json(api_data.config.redacted())
+}
+
+impl From<crate::downloader::orphans::OrphanFile> for leap_api::types::OrphanFile {
+    fn from(value: crate::downloader::orphans::OrphanFile) -> Self {
+        Self {
+            path: value.path.to_string_lossy().into_owned(),
+            size: value.size,
+        }
+    }
+}
+
+/// Lists the video files that don't belong to any video.
+#[tracing::instrument(
+    skip(api_data, _admin)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/orphans")]
+async fn list_orphans(
+    api_data: web::Data<ApiData>,
+    _admin: Admin,
+    query: web::Query<leap_api::api::admin::orphans::get::Query>,
+) -> impl Responder {
+    use leap_api::api::admin::orphans::get::Response;
+
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    match crate::downloader::orphans::find_orphan_files(channel.downloader_config, channel.db).await
+    {
+        Ok(files) => HttpResponse::Ok().json(Response {
+            files: files.into_iter().map(Into::into).collect(),
+        }),
+        Err(e) => {
+            let msg = format!("Unable to list the orphan files: {e:?}");
+            tracing::error!(msg);
+            HttpResponse::InternalServerError().body(msg)
+        }
+    }
+}
+
+/// Deletes the video files that don't belong to any video.
+#[tracing::instrument(
+    skip(api_data, _admin)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[delete("/orphans")]
+async fn remove_orphans(
+    api_data: web::Data<ApiData>,
+    _admin: Admin,
+    query: web::Query<leap_api::api::admin::orphans::delete::Query>,
+) -> impl Responder {
+    use leap_api::api::admin::orphans::delete::Response;
+
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    match crate::downloader::orphans::remove_orphan_files(channel.downloader_config, channel.db)
+        .await
+    {
+        Ok(removed) => HttpResponse::Ok().json(Response {
+            removed: removed.into_iter().map(Into::into).collect(),
+        }),
+        Err(e) => {
+            let msg = format!("Unable to remove the orphan files: {e:?}");
+            tracing::error!(msg);
+            HttpResponse::InternalServerError().body(msg)
+        }
+    }
+}
+
+/// Reconciles the database with the current manifest, and downloads the videos that were missing.
+#[tracing::instrument(
+    skip(api_data, _admin)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[post("/reconcile")]
+async fn reconcile(
+    api_data: web::Data<ApiData>,
+    _admin: Admin,
+    query: web::Query<leap_api::api::admin::reconcile::post::Query>,
+) -> impl Responder {
+    use leap_api::api::admin::reconcile::post::Response;
+
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let reconciliation =
+        match crate::downloader::reconcile_current_manifest(channel.downloader_config, channel.db)
+            .await
+        {
+            Ok(Some(reconciliation)) => reconciliation,
+            Ok(None) => return HttpResponse::NotFound().body("No manifest is being served"),
+            Err(e) => {
+                let msg = format!("Unable to reconcile the database with the manifest: {e:?}");
+                tracing::error!(msg);
+                return HttpResponse::InternalServerError().body(msg);
+            }
+        };
+
+    // The downloader owns the downloads, so it is in charge of downloading the restored videos
+    if reconciliation.inserted > 0
+        && let Err(e) = channel
+            .cmd_sender
+            .send(crate::downloader::UserCommand::DownloadPending)
+    {
+        tracing::error!("Unable to download the restored videos: {e}");
+    }
+    HttpResponse::Ok().json(Response {
+        inserted: reconciliation.inserted,
+        deleted: reconciliation.deleted,
+    })
+}
+
+/// Imports the video files of a local directory as the content of the videos that are not
+/// downloaded yet, matching them by checksum.
+#[tracing::instrument(
+    skip(api_data, _admin)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[post("/import")]
+async fn import(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::admin::import::post::Query>,
+    _admin: Admin,
+) -> impl Responder {
+    use leap_api::api::admin::import::post::{ImportedFile, Response};
+
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let report = match crate::downloader::import::import_directory(
+        channel.downloader_config,
+        channel.db,
+        channel.store.as_ref(),
+        &api_data.hashing,
+        std::path::Path::new(&query.path),
+    )
+    .await
+    {
+        Ok(Some(report)) => report,
+        Ok(None) => return HttpResponse::NotFound().body("No manifest is being served"),
+        Err(e) => {
+            let msg = format!("Unable to import the files of {}: {e:?}", query.path);
+            tracing::error!(msg);
+            return HttpResponse::InternalServerError().body(msg);
+        }
+    };
+
+    for file in &report.imported {
+        let status = crate::db::DownloadStatus::Downloaded(file.content_path.clone());
+        // Sending only fails when there are no subscribers, which is fine
+        let _ = channel.events.send(leap_api::types::ContentEvent {
+            id: file.id.to_string(),
+            status: status.into(),
+        });
+    }
+    HttpResponse::Ok().json(Response {
+        imported: report
+            .imported
+            .into_iter()
+            .map(|file| ImportedFile {
+                path: file.path.to_string_lossy().into_owned(),
+                id: file.id.to_string(),
+            })
+            .collect(),
+        unmatched: report
+            .unmatched
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::{App, http::StatusCode, test};
+    use googletest::prelude::*;
+    use secrecy::SecretString;
+
+    const ADMIN_API_KEY: &str = "admin-key";
+
+    async fn get_config_response(
+        config: crate::cfg::LeapConfig,
+        api_key: Option<&str>,
+    ) -> actix_web::dev::ServiceResponse {
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+
+        let mut req = test::TestRequest::get().uri("/api/admin/config");
+        if let Some(api_key) = api_key {
+            req = req.insert_header((header::AUTHORIZATION, format!("Bearer {api_key}")));
+        }
+        test::call_service(&app, req.to_request()).await
+    }
+
+    fn admin_config(runtime_path: &std::path::Path) -> crate::cfg::LeapConfig {
+        let mut config = crate::api::test::test_config(runtime_path);
+        config.http_config.admin_api_key = Some(SecretString::from(ADMIN_API_KEY));
+        config
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_config_redacts_secrets() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = admin_config(tempdir.path());
+        config.s3_config.access_key_id = Some(SecretString::from("access-key-id"));
+        config.s3_config.secret_access_key = Some(SecretString::from("secret-access-key"));
+        config
+            .downloader_config
+            .request_headers
+            .insert("Authorization".to_string(), "Bearer token".to_string());
+
+        let resp = get_config_response(config, Some(ADMIN_API_KEY)).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        expect_that!(
+            body["s3_config"]["access_key_id"],
+            eq(&serde_json::json!("***"))
+        );
+        expect_that!(
+            body["s3_config"]["secret_access_key"],
+            eq(&serde_json::json!("***"))
+        );
+        expect_that!(
+            body["http_config"]["admin_api_key"],
+            eq(&serde_json::json!("***"))
+        );
+        expect_that!(
+            body["downloader_config"]["request_headers"]["Authorization"],
+            eq(&serde_json::json!("***"))
+        );
+        expect_that!(
+            body["s3_config"]["region"],
+            eq(&serde_json::json!("us-east-1"))
+        );
+        expect_that!(
+            body["downloader_config"]["concurrent_downloads"],
+            eq(&serde_json::json!(1))
+        );
+        expect_that!(body["debug"], eq(&serde_json::json!(false)));
+
+        let body = body.to_string();
+        expect_that!(body, not(contains_substring("access-key-id")));
+        expect_that!(body, not(contains_substring("secret-access-key")));
+        expect_that!(body, not(contains_substring(ADMIN_API_KEY)));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_config_requires_admin_key() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+
+        let resp = get_config_response(admin_config(tempdir.path()), None).await;
+        expect_that!(resp.status(), eq(StatusCode::UNAUTHORIZED));
+
+        let resp = get_config_response(admin_config(tempdir.path()), Some("wrong-key")).await;
+        expect_that!(resp.status(), eq(StatusCode::UNAUTHORIZED));
+
+        let config = crate::api::test::test_config(tempdir.path());
+        let resp = get_config_response(config, Some(ADMIN_API_KEY)).await;
+        expect_that!(resp.status(), eq(StatusCode::FORBIDDEN));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_orphans() -> googletest::Result<()> {
+        use leap_api::api::admin::orphans::{delete, get};
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let config = admin_config(tempdir.path());
+        let content_path = config.downloader_config.content_path.clone();
+        let temp_download_path = config.downloader_config.temp_download_path();
+        std::fs::create_dir_all(&temp_download_path).or_fail()?;
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+        let db = std::sync::Arc::clone(&api_data.db);
+
+        let downloaded = uuid::Uuid::new_v4();
+        let downloaded_path = content_path.join(format!("{downloaded}.mp4"));
+        std::fs::write(&downloaded_path, b"video").or_fail()?;
+        db.insert_video(downloaded, "downloaded", 5)
+            .await
+            .or_fail()?;
+        db.set_downloaded(downloaded, &downloaded_path)
+            .await
+            .or_fail()?;
+
+        // The partial file of an active download is not an orphan
+        let downloading = uuid::Uuid::new_v4();
+        let downloading_path = temp_download_path.join(format!("{downloading}.mp4.part"));
+        std::fs::write(&downloading_path, b"vid").or_fail()?;
+        db.insert_video(downloading, "downloading", 5)
+            .await
+            .or_fail()?;
+        expect_true!(db.claim_for_download(downloading).await.or_fail()?);
+
+        // Files of unknown videos, and partial files of videos not being downloaded
+        let orphan_path = content_path.join(format!("{}.mp4", uuid::Uuid::new_v4()));
+        std::fs::write(&orphan_path, b"orphan").or_fail()?;
+        let pending = uuid::Uuid::new_v4();
+        let partial_path = temp_download_path.join(format!("{pending}.mp4.part"));
+        std::fs::write(&partial_path, b"or").or_fail()?;
+        db.insert_video(pending, "pending", 5).await.or_fail()?;
+
+        // Files not named after a video are left alone
+        let other_path = content_path.join("notes.txt");
+        std::fs::write(&other_path, b"notes").or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let admin_request = |req: test::TestRequest| {
+            req.uri("/api/admin/orphans")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {ADMIN_API_KEY}")))
+                .to_request()
+        };
+        let orphan = |path: &std::path::Path, size| leap_api::types::OrphanFile {
+            path: path.to_string_lossy().into_owned(),
+            size,
+        };
+
+        let body: get::Response =
+            test::call_and_read_body_json(&app, admin_request(test::TestRequest::get())).await;
+        expect_that!(
+            body.files,
+            unordered_elements_are![eq(&orphan(&orphan_path, 6)), eq(&orphan(&partial_path, 2))]
+        );
+
+        let body: delete::Response =
+            test::call_and_read_body_json(&app, admin_request(test::TestRequest::delete())).await;
+        expect_that!(
+            body.removed,
+            unordered_elements_are![eq(&orphan(&orphan_path, 6)), eq(&orphan(&partial_path, 2))]
+        );
+        expect_false!(orphan_path.exists());
+        expect_false!(partial_path.exists());
+        expect_true!(downloaded_path.exists());
+        expect_true!(downloading_path.exists());
+        expect_true!(other_path.exists());
+
+        let body: get::Response =
+            test::call_and_read_body_json(&app, admin_request(test::TestRequest::get())).await;
+        expect_that!(body.files, is_empty());
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_reconcile() -> googletest::Result<()> {
+        use leap_api::api::admin::reconcile::post::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let (api_data, mut cmd_receiver) =
+            crate::api::test::create_api_data_with_receiver(admin_config(tempdir.path())).await;
+        let db = std::sync::Arc::clone(&api_data.db);
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let reconcile_request = |api_key: &str| {
+            test::TestRequest::post()
+                .uri("/api/admin/reconcile")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {api_key}")))
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, reconcile_request("wrong-key")).await;
+        expect_that!(resp.status(), eq(StatusCode::UNAUTHORIZED));
+        let resp = test::call_service(&app, reconcile_request(ADMIN_API_KEY)).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+
+        let video = |name: &str| -> googletest::Result<crate::manifest::Video> {
+            Ok(crate::manifest::Video {
+                name: name.to_string(),
+                id: uuid::Uuid::new_v4(),
+                uri: format!("s3://bucket/{name}.mp4").parse().or_fail()?,
+                checksum: "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                    .try_into()
+                    .or_fail()?,
+                file_size: 5,
+                duration_secs: None,
+                resolution: None,
+            })
+        };
+        let (kept, deleted) = (video("kept")?, video("deleted")?);
+        let manifest = crate::manifest::ManifestFile {
+            name: "manifest".to_string(),
+            date: chrono::NaiveDate::from_ymd_opt(2025, 10, 10).or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
+            version: crate::manifest::Version {
+                major: 2,
+                minor: 0,
+                revision: 0,
+            },
+            sections: vec![crate::manifest::Section {
+                name: "Section".to_string(),
+                description: None,
+                content: vec![kept.clone(), deleted.clone()],
+            }],
+        };
+        // The row of a video is missing, and another one was added behind the server's back
+        db.insert_video(kept.id, &kept.name, kept.file_size)
+            .await
+            .or_fail()?;
+        let unknown = uuid::Uuid::new_v4();
+        db.insert_video(unknown, "unknown", 5).await.or_fail()?;
+        db.publish_manifest(&manifest).await;
+
+        let body: Response =
+            test::call_and_read_body_json(&app, reconcile_request(ADMIN_API_KEY)).await;
+        expect_that!(
+            body,
+            eq(&Response {
+                inserted: 1,
+                deleted: 1,
+            })
+        );
+        expect_that!(
+            db.find_video(deleted.id).await,
+            ok(field!(
+                &crate::db::Video.download_status,
+                ref eq(&crate::db::DownloadStatus::Pending)
+            ))
+        );
+        expect_true!(db.find_video(kept.id).await.is_ok());
+        expect_true!(db.find_video(unknown).await.is_err());
+        expect_that!(
+            cmd_receiver.try_recv(),
+            ok(eq(&crate::downloader::UserCommand::DownloadPending))
+        );
+
+        // Nothing changes once reconciled
+        let body: Response =
+            test::call_and_read_body_json(&app, reconcile_request(ADMIN_API_KEY)).await;
+        expect_that!(
+            body,
+            eq(&Response {
+                inserted: 0,
+                deleted: 0,
+            })
+        );
+        expect_true!(cmd_receiver.try_recv().is_err());
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_import() -> googletest::Result<()> {
+        use leap_api::api::admin::import::post::{ImportedFile, Response};
+        use sha2::Digest;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let config = admin_config(tempdir.path());
+        let content_path = config.downloader_config.content_path.clone();
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+        let db = std::sync::Arc::clone(&api_data.db);
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let source = tempdir.path().join("usb");
+        std::fs::create_dir_all(&source).or_fail()?;
+        let import_request = || {
+            test::TestRequest::post()
+                .uri(&format!("/api/admin/import?path={}", source.display()))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {ADMIN_API_KEY}")))
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, import_request()).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+
+        let video = |name: &str, content: &[u8]| -> googletest::Result<crate::manifest::Video> {
+            Ok(crate::manifest::Video {
+                name: name.to_string(),
+                id: uuid::Uuid::new_v4(),
+                uri: format!("s3://bucket/{name}.mp4").parse().or_fail()?,
+                checksum: crate::manifest::Checksum::Sha256(
+                    crate::manifest::Sha256::try_from(sha2::Sha256::digest(content).as_slice())
+                        .or_fail()?,
+                ),
+                file_size: content.len() as u64,
+                duration_secs: None,
+                resolution: None,
+            })
+        };
+        let (first, second) = (video("first", b"first")?, video("second", b"second")?);
+        let manifest = crate::manifest::ManifestFile {
+            name: "manifest".to_string(),
+            date: chrono::NaiveDate::from_ymd_opt(2025, 10, 10).or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
+            version: crate::manifest::Version {
+                major: 2,
+                minor: 0,
+                revision: 0,
+            },
+            sections: vec![crate::manifest::Section {
+                name: "Section".to_string(),
+                description: None,
+                content: vec![first.clone(), second.clone()],
+            }],
+        };
+        for video in [&first, &second] {
+            db.insert_video(video.id, &video.name, video.file_size)
+                .await
+                .or_fail()?;
+        }
+        db.publish_manifest(&manifest).await;
+
+        // Only the first video has a file. The others don't match any video, even with its size.
+        let first_path = source.join("a.mp4");
+        std::fs::write(&first_path, b"first").or_fail()?;
+        let same_size_path = source.join("b.mp4");
+        std::fs::write(&same_size_path, b"fifth").or_fail()?;
+        let other_path = source.join("notes.txt");
+        std::fs::write(&other_path, b"notes for the drive").or_fail()?;
+
+        let body: Response = test::call_and_read_body_json(&app, import_request()).await;
+        expect_that!(
+            body,
+            eq(&Response {
+                imported: vec![ImportedFile {
+                    path: first_path.to_string_lossy().into_owned(),
+                    id: first.id.to_string(),
+                }],
+                unmatched: vec![
+                    same_size_path.to_string_lossy().into_owned(),
+                    other_path.to_string_lossy().into_owned(),
+                ],
+            })
+        );
+        let imported_path = content_path.join(format!("{}.mp4", first.id));
+        expect_that!(
+            db.find_video(first.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::Downloaded(
+                imported_path.clone()
+            ))
+        );
+        expect_that!(
+            std::fs::read(&imported_path).or_fail()?,
+            eq(&b"first".to_vec())
+        );
+        expect_that!(
+            db.find_video(second.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::Pending)
+        );
+        // The source is left untouched
+        expect_true!(first_path.exists());
+
+        // Downloaded videos are not imported again
+        let body: Response = test::call_and_read_body_json(&app, import_request()).await;
+        expect_that!(body.imported, is_empty());
+        expect_that!(body.unmatched.len(), eq(3));
+        Ok(())
+    }
+}
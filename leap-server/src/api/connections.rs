@@ -0,0 +1,293 @@
+//! Limit of the concurrent content requests of each client, so that a single misbehaving client
+//! can't exhaust the file handles and memory of the LEAP by opening many streaming connections.
+//! The connections of clients that stop reading their content are closed for the same reason.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    net::IpAddr,
+    os::fd::{AsRawFd, FromRawFd, RawFd},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_web::{
+    HttpResponse,
+    body::{BodySize, BoxBody, MessageBody},
+    dev::{Extensions, ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web::{self, Bytes},
+};
+
+use super::ApiData;
+use crate::downloader::streams::ActiveStream;
+
+/// Number of in-flight content requests of each client IP.
+#[derive(Debug, Default)]
+pub struct ConnectionLimiter {
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl ConnectionLimiter {
+    /// Registers a new request of `ip`, unless it already has `limit` requests in flight. The
+    /// request is in flight until the returned guard is dropped.
+    fn try_acquire(&self, ip: IpAddr, limit: usize) -> Option<ConnectionGuard> {
+        let mut connections = self.connections.lock().unwrap();
+        let count = connections.entry(ip).or_default();
+        if *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(ConnectionGuard {
+            connections: self.connections.clone(),
+            ip,
+        })
+    }
+}
+
+/// An in-flight request of a client, counted by its [`ConnectionLimiter`].
+struct ConnectionGuard {
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Response body keeping its request in flight, and its content stream active, until the body is
+/// fully sent or dropped.
+struct GuardedBody {
+    body: BoxBody,
+    _guard: Option<ConnectionGuard>,
+    _stream: ActiveStream,
+}
+
+impl MessageBody for GuardedBody {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Pin::new(&mut self.get_mut().body).poll_next(cx)
+    }
+}
+
+/// Middleware rejecting the requests of clients that already have
+/// `max_content_connections_per_ip` requests in flight with `429 Too Many Requests`. The accepted
+/// requests are counted as active content streams.
+pub async fn limit_content_connections(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let Some(api_data) = req.app_data::<web::Data<ApiData>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let guard = match req.peer_addr() {
+        Some(addr) => {
+            let limit = api_data.config.http_config.max_content_connections_per_ip();
+            match api_data.content_connections.try_acquire(addr.ip(), limit) {
+                Some(guard) => Some(guard),
+                None => {
+                    tracing::warn!("Client {} exceeded {limit} content requests", addr.ip());
+                    return Ok(req.into_response(
+                        HttpResponse::TooManyRequests()
+                            .body("Too many concurrent content requests"),
+                    ));
+                }
+            }
+        }
+        None => None,
+    };
+    let stream = api_data.active_streams.start();
+
+    let res = next.call(req).await?;
+    Ok(res.map_body(|_, body| {
+        BoxBody::new(GuardedBody {
+            body: BoxBody::new(body),
+            _guard: guard,
+            _stream: stream,
+        })
+    }))
+}
+
+/// Socket of a client connection, kept by [`register_connection`].
+#[derive(Debug, Clone, Copy)]
+struct ConnectionSocket(RawFd);
+
+/// Keeps the socket of new connections, so that [`close_idle_content`] can close them. Used as
+/// the `on_connect` callback of the server.
+pub fn register_connection(connection: &dyn Any, extensions: &mut Extensions) {
+    if let Some(stream) = connection.downcast_ref::<actix_web::rt::net::TcpStream>() {
+        extensions.insert(ConnectionSocket(stream.as_raw_fd()));
+    }
+}
+
+/// Response body closing its connection once the client has not read anything for `timeout`.
+///
+/// The server only polls the body when the client made room for more data, so the time since the
+/// last poll is the time that the client has been idle. The connection is closed by a watchdog
+/// task, as a stalled body is not polled anymore.
+struct IdleTimeoutBody {
+    body: BoxBody,
+    last_poll: Arc<Mutex<tokio::time::Instant>>,
+    watchdog: tokio::task::JoinHandle<()>,
+}
+
+impl IdleTimeoutBody {
+    fn new(body: BoxBody, socket: ConnectionSocket, timeout: Duration) -> Self {
+        let last_poll = Arc::new(Mutex::new(tokio::time::Instant::now()));
+        let watchdog = actix_web::rt::spawn({
+            let last_poll = last_poll.clone();
+            async move {
+                loop {
+                    let deadline = *last_poll.lock().unwrap() + timeout;
+                    if deadline <= tokio::time::Instant::now() {
+                        break;
+                    }
+                    tokio::time::sleep_until(deadline).await;
+                }
+                tracing::warn!("Closing content connection idle for {timeout:?}");
+                // SAFETY: The socket is open, as the connection owns both the socket and this
+                // body, which aborts the watchdog when dropped. The watchdog runs on the thread of
+                // the connection, so the socket can't be closed while it runs. The socket is only
+                // shut down, its ownership stays with the connection.
+                let stream = std::mem::ManuallyDrop::new(unsafe {
+                    std::net::TcpStream::from_raw_fd(socket.0)
+                });
+                if let Err(e) = stream.shutdown(std::net::Shutdown::Both) {
+                    tracing::error!("Error closing idle content connection: {e}");
+                }
+            }
+        });
+        Self {
+            body,
+            last_poll,
+            watchdog,
+        }
+    }
+}
+
+impl Drop for IdleTimeoutBody {
+    fn drop(&mut self) {
+        self.watchdog.abort();
+    }
+}
+
+impl MessageBody for IdleTimeoutBody {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        *this.last_poll.lock().unwrap() = tokio::time::Instant::now();
+        Pin::new(&mut this.body).poll_next(cx)
+    }
+}
+
+/// Middleware closing the connections of content requests whose clients stop reading the content
+/// for `content_idle_timeout_secs`, freeing the file handle and buffers of the response.
+pub async fn close_idle_content(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let socket = req.request().conn_data::<ConnectionSocket>().copied();
+    let timeout = req
+        .app_data::<web::Data<ApiData>>()
+        .map(|api_data| api_data.config.http_config.content_idle_timeout());
+
+    let res = next.call(req).await?;
+    Ok(match (socket, timeout) {
+        (Some(socket), Some(timeout)) => res.map_body(|_, body| {
+            BoxBody::new(IdleTimeoutBody::new(BoxBody::new(body), socket, timeout))
+        }),
+        _ => res.map_into_boxed_body(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{App, http::StatusCode, test};
+    use core::str::FromStr;
+    use googletest::prelude::*;
+
+    use crate::api::test::{create_api_data_with_config, test_config};
+
+    const VIDEO_ID: &str = "bf978778-1c5d-44b3-b2c1-1cc253563799";
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_content_connections_limit() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = test_config(tempdir.path());
+        config.http_config.max_content_connections_per_ip = Some(2);
+        let api_data = create_api_data_with_config(config).await;
+        let active_streams = api_data.active_streams.clone();
+
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"video content").or_fail()?;
+        api_data.db.insert_video(id, "video", 13).await.or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let get_content = |peer_addr: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/content/{VIDEO_ID}"))
+                .peer_addr(peer_addr.parse().unwrap())
+                .to_request()
+        };
+
+        // The responses are in flight until their bodies are read
+        let first = test::call_service(&app, get_content("10.0.0.1:1000")).await;
+        let second = test::call_service(&app, get_content("10.0.0.1:1001")).await;
+        expect_that!(first.status(), eq(StatusCode::OK));
+        expect_that!(second.status(), eq(StatusCode::OK));
+        expect_that!(active_streams.count(), eq(2));
+
+        let resp = test::call_service(&app, get_content("10.0.0.1:1002")).await;
+        expect_that!(resp.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+
+        // Other clients are not affected
+        let other = test::call_service(&app, get_content("10.0.0.2:1000")).await;
+        expect_that!(other.status(), eq(StatusCode::OK));
+
+        // Finished requests free their slot
+        expect_that!(test::read_body(first).await, eq(&b"video content"[..]));
+        let third = test::call_service(&app, get_content("10.0.0.1:1003")).await;
+        expect_that!(third.status(), eq(StatusCode::OK));
+        let resp = test::call_service(&app, get_content("10.0.0.1:1004")).await;
+        expect_that!(resp.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+        expect_that!(active_streams.count(), eq(3));
+
+        drop((second, other, third));
+        expect_that!(active_streams.count(), eq(0));
+        Ok(())
+    }
+}
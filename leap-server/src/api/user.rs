@@ -1,15 +1,20 @@
 use std::str::FromStr;
 
 use actix_web::{
-    HttpRequest, HttpResponse, Responder, get, post,
+    HttpRequest, HttpResponse, Responder, get, post, put,
     web::{self, Bytes, BytesMut},
 };
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::{io::AsyncReadExt, sync::mpsc::UnboundedSender};
 use tracing::instrument::Instrument;
 
-use leap_api::api::content::meta::get::{GroupedSection, LocalVideoMeta, Progress, VideoStatus};
+use leap_api::api::content::meta::get::{
+    FailureKind, GroupedSection, LocalVideoMeta, Progress, VideoStatus,
+};
 
-use crate::{api::ApiData, downloader::UserCommand};
+use crate::{
+    api::{ApiData, ChannelRef, unknown_channel},
+    downloader::UserCommand,
+};
 
 impl From<crate::db::DownloadStatus> for VideoStatus {
     fn from(value: crate::db::DownloadStatus) -> Self {
@@ -19,7 +24,27 @@ impl From<crate::db::DownloadStatus> for VideoStatus {
                 VideoStatus::Downloading(Progress(completed as f64 / total as f64))
             }
             crate::db::DownloadStatus::Downloaded(_) => VideoStatus::Downloaded,
-            crate::db::DownloadStatus::Failed(msg) => VideoStatus::Failed(msg),
+            crate::db::DownloadStatus::Failed {
+                message,
+                kind,
+                attempts,
+            } => VideoStatus::Failed {
+                message,
+                kind: kind.into(),
+                attempts,
+            },
+        }
+    }
+}
+
+impl From<crate::db::FailureKind> for FailureKind {
+    fn from(value: crate::db::FailureKind) -> Self {
+        match value {
+            crate::db::FailureKind::Unknown => FailureKind::Unknown,
+            crate::db::FailureKind::Network => FailureKind::Network,
+            crate::db::FailureKind::Checksum => FailureKind::Checksum,
+            crate::db::FailureKind::Storage => FailureKind::Storage,
+            crate::db::FailureKind::Interrupted => FailureKind::Interrupted,
         }
     }
 }
@@ -29,9 +54,28 @@ impl From<crate::db::Video> for LocalVideoMeta {
         LocalVideoMeta {
             id: value.id.to_string(),
             name: value.name,
-            size: value.file_size as usize,
+            declared_size: value.file_size as usize,
+            actual_size: value.actual_size.map(|size| size as usize),
             status: value.download_status.into(),
             view_count: value.view_count,
+            duration_secs: value.duration_secs,
+            resolution: value.resolution,
+            pinned: value.pinned,
+        }
+    }
+}
+
+impl From<crate::db::Stats> for leap_api::api::content::summary::get::ContentSummary {
+    fn from(value: crate::db::Stats) -> Self {
+        Self {
+            total: value.total,
+            downloaded: value.downloaded,
+            downloading: value.downloading,
+            failed: value.failed,
+            pending: value.pending,
+            fully_cached: value.is_fully_cached(),
+            disk_bytes_used: None,
+            time_to_cache_secs: None,
         }
     }
 }
@@ -49,22 +93,86 @@ impl From<crate::build_info::BuildInfo> for leap_api::api::version::get::BuildIn
             profile: value.profile.to_string(),
             rustc_version: value.rustc_version.to_string(),
             features: value.features.to_string(),
+            supported_manifest_versions: Some(leap_api::types::VersionRange {
+                min_major: *crate::manifest::SUPPORTED_MAJOR_VERSIONS.start(),
+                max_major: *crate::manifest::SUPPORTED_MAJOR_VERSIONS.end(),
+            }),
+            instance_name: None,
         }
     }
 }
 
 #[tracing::instrument(
+    skip(api_data)
     fields(
         request_id = %uuid::Uuid::new_v4(),
     )
 )]
 #[get("/version")]
-async fn get_version() -> impl Responder {
+async fn get_version(api_data: web::Data<ApiData>) -> impl Responder {
     let info = crate::build_info::get();
-    let info: leap_api::api::version::get::Response = info.into();
+    let mut info: leap_api::api::version::get::Response = info.into();
+    info.instance_name = Some(api_data.config.instance_name());
     HttpResponse::Ok().json(info)
 }
 
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/health")]
+async fn get_health(api_data: web::Data<ApiData>) -> impl Responder {
+    use leap_api::api::health::get::Response;
+
+    let (manifest, unsupported_manifest_version) = match &*api_data.db.current_manifest().await {
+        Some(manifest) => (
+            Some(manifest.name.clone()),
+            (!manifest.version.is_supported()).then(|| manifest.version.to_string()),
+        ),
+        None => (None, None),
+    };
+    let upstream_error = api_data.status.upstream_error();
+    HttpResponse::Ok().json(Response {
+        stale_manifest: manifest.is_some() && upstream_error.is_some(),
+        manifest,
+        upstream_error,
+        unsupported_manifest_version,
+        downloads_paused: api_data.status.downloads_paused(),
+        low_disk_space: api_data.status.low_disk_space(),
+        throttled: api_data.status.throttled(),
+        active_streams: api_data.active_streams.count(),
+        rejected_manifest: api_data.status.rejected_manifest(),
+    })
+}
+
+/// Fills in the size on disk of a downloaded video. The size is only read from the file system the
+/// first time, and cached in the database afterwards so that listing the content doesn't need to
+/// stat every downloaded file.
+async fn with_actual_size(
+    db: &crate::db::Database,
+    mut video: crate::db::Video,
+) -> crate::db::Video {
+    let crate::db::DownloadStatus::Downloaded(filepath) = &video.download_status else {
+        return video;
+    };
+    if video.actual_size.is_some() {
+        return video;
+    }
+
+    match tokio::fs::metadata(filepath).await {
+        Ok(metadata) => {
+            video.actual_size = Some(metadata.len());
+            if let Err(e) = db.set_actual_size(video.id, metadata.len()).await {
+                tracing::warn!("Unable to cache the size of video {}: {e}", video.id);
+            }
+        }
+        Err(e) => tracing::warn!("Unable to read the size of {filepath:?}: {e}"),
+    }
+    video
+}
+
 #[tracing::instrument(
     skip(api_data)
     fields(
@@ -72,12 +180,21 @@ async fn get_version() -> impl Responder {
     )
 )]
 #[get("/content/meta")]
-async fn list_content_metadata(api_data: web::Data<ApiData>) -> impl Responder {
+async fn list_content_metadata(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::content::meta::get::Query>,
+) -> impl Responder {
     use leap_api::api::content::meta::get::Response;
 
-    let sections = match api_data
-        .db
-        .current_manifest_sections()
+    let Some(ChannelRef { db, .. }) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let offset = query.offset.unwrap_or(0);
+    let limit = api_data.config.http_config.page_size(query.limit);
+    let total = db.count_videos().await;
+    let sections = match db
+        // A missing video doesn't prevent listing the rest of the content
+        .current_manifest_sections_page(offset, Some(limit), true)
         .instrument(tracing::info_span!(
             "Querying manifest information from database"
         ))
@@ -90,53 +207,133 @@ async fn list_content_metadata(api_data: web::Data<ApiData>) -> impl Responder {
         }
     };
 
-    let _span =
-        tracing::info_span!("Collecting manifest information as /content/meta response").entered();
+    let mut videos = Vec::with_capacity(sections.len());
+    for section in sections {
+        let mut content = Vec::with_capacity(section.videos.len());
+        for video in section.videos {
+            content.push(with_actual_size(db, video).await.into());
+        }
+        videos.push(GroupedSection {
+            name: section.name,
+            description: section.description,
+            content,
+        });
+    }
 
-    let videos = sections
-        .into_iter()
-        .map(|(name, content)| {
-            let content = content.into_iter().map(|v| v.into()).collect();
-            GroupedSection { name, content }
-        })
-        .collect();
+    HttpResponse::Ok().json(Response {
+        videos,
+        total,
+        limit: Some(limit),
+        offset,
+    })
+}
 
-    HttpResponse::Ok().json(Response { videos })
+/// Formats the content events received by a subscriber as server-sent events. If the subscriber
+/// lags behind and misses some events, a resync event is sent instead, so that the client fetches
+/// the whole content metadata again.
+fn content_event_stream(
+    mut receiver: tokio::sync::broadcast::Receiver<leap_api::types::ContentEvent>,
+) -> impl tokio_stream::Stream<Item = Result<Bytes, anyhow::Error>> {
+    use leap_api::api::content::events::get::RESYNC_EVENT;
+    use tokio::sync::broadcast::error::RecvError;
+
+    async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => match serde_json::to_string(&event) {
+                    Ok(data) => yield Ok(Bytes::from(format!("data: {data}\n\n"))),
+                    Err(e) => {
+                        yield Err(e.into());
+                        break;
+                    }
+                },
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("Content events subscriber missed {missed} events");
+                    yield Ok(Bytes::from(format!("event: {RESYNC_EVENT}\ndata: {{}}\n\n")));
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
 }
 
 #[tracing::instrument(
     skip(api_data)
     fields(
         request_id = %uuid::Uuid::new_v4(),
-        %id
     )
 )]
-#[get("/content/meta/{id}")]
-async fn content_metadata_for_id(
+#[get("/content/events")]
+async fn content_events(
     api_data: web::Data<ApiData>,
-    id: web::Path<String>,
+    query: web::Query<leap_api::api::content::events::get::Query>,
 ) -> impl Responder {
-    use leap_api::api::content::meta::id::get::Response;
-    let Ok(id) = id.into_inner().try_into() else {
-        return HttpResponse::BadRequest().body("Invalid video ID");
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
     };
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(actix_web::http::header::CacheControl(vec![
+            actix_web::http::header::CacheDirective::NoCache,
+        ]))
+        .streaming(Box::pin(content_event_stream(channel.events.subscribe())))
+}
 
-    let meta = match api_data
-        .db
-        .find_video(id)
-        .instrument(tracing::info_span!("Obtaining video information from DB"))
-        .await
-    {
-        Ok(meta) => Some(meta.into()),
-        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => None,
-        Err(err) => {
-            tracing::error!("The database failed with code: {err}");
-            return HttpResponse::InternalServerError()
-                .body(format!("Error querying the video from database: {err}"));
-        }
-    };
+/// Formats the changes in the download status of a single video as server-sent events. The current
+/// status of the video is sent first, and the stream ends after the video is downloaded or its
+/// download fails.
+fn video_progress_stream(
+    db: std::sync::Arc<crate::db::Database>,
+    id: uuid::Uuid,
+    mut receiver: tokio::sync::broadcast::Receiver<leap_api::types::ContentEvent>,
+) -> impl tokio_stream::Stream<Item = Result<Bytes, anyhow::Error>> {
+    use leap_api::types::ContentEvent;
+    use tokio::sync::broadcast::error::RecvError;
 
-    HttpResponse::Ok().json(Response { meta })
+    let event_id = id.to_string();
+    async_stream::stream! {
+        // The status is read from the database initially, and when some events were missed
+        let mut resync = true;
+        loop {
+            let status = if resync {
+                resync = false;
+                match db.find_video(id).await {
+                    Ok(video) => VideoStatus::from(video.download_status),
+                    Err(e) => {
+                        yield Err(e.into());
+                        break;
+                    }
+                }
+            } else {
+                match receiver.recv().await {
+                    Ok(event) if event.id == event_id => event.status,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Progress subscriber of video {id} missed {missed} events");
+                        resync = true;
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            };
+
+            let finished = matches!(status, VideoStatus::Downloaded | VideoStatus::Failed { .. });
+            let event = ContentEvent {
+                id: event_id.clone(),
+                status,
+            };
+            match serde_json::to_string(&event) {
+                Ok(data) => yield Ok(Bytes::from(format!("data: {data}\n\n"))),
+                Err(e) => {
+                    yield Err(e.into());
+                    break;
+                }
+            }
+            if finished {
+                break;
+            }
+        }
+    }
 }
 
 #[tracing::instrument(
@@ -146,140 +343,87 @@ async fn content_metadata_for_id(
         %id
     )
 )]
-#[get("/content/{id}")]
-async fn get_content(
+#[get("/content/{id}/progress/stream")]
+async fn video_progress(
     api_data: web::Data<ApiData>,
     id: web::Path<String>,
-    request: HttpRequest,
+    query: web::Query<leap_api::api::content::id::progress::stream::get::Query>,
 ) -> impl Responder {
-    let Ok(id) = id.into_inner().try_into() else {
-        let msg = "Invalid video ID";
-        tracing::error!(msg);
-        return HttpResponse::BadRequest().body(msg);
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
     };
-    let Ok(crate::db::Video {
-        download_status: crate::db::DownloadStatus::Downloaded(filepath),
-        ..
-    }) = api_data.db.find_video(id).await
-    else {
-        let msg = "Requested video ID is not available";
-        tracing::error!(msg);
-        return HttpResponse::NotFound().body(msg);
+    let Ok(id) = uuid::Uuid::from_str(&id) else {
+        return HttpResponse::BadRequest().body("Invalid video ID");
     };
-
-    let mut file = match tokio::fs::File::open(&filepath).await {
-        Ok(file) => file,
-        Err(e) if e.kind() == tokio::io::ErrorKind::NotFound => {
-            let msg = "Requested video is not on disk";
+    // Subscribing before reading the current status makes sure that no change is missed
+    let receiver = channel.events.subscribe();
+    match channel.db.find_video(id).await {
+        Ok(_) => {}
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
+            let msg = "Requested video ID is not available";
             tracing::error!(msg);
-            return HttpResponse::InternalServerError().body(msg);
+            return HttpResponse::NotFound().body(msg);
         }
-        Err(e) => {
-            let msg = format!("Unexpected error opening file: {e:?}");
-            tracing::error!(msg);
-            return HttpResponse::InternalServerError().body(msg);
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError()
+                .body(format!("Error querying the video from database: {err}"));
         }
-    };
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(actix_web::http::header::CacheControl(vec![
+            actix_web::http::header::CacheDirective::NoCache,
+        ]))
+        .streaming(Box::pin(video_progress_stream(
+            channel.db.clone(),
+            id,
+            receiver,
+        )))
+}
 
-    let meta = match tokio::fs::metadata(&filepath).await {
-        Ok(meta) => meta,
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/content/export")]
+async fn export_content(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::content::export::get::Query>,
+) -> impl Responder {
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let mut videos = match channel.db.stream_all_videos().await {
+        Ok(videos) => videos,
         Err(e) => {
-            let msg = format!("Unexpected error getting metadata for file: {e:?}");
+            let msg = format!("Unexpected error querying content list: {e:?}");
             tracing::error!(msg);
             return HttpResponse::InternalServerError().body(msg);
         }
     };
 
-    let total_length = meta.len();
-
-    let mut req_length = meta.len();
-
-    let range = request
-        .headers()
-        .iter()
-        .find(|(name, _)| *name == "Range")
-        .and_then(|(_, v)| {
-            v.to_str()
-                .inspect_err(|e| tracing::error!("Range header contains non-str value {e}"))
-                .ok()
-                .and_then(|v| {
-                    actix_web::http::header::Range::from_str(v)
-                        .inspect_err(|e| tracing::error!("Invalid range request: {e}"))
-                        .ok()
-                })
-        })
-        .and_then(|v| match v {
-            actix_web::http::header::Range::Bytes(ranges) => {
-                if ranges.len() != 1 {
-                    tracing::error!(
-                        "Only one byte range is currently supported, but got {ranges:?}"
-                    );
-                    None
-                } else {
-                    ranges[0]
-                        .to_satisfiable_range(total_length)
-                        .inspect(|(b, e)| tracing::debug!("Range request: {b}-{e}"))
-                }
-            }
-            actix_web::http::header::Range::Unregistered(b, e) => {
-                tracing::error!("Unsupported unregistered range request: {b}-{e}");
-                None
-            }
-        });
-
-    if let Some((begin, end)) = &range {
-        match file.seek(std::io::SeekFrom::Start(*begin)).await {
-            Ok(v) => v,
-            Err(e) => {
-                let msg = format!("Unexpected seeking file to fulfill range request: {e:?}");
-                tracing::error!(msg);
-                return HttpResponse::InternalServerError().body(msg);
-            }
-        };
-        req_length = end - begin + 1;
-    }
-
-    const RESPONSE_CHUNK_SIZE: u64 = 4096;
-    let s = async_stream::stream! {
-        while req_length > 0 {
-            // Note we are using a new bytes instance each time on purpose. We could have used
-            // `split()` to get the current bytes out and reuse the instance. However, that makes
-            // the bytes turn into a shared instance, which only releases the bytes once all
-            // references to each of the chunks are dropped.
-            //
-            // This would not meet the intent of this code, which is to reduce the memory footprint
-            // of this HTTP method, as some files might be hundreds of megabytes or even gigabytes
-            // in size, and we only have 1 GiB of RAM for the entire platform.
-            let mut bytes = BytesMut::with_capacity(RESPONSE_CHUNK_SIZE as usize);
-            let current_chunk = req_length.min(RESPONSE_CHUNK_SIZE);
-            bytes.resize(current_chunk as usize, 0);
-            let Ok(n) = file.read_exact(&mut bytes).await else {
-                let msg = "Unable to read data from file";
-                tracing::error!(msg);
-                yield Err::<Bytes, anyhow::Error>(anyhow::anyhow!(msg));
-                return;
-            };
-            if n == 0 {
-                return;
+    let lines = async_stream::stream! {
+        while let Some(video) = videos.recv().await {
+            let line = video.map_err(anyhow::Error::from).and_then(|video| {
+                let meta: leap_api::api::content::export::get::Line = video.into();
+                let mut line = serde_json::to_vec(&meta)?;
+                line.push(b'\n');
+                Ok(Bytes::from(line))
+            });
+            if let Err(e) = &line {
+                tracing::error!("Unexpected error exporting content: {e:?}");
             }
-            req_length -= current_chunk;
-            yield Ok::<Bytes, anyhow::Error>(bytes.freeze());
+            yield line;
         }
     };
 
-    if let Some((begin, end)) = range {
-        HttpResponse::PartialContent()
-            .content_type("video/mp4")
-            .append_header((
-                "Content-Range",
-                format!("bytes {begin}-{end}/{total_length}"),
-            ))
-            .streaming(Box::pin(s))
-    } else {
-        HttpResponse::Ok()
-            .content_type("video/mp4")
-            .streaming(Box::pin(s))
-    }
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(Box::pin(lines))
 }
 
 #[tracing::instrument(
@@ -289,57 +433,104 @@ async fn get_content(
         %id
     )
 )]
-#[post("/content/{id}/view")]
-async fn increment_view_cnt(api_data: web::Data<ApiData>, id: web::Path<String>) -> impl Responder {
-    let Ok(id) = id.into_inner().try_into() else {
-        return HttpResponse::BadRequest().body("Invalid video ID");
+#[get("/content/meta/{id}")]
+async fn content_metadata_for_id(
+    api_data: web::Data<ApiData>,
+    id: web::Path<String>,
+    query: web::Query<leap_api::api::content::meta::id::get::Query>,
+) -> impl Responder {
+    use leap_api::api::content::meta::id::get::{InvalidId, Response};
+    let Some(ChannelRef { db, .. }) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
     };
-    let Ok(crate::db::Video {
-        download_status: crate::db::DownloadStatus::Downloaded(_),
-        ..
-    }) = api_data.db.increment_view_count(id).await
-    else {
-        let msg = "Requested video ID is not available";
-        tracing::error!(msg);
-        return HttpResponse::NotFound().body(msg);
+    let id = id.into_inner();
+    let id = match uuid::Uuid::parse_str(&id) {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::info!("Requested metadata of invalid video ID: {e}");
+            return HttpResponse::BadRequest().json(InvalidId {
+                id,
+                message: format!("Invalid video ID: {e}"),
+            });
+        }
+    };
+
+    let meta = match db
+        .find_video(id)
+        .instrument(tracing::info_span!("Obtaining video information from DB"))
+        .await
+    {
+        Ok(meta) => Some(with_actual_size(db, meta).await.into()),
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => None,
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError()
+                .body(format!("Error querying the video from database: {err}"));
+        }
     };
-    HttpResponse::Ok().finish()
+
+    HttpResponse::Ok().json(Response { meta })
 }
 
 #[tracing::instrument(
     skip(api_data)
     fields(
         request_id = %uuid::Uuid::new_v4(),
+        %id
     )
 )]
-#[get("/manifest/latest")]
-async fn get_manifest(api_data: web::Data<ApiData>) -> impl Responder {
-    let manifest = api_data.db.current_manifest().await;
+#[get("/content/{id}/related")]
+async fn related_content(
+    api_data: web::Data<ApiData>,
+    id: web::Path<String>,
+    query: web::Query<leap_api::api::content::id::related::get::Query>,
+) -> impl Responder {
+    use leap_api::api::content::id::related::get::Response;
+    let Some(ChannelRef { db, .. }) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Ok(id) = uuid::Uuid::parse_str(&id) else {
+        return HttpResponse::BadRequest().body("Invalid video ID");
+    };
 
-    let manifest_file = manifest
-        .as_ref()
-        .and_then(|m| serde_json::to_string(m).ok())
-        .unwrap_or_else(|| "".to_string());
+    let related = match db
+        .related_videos(id)
+        .instrument(tracing::info_span!("Querying related videos from database"))
+        .await
+    {
+        Ok(related) => related,
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError().body(format!(
+                "Error querying the related videos from database: {err}"
+            ));
+        }
+    };
 
-    HttpResponse::Ok()
-        .content_type("application/json")
-        .body(manifest_file)
+    let mut videos = Vec::with_capacity(related.len());
+    for video in related {
+        videos.push(with_actual_size(db, video).await.into());
+    }
+    HttpResponse::Ok().json(Response { videos })
 }
 
-#[tracing::instrument(
-    skip(api_data)
-    fields(
-        request_id = %uuid::Uuid::new_v4(),
-    )
-)]
-#[post("/manifest/fetch")]
-async fn fetch_manifest(api_data: web::Data<ApiData>) -> impl Responder {
-    match api_data.cmd_sender.send(UserCommand::FetchManifest) {
-        Ok(()) => HttpResponse::Ok().finish(),
-        Err(e) => {
-            let msg = format!("Unable to handle request: {e}");
-            tracing::error!(msg);
-            HttpResponse::InternalServerError().body(msg)
+impl From<crate::db::VideoEvent> for leap_api::types::VideoEvent {
+    fn from(value: crate::db::VideoEvent) -> Self {
+        use leap_api::types::VideoEventKind;
+        Self {
+            at_millis: value
+                .at
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            kind: match value.kind {
+                crate::db::VideoEventKind::Queued => VideoEventKind::Queued,
+                crate::db::VideoEventKind::Started => VideoEventKind::Started,
+                crate::db::VideoEventKind::Progress => VideoEventKind::Progress,
+                crate::db::VideoEventKind::Failed => VideoEventKind::Failed,
+                crate::db::VideoEventKind::Completed => VideoEventKind::Completed,
+            },
+            message: value.message,
         }
     }
 }
@@ -348,17 +539,2459 @@ async fn fetch_manifest(api_data: web::Data<ApiData>) -> impl Responder {
     skip(api_data)
     fields(
         request_id = %uuid::Uuid::new_v4(),
+        %id
     )
 )]
-#[get("/logfile")]
-async fn log_file(api_data: web::Data<ApiData>) -> impl Responder {
-    let log = match tokio::fs::read_to_string(api_data.config.db_config.logfile()).await {
-        Ok(log) => log,
+#[get("/content/{id}/events")]
+async fn video_events(
+    api_data: web::Data<ApiData>,
+    id: web::Path<String>,
+    query: web::Query<leap_api::api::content::id::events::get::Query>,
+) -> impl Responder {
+    use leap_api::api::content::id::events::get::Response;
+    let Some(ChannelRef { db, .. }) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Ok(id) = uuid::Uuid::parse_str(&id) else {
+        return HttpResponse::BadRequest().body("Invalid video ID");
+    };
+
+    match db.find_video(id).await {
+        Ok(_) => {}
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
+            return HttpResponse::NotFound().body("Unknown video");
+        }
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError()
+                .body(format!("Error querying the video from database: {err}"));
+        }
+    }
+
+    match db
+        .video_events(id)
+        .instrument(tracing::info_span!(
+            "Querying the video events from database"
+        ))
+        .await
+    {
+        Ok(events) => HttpResponse::Ok().json(Response {
+            events: events.into_iter().map(Into::into).collect(),
+        }),
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            HttpResponse::InternalServerError().body(format!(
+                "Error querying the video events from database: {err}"
+            ))
+        }
+    }
+}
+
+/// How long the disk usage of the content is cached for
+const DISK_USAGE_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Returns the disk usage of the content of a channel, walking its content directory at most once
+/// every [`DISK_USAGE_CACHE_TTL`].
+async fn disk_bytes_used(channel: &ChannelRef<'_>) -> Option<u64> {
+    // Concurrent requests wait for the same walk instead of starting their own
+    let mut disk_usage = channel.disk_usage.lock().await;
+    if let Some((computed_at, bytes)) = *disk_usage
+        && computed_at.elapsed() < DISK_USAGE_CACHE_TTL
+    {
+        return Some(bytes);
+    }
+
+    match crate::downloader::content_disk_usage(channel.downloader_config).await {
+        Ok(bytes) => {
+            *disk_usage = Some((std::time::Instant::now(), bytes));
+            Some(bytes)
+        }
+        Err(e) => {
+            tracing::error!("Unable to compute the disk usage of the content: {e}");
+            None
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/content/summary")]
+async fn content_summary(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::content::summary::get::Query>,
+) -> impl Responder {
+    use leap_api::api::content::summary::get::Response;
+
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    match channel.db.stats().await {
+        Ok(stats) => HttpResponse::Ok().json(Response {
+            disk_bytes_used: disk_bytes_used(&channel).await,
+            time_to_cache_secs: channel.status.time_to_cache().map(|time| time.as_secs()),
+            ..stats.into()
+        }),
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            HttpResponse::InternalServerError().body(format!(
+                "Error querying the content summary from database: {err}"
+            ))
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/content/sections")]
+async fn content_sections(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::content::sections::get::Query>,
+) -> impl Responder {
+    use leap_api::api::content::sections::get::{Response, SectionStatus};
+
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Some(manifest) = channel.db.current_manifest().await.clone() else {
+        return HttpResponse::Ok().json(Response { sections: vec![] });
+    };
+
+    let sections = match channel.db.section_status(&manifest).await {
+        Ok(sections) => sections,
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError().body(format!(
+                "Error querying the section status from database: {err}"
+            ));
+        }
+    };
+
+    let sections = sections
+        .into_iter()
+        .map(|(name, status)| SectionStatus {
+            name,
+            downloaded: status.downloaded,
+            total: status.total,
+            ready: status.is_ready(),
+        })
+        .collect();
+
+    HttpResponse::Ok().json(Response { sections })
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+        %name
+    )
+)]
+#[get("/content/sections/{name}/status")]
+async fn section_videos_status(
+    api_data: web::Data<ApiData>,
+    name: web::Path<String>,
+    query: web::Query<leap_api::api::content::sections::name::status::get::Query>,
+) -> impl Responder {
+    use leap_api::api::content::sections::name::status::get::Response;
+
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let videos = match channel.db.section_videos(&name).await {
+        Ok(Some(videos)) => videos,
+        Ok(None) => {
+            return HttpResponse::NotFound().body(format!("Unknown section: {name}"));
+        }
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError().body(format!(
+                "Error querying the videos of the section from database: {err}"
+            ));
+        }
+    };
+
+    let first_playable_id = videos
+        .iter()
+        .find(|video| {
+            matches!(
+                video.download_status,
+                crate::db::DownloadStatus::Downloaded(_)
+            )
+        })
+        .map(|video| video.id.to_string());
+    let mut metas = Vec::with_capacity(videos.len());
+    for video in videos {
+        metas.push(with_actual_size(channel.db, video).await.into());
+    }
+    HttpResponse::Ok().json(Response {
+        videos: metas,
+        first_playable_id,
+    })
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+        %id
+    )
+)]
+#[get(
+    "/content/{id}",
+    wrap = "actix_web::middleware::from_fn(super::connections::limit_content_connections)",
+    wrap = "actix_web::middleware::from_fn(super::connections::close_idle_content)"
+)]
+async fn get_content(
+    api_data: web::Data<ApiData>,
+    id: web::Path<String>,
+    query: web::Query<leap_api::api::content::id::get::Query>,
+    request: HttpRequest,
+) -> impl Responder {
+    use leap_api::api::content::id::get::Unavailable;
+
+    let Some(ChannelRef {
+        db,
+        store,
+        downloader_config,
+        ..
+    }) = api_data.channel(query.channel.as_deref())
+    else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Ok(id) = id.into_inner().try_into() else {
+        let msg = "Invalid video ID";
+        tracing::error!(msg);
+        return HttpResponse::BadRequest().body(msg);
+    };
+    let video = match db.find_video(id).await {
+        Ok(video) => video,
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
+            let msg = "Requested video ID is not available";
+            tracing::error!(msg);
+            return HttpResponse::NotFound().body(msg);
+        }
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError()
+                .body(format!("Error querying the video from database: {err}"));
+        }
+    };
+    let http_config = &api_data.config.http_config;
+    // Videos being downloaded are streamed from their partial file if enabled, as their content is
+    // written
+    let partial_path = match &video.download_status {
+        crate::db::DownloadStatus::InProgress(_) if http_config.serve_partial_content => {
+            Some(crate::downloader::partial_filepath(downloader_config, id))
+                .filter(|path| path.exists())
+        }
+        _ => None,
+    };
+    let (filepath, is_partial) = match (video.download_status, partial_path) {
+        (crate::db::DownloadStatus::Downloaded(filepath), _) => (filepath, false),
+        (_, Some(partial_path)) => (partial_path, true),
+        (status, None) => {
+            // The video is known, but it can't be served yet. Report the download status so that
+            // clients can show the progress instead.
+            tracing::info!("Requested video is not downloaded yet: {status:?}");
+            // Browsers must not keep showing the progress once the content is downloaded
+            return HttpResponse::Conflict()
+                .insert_header(actix_web::http::header::CacheControl(vec![
+                    actix_web::http::header::CacheDirective::NoStore,
+                ]))
+                .json(Unavailable {
+                    status: status.into(),
+                });
+        }
+    };
+
+    let size = if is_partial {
+        // The rest of the content is served as it is downloaded
+        Ok(video.file_size)
+    } else {
+        store.size(&filepath).await
+    };
+    let total_length = match size {
+        Ok(size) => size,
+        Err(e) if e.kind() == tokio::io::ErrorKind::NotFound => {
+            let msg = "Requested video is not on disk";
+            tracing::error!(msg);
+            return HttpResponse::InternalServerError().body(msg);
+        }
+        Err(e) => {
+            let msg = format!("Unexpected error getting the size of the content: {e:?}");
+            tracing::error!(msg);
+            return HttpResponse::InternalServerError().body(msg);
+        }
+    };
+
+    let mut req_length = total_length;
+
+    let range = request
+        .headers()
+        .iter()
+        .find(|(name, _)| *name == "Range")
+        .and_then(|(_, v)| {
+            v.to_str()
+                .inspect_err(|e| tracing::error!("Range header contains non-str value {e}"))
+                .ok()
+                .and_then(|v| {
+                    actix_web::http::header::Range::from_str(v)
+                        .inspect_err(|e| tracing::error!("Invalid range request: {e}"))
+                        .ok()
+                })
+        })
+        .and_then(|v| match v {
+            actix_web::http::header::Range::Bytes(ranges) => {
+                if ranges.len() != 1 {
+                    tracing::error!(
+                        "Only one byte range is currently supported, but got {ranges:?}"
+                    );
+                    None
+                } else {
+                    ranges[0]
+                        .to_satisfiable_range(total_length)
+                        .inspect(|(b, e)| tracing::debug!("Range request: {b}-{e}"))
+                }
+            }
+            actix_web::http::header::Range::Unregistered(b, e) => {
+                tracing::error!("Unsupported unregistered range request: {b}-{e}");
+                None
+            }
+        });
+
+    let offset = range.map_or(0, |(begin, _)| begin);
+    let mut file = match store.open_range(&filepath, offset).await {
+        Ok(file) => file,
+        Err(e) => {
+            let msg = format!("Unexpected error opening the content: {e:?}");
+            tracing::error!(msg);
+            return HttpResponse::InternalServerError().body(msg);
+        }
+    };
+    if let Some((begin, end)) = &range {
+        req_length = end - begin + 1;
+    }
+
+    let chunk_size = api_data.config.downloader_config.io_chunk_bytes as u64;
+    if is_partial {
+        let s = partial_content_stream(
+            db.clone(),
+            id,
+            api_data.partial_writes.clone(),
+            filepath,
+            file,
+            offset,
+            req_length,
+            chunk_size,
+        );
+        let mut response = match range {
+            Some((begin, end)) => {
+                let mut response = HttpResponse::PartialContent();
+                response.append_header((
+                    "Content-Range",
+                    format!("bytes {begin}-{end}/{total_length}"),
+                ));
+                response
+            }
+            None => HttpResponse::Ok(),
+        };
+        // The content is not verified yet, so it must not be cached
+        return response
+            .content_type(http_config.content_type())
+            .insert_header((actix_web::http::header::ACCEPT_RANGES, "bytes"))
+            .insert_header(actix_web::http::header::CacheControl(vec![
+                actix_web::http::header::CacheDirective::NoStore,
+            ]))
+            .no_chunking(req_length)
+            .streaming(Box::pin(s));
+    }
+    let s = async_stream::stream! {
+        while req_length > 0 {
+            // Note we are using a new bytes instance each time on purpose. We could have used
+            // `split()` to get the current bytes out and reuse the instance. However, that makes
+            // the bytes turn into a shared instance, which only releases the bytes once all
+            // references to each of the chunks are dropped.
+            //
+            // This would not meet the intent of this code, which is to reduce the memory footprint
+            // of this HTTP method, as some files might be hundreds of megabytes or even gigabytes
+            // in size, and we only have 1 GiB of RAM for the entire platform.
+            let mut bytes = BytesMut::with_capacity(chunk_size as usize);
+            let current_chunk = req_length.min(chunk_size);
+            bytes.resize(current_chunk as usize, 0);
+            let Ok(n) = file.read_exact(&mut bytes).await else {
+                let msg = "Unable to read data from file";
+                tracing::error!(msg);
+                yield Err::<Bytes, anyhow::Error>(anyhow::anyhow!(msg));
+                return;
+            };
+            if n == 0 {
+                return;
+            }
+            req_length -= current_chunk;
+            yield Ok::<Bytes, anyhow::Error>(bytes.freeze());
+        }
+    };
+
+    let mut response = if let Some((begin, end)) = range {
+        let mut response = HttpResponse::PartialContent();
+        response.append_header((
+            "Content-Range",
+            format!("bytes {begin}-{end}/{total_length}"),
+        ));
+        response
+    } else {
+        HttpResponse::Ok()
+    };
+
+    // The configured headers are validated on startup
+    response.content_type(http_config.content_type());
+    response.insert_header(actix_web::http::header::CacheControl(vec![
+        actix_web::http::header::CacheDirective::Public,
+        actix_web::http::header::CacheDirective::MaxAge(
+            u32::try_from(http_config.content_max_age().as_secs()).unwrap_or(u32::MAX),
+        ),
+    ]));
+    for (name, value) in &http_config.content_headers {
+        response.insert_header((name.as_str(), value.as_str()));
+    }
+    response.streaming(Box::pin(s))
+}
+
+/// How often a video being downloaded is checked for more content while it is streamed
+const PARTIAL_CONTENT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How long a video being downloaded is streamed without getting more content before giving up,
+/// e.g. because its download restarted in another file
+const PARTIAL_CONTENT_STALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Streams `length` bytes of a video being downloaded from its partial file at `path`, starting
+/// at `offset`, waiting for the downloader to write them. Only the content up to the offset
+/// confirmed by the downloader is read, as the content past it may be partially written. The
+/// stream fails if the download stops before writing them.
+#[allow(clippy::too_many_arguments)]
+fn partial_content_stream(
+    db: std::sync::Arc<crate::db::Database>,
+    id: uuid::Uuid,
+    partial_writes: crate::downloader::partial::PartialWrites,
+    path: std::path::PathBuf,
+    mut file: crate::store::ContentReader,
+    mut offset: u64,
+    mut length: u64,
+    chunk_size: u64,
+) -> impl tokio_stream::Stream<Item = Result<Bytes, anyhow::Error>> {
+    async_stream::stream! {
+        let mut last_read = tokio::time::Instant::now();
+        let mut downloaded = false;
+        while length > 0 {
+            // Once downloaded, the whole content is written
+            let readable = if downloaded {
+                length
+            } else {
+                partial_writes
+                    .confirmed_offset(&path)
+                    .unwrap_or(0)
+                    .saturating_sub(offset)
+                    .min(length)
+            };
+            let mut bytes = BytesMut::with_capacity(chunk_size as usize);
+            bytes.resize(readable.min(chunk_size) as usize, 0);
+            let n = if bytes.is_empty() {
+                0
+            } else {
+                match file.read(&mut bytes).await {
+                    Ok(n) => n,
+                    Err(e) => {
+                        tracing::error!("Unable to read data from the partial file: {e}");
+                        yield Err(anyhow::anyhow!("Unable to read data from file"));
+                        return;
+                    }
+                }
+            };
+            if n > 0 {
+                bytes.truncate(n);
+                offset += n as u64;
+                length -= n as u64;
+                last_read = tokio::time::Instant::now();
+                yield Ok(bytes.freeze());
+                continue;
+            }
+
+            // All the content confirmed so far was read. The file keeps its content when it is
+            // moved to its final path, so the rest of the content is read once more after the
+            // download completes.
+            if downloaded || last_read.elapsed() > PARTIAL_CONTENT_STALL_TIMEOUT {
+                yield Err(anyhow::anyhow!("The content of the video is incomplete"));
+                return;
+            }
+            match db.find_video(id).await.map(|video| video.download_status) {
+                Ok(crate::db::DownloadStatus::InProgress(_)) => {
+                    tokio::time::sleep(PARTIAL_CONTENT_POLL_INTERVAL).await;
+                }
+                Ok(crate::db::DownloadStatus::Downloaded(_)) => downloaded = true,
+                Ok(status) => {
+                    tracing::info!("The download of the streamed video stopped: {status:?}");
+                    yield Err(anyhow::anyhow!("The download of the video stopped"));
+                    return;
+                }
+                Err(e) => {
+                    tracing::error!("The database failed with code: {e}");
+                    yield Err(anyhow::anyhow!("Error querying the video from database"));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+        %id
+    )
+)]
+#[get("/content/{id}/available")]
+async fn content_availability(
+    api_data: web::Data<ApiData>,
+    id: web::Path<String>,
+    query: web::Query<leap_api::api::content::id::available::get::Query>,
+) -> impl Responder {
+    use leap_api::api::content::id::available::get::Response;
+
+    let Some(ChannelRef { db, .. }) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Ok(id) = id.into_inner().try_into() else {
+        return HttpResponse::BadRequest().body("Invalid video ID");
+    };
+    let video = match db.find_video(id).await {
+        Ok(video) => video,
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
+            return HttpResponse::NotFound().body("Requested video ID is not available");
+        }
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError()
+                .body(format!("Error querying the video from database: {err}"));
+        }
+    };
+
+    // Only the metadata of the file is read, so that clients can check often
+    let available = match &video.download_status {
+        crate::db::DownloadStatus::Downloaded(filepath) => {
+            match api_data.store.size(filepath).await {
+                Ok(_) => true,
+                Err(e) => {
+                    tracing::error!("Downloaded video {filepath:?} can't be served: {e}");
+                    false
+                }
+            }
+        }
+        _ => false,
+    };
+    HttpResponse::Ok().json(Response {
+        available,
+        status: video.download_status.into(),
+    })
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+        %id
+    )
+)]
+#[post("/content/{id}/view")]
+async fn increment_view_cnt(
+    api_data: web::Data<ApiData>,
+    id: web::Path<String>,
+    query: web::Query<leap_api::api::content::id::view::post::Query>,
+) -> impl Responder {
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Ok(id) = id.into_inner().try_into() else {
+        return HttpResponse::BadRequest().body("Invalid video ID");
+    };
+    let Ok(crate::db::Video {
+        download_status: crate::db::DownloadStatus::Downloaded(_),
+        view_count,
+        ..
+    }) = channel.db.increment_view_count(id).await
+    else {
+        let msg = "Requested video ID is not available";
+        tracing::error!(msg);
+        return HttpResponse::NotFound().body(msg);
+    };
+    HttpResponse::Ok().json(leap_api::api::content::id::view::post::Response { view_count })
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/manifest/latest")]
+async fn get_manifest(api_data: web::Data<ApiData>) -> impl Responder {
+    let manifest = api_data.db.current_manifest().await;
+
+    // Nothing is served until a manifest is fetched from the upstream
+    let Some(manifest_file) = manifest
+        .as_ref()
+        .and_then(|m| serde_json::to_string(m).ok())
+    else {
+        return HttpResponse::NoContent().finish();
+    };
+
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(manifest_file)
+}
+
+#[tracing::instrument(
+    skip(api_data, request)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/manifest/export")]
+async fn export_manifest(api_data: web::Data<ApiData>, request: HttpRequest) -> impl Responder {
+    let Some(manifest) = api_data.db.current_manifest().await.clone() else {
+        return HttpResponse::NotFound().body("No manifest is being served");
+    };
+
+    // The content is downloaded from this server, at the address used by the client
+    let connection_info = request.connection_info();
+    let base_url = format!(
+        "{}://{}{}",
+        connection_info.scheme(),
+        connection_info.host(),
+        api_data.config.http_config.base_path()
+    );
+    match manifest.with_content_base_url(&base_url) {
+        Ok(manifest) => HttpResponse::Ok().json(manifest),
+        Err(e) => {
+            let msg = format!("Unable to export the manifest for {base_url}: {e}");
+            tracing::error!(msg);
+            HttpResponse::InternalServerError().body(msg)
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[post("/manifest/fetch")]
+async fn fetch_manifest(api_data: web::Data<ApiData>) -> impl Responder {
+    // Debounce the fetches, so that impatient users don't queue many of them
+    let cooldown = api_data.config.downloader_config.manual_fetch_cooldown;
+    {
+        let now = std::time::Instant::now();
+        let mut last_manual_fetch = api_data
+            .last_manual_fetch
+            .lock()
+            .expect("Poisoned manual fetch lock");
+        if let Some(elapsed) = last_manual_fetch.map(|last| now - last)
+            && elapsed < cooldown
+        {
+            let retry_after = (cooldown - elapsed).as_secs_f64().ceil() as u64;
+            tracing::info!("Rejecting manifest fetch, retry after {retry_after} seconds");
+            return HttpResponse::TooManyRequests()
+                .insert_header((
+                    actix_web::http::header::RETRY_AFTER,
+                    retry_after.to_string(),
+                ))
+                .body("The manifest was fetched recently, please try again later");
+        }
+        *last_manual_fetch = Some(now);
+    }
+
+    match api_data.cmd_sender.send(UserCommand::FetchManifest) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(e) => {
+            let msg = format!("Unable to handle request: {e}");
+            tracing::error!(msg);
+            HttpResponse::InternalServerError().body(msg)
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[post("/downloader/pause")]
+async fn pause_downloader(api_data: web::Data<ApiData>) -> impl Responder {
+    send_command(&api_data.cmd_sender, UserCommand::Pause)
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[post("/downloader/resume")]
+async fn resume_downloader(api_data: web::Data<ApiData>) -> impl Responder {
+    send_command(&api_data.cmd_sender, UserCommand::Resume)
+}
+
+/// Sends a command to a downloader, which handles it asynchronously.
+fn send_command(cmd_sender: &UnboundedSender<UserCommand>, command: UserCommand) -> HttpResponse {
+    match cmd_sender.send(command) {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => {
+            let msg = format!("Unable to handle request: {e}");
+            tracing::error!(msg);
+            HttpResponse::InternalServerError().body(msg)
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[post("/content/local/redownload")]
+async fn redownload_content(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::content::local::redownload::post::Query>,
+) -> impl Responder {
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Ok(id) = uuid::Uuid::from_str(&query.id) else {
+        return HttpResponse::BadRequest().body("Invalid video ID");
+    };
+    match channel.db.find_video(id).await {
+        Ok(_) => {}
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
+            let msg = "Requested video ID is not available";
+            tracing::error!(msg);
+            return HttpResponse::NotFound().body(msg);
+        }
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            return HttpResponse::InternalServerError()
+                .body(format!("Error querying the video from database: {err}"));
+        }
+    }
+
+    // The downloader owns the content files, so it is in charge of deleting and downloading them
+    send_command(channel.cmd_sender, UserCommand::Redownload(id))
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[put("/content/local/pin")]
+async fn pin_content(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::content::local::pin::put::Query>,
+) -> impl Responder {
+    let Some(channel) = api_data.channel(query.channel.as_deref()) else {
+        return unknown_channel(query.channel.as_deref());
+    };
+    let Ok(id) = uuid::Uuid::from_str(&query.id) else {
+        return HttpResponse::BadRequest().body("Invalid video ID");
+    };
+    match channel.db.set_pinned(id, query.pinned).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
+            let msg = "Requested video ID is not available";
+            tracing::error!(msg);
+            HttpResponse::NotFound().body(msg)
+        }
+        Err(err) => {
+            tracing::error!("The database failed with code: {err}");
+            HttpResponse::InternalServerError()
+                .body(format!("Error updating the video in database: {err}"))
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip(api_data)
+    fields(
+        request_id = %uuid::Uuid::new_v4(),
+    )
+)]
+#[get("/logfile")]
+async fn log_file(
+    api_data: web::Data<ApiData>,
+    query: web::Query<leap_api::api::logfile::get::Query>,
+) -> impl Responder {
+    use tokio::io::AsyncBufReadExt;
+
+    let min_level = match query.level.as_deref() {
+        Some(level) => match parse_log_level(level) {
+            Some(level) => Some(level),
+            None => {
+                let msg = format!("Invalid log level: {level}");
+                tracing::error!(msg);
+                return HttpResponse::BadRequest().body(msg);
+            }
+        },
+        None => None,
+    };
+    let file = match tokio::fs::File::open(api_data.config.db_config.logfile()).await {
+        Ok(file) => file,
         Err(e) => {
             let msg = format!("Unexpected error opening file: {e:?}");
             tracing::error!(msg);
             return HttpResponse::InternalServerError().body(msg);
         }
     };
-    HttpResponse::Ok().body(log)
+
+    let leap_api::api::logfile::get::Query { tail, contains, .. } = query.into_inner();
+    let matches = move |line: &str| {
+        contains.as_deref().is_none_or(|text| line.contains(text))
+            && min_level.is_none_or(|min_level| {
+                log_line_level(line).is_some_and(|level| level >= min_level)
+            })
+    };
+
+    // The matching lines are streamed as the logfile is read, so that it is never loaded in
+    // memory. When tailing, only the last matching lines are kept until the end of the file.
+    let lines = async_stream::stream! {
+        let mut lines = tokio::io::BufReader::new(file).lines();
+        let mut last_lines = std::collections::VecDeque::new();
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Unexpected error reading the logfile: {e:?}");
+                    yield Err(anyhow::anyhow!("Unable to read the logfile"));
+                    return;
+                }
+            };
+            if !matches(&line) {
+                continue;
+            }
+            let line = Bytes::from(line + "\n");
+            match tail {
+                Some(tail) => {
+                    last_lines.push_back(line);
+                    if last_lines.len() > tail {
+                        last_lines.pop_front();
+                    }
+                }
+                None => yield Ok(line),
+            }
+        }
+        for line in last_lines {
+            yield Ok(line);
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(Box::pin(lines))
+}
+
+/// Returns the numeric value of a log level, as written in the logfile, from either its name or
+/// its value.
+fn parse_log_level(level: &str) -> Option<u64> {
+    match level.to_ascii_lowercase().as_str() {
+        "trace" => Some(10),
+        "debug" => Some(20),
+        "info" => Some(30),
+        "warn" => Some(40),
+        "error" => Some(50),
+        "fatal" => Some(60),
+        value => value.parse().ok().filter(|value| (10..=60).contains(value)),
+    }
+}
+
+/// Returns the numeric level of a line of the logfile, or `None` if it isn't a log entry.
+fn log_line_level(line: &str) -> Option<u64> {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()?
+        .get("level")?
+        .as_u64()
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use actix_web::{App, http::StatusCode, test};
+    use googletest::prelude::*;
+    use leap_api::api::content::id::get::Unavailable;
+
+    use super::*;
+    use crate::api::test::create_api_data;
+
+    const VIDEO_ID: &str = "bf978778-1c5d-44b3-b2c1-1cc253563799";
+
+    async fn get_content_response(
+        api_data: web::Data<ApiData>,
+        id: &str,
+    ) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/content/{id}"))
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_pending() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 100).await.or_fail()?;
+
+        let resp = get_content_response(api_data, VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::CONFLICT));
+        let body: Unavailable = test::read_body_json(resp).await;
+        expect_that!(body.status, eq(&VideoStatus::Pending));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_downloading() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 100).await.or_fail()?;
+        api_data
+            .db
+            .update_download_progress(id, 25)
+            .await
+            .or_fail()?;
+
+        let resp = get_content_response(api_data, VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::CONFLICT));
+        let body: Unavailable = test::read_body_json(resp).await;
+        expect_that!(body.status, eq(&VideoStatus::Downloading(Progress(0.25))));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_unknown() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+
+        let resp = get_content_response(api_data, VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_content_event_stream_lagging_receiver() -> googletest::Result<()> {
+        use leap_api::types::ContentEvent;
+        use tokio_stream::StreamExt;
+
+        let (sender, receiver) = tokio::sync::broadcast::channel(2);
+        let stream = content_event_stream(receiver);
+        let mut stream = std::pin::pin!(stream);
+
+        // The receiver does not keep up with the sender, which does not block
+        for progress in [0.25, 0.5, 0.75] {
+            sender
+                .send(ContentEvent {
+                    id: VIDEO_ID.to_string(),
+                    status: VideoStatus::Downloading(Progress(progress)),
+                })
+                .or_fail()?;
+        }
+        drop(sender);
+
+        let mut messages = vec![];
+        while let Some(message) = stream.next().await {
+            messages.push(String::from_utf8(message.or_fail()?.to_vec()).or_fail()?);
+        }
+
+        let event_data = |progress| {
+            format!("data: {{\"id\":\"{VIDEO_ID}\",\"status\":{{\"Downloading\":{progress}}}}}\n\n")
+        };
+        expect_that!(
+            messages,
+            eq(&vec![
+                "event: resync\ndata: {}\n\n".to_string(),
+                event_data(0.5),
+                event_data(0.75),
+            ])
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_video_progress_stream_filters_other_videos() -> googletest::Result<()> {
+        use leap_api::types::ContentEvent;
+        use tokio_stream::StreamExt;
+
+        const OTHER_VIDEO_ID: &str = "5eb9e089-79cf-478d-9121-9ca3e7bb1d4a";
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 100).await.or_fail()?;
+
+        let (sender, receiver) = tokio::sync::broadcast::channel(16);
+        let stream = video_progress_stream(api_data.db.clone(), id, receiver);
+        let event = |id: &str, status| ContentEvent {
+            id: id.to_string(),
+            status,
+        };
+        for sent in [
+            event(OTHER_VIDEO_ID, VideoStatus::Downloading(Progress(0.5))),
+            event(VIDEO_ID, VideoStatus::Downloading(Progress(0.25))),
+            event(OTHER_VIDEO_ID, VideoStatus::Downloaded),
+            event(VIDEO_ID, VideoStatus::Downloaded),
+            // Not received, the stream ends once the video is downloaded
+            event(VIDEO_ID, VideoStatus::Pending),
+        ] {
+            sender.send(sent).or_fail()?;
+        }
+
+        // The sender is still alive, so the stream has to end by itself
+        let messages = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            stream
+                .map(|message| String::from_utf8(message.unwrap().to_vec()).unwrap())
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .or_fail()?;
+
+        let event_data =
+            |status: &str| format!("data: {{\"id\":\"{VIDEO_ID}\",\"status\":{status}}}\n\n");
+        expect_that!(
+            messages,
+            eq(&vec![
+                event_data("\"Pending\""),
+                event_data("{\"Downloading\":0.25}"),
+                event_data("\"Downloaded\""),
+            ])
+        );
+        drop(sender);
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_export_content() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        for (index, name) in ["first", "second", "third"].into_iter().enumerate() {
+            api_data
+                .db
+                .insert_video(uuid::Uuid::new_v4(), name, 100 * index as u64)
+                .await
+                .or_fail()?;
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data.clone())
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/api/content/export")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+
+        let body = test::read_body(resp).await;
+        let lines = std::str::from_utf8(&body)
+            .or_fail()?
+            .lines()
+            .map(serde_json::from_str::<LocalVideoMeta>)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .or_fail()?;
+        expect_that!(
+            lines.len(),
+            eq(api_data.db.list_all_videos().await.or_fail()?.len())
+        );
+        expect_that!(
+            lines,
+            unordered_elements_are![
+                field!(&LocalVideoMeta.name, ref eq("first")),
+                field!(&LocalVideoMeta.name, ref eq("second")),
+                field!(&LocalVideoMeta.name, ref eq("third")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_cache_control() -> googletest::Result<()> {
+        use actix_web::http::header;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.http_config.content_max_age_secs = Some(600);
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 5).await.or_fail()?;
+        let cache_control = |resp: &actix_web::dev::ServiceResponse| {
+            resp.headers()
+                .get(header::CACHE_CONTROL)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+
+        // The progress reported while downloading is not cached
+        let resp = get_content_response(api_data.clone(), VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::CONFLICT));
+        expect_that!(cache_control(&resp), some(eq("no-store")));
+
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"video").or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+        let resp = get_content_response(api_data, VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        expect_that!(cache_control(&resp), some(eq("public, max-age=600")));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_configured_headers() -> googletest::Result<()> {
+        use actix_web::http::header;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.http_config.content_type = Some("application/octet-stream".to_string());
+        config.http_config.content_headers = [
+            ("Cache-Control", "max-age=3600"),
+            ("X-Content-Type-Options", "nosniff"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        config.http_config.validate().or_fail()?;
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"video").or_fail()?;
+        api_data.db.insert_video(id, "video", 5).await.or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+
+        let resp = get_content_response(api_data, VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        let header_value = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        expect_that!(
+            header_value(header::CONTENT_TYPE),
+            some(eq("application/octet-stream"))
+        );
+        expect_that!(
+            header_value(header::CACHE_CONTROL),
+            some(eq("max-age=3600"))
+        );
+        expect_that!(
+            header_value(header::X_CONTENT_TYPE_OPTIONS),
+            some(eq("nosniff"))
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_custom_chunk_size() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.downloader_config.io_chunk_bytes = *crate::cfg::IO_CHUNK_BYTES_RANGE.start();
+        config.downloader_config.validate().or_fail()?;
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+
+        let content: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, &content).or_fail()?;
+        api_data
+            .db
+            .insert_video(id, "video", content.len() as u64)
+            .await
+            .or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data.clone())
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/content/{VIDEO_ID}"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        expect_that!(test::read_body(resp).await.to_vec(), eq(&content));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/content/{VIDEO_ID}"))
+            .insert_header(("Range", "bytes=4000-9000"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::PARTIAL_CONTENT));
+        expect_that!(
+            test::read_body(resp).await.to_vec(),
+            eq(&content[4000..=9000])
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_invalid_content_headers() -> googletest::Result<()> {
+        let mut config = crate::cfg::HttpServerConfig::default();
+        config
+            .content_headers
+            .insert("Invalid Header".to_string(), "value".to_string());
+        expect_true!(config.validate().is_err());
+
+        let mut config = crate::cfg::HttpServerConfig::default();
+        config
+            .content_headers
+            .insert("X-Header".to_string(), "invalid\nvalue".to_string());
+        expect_true!(config.validate().is_err());
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_fetch_manifest_cooldown() -> googletest::Result<()> {
+        use actix_web::http::header;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.downloader_config.manual_fetch_cooldown = std::time::Duration::from_secs(30);
+        let (api_data, mut cmd_receiver) =
+            crate::api::test::create_api_data_with_receiver(config).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+
+        let fetch = || {
+            test::TestRequest::post()
+                .uri("/api/manifest/fetch")
+                .to_request()
+        };
+        let resp = test::call_service(&app, fetch()).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+
+        let resp = test::call_service(&app, fetch()).await;
+        expect_that!(resp.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+        expect_that!(
+            resp.headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok()),
+            some(eq("30"))
+        );
+
+        // Only the first fetch reached the downloader
+        expect_true!(matches!(
+            cmd_receiver.try_recv(),
+            Ok(UserCommand::FetchManifest)
+        ));
+        expect_true!(cmd_receiver.try_recv().is_err());
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_pause_and_resume_downloader() -> googletest::Result<()> {
+        use leap_api::api::health::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let config = crate::api::test::test_config(tempdir.path());
+        let (api_data, mut cmd_receiver) =
+            crate::api::test::create_api_data_with_receiver(config).await;
+        let status = api_data.status.clone();
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+
+        for (uri, command) in [
+            ("/api/downloader/pause", UserCommand::Pause),
+            ("/api/downloader/resume", UserCommand::Resume),
+        ] {
+            let req = test::TestRequest::post().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            expect_that!(resp.status(), eq(StatusCode::ACCEPTED));
+            expect_that!(cmd_receiver.try_recv(), ok(eq(&command)));
+        }
+
+        // The health reports the state set by the downloader
+        status.set_downloads_paused(true);
+        let req = test::TestRequest::get().uri("/api/health").to_request();
+        let body: Response = test::call_and_read_body_json(&app, req).await;
+        expect_true!(body.downloads_paused);
+
+        status.set_low_disk_space(true);
+        let req = test::TestRequest::get().uri("/api/health").to_request();
+        let body: Response = test::call_and_read_body_json(&app, req).await;
+        expect_true!(body.low_disk_space);
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_content_metadata_actual_size() -> googletest::Result<()> {
+        use leap_api::api::content::meta::id::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"truncated").or_fail()?;
+        api_data.db.insert_video(id, "video", 100).await.or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let get_meta = || {
+            test::TestRequest::get()
+                .uri(&format!("/api/content/meta/{VIDEO_ID}"))
+                .to_request()
+        };
+
+        let body: Response = test::call_and_read_body_json(&app, get_meta()).await;
+        expect_that!(
+            body.meta,
+            some(matches_pattern!(LocalVideoMeta {
+                declared_size: eq(&100),
+                actual_size: some(eq(&9)),
+                ..
+            }))
+        );
+
+        // The size is cached, so it is not read from disk again
+        std::fs::write(&filepath, b"video").or_fail()?;
+        let body: Response = test::call_and_read_body_json(&app, get_meta()).await;
+        expect_that!(
+            body.meta,
+            some(matches_pattern!(LocalVideoMeta {
+                actual_size: some(eq(&9)),
+                ..
+            }))
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_content_metadata_failure_message() -> googletest::Result<()> {
+        use leap_api::api::content::meta::id::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 100).await.or_fail()?;
+        api_data
+            .db
+            .set_download_failed(
+                id,
+                "Connection reset by peer",
+                crate::db::FailureKind::Network,
+            )
+            .await
+            .or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let expected_status = VideoStatus::Failed {
+            message: "Connection reset by peer".to_string(),
+            kind: FailureKind::Network,
+            attempts: 1,
+        };
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/content/meta/{VIDEO_ID}"))
+            .to_request();
+        let body: Response = test::call_and_read_body_json(&app, req).await;
+        expect_that!(
+            body.meta,
+            some(field!(&LocalVideoMeta.status, ref eq(&expected_status)))
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/api/content/export")
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let line: LocalVideoMeta = serde_json::from_slice(&body).or_fail()?;
+        expect_that!(line.status, eq(&expected_status));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_health_stale_manifest() -> googletest::Result<()> {
+        use leap_api::api::health::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let db = std::sync::Arc::clone(&api_data.db);
+        let status = std::sync::Arc::clone(&api_data.status);
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let health_request = || test::TestRequest::get().uri("/api/health").to_request();
+
+        // Without a manifest to serve, upstream errors don't make the content stale
+        status.set_upstream_error(Some("Error fetching manifest".to_string()));
+        let body: Response = test::call_and_read_body_json(&app, health_request()).await;
+        expect_that!(
+            body,
+            eq(&Response {
+                manifest: None,
+                stale_manifest: false,
+                upstream_error: Some("Error fetching manifest".to_string()),
+                unsupported_manifest_version: None,
+                downloads_paused: false,
+                low_disk_space: false,
+                throttled: None,
+                active_streams: 0,
+                rejected_manifest: None,
+            })
+        );
+
+        db.publish_manifest(&crate::manifest::ManifestFile {
+            name: "manifest".to_string(),
+            date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
+            version: crate::manifest::Version {
+                major: 1,
+                minor: 0,
+                revision: 0,
+            },
+            sections: vec![],
+        })
+        .await;
+        let body: Response = test::call_and_read_body_json(&app, health_request()).await;
+        expect_that!(body.manifest, some(eq("manifest")));
+        expect_true!(body.stale_manifest);
+
+        status.set_upstream_error(None);
+        let body: Response = test::call_and_read_body_json(&app, health_request()).await;
+        expect_that!(
+            body,
+            eq(&Response {
+                manifest: Some("manifest".to_string()),
+                stale_manifest: false,
+                upstream_error: None,
+                unsupported_manifest_version: None,
+                downloads_paused: false,
+                low_disk_space: false,
+                throttled: None,
+                active_streams: 0,
+                rejected_manifest: None,
+            })
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_content_summary_disk_usage() -> googletest::Result<()> {
+        use leap_api::api::content::summary::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let content_path = api_data.config.downloader_config.content_path.clone();
+        std::fs::create_dir_all(content_path.join(".tmp")).or_fail()?;
+        std::fs::write(content_path.join("video.mp4"), [0; 100]).or_fail()?;
+        std::fs::write(content_path.join(".tmp").join("partial.mp4"), [0; 20]).or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let get_summary = || {
+            test::TestRequest::get()
+                .uri("/api/content/summary")
+                .to_request()
+        };
+
+        let body: Response = test::call_and_read_body_json(&app, get_summary()).await;
+        expect_that!(body.disk_bytes_used, some(eq(120)));
+
+        // The usage is cached, so new files are not reported right away
+        std::fs::write(content_path.join("orphan.mp4"), [0; 5]).or_fail()?;
+        let body: Response = test::call_and_read_body_json(&app, get_summary()).await;
+        expect_that!(body.disk_bytes_used, some(eq(120)));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_health_unsupported_manifest_version() -> googletest::Result<()> {
+        use leap_api::api::health::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        api_data
+            .db
+            .publish_manifest(&crate::manifest::ManifestFile {
+                name: "manifest".to_string(),
+                date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+                published_at: None,
+                adopt_after: None,
+                min_vds_version: None,
+                version: crate::manifest::Version {
+                    major: crate::manifest::SUPPORTED_MAJOR_VERSIONS.end() + 1,
+                    minor: 0,
+                    revision: 0,
+                },
+                sections: vec![],
+            })
+            .await;
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/api/health").to_request();
+        let body: Response = test::call_and_read_body_json(&app, req).await;
+        expect_that!(body.unsupported_manifest_version, some(eq("v3.0.0")));
+        expect_false!(body.stale_manifest);
+
+        let req = test::TestRequest::get().uri("/api/version").to_request();
+        let body: leap_api::api::version::get::Response =
+            test::call_and_read_body_json(&app, req).await;
+        expect_that!(
+            body.supported_manifest_versions,
+            some(eq(leap_api::types::VersionRange {
+                min_major: 1,
+                max_major: 2,
+            }))
+        );
+        expect_that!(body.instance_name, some(eq("test-instance")));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_export_manifest() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let manifest = crate::manifest::ManifestFile {
+            name: "manifest".to_string(),
+            date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
+            version: crate::manifest::Version {
+                major: 2,
+                minor: 0,
+                revision: 0,
+            },
+            sections: vec![crate::manifest::Section {
+                name: "Section".to_string(),
+                description: None,
+                content: vec![crate::manifest::Video {
+                    name: "video".to_string(),
+                    id,
+                    uri: "s3://bucket/video.mp4".parse().or_fail()?,
+                    checksum: "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                        .try_into()
+                        .or_fail()?,
+                    file_size: 13,
+                    duration_secs: None,
+                    resolution: None,
+                }],
+            }],
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data.clone())
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let export = || {
+            test::TestRequest::get()
+                .uri("/api/manifest/export")
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, export()).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"video content").or_fail()?;
+        api_data.db.insert_video(id, "video", 13).await.or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+        api_data.db.publish_manifest(&manifest).await;
+
+        let body = test::call_and_read_body(&app, export()).await;
+        let exported = crate::manifest::validate_manifest_bytes(&body, true).or_fail()?;
+        let uri = &exported.sections[0].content[0].uri;
+        expect_that!(
+            uri.to_string(),
+            eq(&format!("http://localhost:8080/api/content/{VIDEO_ID}"))
+        );
+        // Everything else is preserved
+        let mut expected = manifest.clone();
+        expected.sections[0].content[0].uri = uri.clone();
+        expect_that!(exported, eq(&expected));
+
+        // The URIs point at the content served by this server
+        let req = test::TestRequest::get().uri(uri.path()).to_request();
+        let content = test::call_and_read_body(&app, req).await;
+        expect_that!(content, eq(&b"video content"[..]));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_manifest() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data.clone())
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let manifest_request = || {
+            test::TestRequest::get()
+                .uri("/api/manifest/latest")
+                .to_request()
+        };
+
+        // No manifest is served before one is fetched
+        let resp = test::call_service(&app, manifest_request()).await;
+        expect_that!(resp.status(), eq(StatusCode::NO_CONTENT));
+        expect_that!(test::read_body(resp).await.len(), eq(0));
+
+        let manifest = crate::manifest::ManifestFile {
+            name: "manifest".to_string(),
+            date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
+            version: crate::manifest::Version {
+                major: 2,
+                minor: 0,
+                revision: 0,
+            },
+            sections: vec![],
+        };
+        api_data.db.publish_manifest(&manifest).await;
+
+        let resp = test::call_service(&app, manifest_request()).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        let body = test::read_body(resp).await;
+        let served: crate::manifest::ManifestFile = serde_json::from_slice(&body).or_fail()?;
+        expect_that!(served, eq(&manifest));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_section_videos_status() -> googletest::Result<()> {
+        use leap_api::api::content::sections::name::status::get::Response;
+
+        const DOWNLOADED_ID: &str = "0ff3a4d4-4a11-4b0c-a46e-2dcbca8b2a55";
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let video = |id: &str, name: &str| -> googletest::Result<crate::manifest::Video> {
+            Ok(crate::manifest::Video {
+                name: name.to_string(),
+                id: uuid::Uuid::from_str(id).or_fail()?,
+                uri: format!("s3://bucket/{name}.mp4").parse().or_fail()?,
+                checksum: "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                    .try_into()
+                    .or_fail()?,
+                file_size: 13,
+                duration_secs: None,
+                resolution: None,
+            })
+        };
+        let manifest = crate::manifest::ManifestFile {
+            name: "manifest".to_string(),
+            date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
+            version: crate::manifest::Version {
+                major: 2,
+                minor: 0,
+                revision: 0,
+            },
+            sections: vec![crate::manifest::Section {
+                name: "Algebra".to_string(),
+                description: None,
+                content: vec![
+                    video(VIDEO_ID, "pending")?,
+                    video(DOWNLOADED_ID, "downloaded")?,
+                ],
+            }],
+        };
+        for video in &manifest.sections[0].content {
+            api_data
+                .db
+                .insert_video(video.id, &video.name, 13)
+                .await
+                .or_fail()?;
+        }
+        // Only the second video of the section is downloaded
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"video content").or_fail()?;
+        api_data
+            .db
+            .set_downloaded(uuid::Uuid::from_str(DOWNLOADED_ID).or_fail()?, &filepath)
+            .await
+            .or_fail()?;
+        api_data.db.publish_manifest(&manifest).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/content/sections/Algebra/status")
+            .to_request();
+        let body: Response = test::call_and_read_body_json(&app, req).await;
+        expect_that!(body.first_playable_id, some(eq(DOWNLOADED_ID)));
+        expect_that!(
+            body.videos,
+            elements_are![
+                all!(
+                    field!(&LocalVideoMeta.id, ref eq(VIDEO_ID)),
+                    field!(&LocalVideoMeta.status, ref eq(&VideoStatus::Pending))
+                ),
+                all!(
+                    field!(&LocalVideoMeta.id, ref eq(DOWNLOADED_ID)),
+                    field!(&LocalVideoMeta.status, ref eq(&VideoStatus::Downloaded))
+                ),
+            ]
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/api/content/sections/Geometry/status")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_content_metadata_invalid_id() -> googletest::Result<()> {
+        use leap_api::api::content::meta::id::get::InvalidId;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let app = test::init_service(
+            App::new()
+                .app_data(create_api_data(tempdir.path()).await)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/content/meta/not-a-uuid")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::BAD_REQUEST));
+        let body: InvalidId = test::read_body_json(resp).await;
+        expect_that!(
+            body,
+            matches_pattern!(InvalidId {
+                id: eq("not-a-uuid"),
+                message: starts_with("Invalid video ID"),
+            })
+        );
+
+        // Valid ids of unknown videos are not an error
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/content/meta/{VIDEO_ID}"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_video_status_failed_serde() -> googletest::Result<()> {
+        let status = VideoStatus::Failed {
+            message: "Bad checksum".to_string(),
+            kind: FailureKind::Checksum,
+            attempts: 3,
+        };
+        let json = serde_json::to_value(&status).or_fail()?;
+        expect_that!(
+            json,
+            eq(&serde_json::json!({
+                "Failed": { "message": "Bad checksum", "kind": "Checksum", "attempts": 3 }
+            }))
+        );
+        expect_that!(serde_json::from_value::<VideoStatus>(json), ok(eq(&status)));
+
+        // Older servers only reported the error message
+        expect_that!(
+            serde_json::from_value::<VideoStatus>(serde_json::json!({ "Failed": "Bad checksum" })),
+            ok(eq(&VideoStatus::Failed {
+                message: "Bad checksum".to_string(),
+                kind: FailureKind::Unknown,
+                attempts: 0,
+            }))
+        );
+        expect_that!(
+            serde_json::from_value::<VideoStatus>(serde_json::json!({
+                "Failed": { "message": "Bad checksum" }
+            })),
+            ok(eq(&VideoStatus::Failed {
+                message: "Bad checksum".to_string(),
+                kind: FailureKind::Unknown,
+                attempts: 0,
+            }))
+        );
+
+        // The other statuses keep their representation
+        expect_that!(
+            serde_json::from_value::<VideoStatus>(serde_json::json!({ "Downloading": 0.5 })),
+            ok(eq(&VideoStatus::Downloading(Progress(0.5))))
+        );
+        expect_that!(
+            serde_json::from_value::<VideoStatus>(serde_json::json!("Downloaded")),
+            ok(eq(&VideoStatus::Downloaded))
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_increment_view_count() -> googletest::Result<()> {
+        use leap_api::api::content::id::view::post::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 5).await.or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data.clone())
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let view = || {
+            test::TestRequest::post()
+                .uri(&format!("/api/content/{VIDEO_ID}/view"))
+                .to_request()
+        };
+
+        // Only downloaded videos can be viewed
+        let resp = test::call_service(&app, view()).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"video").or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+        let initial = api_data.db.find_video(id).await.or_fail()?.view_count;
+
+        let resp: Response = test::call_and_read_body_json(&app, view()).await;
+        expect_that!(resp.view_count, eq(initial + 1));
+        let resp: Response = test::call_and_read_body_json(&app, view()).await;
+        expect_that!(resp.view_count, eq(initial + 2));
+        expect_that!(
+            api_data.db.find_video(id).await.or_fail()?.view_count,
+            eq(initial + 2)
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_content_availability() -> googletest::Result<()> {
+        use leap_api::api::content::id::available::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 5).await.or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data.clone())
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let availability = |id: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/content/{id}/available"))
+                .to_request()
+        };
+
+        let body: Response = test::call_and_read_body_json(&app, availability(VIDEO_ID)).await;
+        expect_that!(
+            body,
+            eq(&Response {
+                available: false,
+                status: VideoStatus::Pending,
+            })
+        );
+
+        let filepath = tempdir.path().join("video.mp4");
+        std::fs::write(&filepath, b"video").or_fail()?;
+        api_data.db.set_downloaded(id, &filepath).await.or_fail()?;
+        let body: Response = test::call_and_read_body_json(&app, availability(VIDEO_ID)).await;
+        expect_that!(
+            body,
+            eq(&Response {
+                available: true,
+                status: VideoStatus::Downloaded,
+            })
+        );
+
+        // Downloaded videos missing from disk can't be served
+        std::fs::remove_file(&filepath).or_fail()?;
+        let body: Response = test::call_and_read_body_json(&app, availability(VIDEO_ID)).await;
+        expect_false!(body.available);
+
+        let resp =
+            test::call_service(&app, availability("f47e6cdc-1bcf-439a-9ea4-038dc7153648")).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_list_content_metadata_page_size() -> googletest::Result<()> {
+        use leap_api::api::content::meta::get::Response;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.http_config.default_page_size = Some(2);
+        config.http_config.max_page_size = Some(3);
+        config.http_config.validate().or_fail()?;
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+
+        let mut content = vec![];
+        for index in 0..5 {
+            let id = uuid::Uuid::new_v4();
+            let name = format!("video {index}");
+            api_data.db.insert_video(id, &name, 100).await.or_fail()?;
+            content.push(crate::manifest::Video {
+                name,
+                id,
+                uri: format!("s3://bucket/video-{index}.mp4").parse().or_fail()?,
+                checksum: "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                    .try_into()
+                    .or_fail()?,
+                file_size: 100,
+                duration_secs: None,
+                resolution: None,
+            });
+        }
+        api_data
+            .db
+            .publish_manifest(&crate::manifest::ManifestFile {
+                name: "manifest".to_string(),
+                date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+                published_at: None,
+                adopt_after: None,
+                min_vds_version: None,
+                version: crate::manifest::Version {
+                    major: 1,
+                    minor: 0,
+                    revision: 0,
+                },
+                sections: vec![crate::manifest::Section {
+                    name: "section".to_string(),
+                    description: None,
+                    content,
+                }],
+            })
+            .await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let list = |query: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/content/meta{query}"))
+                .to_request()
+        };
+        let video_count = |body: &Response| -> usize {
+            body.videos
+                .iter()
+                .map(|section| section.content.len())
+                .sum()
+        };
+
+        // The default limit applies when the request has none
+        let body: Response = test::call_and_read_body_json(&app, list("")).await;
+        expect_that!(video_count(&body), eq(2));
+        expect_that!(body.limit, some(eq(2)));
+        expect_that!(body.total, eq(5));
+
+        // Larger limits are clamped
+        let body: Response = test::call_and_read_body_json(&app, list("?limit=10&offset=1")).await;
+        expect_that!(video_count(&body), eq(3));
+        expect_that!(body.limit, some(eq(3)));
+        expect_that!(body.offset, eq(1));
+        expect_that!(body.total, eq(5));
+
+        let body: Response = test::call_and_read_body_json(&app, list("?limit=1")).await;
+        expect_that!(video_count(&body), eq(1));
+        expect_that!(body.limit, some(eq(1)));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_list_content_metadata_grouped_by_section() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+
+        let mut sections = vec![];
+        for section in ["first", "second"] {
+            let id = uuid::Uuid::new_v4();
+            let name = format!("{section} video");
+            api_data.db.insert_video(id, &name, 100).await.or_fail()?;
+            sections.push(crate::manifest::Section {
+                name: section.to_string(),
+                description: (section == "first").then(|| "The first lessons".to_string()),
+                content: vec![crate::manifest::Video {
+                    name,
+                    id,
+                    uri: format!("s3://bucket/{id}.mp4").parse().or_fail()?,
+                    checksum: "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                        .try_into()
+                        .or_fail()?,
+                    file_size: 100,
+                    duration_secs: None,
+                    resolution: None,
+                }],
+            });
+        }
+        api_data
+            .db
+            .publish_manifest(&crate::manifest::ManifestFile {
+                name: "manifest".to_string(),
+                date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+                published_at: None,
+                adopt_after: None,
+                min_vds_version: None,
+                version: crate::manifest::Version {
+                    major: 1,
+                    minor: 0,
+                    revision: 0,
+                },
+                sections,
+            })
+            .await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/api/content/meta")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // The site relies on this shape to display the sections
+        let sections = body["videos"].as_array().or_fail()?;
+        expect_that!(sections.len(), eq(2));
+        for (section, name) in sections.iter().zip(["first", "second"]) {
+            expect_that!(section["name"], eq(&serde_json::json!(name)));
+            let content = section["content"].as_array().or_fail()?;
+            expect_that!(content.len(), eq(1));
+            expect_that!(
+                content[0]["name"],
+                eq(&serde_json::json!(format!("{name} video")))
+            );
+        }
+
+        // The description is only given for the sections that have one
+        let body: leap_api::api::content::meta::get::Response =
+            serde_json::from_value(body).or_fail()?;
+        expect_that!(
+            body.videos,
+            elements_are![
+                field!(&GroupedSection.description, ref some(eq("The first lessons"))),
+                field!(&GroupedSection.description, ref none()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_pin_content() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 100).await.or_fail()?;
+        let db = std::sync::Arc::clone(&api_data.db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let pin = |id: &str, pinned: bool| {
+            test::TestRequest::put()
+                .uri(&format!("/api/content/local/pin?id={id}&pinned={pinned}"))
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, pin(VIDEO_ID, true)).await;
+        expect_that!(resp.status(), eq(StatusCode::NO_CONTENT));
+        expect_true!(db.find_video(id).await.or_fail()?.pinned);
+
+        let resp = test::call_service(&app, pin(VIDEO_ID, false)).await;
+        expect_that!(resp.status(), eq(StatusCode::NO_CONTENT));
+        expect_false!(db.find_video(id).await.or_fail()?.pinned);
+
+        let resp =
+            test::call_service(&app, pin("f47e6cdc-1bcf-439a-9ea4-038dc7153648", true)).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+        let resp = test::call_service(&app, pin("invalid", true)).await;
+        expect_that!(resp.status(), eq(StatusCode::BAD_REQUEST));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_video_events() -> googletest::Result<()> {
+        use leap_api::api::content::id::events::get::{Response, VideoEventKind};
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        api_data.db.insert_video(id, "video", 100).await.or_fail()?;
+        api_data
+            .db
+            .set_download_failed(id, "Connection reset", crate::db::FailureKind::Network)
+            .await
+            .or_fail()?;
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data)
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/content/{VIDEO_ID}/events"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        let body: Response = test::read_body_json(resp).await;
+        expect_that!(
+            body.events,
+            elements_are![
+                matches_pattern!(leap_api::types::VideoEvent {
+                    kind: eq(&VideoEventKind::Queued),
+                    ..
+                }),
+                matches_pattern!(leap_api::types::VideoEvent {
+                    kind: eq(&VideoEventKind::Failed),
+                    message: eq("Connection reset"),
+                    ..
+                }),
+            ]
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/content/{}/events", uuid::Uuid::new_v4()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        expect_that!(resp.status(), eq(StatusCode::NOT_FOUND));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_from_memory_cache() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.http_config.content_cache_bytes = Some(1024);
+        config.http_config.content_cache_max_file_bytes = Some(8);
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+
+        let small_id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let large_id = uuid::Uuid::new_v4();
+        let small_path = tempdir.path().join("small.mp4");
+        let large_path = tempdir.path().join("large.mp4");
+        std::fs::write(&small_path, b"intro").or_fail()?;
+        std::fs::write(&large_path, b"full lesson").or_fail()?;
+        for (id, path) in [(small_id, &small_path), (large_id, &large_path)] {
+            api_data.db.insert_video(id, "video", 5).await.or_fail()?;
+            api_data.db.set_downloaded(id, path).await.or_fail()?;
+        }
+
+        for id in [small_id, large_id] {
+            let resp = get_content_response(api_data.clone(), &id.to_string()).await;
+            expect_that!(resp.status(), eq(StatusCode::OK));
+        }
+
+        // The small file is served from memory without reading the storage, while the large one
+        // is always read from the storage
+        std::fs::remove_file(&small_path).or_fail()?;
+        std::fs::remove_file(&large_path).or_fail()?;
+        let resp = get_content_response(api_data.clone(), VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        expect_that!(test::read_body(resp).await.to_vec(), eq(b"intro"));
+        let resp = get_content_response(api_data, &large_id.to_string()).await;
+        expect_that!(resp.status(), eq(StatusCode::INTERNAL_SERVER_ERROR));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_get_content_while_downloading() -> googletest::Result<()> {
+        use actix_web::{body::MessageBody, http::header};
+        use std::io::Write;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.http_config.serve_partial_content = true;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let partial_path = crate::downloader::partial_filepath(&config.downloader_config, id);
+        let content_path = tempdir.path().join("video.mp4");
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+        api_data.db.insert_video(id, "video", 10).await.or_fail()?;
+        expect_true!(api_data.db.claim_for_download(id).await.or_fail()?);
+        std::fs::create_dir_all(partial_path.parent().or_fail()?).or_fail()?;
+        std::fs::write(&partial_path, [0, 1, 2, 3]).or_fail()?;
+        let partial_write = api_data.partial_writes.start(&partial_path);
+        partial_write.confirm(4);
+
+        let resp = get_content_response(api_data.clone(), VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        let header_value = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        expect_that!(header_value(header::ACCEPT_RANGES), some(eq("bytes")));
+        expect_that!(header_value(header::CONTENT_LENGTH), some(eq("10")));
+        expect_that!(header_value(header::CACHE_CONTROL), some(eq("no-store")));
+
+        // The content downloaded so far is streamed right away
+        let mut body = resp.into_body();
+        let chunk = std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx)).await;
+        expect_that!(
+            chunk.map(|chunk| chunk.map(|bytes| bytes.to_vec())),
+            some(ok(eq(&vec![0, 1, 2, 3])))
+        );
+
+        // The rest of the content is streamed as it is downloaded, even after the partial file is
+        // moved to its final path
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&partial_path)
+            .or_fail()?
+            .write_all(&[4, 5, 6, 7, 8, 9])
+            .or_fail()?;
+        std::fs::rename(&partial_path, &content_path).or_fail()?;
+        api_data
+            .db
+            .set_downloaded(id, &content_path)
+            .await
+            .or_fail()?;
+        let rest = actix_web::body::to_bytes(body).await;
+        expect_that!(
+            rest.map(|bytes| bytes.to_vec()),
+            ok(eq(&vec![4, 5, 6, 7, 8, 9]))
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_partial_content_is_not_read_past_the_confirmed_offset() -> googletest::Result<()>
+    {
+        use std::io::{Seek, Write};
+
+        const CHUNK_SIZE: usize = 1000;
+        const CHUNKS: usize = 4;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = crate::api::test::test_config(tempdir.path());
+        config.http_config.serve_partial_content = true;
+        let id = uuid::Uuid::from_str(VIDEO_ID).or_fail()?;
+        let partial_path = crate::downloader::partial_filepath(&config.downloader_config, id);
+        let content_path = tempdir.path().join("video.mp4");
+        let api_data = crate::api::test::create_api_data_with_config(config).await;
+        let content: Vec<u8> = (0..CHUNK_SIZE * CHUNKS)
+            .map(|i| (i % 255) as u8 + 1)
+            .collect();
+        api_data
+            .db
+            .insert_video(id, "video", content.len() as u64)
+            .await
+            .or_fail()?;
+        expect_true!(api_data.db.claim_for_download(id).await.or_fail()?);
+        std::fs::create_dir_all(partial_path.parent().or_fail()?).or_fail()?;
+        std::fs::write(&partial_path, b"").or_fail()?;
+        let partial_write = api_data.partial_writes.start(&partial_path);
+
+        let resp = get_content_response(api_data.clone(), VIDEO_ID).await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+
+        // Each chunk is first allocated with zeroes and then filled, so that reading it before it
+        // is confirmed would return a torn write
+        let writer = {
+            let (api_data, partial_path, content_path, content) = (
+                api_data.clone(),
+                partial_path.clone(),
+                content_path.clone(),
+                content.clone(),
+            );
+            tokio::spawn(async move {
+                let mut file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&partial_path)
+                    .unwrap();
+                for (i, chunk) in content.chunks(CHUNK_SIZE).enumerate() {
+                    let offset = (i * CHUNK_SIZE) as u64;
+                    file.set_len(offset + chunk.len() as u64).unwrap();
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    file.seek(std::io::SeekFrom::Start(offset)).unwrap();
+                    file.write_all(chunk).unwrap();
+                    partial_write.confirm(offset + chunk.len() as u64);
+                }
+                drop(file);
+                std::fs::rename(&partial_path, &content_path).unwrap();
+                api_data.db.set_downloaded(id, &content_path).await.unwrap();
+                drop(partial_write);
+            })
+        };
+
+        let body = actix_web::body::to_bytes(resp.into_body()).await;
+        writer.await.or_fail()?;
+        expect_that!(body.map(|bytes| bytes.to_vec()), ok(eq(&content)));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_log_file_is_filtered() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = create_api_data(tempdir.path()).await;
+        let log_line = |level: u64, msg: &str| {
+            serde_json::json!({ "time": "2026-10-18T10:00:00Z", "level": level, "msg": msg })
+                .to_string()
+        };
+        let lines = [
+            log_line(30, "Disk usage checked"),
+            log_line(40, "Disk almost full"),
+            log_line(50, "Disk full"),
+            log_line(50, "Network down"),
+        ];
+        std::fs::write(api_data.config.db_config.logfile(), lines.join("\n") + "\n").or_fail()?;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(api_data.clone())
+                .configure(crate::api::register_handlers(&Default::default())),
+        )
+        .await;
+        let get_logfile = async |query: &str| {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/logfile{query}"))
+                .to_request();
+            test::call_service(&app, req).await
+        };
+        let get_lines = async |query: &str| {
+            let resp = get_logfile(query).await;
+            expect_that!(resp.status(), eq(StatusCode::OK), "{query}");
+            let body = test::read_body(resp).await;
+            String::from_utf8_lossy(&body)
+                .lines()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        expect_that!(get_lines("").await, eq(&lines));
+        expect_that!(
+            get_lines("?level=warn&contains=Disk").await,
+            elements_are![eq(&lines[1]), eq(&lines[2])]
+        );
+        expect_that!(
+            get_lines("?level=50").await,
+            elements_are![eq(&lines[2]), eq(&lines[3])]
+        );
+        expect_that!(
+            get_lines("?contains=Disk&tail=1").await,
+            elements_are![eq(&lines[2])]
+        );
+        expect_that!(
+            get_logfile("?level=loud").await.status(),
+            eq(StatusCode::BAD_REQUEST)
+        );
+        Ok(())
+    }
 }
@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Result};
 use config::Config;
@@ -7,10 +10,128 @@ use secrecy::{ExposeSecret, SecretString};
 
 pub const DEFAULT_CONFIG_PATH: &str = "/var/lib/leap/config/config.toml";
 
+pub const DEFAULT_BACKOFF_JITTER_FRACTION: f64 = 0.1;
+
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 64 * 1024;
+
+pub const DEFAULT_CONTENT_TYPE: &str = "video/mp4";
+
+pub const DEFAULT_MAX_CONTENT_CONNECTIONS_PER_IP: usize = 8;
+
+pub const DEFAULT_CONTENT_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub const DEFAULT_CONTENT_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(24 * 3600);
+
+pub const DEFAULT_CONTENT_CACHE_MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+pub const DEFAULT_MAX_PAGE_SIZE: usize = 1000;
+
+pub const DEFAULT_MAX_MANIFEST_BYTES: usize = 4 * 1024 * 1024;
+
+pub const DEFAULT_MANUAL_FETCH_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(10);
+
+pub const DEFAULT_MANIFEST_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+pub const DEFAULT_UNREACHABLE_AFTER_FAILURES: u32 = 10;
+
+pub const DEFAULT_MAX_UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+pub const DEFAULT_IO_CHUNK_BYTES: usize = 64 * 1024;
+
+pub const DEFAULT_HASHING_THREADS: usize = 2;
+
+pub const DEFAULT_YIELD_TO_STREAMS: usize = 1;
+
+/// User agent of the requests of the HTTP backend, unless configured in `request_headers`.
+pub const DEFAULT_USER_AGENT: &str = concat!("leap-server/", env!("CARGO_PKG_VERSION"));
+
+/// Supported range of `io_chunk_bytes`. Smaller chunks waste time in system calls, and larger
+/// chunks waste memory.
+pub const IO_CHUNK_BYTES_RANGE: std::ops::RangeInclusive<usize> = 4 * 1024..=16 * 1024 * 1024;
+
+pub const DEFAULT_RECONCILE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+pub const DEFAULT_PROGRESS_EVENT_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(500);
+
+pub const DEFAULT_ERROR_LOG_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
+
+pub const DEFAULT_MIN_FREE_BYTES: u64 = 256 * 1024 * 1024;
+
+pub const DEFAULT_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+pub const DEFAULT_LOG_MAX_FILES: usize = 5;
+
 fn default_path_style() -> bool {
     false
 }
 
+fn default_backoff_jitter_fraction() -> f64 {
+    DEFAULT_BACKOFF_JITTER_FRACTION
+}
+
+fn default_max_manifest_bytes() -> usize {
+    DEFAULT_MAX_MANIFEST_BYTES
+}
+
+fn default_log_max_bytes() -> u64 {
+    DEFAULT_LOG_MAX_BYTES
+}
+
+fn default_log_max_files() -> usize {
+    DEFAULT_LOG_MAX_FILES
+}
+
+fn default_manual_fetch_cooldown() -> std::time::Duration {
+    DEFAULT_MANUAL_FETCH_COOLDOWN
+}
+
+fn default_manifest_retry_interval() -> std::time::Duration {
+    DEFAULT_MANIFEST_RETRY_INTERVAL
+}
+
+fn default_unreachable_after_failures() -> u32 {
+    DEFAULT_UNREACHABLE_AFTER_FAILURES
+}
+
+fn default_max_update_interval() -> std::time::Duration {
+    DEFAULT_MAX_UPDATE_INTERVAL
+}
+
+fn default_io_chunk_bytes() -> usize {
+    DEFAULT_IO_CHUNK_BYTES
+}
+
+fn default_hashing_threads() -> usize {
+    DEFAULT_HASHING_THREADS
+}
+
+fn default_yield_to_streams() -> usize {
+    DEFAULT_YIELD_TO_STREAMS
+}
+
+fn default_reconcile_interval() -> std::time::Duration {
+    DEFAULT_RECONCILE_INTERVAL
+}
+
+fn default_min_free_bytes() -> u64 {
+    DEFAULT_MIN_FREE_BYTES
+}
+
+fn default_progress_event_interval() -> std::time::Duration {
+    DEFAULT_PROGRESS_EVENT_INTERVAL
+}
+
+fn default_verify_manifest_content_type() -> bool {
+    true
+}
+
+fn default_error_log_interval() -> std::time::Duration {
+    DEFAULT_ERROR_LOG_INTERVAL
+}
+
 fn default_aws_region() -> String {
     "us-east-1".to_string()
 }
@@ -41,6 +162,58 @@ pub struct RetryParams {
     /// The maximum backoff time after a download failure.
     #[serde(with = "humantime_serde")]
     pub max_backoff: std::time::Duration,
+
+    /// Fraction of the backoff time that is randomly added or subtracted to it, so that videos
+    /// failing at the same time do not retry at the same time. Must be between 0 and 1.
+    #[serde(default = "default_backoff_jitter_fraction")]
+    pub backoff_jitter_fraction: f64,
+}
+
+/// How the files already present in the content directory are checked before considering their
+/// videos as downloaded, e.g. when the content directory was seeded from external storage.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExistingFileCheck {
+    /// Existing files are ignored, and their videos are downloaded again.
+    Disabled,
+    /// Files with the size declared by the manifest are used as they are.
+    Size,
+    /// Files with the size and checksum declared by the manifest are used as they are.
+    #[default]
+    Checksum,
+}
+
+/// Conditions of the device under which the downloads are paused, e.g. on fanless or
+/// battery-powered devices. Nothing pauses the downloads by default.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ResourceGuardConfig {
+    /// Downloads are paused while any thermal zone of the device is hotter than this, in degrees
+    /// Celsius.
+    #[serde(default)]
+    pub max_temperature_celsius: Option<f64>,
+
+    /// Downloads are paused while the device runs on battery.
+    #[serde(default)]
+    pub pause_on_battery: bool,
+}
+
+impl ResourceGuardConfig {
+    /// Returns whether any condition pauses the downloads.
+    pub fn is_enabled(&self) -> bool {
+        self.max_temperature_celsius.is_some() || self.pause_on_battery
+    }
+}
+
+/// A named manifest source, whose videos are downloaded and listed separately from the videos of
+/// the other channels.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct ChannelConfig {
+    /// URI of the remote server providing the manifest and content of the channel.
+    #[serde(with = "parse_uri")]
+    pub remote_server: Uri,
+
+    /// Directory inside `content_path` where the video files of the channel are stored.
+    pub content_subdir: PathBuf,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
@@ -48,10 +221,35 @@ pub struct DownloaderConfig {
     /// Number of maximum concurrent downloads.
     pub concurrent_downloads: usize,
 
+    /// Videos larger than this size, in bytes, are downloaded alone, so that they don't share the
+    /// bandwidth with other downloads. All the videos are downloaded concurrently if missing.
+    #[serde(default)]
+    pub solo_download_bytes: Option<u64>,
+
+    /// Maximum number of concurrent downloads while at least `yield_to_streams` videos are
+    /// streamed, so that the downloads don't slow down the videos being watched. The downloads in
+    /// progress are completed, but no new ones start above this limit. The streams don't limit the
+    /// downloads if missing.
+    #[serde(default)]
+    pub streaming_concurrent_downloads: Option<usize>,
+
+    /// Number of videos streamed at once from which the downloads are limited to
+    /// `streaming_concurrent_downloads`.
+    #[serde(default = "default_yield_to_streams")]
+    pub yield_to_streams: usize,
+
     /// The read/writeable path where the video files will be stored.
     pub content_path: PathBuf,
 
-    /// URI of the remote server providing the manifest and content cached by the LEAP.
+    /// The read/writeable path where the video files are stored while being downloaded. Should be
+    /// in the same filesystem as `content_path`, so that moving finished downloads is atomic.
+    /// Defaults to the `.tmp` directory inside `content_path`.
+    #[serde(default)]
+    pub temp_download_path: Option<PathBuf>,
+
+    /// URI of the remote server providing the manifest and content cached by the LEAP. Either a
+    /// local path, an `s3://` bucket, or the `http(s)://` URL of the manifest exported by another
+    /// LEAP.
     #[serde(with = "parse_uri")]
     pub remote_server: Uri,
 
@@ -61,6 +259,226 @@ pub struct DownloaderConfig {
 
     /// Retry parameters when a download fails.
     pub retry_params: RetryParams,
+
+    /// Maximum size of the manifest, in bytes. Larger manifests are discarded without being
+    /// fully read.
+    #[serde(default = "default_max_manifest_bytes")]
+    pub max_manifest_bytes: usize,
+
+    /// Minimum interval between user-triggered manifest fetches. Scheduled fetches are not
+    /// affected.
+    #[serde(default = "default_manual_fetch_cooldown", with = "humantime_serde")]
+    pub manual_fetch_cooldown: std::time::Duration,
+
+    /// Interval before fetching the manifest again after a failed fetch. It doubles after each
+    /// consecutive failure, up to `update_interval`.
+    #[serde(default = "default_manifest_retry_interval", with = "humantime_serde")]
+    pub manifest_retry_interval: std::time::Duration,
+
+    /// Number of consecutive failed fetches after which the upstream is considered unreachable.
+    /// The manifest is then fetched less often than `update_interval`, doubling the interval after
+    /// each further failure up to `max_update_interval`. User-triggered fetches are not delayed.
+    #[serde(default = "default_unreachable_after_failures")]
+    pub unreachable_after_failures: u32,
+
+    /// Maximum interval between manifest fetches while the upstream is unreachable. Intervals
+    /// shorter than `update_interval` disable the backoff.
+    #[serde(default = "default_max_update_interval", with = "humantime_serde")]
+    pub max_update_interval: std::time::Duration,
+
+    /// Grace period during which the downloaded videos removed from the manifest keep being
+    /// served, before their content is deleted. The content is deleted as soon as the videos are
+    /// removed when missing.
+    #[serde(default, with = "humantime_serde")]
+    pub retain_removed_for: Option<std::time::Duration>,
+
+    /// URL to which a summary of the cached content is posted once all the videos of a manifest
+    /// are downloaded. No notification is sent when missing.
+    #[serde(default)]
+    pub completion_webhook_url: Option<String>,
+
+    /// How the files already present in `content_path` are checked before using them instead of
+    /// downloading their videos. Checking the checksum reads the whole files.
+    #[serde(default)]
+    pub existing_file_check: ExistingFileCheck,
+
+    /// Interval at which some of the downloaded videos are checked to still be on disk with the
+    /// right size. Videos whose files are missing or have a wrong size are downloaded again.
+    #[serde(default = "default_reconcile_interval", with = "humantime_serde")]
+    pub reconcile_interval: std::time::Duration,
+
+    /// Minimum interval between the progress events of a video sent to the subscribers of the
+    /// content events. Faster updates are dropped, but the completion and failure of the downloads
+    /// are always sent right away.
+    #[serde(default = "default_progress_event_interval", with = "humantime_serde")]
+    pub progress_event_interval: std::time::Duration,
+
+    /// Interval within which the repetitions of the same download error are only counted, instead
+    /// of logged. They are reported along with the next occurrence after the interval, or once the
+    /// downloads of the manifest end.
+    #[serde(default = "default_error_log_interval", with = "humantime_serde")]
+    pub error_log_interval: std::time::Duration,
+
+    /// Minimum free space, in bytes, of the filesystems of `content_path` and of the database.
+    /// Downloads are paused while either has less free space, so that the database can still be
+    /// written. The content already downloaded keeps being served.
+    #[serde(default = "default_min_free_bytes")]
+    pub min_free_bytes: u64,
+
+    /// Rejects the manifests with unknown keys, such as misspelled ones, instead of ignoring
+    /// them. Disabled by default, so that manifests with fields added by newer versions can be
+    /// used.
+    #[serde(default)]
+    pub strict_manifest: bool,
+
+    /// Rejects the manifests served as HTML or not starting as a JSON object, such as the login or
+    /// error pages of a misconfigured upstream, with a clearer error than the parsing one.
+    #[serde(default = "default_verify_manifest_content_type")]
+    pub verify_manifest_content_type: bool,
+
+    /// Conditions of the device, such as its temperature, under which the downloads are paused.
+    #[serde(default)]
+    pub resource_guard: ResourceGuardConfig,
+
+    /// URI schemes of the videos that are downloaded. Videos with other schemes are marked as
+    /// failed instead of being fetched. Relative URIs are always allowed. Defaults to the schemes
+    /// served by the backend of `remote_server`, see [`DownloaderConfig::allowed_uri_schemes`].
+    #[serde(default)]
+    pub allowed_uri_schemes: Option<Vec<String>>,
+
+    /// Size of the chunks in which the content is read and written when downloading and serving
+    /// it, in bytes. Larger chunks are faster on SSDs, smaller chunks save memory on SD cards.
+    /// Must be within [`IO_CHUNK_BYTES_RANGE`].
+    #[serde(default = "default_io_chunk_bytes")]
+    pub io_chunk_bytes: usize,
+
+    /// Maximum number of threads computing the checksums of the content at once. Checksums are
+    /// computed outside of the threads serving the HTTP requests and driving the downloads, so that
+    /// hashing large files doesn't slow them down. Must be at least 1.
+    #[serde(default = "default_hashing_threads")]
+    pub hashing_threads: usize,
+
+    /// Extra headers sent with every request of the HTTP backend, such as an auth token required
+    /// by the upstream. The values are treated as secrets.
+    #[serde(default)]
+    pub request_headers: BTreeMap<String, String>,
+
+    /// Additional manifest sources, by name. The content of each channel is cached and served
+    /// separately from the default channel, which uses `remote_server`.
+    #[serde(default)]
+    pub channels: BTreeMap<String, ChannelConfig>,
+}
+
+impl DownloaderConfig {
+    /// The directory where videos are downloaded before being moved to `content_path`.
+    pub fn temp_download_path(&self) -> PathBuf {
+        self.temp_download_path
+            .clone()
+            .unwrap_or_else(|| self.content_path.join(".tmp"))
+    }
+
+    /// Checks that the configuration values are within the supported ranges, and that the channel
+    /// names and content directories can be used as paths.
+    pub fn validate(&self) -> Result<()> {
+        if self.reconcile_interval.is_zero() {
+            anyhow::bail!("reconcile_interval must be positive");
+        }
+        if self.hashing_threads == 0 {
+            anyhow::bail!("hashing_threads must be at least 1");
+        }
+        if !IO_CHUNK_BYTES_RANGE.contains(&self.io_chunk_bytes) {
+            anyhow::bail!(
+                "io_chunk_bytes must be between {} and {} bytes",
+                IO_CHUNK_BYTES_RANGE.start(),
+                IO_CHUNK_BYTES_RANGE.end()
+            );
+        }
+        self.request_header_map()?;
+        for (name, channel) in &self.channels {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                anyhow::bail!(
+                    "Invalid channel name {name:?}: only letters, digits, '-' and '_' are allowed"
+                );
+            }
+            let mut components = channel.content_subdir.components();
+            if !matches!(
+                (components.next(), components.next()),
+                (Some(std::path::Component::Normal(_)), None)
+            ) {
+                anyhow::bail!(
+                    "The content_subdir of channel {name} must be a single directory name, got {:?}",
+                    channel.content_subdir
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the headers sent with every request of the HTTP backend: [`DEFAULT_USER_AGENT`]
+    /// followed by the `request_headers`, which can override it. The configured values are marked
+    /// as sensitive, so that they are not logged.
+    pub fn request_header_map(&self) -> Result<http::HeaderMap> {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            http::header::USER_AGENT,
+            http::HeaderValue::from_static(DEFAULT_USER_AGENT),
+        );
+        for (name, value) in &self.request_headers {
+            let name = http::HeaderName::try_from(name.as_str())
+                .with_context(|| format!("Invalid request header name: {name}"))?;
+            // The value is not part of the error, as it might be a secret
+            let mut value = http::HeaderValue::try_from(value.as_str())
+                .with_context(|| format!("Invalid value of request header {name}"))?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+
+    /// Returns the URI schemes of the videos that are downloaded: the configured ones, or the
+    /// schemes of the backend of `remote_server`. The file backend only uses the paths of the
+    /// URIs, and also accepts `s3` URIs to test the manifests of S3 buckets.
+    pub fn allowed_uri_schemes(&self) -> Vec<String> {
+        if let Some(schemes) = &self.allowed_uri_schemes {
+            return schemes.clone();
+        }
+        let schemes: &[&str] = match self.remote_server.scheme_str() {
+            None | Some("file") => &["file", "s3"],
+            Some("s3") => &["s3"],
+            Some("http" | "https") => &["http", "https"],
+            Some(_) => &[],
+        };
+        schemes.iter().map(|scheme| scheme.to_string()).collect()
+    }
+
+    /// Whether a video with this URI may be downloaded, according to
+    /// [`DownloaderConfig::allowed_uri_schemes`].
+    pub fn is_uri_allowed(&self, uri: &Uri) -> bool {
+        uri.scheme_str().is_none_or(|scheme| {
+            self.allowed_uri_schemes()
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+        })
+    }
+
+    /// The configuration of the downloader of a channel. The files of the channel are stored in
+    /// its own subdirectories of the download paths.
+    pub fn channel_config(&self, channel: &ChannelConfig) -> DownloaderConfig {
+        DownloaderConfig {
+            remote_server: channel.remote_server.clone(),
+            content_path: self.content_path.join(&channel.content_subdir),
+            temp_download_path: self
+                .temp_download_path
+                .as_ref()
+                .map(|path| path.join(&channel.content_subdir)),
+            channels: BTreeMap::new(),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
@@ -95,6 +513,14 @@ impl DbConfig {
     pub fn logfile(&self) -> PathBuf {
         self.runtime_path.join("leap_runtime.log")
     }
+
+    /// The configuration of the database of a channel, stored in its own runtime directory.
+    pub fn channel_config(&self, name: &str) -> DbConfig {
+        DbConfig {
+            runtime_path: self.runtime_path.join("channels").join(name),
+            ..self.clone()
+        }
+    }
 }
 
 /// Configuration to access the S3 server. Note the bucket is handled separately in the main
@@ -122,12 +548,207 @@ pub struct S3Config {
     pub region: String,
 }
 
+/// Configuration of the HTTP server.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+pub struct HttpServerConfig {
+    /// Origins allowed to make cross-origin requests to the API (e.g.:
+    /// `http://localhost:8080`). When empty, only same-origin requests are allowed.
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// Number of HTTP worker threads. Defaults to the number of CPU cores.
+    pub http_workers: Option<usize>,
+
+    /// Time that idle connections are kept alive, in seconds. Defaults to 5 seconds.
+    pub keep_alive_secs: Option<u64>,
+
+    /// Maximum size of the request bodies accepted by the API, in bytes. Defaults to
+    /// [`DEFAULT_MAX_PAYLOAD_BYTES`].
+    pub max_payload_bytes: Option<usize>,
+
+    /// Content type of the served content. Defaults to [`DEFAULT_CONTENT_TYPE`].
+    pub content_type: Option<String>,
+
+    /// Extra headers added to the responses serving content (e.g.: `Cache-Control`).
+    #[serde(default)]
+    pub content_headers: BTreeMap<String, String>,
+
+    /// Key required by the admin API, sent as a bearer token. The admin API is disabled when
+    /// missing.
+    #[serde(default, serialize_with = "serialize_secret_str")]
+    pub admin_api_key: Option<SecretString>,
+
+    /// Maximum number of content requests that a single client IP can have in flight. Further
+    /// requests are rejected with `429 Too Many Requests`. Defaults to
+    /// [`DEFAULT_MAX_CONTENT_CONNECTIONS_PER_IP`].
+    pub max_content_connections_per_ip: Option<usize>,
+
+    /// Time after which the connection of a content request is closed when its client stops
+    /// reading the content, in seconds. The timer restarts whenever the client reads more, so
+    /// slow clients are not affected. Defaults to [`DEFAULT_CONTENT_IDLE_TIMEOUT`].
+    pub content_idle_timeout_secs: Option<u64>,
+
+    /// Time for which browsers may cache the served content, in seconds, sent as
+    /// `Cache-Control: public, max-age=...`. Responses to content that is not downloaded yet are
+    /// never cached. A `Cache-Control` header in `content_headers` takes precedence. Defaults to
+    /// [`DEFAULT_CONTENT_MAX_AGE`].
+    pub content_max_age_secs: Option<u64>,
+
+    /// Memory used to cache small content files, in bytes, so that frequently played short
+    /// videos are not read from the storage on every request. The least recently played files
+    /// are evicted first. The cache is disabled if missing or 0.
+    pub content_cache_bytes: Option<u64>,
+
+    /// Maximum size of the content files kept in memory, in bytes. Larger files are always read
+    /// from the storage. Defaults to [`DEFAULT_CONTENT_CACHE_MAX_FILE_BYTES`].
+    pub content_cache_max_file_bytes: Option<u64>,
+
+    /// Whether `GET api/content/{id}` serves the videos being downloaded, streaming their content
+    /// as it is downloaded instead of responding with `409 Conflict`. The streamed content is not
+    /// verified against its checksum yet.
+    #[serde(default)]
+    pub serve_partial_content: bool,
+
+    /// Number of videos listed by `GET api/content/meta` when the request has no `limit`. Defaults
+    /// to [`DEFAULT_PAGE_SIZE`].
+    pub default_page_size: Option<usize>,
+
+    /// Maximum number of videos listed by `GET api/content/meta`. Larger `limit`s are clamped.
+    /// Defaults to [`DEFAULT_MAX_PAGE_SIZE`].
+    pub max_page_size: Option<usize>,
+
+    /// Path under which a reverse proxy serves this server (e.g.: `/leap`), with the prefix
+    /// stripped from the forwarded requests. The site and the exported manifest link to the API
+    /// and the content under this path. Served at the root if missing.
+    pub base_path: Option<String>,
+}
+
+impl HttpServerConfig {
+    /// Checks that the configuration values are within the supported ranges.
+    pub fn validate(&self) -> Result<()> {
+        if self.http_workers == Some(0) {
+            anyhow::bail!("http_workers must be at least 1");
+        }
+        if self.max_payload_bytes == Some(0) {
+            anyhow::bail!("max_payload_bytes must be at least 1");
+        }
+        if self.max_content_connections_per_ip == Some(0) {
+            anyhow::bail!("max_content_connections_per_ip must be at least 1");
+        }
+        if self.content_idle_timeout_secs == Some(0) {
+            anyhow::bail!("content_idle_timeout_secs must be at least 1");
+        }
+        if self.default_page_size == Some(0) || self.max_page_size == Some(0) {
+            anyhow::bail!("default_page_size and max_page_size must be at least 1");
+        }
+        if self.default_page_size() > self.max_page_size() {
+            anyhow::bail!("default_page_size can't be larger than max_page_size");
+        }
+        if let Some(base_path) = &self.base_path
+            && (!base_path.starts_with('/')
+                || base_path.contains(['"', '\'', '<', '>', ' ', '?', '#']))
+        {
+            anyhow::bail!("base_path must be an absolute path, got: {base_path}");
+        }
+        if let Some(content_type) = &self.content_type {
+            actix_web::http::header::HeaderValue::try_from(content_type.as_str())
+                .with_context(|| format!("Invalid content_type: {content_type}"))?;
+        }
+        for (name, value) in &self.content_headers {
+            actix_web::http::header::HeaderName::try_from(name.as_str())
+                .with_context(|| format!("Invalid content header name: {name}"))?;
+            actix_web::http::header::HeaderValue::try_from(value.as_str())
+                .with_context(|| format!("Invalid value of content header {name}: {value}"))?;
+        }
+        Ok(())
+    }
+
+    /// The content type of the served content.
+    pub fn content_type(&self) -> &str {
+        self.content_type.as_deref().unwrap_or(DEFAULT_CONTENT_TYPE)
+    }
+
+    /// The maximum size of request bodies accepted by the API.
+    pub fn max_payload_bytes(&self) -> usize {
+        self.max_payload_bytes.unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES)
+    }
+
+    /// The maximum number of in-flight content requests of a single client IP.
+    pub fn max_content_connections_per_ip(&self) -> usize {
+        self.max_content_connections_per_ip
+            .unwrap_or(DEFAULT_MAX_CONTENT_CONNECTIONS_PER_IP)
+    }
+
+    /// The time after which the connection of a stalled content request is closed.
+    pub fn content_idle_timeout(&self) -> std::time::Duration {
+        self.content_idle_timeout_secs
+            .map_or(DEFAULT_CONTENT_IDLE_TIMEOUT, std::time::Duration::from_secs)
+    }
+
+    /// The time for which browsers may cache the served content.
+    pub fn content_max_age(&self) -> std::time::Duration {
+        self.content_max_age_secs
+            .map_or(DEFAULT_CONTENT_MAX_AGE, std::time::Duration::from_secs)
+    }
+
+    /// The memory used to cache small content files, 0 if the cache is disabled.
+    pub fn content_cache_bytes(&self) -> u64 {
+        self.content_cache_bytes.unwrap_or(0)
+    }
+
+    /// The maximum size of the content files kept in memory.
+    pub fn content_cache_max_file_bytes(&self) -> u64 {
+        self.content_cache_max_file_bytes
+            .unwrap_or(DEFAULT_CONTENT_CACHE_MAX_FILE_BYTES)
+    }
+
+    /// The number of listed videos when requests have no limit.
+    pub fn default_page_size(&self) -> usize {
+        self.default_page_size.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+
+    /// The maximum number of listed videos.
+    pub fn max_page_size(&self) -> usize {
+        self.max_page_size.unwrap_or(DEFAULT_MAX_PAGE_SIZE)
+    }
+
+    /// The path under which the server is reachable, without the trailing slash. Empty when the
+    /// server is served at the root.
+    pub fn base_path(&self) -> &str {
+        self.base_path
+            .as_deref()
+            .unwrap_or_default()
+            .trim_end_matches('/')
+    }
+
+    /// The number of videos listed for a request with the given `limit`.
+    pub fn page_size(&self, limit: Option<usize>) -> usize {
+        limit
+            .unwrap_or(self.default_page_size())
+            .min(self.max_page_size())
+    }
+}
+
 /// Configuration of the LEAP application.
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct LeapConfig {
     /// Enables debug logging/tracing.
     pub debug: bool,
 
+    /// Size in bytes after which the log file is rotated.
+    #[serde(default = "default_log_max_bytes")]
+    pub log_max_bytes: u64,
+
+    /// Number of rotated log files kept besides the active one.
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: usize,
+
+    /// Name telling this server apart from the others of a fleet. Shown in the version
+    /// information, the logs and the site. Defaults to the hostname of the machine.
+    #[serde(default)]
+    pub instance_name: Option<String>,
+
     /// Downloader service configuration.
     pub downloader_config: DownloaderConfig,
 
@@ -136,18 +757,78 @@ pub struct LeapConfig {
 
     /// S3 configuration.
     pub s3_config: S3Config,
+
+    /// HTTP server configuration.
+    #[serde(default)]
+    pub http_config: HttpServerConfig,
+}
+
+/// Name of the instance when it is not configured and the hostname is not available.
+pub const FALLBACK_INSTANCE_NAME: &str = "leap";
+
+/// Returns the hostname of the machine, which names the instances without a configured name.
+pub fn default_instance_name() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|hostname| hostname.into_string().ok())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| FALLBACK_INSTANCE_NAME.to_string())
+}
+
+/// Value replacing the secrets of a redacted configuration.
+pub const REDACTED_SECRET: &str = "***";
+
+impl LeapConfig {
+    /// Returns the configured name of the instance, or the default one if missing.
+    pub fn instance_name(&self) -> String {
+        self.instance_name
+            .clone()
+            .unwrap_or_else(default_instance_name)
+    }
+
+    /// Returns a copy of the configuration with all the secrets replaced by [`REDACTED_SECRET`], so
+    /// that it can be shown to operators.
+    pub fn redacted(&self) -> Self {
+        let redact = |secret: &Option<SecretString>| {
+            secret.as_ref().map(|_| SecretString::from(REDACTED_SECRET))
+        };
+
+        let mut config = self.clone();
+        config.s3_config.access_key_id = redact(&self.s3_config.access_key_id);
+        config.s3_config.secret_access_key = redact(&self.s3_config.secret_access_key);
+        config.http_config.admin_api_key = redact(&self.http_config.admin_api_key);
+        for value in config.downloader_config.request_headers.values_mut() {
+            *value = REDACTED_SECRET.to_string();
+        }
+        config
+    }
 }
 
 /// Parses the configuration of the LEAP, returning a LeapConfig struct.
 /// Uses the given path to read a structured file format (toml, yaml, json, etc).
-/// Individual values can be overriden by `LEAP_`-prefixed environment variables.
+/// Individual values can be overriden by `LEAP_`-prefixed environment variables, which take
+/// precedence over the file. Nested values are separated by `__`, e.g.:
+/// `LEAP_DOWNLOADER_CONFIG__CONCURRENT_DOWNLOADS=2`. Unknown top-level keys, such as a mistyped
+/// variable, are rejected instead of being ignored.
 pub fn get_config(path: &Path) -> Result<LeapConfig> {
+    build_config(path, environment())
+}
+
+/// The source of the environment variables overriding the configuration file.
+fn environment() -> config::Environment {
+    // The prefix separator defaults to the nesting separator when missing
+    config::Environment::with_prefix("LEAP")
+        .prefix_separator("_")
+        .separator("__")
+}
+
+fn build_config(path: &Path, environment: config::Environment) -> Result<LeapConfig> {
     let config = Config::builder()
         .add_source(config::File::with_name(
             path.to_str()
                 .context("Parsing configuration path as a str")?,
         ))
-        .add_source(config::Environment::with_prefix("LEAP"))
+        .add_source(environment)
         .build()
         .context("Building the configuration of the LEAP from file and environment")?;
 
@@ -191,3 +872,66 @@ mod parse_uri {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+
+    /// Writes the configuration template to `dir`, returning its path.
+    fn write_template(dir: &Path) -> googletest::Result<PathBuf> {
+        let path = dir.join("config.toml");
+        std::fs::write(&path, include_str!("../../docs/leap_config_template.toml")).or_fail()?;
+        Ok(path)
+    }
+
+    fn environment_with(vars: &[(&str, &str)]) -> config::Environment {
+        environment().source(Some(
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        ))
+    }
+
+    #[googletest::test]
+    fn test_environment_overrides_nested_values() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let path = write_template(tempdir.path())?;
+
+        let config = build_config(&path, environment_with(&[])).or_fail()?;
+        expect_that!(config.downloader_config.concurrent_downloads, eq(8));
+        expect_that!(config.debug, eq(false));
+
+        let config = build_config(
+            &path,
+            environment_with(&[
+                ("LEAP_DOWNLOADER_CONFIG__CONCURRENT_DOWNLOADS", "2"),
+                ("LEAP_DEBUG", "true"),
+            ]),
+        )
+        .or_fail()?;
+        expect_that!(config.downloader_config.concurrent_downloads, eq(2));
+        expect_that!(config.debug, eq(true));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_unknown_keys_are_rejected() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let path = write_template(tempdir.path())?;
+
+        // A single `_` does not nest, so this is an unknown top-level key
+        let result = build_config(
+            &path,
+            environment_with(&[("LEAP_DOWNLOADER_CONFIG_CONCURRENT_DOWNLOADS", "2")]),
+        );
+        expect_that!(
+            result.map_err(|e| format!("{e:#}")),
+            err(contains_substring(
+                "unknown field `downloader_config_concurrent_downloads`"
+            ))
+        );
+        Ok(())
+    }
+}
@@ -1,13 +1,17 @@
 mod models;
 mod schema;
 
-use std::{path::Path, sync::Arc};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::{cfg::DbConfig, manifest::ManifestFile};
-pub use models::{DownloadStatus, Video};
+pub use models::{DownloadStatus, FailureKind, MediaInfo, Video, VideoEvent, VideoEventKind};
 
 use deadpool_diesel::{Manager, Pool};
-use diesel::{connection::SimpleConnection, prelude::*};
+use diesel::{connection::SimpleConnection, migration::MigrationSource, prelude::*};
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
 use tokio::sync::RwLock;
 
@@ -23,6 +27,11 @@ pub enum Error {
     Diesel(#[from] diesel::result::Error),
     #[error("Migration error")]
     Migration,
+    #[error(
+        "The database was created by a newer version of the server, which applied the unknown \
+        migrations {0:?}. Upgrade the server or restore a backup of the database."
+    )]
+    NewerDatabase(Vec<String>),
     #[error("Invalid download status: {0:?}")]
     InvalidDownloadStatus(i64),
     #[error("Invalid uuid: {0:?}")]
@@ -39,6 +48,49 @@ pub enum Error {
 
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// Number of events kept in the download history of each video. Older events are deleted as new
+/// ones are recorded.
+pub const MAX_EVENTS_PER_VIDEO: i64 = 100;
+
+/// Aggregated download status counts over all the videos in the database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub total: u64,
+    pub downloaded: u64,
+    pub downloading: u64,
+    pub failed: u64,
+    pub pending: u64,
+}
+
+impl Stats {
+    /// Returns true if there is content and all of it is available locally.
+    pub fn is_fully_cached(&self) -> bool {
+        self.total > 0 && self.downloaded == self.total
+    }
+}
+
+/// A section of the current manifest, with the database entries of its videos.
+#[derive(Debug, Clone)]
+pub struct ManifestSection {
+    pub name: String,
+    pub description: Option<String>,
+    pub videos: Vec<Video>,
+}
+
+/// Download completion of the videos in a manifest section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SectionStatus {
+    pub downloaded: u64,
+    pub total: u64,
+}
+
+impl SectionStatus {
+    /// Returns true if the section has content and all of it is available locally.
+    pub fn is_ready(&self) -> bool {
+        self.total > 0 && self.downloaded == self.total
+    }
+}
+
 /// An abstraction over:
 /// - An sqlite database that handles the video status information.
 /// - A manifest file saved directly in fs storage. This was simpler
@@ -53,7 +105,8 @@ pub struct Database {
 
 impl Database {
     /// Opens the database using the given configuration. Returns an error if the
-    /// database could not be opened. Also loads the manifest file from storage.
+    /// database could not be opened. The manifest saved in storage is not published until its
+    /// videos are known to be in the database, see [`Self::saved_manifest`].
     pub async fn open(config: DbConfig) -> Result<Self> {
         let db_path = config.db_path();
         if let Some(dir) = db_path.parent() {
@@ -76,18 +129,10 @@ impl Database {
             }))
             .build()?;
 
-        let manifest_path = config.manifest_path();
-        let current_manifest: Arc<RwLock<Option<ManifestFile>>> = Arc::new(RwLock::new(
-            tokio::fs::read(manifest_path)
-                .await
-                .ok()
-                .and_then(|content| serde_json::from_slice(&content).ok()),
-        ));
-
         Ok(Self {
             config,
             pool,
-            current_manifest,
+            current_manifest: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -96,11 +141,32 @@ impl Database {
     /// migrations to the database so that we do not have to carry out these actions manually.
     ///
     /// This function performs any pending migrations, for either a non-existent database (being
-    /// created now) or a database from a previous version of the software.
+    /// created now) or a database from a previous version of the software. Fails with
+    /// [`Error::NewerDatabase`] for a database migrated by a newer version of the software, as its
+    /// schema may be incompatible with this one.
     pub async fn apply_pending_migrations(&self) -> Result<()> {
         let connection = self.pool.get().await?;
         connection
             .interact(move |conn| {
+                // Migrations only go forward, so the schema of a database migrated by a newer
+                // version of the server may be incompatible with this one, e.g.: after a rollback
+                let known: Vec<_> =
+                    MigrationSource::<diesel::sqlite::Sqlite>::migrations(&MIGRATIONS)
+                        .map_err(|_| Error::Migration)?
+                        .iter()
+                        .map(|migration| migration.name().version().as_owned())
+                        .collect();
+                let unknown: Vec<_> = conn
+                    .applied_migrations()
+                    .map_err(|_| Error::Migration)?
+                    .into_iter()
+                    .filter(|version| !known.contains(version))
+                    .map(|version| version.to_string())
+                    .collect();
+                if !unknown.is_empty() {
+                    return Err(Error::NewerDatabase(unknown));
+                }
+
                 conn.run_pending_migrations(MIGRATIONS)
                     .map_err(|_| Error::Migration)?;
                 Ok(())
@@ -134,6 +200,15 @@ impl Database {
         Ok(())
     }
 
+    /// Reads the manifest saved with [`Self::save_manifest_to_disk`], if any. The process might
+    /// have stopped right after saving it, so its videos might be missing from the database.
+    pub async fn saved_manifest(&self) -> Option<ManifestFile> {
+        let content = tokio::fs::read(self.config.manifest_path()).await.ok()?;
+        serde_json::from_slice(&content)
+            .inspect_err(|e| tracing::warn!("Ignoring invalid saved manifest: {e}"))
+            .ok()
+    }
+
     /// Publishes a manifest to make it available for the currently running software. For
     /// concurrency issues (to prevent a manifest which does not yet contain corresponding video
     /// entries in the database) this is decoupled from saving the manifest to disk, which can
@@ -156,16 +231,64 @@ impl Database {
         self.current_manifest.read().await
     }
 
+    /// Path of the database file. Its filesystem must keep some free space for the journal.
+    pub fn db_path(&self) -> PathBuf {
+        self.config.db_path()
+    }
+
+    /// Returns the number of videos in the current manifest. Videos listed in several sections are
+    /// counted once per section.
+    pub async fn count_videos(&self) -> usize {
+        self.current_manifest
+            .read()
+            .await
+            .as_ref()
+            .map(|manifest| manifest.sections.iter().map(|s| s.content.len()).sum())
+            .unwrap_or(0)
+    }
+
     /// Returns the current manifest content divided by sections and ordered in the same way as the
-    /// manifest (for both the sections and the videos within a section).
-    pub async fn current_manifest_sections(&self) -> Result<Vec<(String, Vec<Video>)>> {
-        let manifest_sections = self
+    /// manifest (for both the sections and the videos within a section). Fails with
+    /// [`Error::MissingVideoInDb`] if a video of the manifest is not in the database.
+    pub async fn current_manifest_sections(&self) -> Result<Vec<ManifestSection>> {
+        self.current_manifest_sections_page(0, None, false).await
+    }
+
+    /// Same as [`Self::current_manifest_sections`], but only returns the videos within the given
+    /// page of the list of videos of all sections. When paginating, sections without videos in the
+    /// page are omitted. Pinned videos are listed first within their section.
+    ///
+    /// Videos of the manifest missing from the database, e.g.: briefly while it is reconciled with
+    /// the manifest, are omitted from their section if `lenient` is set, instead of failing.
+    pub async fn current_manifest_sections_page(
+        &self,
+        offset: usize,
+        limit: Option<usize>,
+        lenient: bool,
+    ) -> Result<Vec<ManifestSection>> {
+        let paginated = offset > 0 || limit.is_some();
+        let end = limit.map_or(usize::MAX, |limit| offset.saturating_add(limit));
+        let pinned = self.pinned_video_ids().await?;
+        let mut index = 0;
+        let manifest_sections: Vec<_> = self
             .current_manifest
             .read()
             .await
             .as_ref()
             .map(|manifest| manifest.sections.clone())
-            .unwrap_or(vec![]);
+            .unwrap_or(vec![])
+            .into_iter()
+            .filter_map(|mut s| {
+                let is_empty = s.content.is_empty();
+                // The sort is stable, so the manifest order is kept otherwise
+                s.content.sort_by_key(|v| !pinned.contains(&v.id));
+                s.content.retain(|_| {
+                    index += 1;
+                    (offset..end).contains(&(index - 1))
+                });
+                (!s.content.is_empty() || (is_empty && !paginated)).then_some(s)
+            })
+            .collect();
 
         let ids: Vec<String> = manifest_sections
             .iter()
@@ -188,24 +311,172 @@ impl Database {
         manifest_sections
             .into_iter()
             .map(|s| {
-                s.content
+                let mut videos = Vec::with_capacity(s.content.len());
+                for v in &s.content {
+                    // Here we need to order the videos as in the manifes section.
+                    // This is the reason why we can't just filter the videos matching relevant
+                    // ids.
+                    match videos_from_db.iter().find(|inner| inner.id == v.id) {
+                        Some(video) => videos.push(video.clone()),
+                        None if lenient => tracing::warn!(
+                            "Video {} of section {:?} is not in the database. Omitting it",
+                            v.id,
+                            s.name
+                        ),
+                        None => return Err(Error::MissingVideoInDb(v.id)),
+                    }
+                }
+                Ok(ManifestSection {
+                    name: s.name,
+                    description: s.description,
+                    videos,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the other videos of the first section of the current manifest containing the given
+    /// video, ordered as in the manifest. Returns an empty list if the video isn't in any section.
+    pub async fn related_videos(&self, req_id: uuid::Uuid) -> Result<Vec<Video>> {
+        let related: Vec<uuid::Uuid> = self
+            .current_manifest
+            .read()
+            .await
+            .as_ref()
+            .and_then(|manifest| {
+                manifest
+                    .sections
                     .iter()
-                    .map(|v| {
-                        // Here we need to order the videos as in the manifes section.
-                        // This is the reason why we can't just filter the videos matching relevant
-                        // ids.
-                        videos_from_db
-                            .iter()
-                            .find(|inner| inner.id == v.id)
-                            .cloned()
-                            .ok_or_else(|| Error::MissingVideoInDb(v.id))
-                    })
-                    .collect::<Result<Vec<Video>>>()
-                    .map(|inner| (s.name, inner))
+                    .find(|s| s.content.iter().any(|v| v.id == req_id))
+            })
+            .map(|section| {
+                section
+                    .content
+                    .iter()
+                    .map(|v| v.id)
+                    .filter(|id| *id != req_id)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if related.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids: Vec<String> = related.iter().map(|id| id.to_string()).collect();
+        let connection = self.pool.get().await?;
+        let videos_from_db: Vec<Video> = connection
+            .interact(move |conn| -> Result<Vec<Video>> {
+                use schema::videos::dsl;
+
+                Ok(dsl::videos
+                    .filter(dsl::id.eq_any(ids))
+                    .select(Video::as_select())
+                    .get_results(conn)?)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+
+        related
+            .into_iter()
+            .map(|id| {
+                videos_from_db
+                    .iter()
+                    .find(|video| video.id == id)
+                    .cloned()
+                    .ok_or(Error::MissingVideoInDb(id))
             })
             .collect()
     }
 
+    /// Returns the videos of the first section of the current manifest with the given name,
+    /// ordered as in the manifest. Returns `None` if there is no such section.
+    pub async fn section_videos(&self, name: &str) -> Result<Option<Vec<Video>>> {
+        let Some(section_ids): Option<Vec<uuid::Uuid>> = self
+            .current_manifest
+            .read()
+            .await
+            .as_ref()
+            .and_then(|manifest| manifest.sections.iter().find(|s| s.name == name))
+            .map(|section| section.content.iter().map(|v| v.id).collect())
+        else {
+            return Ok(None);
+        };
+
+        let ids: Vec<String> = section_ids.iter().map(|id| id.to_string()).collect();
+        let connection = self.pool.get().await?;
+        let videos_from_db: Vec<Video> = connection
+            .interact(move |conn| -> Result<Vec<Video>> {
+                use schema::videos::dsl;
+
+                Ok(dsl::videos
+                    .filter(dsl::id.eq_any(ids))
+                    .select(Video::as_select())
+                    .get_results(conn)?)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+
+        section_ids
+            .into_iter()
+            .map(|id| {
+                videos_from_db
+                    .iter()
+                    .find(|video| video.id == id)
+                    .cloned()
+                    .ok_or(Error::MissingVideoInDb(id))
+            })
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    /// Returns the download completion of each section in the given manifest, in the same order
+    /// as the manifest.
+    pub async fn section_status(
+        &self,
+        manifest: &ManifestFile,
+    ) -> Result<Vec<(String, SectionStatus)>> {
+        let ids: Vec<String> = manifest
+            .sections
+            .iter()
+            .flat_map(|s| s.content.iter().map(|v| v.id.to_string()))
+            .collect();
+
+        let connection = self.pool.get().await?;
+        let downloaded_ids: Vec<String> = connection
+            .interact(move |conn| -> Result<Vec<String>> {
+                use schema::videos::dsl;
+
+                Ok(dsl::videos
+                    .filter(dsl::id.eq_any(ids))
+                    .filter(dsl::download_status.eq(models::DOWNLOAD_STATUS_DOWNLOADED))
+                    .select(dsl::id)
+                    .get_results(conn)?)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+
+        let downloaded_ids = downloaded_ids
+            .iter()
+            .map(|id| uuid::Uuid::try_parse(id))
+            .collect::<core::result::Result<HashSet<uuid::Uuid>, _>>()?;
+
+        Ok(manifest
+            .sections
+            .iter()
+            .map(|s| {
+                let status = SectionStatus {
+                    downloaded: s
+                        .content
+                        .iter()
+                        .filter(|v| downloaded_ids.contains(&v.id))
+                        .count() as u64,
+                    total: s.content.len() as u64,
+                };
+                (s.name.clone(), status)
+            })
+            .collect())
+    }
+
     /// Returns a list of all the videos in the database.
     pub async fn list_all_videos(&self) -> Result<Vec<Video>> {
         let connection = self.pool.get().await?;
@@ -220,6 +491,102 @@ impl Database {
             .expect("Unexpected panic of a background DB thread")
     }
 
+    /// Streams all the videos in the database through the returned channel, without loading them
+    /// all in memory. The channel is closed once all the videos have been sent, or after sending
+    /// the first error.
+    pub async fn stream_all_videos(&self) -> Result<tokio::sync::mpsc::Receiver<Result<Video>>> {
+        // Number of videos read from the database ahead of the receiver
+        const BUFFER_SIZE: usize = 64;
+
+        let connection = self.pool.get().await?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(BUFFER_SIZE);
+        tokio::spawn(async move {
+            connection
+                .interact(move |conn| {
+                    use diesel::connection::DefaultLoadingMode;
+                    use schema::videos::dsl;
+
+                    let videos = match dsl::videos
+                        .select(Video::as_select())
+                        .load_iter::<Video, DefaultLoadingMode>(conn)
+                    {
+                        Ok(videos) => videos,
+                        Err(e) => {
+                            let _ = sender.blocking_send(Err(e.into()));
+                            return;
+                        }
+                    };
+                    for video in videos {
+                        let is_err = video.is_err();
+                        // Stop reading if the receiver is gone
+                        if sender.blocking_send(video.map_err(Into::into)).is_err() || is_err {
+                            return;
+                        }
+                    }
+                })
+                .await
+                .expect("Unexpected panic of a background DB thread")
+        });
+        Ok(receiver)
+    }
+
+    /// Returns up to `limit` downloaded videos, ordered by id and starting after the video with id
+    /// `after`, if given. Used to go through the downloaded videos in batches.
+    pub async fn downloaded_videos_after(
+        &self,
+        after: Option<uuid::Uuid>,
+        limit: usize,
+    ) -> Result<Vec<Video>> {
+        let after = after.map(|id| id.to_string()).unwrap_or_default();
+
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |conn| {
+                use schema::videos::dsl;
+
+                let videos: Vec<Video> = dsl::videos
+                    .filter(dsl::download_status.eq(models::DOWNLOAD_STATUS_DOWNLOADED))
+                    .filter(dsl::id.gt(after))
+                    .order(dsl::id)
+                    .limit(limit as i64)
+                    .select(Video::as_select())
+                    .get_results(conn)?;
+                Ok(videos)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
+    }
+
+    /// Returns the number of videos in each download status.
+    pub async fn stats(&self) -> Result<Stats> {
+        let connection = self.pool.get().await?;
+        let counts: Vec<(i64, i64)> = connection
+            .interact(move |conn| -> Result<Vec<(i64, i64)>> {
+                use schema::videos::dsl;
+
+                Ok(dsl::videos
+                    .group_by(dsl::download_status)
+                    .select((dsl::download_status, diesel::dsl::count_star()))
+                    .get_results(conn)?)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+
+        let mut stats = Stats::default();
+        for (status, count) in counts {
+            let count = count as u64;
+            match status {
+                models::DOWNLOAD_STATUS_NOT_STARTED => stats.pending += count,
+                models::DOWNLOAD_STATUS_FAILED => stats.failed += count,
+                models::DOWNLOAD_STATUS_IN_PROGRESS => stats.downloading += count,
+                models::DOWNLOAD_STATUS_DOWNLOADED => stats.downloaded += count,
+                v => return Err(Error::InvalidDownloadStatus(v)),
+            }
+            stats.total += count;
+        }
+        Ok(stats)
+    }
+
     /// Finds a video by UUID
     pub async fn find_video(&self, req_id: uuid::Uuid) -> Result<Video> {
         let req_id = req_id.to_string();
@@ -263,8 +630,13 @@ impl Database {
         let connection = self.pool.get().await?;
         connection
             .interact(move |c| {
-                diesel::delete(videos.filter(id.eq(req_id))).execute(c)?;
-                Ok(())
+                c.transaction(|c| {
+                    diesel::delete(videos.filter(id.eq(&req_id))).execute(c)?;
+                    use schema::video_events::dsl as events;
+                    diesel::delete(events::video_events.filter(events::video_id.eq(&req_id)))
+                        .execute(c)?;
+                    Ok(())
+                })
             })
             .await
             .expect("Unexpected panic of a background DB thread")
@@ -283,10 +655,14 @@ impl Database {
         let connection = self.pool.get().await?;
         connection
             .interact(move |c| {
-                diesel::insert_into(schema::videos::dsl::videos)
-                    .values(new_vid)
-                    .execute(c)?;
-                Ok(())
+                c.transaction(|c| {
+                    let id = new_vid.id.clone();
+                    diesel::insert_into(schema::videos::dsl::videos)
+                        .values(new_vid)
+                        .execute(c)?;
+                    record_event(c, &id, VideoEventKind::Queued, "")?;
+                    Ok(())
+                })
             })
             .await
             .expect("Unexpected panic of a background DB thread")
@@ -307,22 +683,23 @@ impl Database {
             .expect("Unexpected panic of a background DB thread")
     }
 
-    /// Updates the download progress for a given video. `downloaded_size` should be
-    /// smaller than the file size of the video.
-    pub async fn update_download_progress(
-        &self,
-        req_id: uuid::Uuid,
-        downloaded_size: u64,
-    ) -> Result<()> {
+    /// Stores the known values of the media information of a video. Unknown values are left as
+    /// they are.
+    pub async fn update_media_info(&self, req_id: uuid::Uuid, media_info: MediaInfo) -> Result<()> {
         let connection = self.pool.get().await?;
         connection
             .interact(move |c| {
                 use schema::videos::dsl;
+                if media_info.duration_secs.is_none() && media_info.resolution.is_none() {
+                    return Ok(());
+                }
+                // Unknown values are skipped by the changeset, so both columns are set at once.
                 diesel::update(dsl::videos.find(req_id.to_string()))
                     .set((
-                        dsl::download_status.eq(models::DOWNLOAD_STATUS_IN_PROGRESS),
-                        dsl::downloaded_size.eq(downloaded_size as i64),
-                        dsl::message.eq(""),
+                        media_info
+                            .duration_secs
+                            .map(|secs| dsl::duration_secs.eq(secs as i64)),
+                        media_info.resolution.map(|res| dsl::resolution.eq(res)),
                     ))
                     .execute(c)?;
                 Ok(())
@@ -331,20 +708,15 @@ impl Database {
             .expect("Unexpected panic of a background DB thread")
     }
 
-    /// Marks the given video as failed with the given error message.
-    pub async fn set_download_failed(&self, req_id: uuid::Uuid, message: &str) -> Result<()> {
-        let message = message.to_string(); // Need a copy since interact runs on a separate thread
-        // and requires 'static.
-
+    /// Caches the size on disk of the downloaded file of the given video. The cached size is
+    /// cleared whenever a new file is downloaded for the video.
+    pub async fn set_actual_size(&self, req_id: uuid::Uuid, actual_size: u64) -> Result<()> {
         let connection = self.pool.get().await?;
         connection
             .interact(move |c| {
                 use schema::videos::dsl;
                 diesel::update(dsl::videos.find(req_id.to_string()))
-                    .set((
-                        dsl::download_status.eq(models::DOWNLOAD_STATUS_FAILED),
-                        dsl::message.eq(message),
-                    ))
+                    .set(dsl::actual_size.eq(actual_size as i64))
                     .execute(c)?;
                 Ok(())
             })
@@ -352,86 +724,523 @@ impl Database {
             .expect("Unexpected panic of a background DB thread")
     }
 
-    /// Marks the given video as downloaded, at the given file path.
-    pub async fn set_downloaded(&self, req_id: uuid::Uuid, file_path: &Path) -> Result<()> {
-        let file_path = file_path.as_os_str().to_owned(); // Need a copy since interact runs on a separate thread
-        // and requires 'static.
+    /// Marks a video as removed from the manifest at the given time, unless it was already marked.
+    /// Returns the time at which the video was first marked as removed.
+    pub async fn mark_removed(
+        &self,
+        req_id: uuid::Uuid,
+        at: std::time::SystemTime,
+    ) -> Result<std::time::SystemTime> {
+        let at = at
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        let connection = self.pool.get().await?;
+        let removed_at = connection
+            .interact(move |c| -> Result<i64> {
+                use schema::videos::dsl;
+                diesel::update(dsl::videos.find(req_id.to_string()))
+                    .filter(dsl::removed_at.is_null())
+                    .set(dsl::removed_at.eq(at))
+                    .execute(c)?;
+                let removed_at: Option<i64> = dsl::videos
+                    .find(req_id.to_string())
+                    .select(dsl::removed_at)
+                    .get_result(c)?;
+                Ok(removed_at.unwrap_or(at))
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+        Ok(std::time::UNIX_EPOCH + std::time::Duration::from_secs(removed_at.max(0) as u64))
+    }
 
+    /// Clears the removal mark of a video which is back in the manifest.
+    pub async fn clear_removed(&self, req_id: uuid::Uuid) -> Result<()> {
         let connection = self.pool.get().await?;
         connection
             .interact(move |c| {
                 use schema::videos::dsl;
                 diesel::update(dsl::videos.find(req_id.to_string()))
-                    .set((
-                        dsl::download_status.eq(models::DOWNLOAD_STATUS_DOWNLOADED),
-                        dsl::downloaded_size.eq(dsl::file_size),
-                        dsl::message.eq(""),
-                        dsl::file_path.eq(file_path.as_encoded_bytes()),
-                    ))
+                    .filter(dsl::removed_at.is_not_null())
+                    .set(dsl::removed_at.eq(None::<i64>))
                     .execute(c)?;
                 Ok(())
             })
             .await
             .expect("Unexpected panic of a background DB thread")
     }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    use core::str::FromStr;
-    use googletest::prelude::*;
-    use std::{path::PathBuf, time::Duration};
-    use tempfile::TempDir;
 
-    fn create_dbconfig(runtime_path: &Path) -> DbConfig {
-        DbConfig {
-            busy_timeout: Duration::from_secs(2),
-            runtime_path: runtime_path.into(),
-            pool_size: 16,
-        }
+    /// Pins or unpins a video. Pinned videos are listed first, and are never deleted, even if they
+    /// are removed from the manifest.
+    pub async fn set_pinned(&self, req_id: uuid::Uuid, pinned: bool) -> Result<()> {
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |c| -> Result<()> {
+                use schema::videos::dsl;
+                let updated = diesel::update(dsl::videos.find(req_id.to_string()))
+                    .set(dsl::pinned.eq(pinned))
+                    .execute(c)?;
+                if updated == 0 {
+                    return Err(diesel::result::Error::NotFound.into());
+                }
+                Ok(())
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
     }
 
-    #[tokio::test]
-    #[googletest::test]
-    async fn test_open_db() -> googletest::Result<()> {
-        let tempdir = TempDir::new().or_fail()?;
-        let db_config = create_dbconfig(tempdir.path());
-        let _db = Database::open(db_config).await.or_fail()?;
-        Ok(())
+    /// Returns which of the given videos are in the database, with a single query.
+    pub async fn existing_video_ids(&self, ids: &[uuid::Uuid]) -> Result<HashSet<uuid::Uuid>> {
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        let connection = self.pool.get().await?;
+        let existing: Vec<String> = connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                dsl::videos
+                    .filter(dsl::id.eq_any(ids))
+                    .select(dsl::id)
+                    .load::<String>(c)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+        Ok(existing
+            .iter()
+            .map(|id| uuid::Uuid::try_parse(id))
+            .collect::<core::result::Result<_, _>>()?)
     }
 
-    #[tokio::test]
-    #[googletest::test]
-    async fn test_insert_and_get_video() -> googletest::Result<()> {
-        let tempdir = TempDir::new().or_fail()?;
-        let db_config = create_dbconfig(tempdir.path());
-        let db = Database::open(db_config).await.or_fail()?;
-        db.apply_pending_migrations().await.or_fail()?;
-
-        let uuid = uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799").or_fail()?;
-        db.insert_video(uuid, "my video", 1234567).await.or_fail()?;
+    /// Returns the IDs of the pinned videos.
+    pub async fn pinned_video_ids(&self) -> Result<HashSet<uuid::Uuid>> {
+        let connection = self.pool.get().await?;
+        let ids: Vec<String> = connection
+            .interact(|c| {
+                use schema::videos::dsl;
+                dsl::videos
+                    .filter(dsl::pinned.eq(true))
+                    .select(dsl::id)
+                    .load::<String>(c)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+        ids.iter()
+            .map(|id| Ok(uuid::Uuid::parse_str(id)?))
+            .collect()
+    }
 
-        let video = db.find_video(uuid).await.or_fail()?;
-        expect_that!(
-            video,
-            eq(&Video {
-                id: uuid,
-                name: "my video".to_string(),
-                file_size: 1234567,
-                download_status: DownloadStatus::Pending,
-                view_count: 0
+    /// Updates the download progress for a given video. `downloaded_size` should be
+    /// smaller than the file size of the video, and is clamped to it otherwise. Records a progress
+    /// event each time the download goes past a quarter of the file.
+    pub async fn update_download_progress(
+        &self,
+        req_id: uuid::Uuid,
+        downloaded_size: u64,
+    ) -> Result<()> {
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                let req_id = req_id.to_string();
+                // The row is read before it is written, so the write lock is taken upfront. SQLite
+                // fails right away, without waiting for the busy timeout, when another connection
+                // writes in between.
+                c.immediate_transaction(|c| {
+                    let (file_size, previous_size): (i64, i64) = dsl::videos
+                        .find(&req_id)
+                        .select((dsl::file_size, dsl::downloaded_size))
+                        .get_result(c)?;
+                    // The database rejects larger sizes, which would be shown as more than 100%
+                    let downloaded_size = if downloaded_size as i64 > file_size {
+                        tracing::warn!(
+                            "Download progress of video {req_id} exceeds its file size: \
+                             {downloaded_size} > {file_size}. Clamping it"
+                        );
+                        file_size
+                    } else {
+                        downloaded_size as i64
+                    };
+                    diesel::update(dsl::videos.find(&req_id))
+                        .set((
+                            dsl::download_status.eq(models::DOWNLOAD_STATUS_IN_PROGRESS),
+                            dsl::downloaded_size.eq(downloaded_size),
+                            dsl::message.eq(""),
+                        ))
+                        .execute(c)?;
+
+                    // Completion is recorded when the video is downloaded, not here
+                    let quarter = |size: i64| {
+                        if file_size > 0 {
+                            size * 4 / file_size
+                        } else {
+                            0
+                        }
+                    };
+                    let reached = quarter(downloaded_size);
+                    if reached > quarter(previous_size) && reached < 4 {
+                        let message = format!("{}%", reached * 25);
+                        record_event(c, &req_id, VideoEventKind::Progress, &message)?;
+                    }
+                    Ok(())
+                })
             })
-        );
-        Ok(())
+            .await
+            .expect("Unexpected panic of a background DB thread")
     }
 
-    #[tokio::test]
-    #[googletest::test]
-    async fn test_increment_view_count() -> googletest::Result<()> {
-        let tempdir = TempDir::new().or_fail()?;
-        let db_config = create_dbconfig(tempdir.path());
+    /// Atomically marks the given video as in progress if it is pending or failed. Returns whether
+    /// the caller claimed the download, in which case it is responsible for downloading the video.
+    /// Videos which are already downloaded or being downloaded by someone else can't be claimed.
+    pub async fn claim_for_download(&self, req_id: uuid::Uuid) -> Result<bool> {
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                let req_id = req_id.to_string();
+                c.transaction(|c| {
+                    let claimed = diesel::update(dsl::videos.find(&req_id).filter(
+                        dsl::download_status.eq_any([
+                            models::DOWNLOAD_STATUS_NOT_STARTED,
+                            models::DOWNLOAD_STATUS_FAILED,
+                        ]),
+                    ))
+                    .set((
+                        dsl::download_status.eq(models::DOWNLOAD_STATUS_IN_PROGRESS),
+                        dsl::downloaded_size.eq(0),
+                        dsl::message.eq(""),
+                    ))
+                    .execute(c)?;
+                    if claimed == 1 {
+                        record_event(c, &req_id, VideoEventKind::Started, "")?;
+                    }
+                    Ok(claimed == 1)
+                })
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
+    }
+
+    /// Marks the given video as pending, discarding its download progress and failed attempts.
+    pub async fn reset_download(&self, req_id: uuid::Uuid) -> Result<()> {
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                let req_id = req_id.to_string();
+                c.transaction(|c| {
+                    diesel::update(dsl::videos.find(&req_id))
+                        .set((
+                            dsl::download_status.eq(models::DOWNLOAD_STATUS_NOT_STARTED),
+                            dsl::downloaded_size.eq(0),
+                            dsl::message.eq(""),
+                            dsl::failure_kind.eq(models::FAILURE_KIND_UNKNOWN),
+                            dsl::failed_attempts.eq(0),
+                            dsl::retry_at.eq(None::<i64>),
+                        ))
+                        .execute(c)?;
+                    record_event(c, &req_id, VideoEventKind::Queued, "")?;
+                    Ok(())
+                })
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
+    }
+
+    /// Records the time at which the failed download of a video is retried, so that its backoff is
+    /// honored after a restart.
+    pub async fn set_retry_at(&self, req_id: uuid::Uuid, at: std::time::SystemTime) -> Result<()> {
+        let at = at
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                diesel::update(dsl::videos.find(req_id.to_string()))
+                    .set(dsl::retry_at.eq(at))
+                    .execute(c)?;
+                Ok(())
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
+    }
+
+    /// Returns the time at which the failed download of a video is retried, if any. It is cleared
+    /// once the video is downloaded or reset.
+    pub async fn retry_at(&self, req_id: uuid::Uuid) -> Result<Option<std::time::SystemTime>> {
+        let connection = self.pool.get().await?;
+        let retry_at = connection
+            .interact(move |c| -> Result<Option<i64>> {
+                use schema::videos::dsl;
+                Ok(dsl::videos
+                    .find(req_id.to_string())
+                    .select(dsl::retry_at)
+                    .get_result(c)?)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+        Ok(retry_at
+            .map(|at| std::time::UNIX_EPOCH + std::time::Duration::from_secs(at.max(0) as u64)))
+    }
+
+    /// Marks the given video as failed with the given error message, counting one more failed
+    /// attempt.
+    pub async fn set_download_failed(
+        &self,
+        req_id: uuid::Uuid,
+        message: &str,
+        kind: FailureKind,
+    ) -> Result<()> {
+        let message = message.to_string(); // Need a copy since interact runs on a separate thread
+        // and requires 'static.
+
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                let req_id = req_id.to_string();
+                c.transaction(|c| {
+                    diesel::update(dsl::videos.find(&req_id))
+                        .set((
+                            dsl::download_status.eq(models::DOWNLOAD_STATUS_FAILED),
+                            dsl::message.eq(&message),
+                            dsl::failure_kind.eq(kind.to_db()),
+                            dsl::failed_attempts.eq(dsl::failed_attempts + 1),
+                        ))
+                        .execute(c)?;
+                    record_event(c, &req_id, VideoEventKind::Failed, &message)?;
+                    Ok(())
+                })
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
+    }
+
+    /// Marks the given video as downloaded, at the given file path.
+    pub async fn set_downloaded(&self, req_id: uuid::Uuid, file_path: &Path) -> Result<()> {
+        let file_path = file_path.as_os_str().to_owned(); // Need a copy since interact runs on a separate thread
+        // and requires 'static.
+
+        let connection = self.pool.get().await?;
+        connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                let req_id = req_id.to_string();
+                c.transaction(|c| {
+                    diesel::update(dsl::videos.find(&req_id))
+                        .set((
+                            dsl::download_status.eq(models::DOWNLOAD_STATUS_DOWNLOADED),
+                            dsl::downloaded_size.eq(dsl::file_size),
+                            dsl::message.eq(""),
+                            dsl::file_path.eq(file_path.as_encoded_bytes()),
+                            dsl::actual_size.eq(None::<i64>),
+                            dsl::failure_kind.eq(models::FAILURE_KIND_UNKNOWN),
+                            dsl::failed_attempts.eq(0),
+                            dsl::retry_at.eq(None::<i64>),
+                        ))
+                        .execute(c)?;
+                    record_event(c, &req_id, VideoEventKind::Completed, "")?;
+                    Ok(())
+                })
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
+    }
+
+    /// Returns the download history of a video, oldest event first. Only the latest
+    /// [`MAX_EVENTS_PER_VIDEO`] events are kept.
+    pub async fn video_events(&self, req_id: uuid::Uuid) -> Result<Vec<VideoEvent>> {
+        let connection = self.pool.get().await?;
+        let events = connection
+            .interact(move |c| -> Result<Vec<(i64, i64, String)>> {
+                use schema::video_events::dsl;
+                Ok(dsl::video_events
+                    .filter(dsl::video_id.eq(req_id.to_string()))
+                    .order(dsl::seq.asc())
+                    .select((dsl::at, dsl::kind, dsl::message))
+                    .load(c)?)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")?;
+        Ok(events
+            .into_iter()
+            // Events of kinds unknown to this version, e.g. recorded by a newer server, are skipped
+            .filter_map(|(at, kind, message)| {
+                Some(VideoEvent {
+                    at: std::time::UNIX_EPOCH + std::time::Duration::from_millis(at.max(0) as u64),
+                    kind: VideoEventKind::from_db(kind)?,
+                    message,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Appends an event to the download history of a video, deleting its oldest events beyond
+/// [`MAX_EVENTS_PER_VIDEO`]. Meant to be called in the same transaction as the state change the
+/// event records.
+fn record_event(
+    c: &mut SqliteConnection,
+    video_id: &str,
+    kind: VideoEventKind,
+    message: &str,
+) -> QueryResult<()> {
+    use schema::video_events::dsl;
+    let at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+    diesel::insert_into(dsl::video_events)
+        .values((
+            dsl::video_id.eq(video_id),
+            dsl::at.eq(at),
+            dsl::kind.eq(kind.to_db()),
+            dsl::message.eq(message),
+        ))
+        .execute(c)?;
+
+    let oldest_kept: Option<i32> = dsl::video_events
+        .filter(dsl::video_id.eq(video_id))
+        .order(dsl::seq.desc())
+        .offset(MAX_EVENTS_PER_VIDEO - 1)
+        .select(dsl::seq)
+        .first(c)
+        .optional()?;
+    if let Some(oldest_kept) = oldest_kept {
+        diesel::delete(
+            dsl::video_events
+                .filter(dsl::video_id.eq(video_id))
+                .filter(dsl::seq.lt(oldest_kept)),
+        )
+        .execute(c)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use core::str::FromStr;
+    use googletest::prelude::*;
+    use std::{path::PathBuf, time::Duration};
+    use tempfile::TempDir;
+
+    fn create_dbconfig(runtime_path: &Path) -> DbConfig {
+        DbConfig {
+            busy_timeout: Duration::from_secs(2),
+            runtime_path: runtime_path.into(),
+            pool_size: 16,
+        }
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_open_db() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let _db = Database::open(db_config).await.or_fail()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_database_of_newer_version_is_rejected() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+        // Applying the migrations again is a no-op
+        db.apply_pending_migrations().await.or_fail()?;
+
+        // A migration of a future version of the server
+        db.pool
+            .get()
+            .await
+            .or_fail()?
+            .interact(|c| {
+                diesel::sql_query(
+                    "INSERT INTO __diesel_schema_migrations (version) VALUES ('29991231000000')",
+                )
+                .execute(c)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread")
+            .or_fail()?;
+
+        let result = db.apply_pending_migrations().await;
+        expect_that!(
+            result,
+            err(matches_pattern!(Error::NewerDatabase(elements_are![eq(
+                "29991231000000"
+            )])))
+        );
+        expect_that!(
+            result.map_err(|e| e.to_string()),
+            err(contains_substring("created by a newer version"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_existing_video_ids() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let present = [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()];
+        for id in present {
+            db.insert_video(id, "video", 100).await.or_fail()?;
+        }
+        let missing = uuid::Uuid::new_v4();
+
+        expect_that!(
+            db.existing_video_ids(&[present[0], missing, present[1]])
+                .await
+                .or_fail()?,
+            unordered_elements_are![eq(&present[0]), eq(&present[1])]
+        );
+        expect_that!(db.existing_video_ids(&[]).await.or_fail()?, is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_insert_and_get_video() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let uuid = uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799").or_fail()?;
+        db.insert_video(uuid, "my video", 1234567).await.or_fail()?;
+
+        let video = db.find_video(uuid).await.or_fail()?;
+        expect_that!(
+            video,
+            eq(&Video {
+                id: uuid,
+                name: "my video".to_string(),
+                file_size: 1234567,
+                download_status: DownloadStatus::Pending,
+                view_count: 0,
+                duration_secs: None,
+                resolution: None,
+                actual_size: None,
+                pinned: false,
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_increment_view_count() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
         let db = Database::open(db_config).await.or_fail()?;
         db.apply_pending_migrations().await.or_fail()?;
 
@@ -455,7 +1264,11 @@ mod test {
                 name: "my video".to_string(),
                 file_size: 1234567,
                 download_status: DownloadStatus::Pending,
-                view_count: 3
+                view_count: 3,
+                duration_secs: None,
+                resolution: None,
+                actual_size: None,
+                pinned: false,
             })
         );
         Ok(())
@@ -482,7 +1295,11 @@ mod test {
                 name: "my video".to_string(),
                 file_size: 1234567,
                 download_status: DownloadStatus::InProgress((1234000, 1234567)),
-                view_count: 0
+                view_count: 0,
+                duration_secs: None,
+                resolution: None,
+                actual_size: None,
+                pinned: false,
             })
         );
 
@@ -496,12 +1313,51 @@ mod test {
                 name: "my video".to_string(),
                 file_size: 1234567,
                 download_status: DownloadStatus::InProgress((1234400, 1234567)),
-                view_count: 0
+                view_count: 0,
+                duration_secs: None,
+                resolution: None,
+                actual_size: None,
+                pinned: false,
             })
         );
         Ok(())
     }
 
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_progress_is_clamped_to_file_size() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let id = uuid::Uuid::new_v4();
+        db.insert_video(id, "video", 100).await.or_fail()?;
+        db.update_download_progress(id, 150).await.or_fail()?;
+        expect_that!(
+            db.find_video(id).await.or_fail()?.download_status,
+            eq(&DownloadStatus::InProgress((100, 100)))
+        );
+
+        // The database rejects the larger sizes written by other means
+        let connection = db.pool.get().await.or_fail()?;
+        let result = connection
+            .interact(move |c| {
+                use schema::videos::dsl;
+                diesel::update(dsl::videos.find(id.to_string()))
+                    .set(dsl::downloaded_size.eq(dsl::file_size + 1))
+                    .execute(c)
+            })
+            .await
+            .expect("Unexpected panic of a background DB thread");
+        expect_that!(result, err(anything()));
+        expect_that!(
+            db.find_video(id).await.or_fail()?.download_status,
+            eq(&DownloadStatus::InProgress((100, 100)))
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     #[googletest::test]
     async fn test_downloaded() -> googletest::Result<()> {
@@ -524,7 +1380,11 @@ mod test {
                 name: "my video".to_string(),
                 file_size: 1234567,
                 download_status: DownloadStatus::Downloaded("/path/to/the/file.mp4".into()),
-                view_count: 0
+                view_count: 0,
+                duration_secs: None,
+                resolution: None,
+                actual_size: None,
+                pinned: false,
             })
         );
 
@@ -542,9 +1402,12 @@ mod test {
         let uuid = uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799").or_fail()?;
         db.insert_video(uuid, "my video", 1234567).await.or_fail()?;
 
+        db.set_download_failed(uuid, "Connection reset", FailureKind::Network)
+            .await?;
         db.set_download_failed(
             uuid,
             "Something failed, but I kid you not, I don't know what it is",
+            FailureKind::Unknown,
         )
         .await?;
 
@@ -555,12 +1418,92 @@ mod test {
                 id: uuid,
                 name: "my video".to_string(),
                 file_size: 1234567,
-                download_status: DownloadStatus::Failed(
-                    "Something failed, but I kid you not, I don't know what it is".to_string()
-                ),
-                view_count: 0
+                download_status: DownloadStatus::Failed {
+                    message: "Something failed, but I kid you not, I don't know what it is"
+                        .to_string(),
+                    kind: FailureKind::Unknown,
+                    attempts: 2,
+                },
+                view_count: 0,
+                duration_secs: None,
+                resolution: None,
+                actual_size: None,
+                pinned: false,
+            })
+        );
+
+        // Resetting the download forgets the failed attempts
+        db.reset_download(uuid).await.or_fail()?;
+        db.set_download_failed(uuid, "Bad checksum", FailureKind::Checksum)
+            .await?;
+        expect_that!(
+            db.find_video(uuid).await.or_fail()?.download_status,
+            eq(&DownloadStatus::Failed {
+                message: "Bad checksum".to_string(),
+                kind: FailureKind::Checksum,
+                attempts: 1,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_stats() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let stats = db.stats().await.or_fail()?;
+        expect_that!(stats, eq(Stats::default()));
+        expect_false!(stats.is_fully_cached());
+
+        let ids = [
+            "bf978778-1c5d-44b3-b2c1-1cc253563799",
+            "5eb9e089-79cf-478d-9121-9ca3e7bb1d4a",
+            "eddb4450-a9ff-4a4b-ad81-2a8b78998405",
+            "f47e6cdc-1bcf-439a-9ea4-038dc7153648",
+            "9e0f44b6-3dc6-4f56-8c9f-7e28feac1d03",
+        ]
+        .map(|id| uuid::Uuid::from_str(id).unwrap());
+        for id in &ids {
+            db.insert_video(*id, "my video", 1234).await.or_fail()?;
+        }
+
+        db.set_downloaded(ids[0], Path::new("/path/a.mp4"))
+            .await
+            .or_fail()?;
+        db.set_downloaded(ids[1], Path::new("/path/b.mp4"))
+            .await
+            .or_fail()?;
+        db.update_download_progress(ids[2], 12).await.or_fail()?;
+        db.set_download_failed(ids[3], "failed", FailureKind::Unknown)
+            .await
+            .or_fail()?;
+
+        let stats = db.stats().await.or_fail()?;
+        expect_that!(
+            stats,
+            eq(Stats {
+                total: 5,
+                downloaded: 2,
+                downloading: 1,
+                failed: 1,
+                pending: 1,
             })
         );
+        expect_false!(stats.is_fully_cached());
+
+        for id in &ids[2..] {
+            db.set_downloaded(*id, Path::new("/path/c.mp4"))
+                .await
+                .or_fail()?;
+        }
+        let stats = db.stats().await.or_fail()?;
+        expect_that!(stats.downloaded, eq(5));
+        expect_true!(stats.is_fully_cached());
 
         Ok(())
     }
@@ -569,6 +1512,9 @@ mod test {
         Ok(ManifestFile {
             name: "manifest".to_string(),
             date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
             version: crate::manifest::Version {
                 major: 2,
                 minor: 0,
@@ -577,55 +1523,65 @@ mod test {
             sections: vec![
                 crate::manifest::Section {
                     name: "".to_string(),
+                    description: None,
                     content: vec![
                         crate::manifest::Video {
                             name: "Linear equations".to_string(),
                             id: uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799")
                                 .or_fail()?,
                             uri: "s3://bucket/linear-equations.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123456,
+                            duration_secs: None,
+                            resolution: None,
                         },
                         crate::manifest::Video {
                             name: "Quadratic equations".to_string(),
                             id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a")
                                 .or_fail()?,
                             uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123457,
+                            duration_secs: None,
+                            resolution: None,
                         },
                     ],
                 },
                 crate::manifest::Section {
                     name: "Integration".to_string(),
+                    description: Some("Areas under curves".to_string()),
                     content: vec![
                         crate::manifest::Video {
                             name: "Riemann sum".to_string(),
                             id: uuid::Uuid::from_str("eddb4450-a9ff-4a4b-ad81-2a8b78998405")
                                 .or_fail()?,
                             uri: "s3://bucket/riemann-sum.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "a6d3b80cd14f78b21ffbf5995bbda38ad8834459557782d245ed720134d36fc4"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123459,
+                            duration_secs: None,
+                            resolution: None,
                         },
                         crate::manifest::Video {
                             name: "List of integrals".to_string(),
                             id: uuid::Uuid::from_str("f47e6cdc-1bcf-439a-9ea4-038dc7153648")
                                 .or_fail()?,
                             uri: "s3://bucket/list-of-integrals.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "98780990e94fb55d0b88ebcd78fe82f069eac547731a4b0822332d826c970aec"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123460,
+                            duration_secs: None,
+                            resolution: None,
                         },
                     ],
                 },
@@ -678,11 +1634,12 @@ mod test {
         let sections = db.current_manifest_sections().await.or_fail()?;
 
         assert_that!(sections.len(), eq(manifest.sections.len()));
-        for ((name, content), manifest_section) in sections.iter().zip(manifest.sections) {
-            expect_that!(name, eq(&manifest_section.name));
-            expect_that!(content.len(), eq(manifest_section.content.len()));
+        for (section, manifest_section) in sections.iter().zip(manifest.sections) {
+            expect_that!(section.name, eq(&manifest_section.name));
+            expect_that!(section.description, eq(&manifest_section.description));
+            expect_that!(section.videos.len(), eq(manifest_section.content.len()));
 
-            for (video, manifest_video) in content.iter().zip(manifest_section.content) {
+            for (video, manifest_video) in section.videos.iter().zip(manifest_section.content) {
                 expect_that!(
                     video,
                     matches_pattern!(Video {
@@ -691,6 +1648,10 @@ mod test {
                         file_size: eq(&manifest_video.file_size),
                         download_status: eq(&DownloadStatus::Pending),
                         view_count: eq(&0),
+                        duration_secs: eq(&None),
+                        resolution: eq(&None),
+                        actual_size: eq(&None),
+                        pinned: eq(&false),
                     })
                 );
             }
@@ -698,4 +1659,400 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_related_videos() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config.clone()).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let mut manifest = manifest_for_test()?;
+        let mut extra = manifest.sections[1].content[0].clone();
+        extra.id = uuid::Uuid::from_str("0ff3a4d4-2b6f-4e8f-a1c5-5c1d8e4e0a11").or_fail()?;
+        extra.name = "Integration by parts".to_string();
+        manifest.sections[1].content.insert(1, extra);
+        db.publish_manifest(&manifest).await;
+        for video in manifest.sections.iter().flat_map(|s| &s.content) {
+            db.insert_video(video.id, &video.name, video.file_size)
+                .await
+                .or_fail()?;
+        }
+
+        let related_names = |id| {
+            let db = &db;
+            async move {
+                db.related_videos(id)
+                    .await
+                    .map(|videos| videos.into_iter().map(|v| v.name).collect::<Vec<_>>())
+            }
+        };
+        let integration = &manifest.sections[1].content;
+        expect_that!(
+            related_names(integration[1].id).await.or_fail()?,
+            elements_are![eq("Riemann sum"), eq("List of integrals")]
+        );
+        expect_that!(
+            related_names(integration[0].id).await.or_fail()?,
+            elements_are![eq("Integration by parts"), eq("List of integrals")]
+        );
+        expect_that!(
+            related_names(manifest.sections[0].content[0].id)
+                .await
+                .or_fail()?,
+            elements_are![eq("Quadratic equations")]
+        );
+        expect_that!(
+            related_names(uuid::Uuid::new_v4()).await.or_fail()?,
+            is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_pinned_videos() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config.clone()).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let manifest = manifest_for_test()?;
+        db.publish_manifest(&manifest).await;
+        for video in manifest.sections.iter().flat_map(|s| &s.content) {
+            db.insert_video(video.id, &video.name, video.file_size)
+                .await
+                .or_fail()?;
+        }
+        let section_names = || async {
+            db.current_manifest_sections().await.map(|sections| {
+                sections
+                    .into_iter()
+                    .map(|s| s.videos.into_iter().map(|v| v.name).collect::<Vec<_>>())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let pinned_id = manifest.sections[1].content[1].id;
+        db.set_pinned(pinned_id, true).await.or_fail()?;
+        expect_true!(db.find_video(pinned_id).await.or_fail()?.pinned);
+        expect_that!(
+            db.pinned_video_ids().await.or_fail()?,
+            elements_are![eq(&pinned_id)]
+        );
+        expect_that!(
+            section_names().await.or_fail()?,
+            elements_are![
+                elements_are![eq("Linear equations"), eq("Quadratic equations")],
+                elements_are![eq("List of integrals"), eq("Riemann sum")],
+            ]
+        );
+        // Pinned videos are listed first in the pages too
+        let page = db
+            .current_manifest_sections_page(2, Some(1), false)
+            .await
+            .or_fail()?;
+        let page_ids: Vec<_> = page.iter().flat_map(|s| &s.videos).map(|v| v.id).collect();
+        expect_that!(page_ids, elements_are![eq(&pinned_id)]);
+
+        db.set_pinned(pinned_id, false).await.or_fail()?;
+        expect_false!(db.find_video(pinned_id).await.or_fail()?.pinned);
+        expect_that!(db.pinned_video_ids().await.or_fail()?, is_empty());
+        expect_that!(
+            section_names().await.or_fail()?,
+            elements_are![
+                elements_are![eq("Linear equations"), eq("Quadratic equations")],
+                elements_are![eq("Riemann sum"), eq("List of integrals")],
+            ]
+        );
+
+        expect_true!(matches!(
+            db.set_pinned(uuid::Uuid::new_v4(), true).await,
+            Err(Error::Diesel(diesel::result::Error::NotFound))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_current_manifest_sections_with_missing_video() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config.clone()).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let manifest = manifest_for_test()?;
+        db.publish_manifest(&manifest).await;
+        let missing = manifest.sections[1].content[0].id;
+        for video in manifest.sections.iter().flat_map(|s| &s.content) {
+            if video.id != missing {
+                db.insert_video(video.id, &video.name, video.file_size)
+                    .await
+                    .or_fail()?;
+            }
+        }
+
+        expect_that!(
+            db.current_manifest_sections().await,
+            err(matches_pattern!(Error::MissingVideoInDb(eq(&missing))))
+        );
+        expect_that!(
+            db.current_manifest_sections_page(0, None, false).await,
+            err(matches_pattern!(Error::MissingVideoInDb(eq(&missing))))
+        );
+
+        // The rest of the videos are listed in the manifest order
+        let sections = db
+            .current_manifest_sections_page(0, None, true)
+            .await
+            .or_fail()?;
+        assert_that!(sections.len(), eq(manifest.sections.len()));
+        for (section, manifest_section) in sections.iter().zip(&manifest.sections) {
+            expect_that!(
+                section.videos.iter().map(|v| v.id).collect::<Vec<_>>(),
+                eq(&manifest_section
+                    .content
+                    .iter()
+                    .map(|v| v.id)
+                    .filter(|id| *id != missing)
+                    .collect::<Vec<_>>())
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_current_manifest_sections_page() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config.clone()).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        expect_that!(db.count_videos().await, eq(0));
+
+        let manifest = manifest_for_test()?;
+        db.publish_manifest(&manifest).await;
+        for video in manifest.sections.iter().flat_map(|s| &s.content) {
+            db.insert_video(video.id, &video.name, video.file_size)
+                .await
+                .or_fail()?;
+        }
+
+        let page_names = |page: Vec<ManifestSection>| -> Vec<(String, Vec<String>)> {
+            page.into_iter()
+                .map(|s| (s.name, s.videos.into_iter().map(|v| v.name).collect()))
+                .collect()
+        };
+
+        // The page spans both sections
+        let page = db
+            .current_manifest_sections_page(1, Some(2), false)
+            .await
+            .or_fail()?;
+        expect_that!(
+            page_names(page),
+            eq(&vec![
+                ("".to_string(), vec!["Quadratic equations".to_string()]),
+                ("Integration".to_string(), vec!["Riemann sum".to_string()]),
+            ])
+        );
+
+        // Sections without videos in the page are omitted
+        let page = db
+            .current_manifest_sections_page(3, Some(10), false)
+            .await
+            .or_fail()?;
+        expect_that!(
+            page_names(page),
+            eq(&vec![(
+                "Integration".to_string(),
+                vec!["List of integrals".to_string()]
+            )])
+        );
+
+        let page = db
+            .current_manifest_sections_page(4, None, false)
+            .await
+            .or_fail()?;
+        expect_that!(page, is_empty());
+
+        // The count does not depend on the pagination
+        expect_that!(db.count_videos().await, eq(4));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_section_status() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config.clone()).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let manifest = manifest_for_test()?;
+        for video in manifest.sections.iter().flat_map(|s| &s.content) {
+            db.insert_video(video.id, &video.name, video.file_size)
+                .await
+                .or_fail()?;
+        }
+
+        // Fully download the first section, and only one video of the second one.
+        for video in &manifest.sections[0].content {
+            db.set_downloaded(video.id, Path::new("/path/to/file.mp4"))
+                .await
+                .or_fail()?;
+        }
+        let partial_section = &manifest.sections[1].content;
+        db.set_downloaded(partial_section[0].id, Path::new("/path/to/file.mp4"))
+            .await
+            .or_fail()?;
+        db.update_download_progress(partial_section[1].id, 100)
+            .await
+            .or_fail()?;
+
+        let status = db.section_status(&manifest).await.or_fail()?;
+        expect_that!(
+            status,
+            eq(&vec![
+                (
+                    "".to_string(),
+                    SectionStatus {
+                        downloaded: 2,
+                        total: 2
+                    }
+                ),
+                (
+                    "Integration".to_string(),
+                    SectionStatus {
+                        downloaded: 1,
+                        total: 2
+                    }
+                ),
+            ])
+        );
+        expect_true!(status[0].1.is_ready());
+        expect_false!(status[1].1.is_ready());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_stream_all_videos() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let manifest = manifest_for_test()?;
+        for video in manifest.sections.iter().flat_map(|s| &s.content) {
+            db.insert_video(video.id, &video.name, video.file_size)
+                .await
+                .or_fail()?;
+        }
+
+        let mut receiver = db.stream_all_videos().await.or_fail()?;
+        let mut streamed = vec![];
+        while let Some(video) = receiver.recv().await {
+            streamed.push(video.or_fail()?);
+        }
+
+        let mut expected = db.list_all_videos().await.or_fail()?;
+        expected.sort_by_key(|v| v.id);
+        streamed.sort_by_key(|v| v.id);
+        expect_that!(streamed, eq(&expected));
+        expect_that!(streamed.len(), eq(4));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[googletest::test]
+    async fn test_claim_for_download() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let uuid = uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799").or_fail()?;
+        db.insert_video(uuid, "my video", 1234567).await.or_fail()?;
+
+        // Only one of the racing claims can win
+        let (first, second) =
+            tokio::join!(db.claim_for_download(uuid), db.claim_for_download(uuid));
+        expect_true!(first.or_fail()? ^ second.or_fail()?);
+        expect_that!(
+            db.find_video(uuid).await.or_fail()?.download_status,
+            eq(&DownloadStatus::InProgress((0, 1234567)))
+        );
+
+        // Failed downloads can be claimed again
+        db.set_download_failed(uuid, "error", FailureKind::Network)
+            .await
+            .or_fail()?;
+        expect_true!(db.claim_for_download(uuid).await.or_fail()?);
+
+        // Downloaded videos can't be claimed
+        db.set_downloaded(uuid, &PathBuf::from("/tmp/video.mp4"))
+            .await
+            .or_fail()?;
+        expect_false!(db.claim_for_download(uuid).await.or_fail()?);
+
+        // Unknown videos can't be claimed either
+        let unknown = uuid::Uuid::from_str("fa978778-1c5d-44b3-b2c1-1cc253563799").or_fail()?;
+        expect_false!(db.claim_for_download(unknown).await.or_fail()?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_video_events_progress_milestones_and_cap() -> googletest::Result<()> {
+        let tempdir = TempDir::new().or_fail()?;
+        let db_config = create_dbconfig(tempdir.path());
+        let db = Database::open(db_config).await.or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+
+        let id = uuid::Uuid::new_v4();
+        db.insert_video(id, "video", 100).await.or_fail()?;
+        expect_true!(db.claim_for_download(id).await.or_fail()?);
+        for downloaded_size in [10, 20, 30, 60, 99] {
+            db.update_download_progress(id, downloaded_size)
+                .await
+                .or_fail()?;
+        }
+        let events = db.video_events(id).await.or_fail()?;
+        expect_that!(
+            events
+                .iter()
+                .map(|e| (e.kind, e.message.as_str()))
+                .collect::<Vec<_>>(),
+            elements_are![
+                eq(&(VideoEventKind::Queued, "")),
+                eq(&(VideoEventKind::Started, "")),
+                eq(&(VideoEventKind::Progress, "25%")),
+                eq(&(VideoEventKind::Progress, "50%")),
+                eq(&(VideoEventKind::Progress, "75%")),
+            ]
+        );
+
+        // Only the latest events are kept
+        for attempt in 0..MAX_EVENTS_PER_VIDEO {
+            db.set_download_failed(id, &format!("attempt {attempt}"), FailureKind::Network)
+                .await
+                .or_fail()?;
+        }
+        let events = db.video_events(id).await.or_fail()?;
+        expect_that!(events.len() as i64, eq(MAX_EVENTS_PER_VIDEO));
+        expect_that!(events[0].message, eq("attempt 0"));
+        expect_that!(
+            events[events.len() - 1].message,
+            eq(&format!("attempt {}", MAX_EVENTS_PER_VIDEO - 1))
+        );
+
+        // The history is deleted along with the video
+        db.delete_video(id).await.or_fail()?;
+        expect_that!(db.video_events(id).await.or_fail()?, is_empty());
+        Ok(())
+    }
 }
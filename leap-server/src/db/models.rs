@@ -2,7 +2,7 @@ use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
 
 use diesel::{
     prelude::*,
-    sql_types::{BigInt, Binary, Text},
+    sql_types::{BigInt, Binary, Nullable, Text},
 };
 
 use super::schema;
@@ -10,11 +10,51 @@ use super::schema;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DownloadStatus {
     Pending,
-    Failed(String),
+    Failed {
+        message: String,
+        kind: FailureKind,
+        /// Number of failed download attempts since the video was last downloaded or reset
+        attempts: u32,
+    },
     InProgress((u64, u64)),
     Downloaded(PathBuf),
 }
 
+/// Cause of a failed download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    Unknown,
+    Network,
+    Checksum,
+    Storage,
+    Interrupted,
+}
+
+impl FailureKind {
+    /// Value stored in the database
+    pub fn to_db(self) -> i64 {
+        match self {
+            FailureKind::Unknown => FAILURE_KIND_UNKNOWN,
+            FailureKind::Network => FAILURE_KIND_NETWORK,
+            FailureKind::Checksum => FAILURE_KIND_CHECKSUM,
+            FailureKind::Storage => FAILURE_KIND_STORAGE,
+            FailureKind::Interrupted => FAILURE_KIND_INTERRUPTED,
+        }
+    }
+
+    /// Parses the value stored in the database. Unknown values are reported as
+    /// [`FailureKind::Unknown`], so that older servers can read the values of newer ones.
+    fn from_db(value: i64) -> Self {
+        match value {
+            FAILURE_KIND_NETWORK => FailureKind::Network,
+            FAILURE_KIND_CHECKSUM => FailureKind::Checksum,
+            FAILURE_KIND_STORAGE => FailureKind::Storage,
+            FAILURE_KIND_INTERRUPTED => FailureKind::Interrupted,
+            _ => FailureKind::Unknown,
+        }
+    }
+}
+
 impl DownloadStatus {
     pub fn is_downloaded(&self) -> bool {
         matches!(self, DownloadStatus::Downloaded(_))
@@ -28,6 +68,8 @@ impl Selectable<diesel::sqlite::Sqlite> for DownloadStatus {
         schema::videos::dsl::download_status,
         schema::videos::dsl::message,
         schema::videos::dsl::file_path,
+        schema::videos::dsl::failure_kind,
+        schema::videos::dsl::failed_attempts,
     );
 
     fn construct_selection() -> Self::SelectExpression {
@@ -37,19 +79,35 @@ impl Selectable<diesel::sqlite::Sqlite> for DownloadStatus {
             schema::videos::dsl::download_status,
             schema::videos::dsl::message,
             schema::videos::dsl::file_path,
+            schema::videos::dsl::failure_kind,
+            schema::videos::dsl::failed_attempts,
         )
     }
 }
 
-impl Queryable<(BigInt, BigInt, BigInt, Text, Binary), diesel::sqlite::Sqlite> for DownloadStatus {
-    type Row = (i64, i64, i64, String, Vec<u8>);
+impl Queryable<(BigInt, BigInt, BigInt, Text, Binary, BigInt, BigInt), diesel::sqlite::Sqlite>
+    for DownloadStatus
+{
+    type Row = (i64, i64, i64, String, Vec<u8>, i64, i64);
 
     fn build(
-        (file_size, downloaded_size, download_status, message, file_path): Self::Row,
+        (
+            file_size,
+            downloaded_size,
+            download_status,
+            message,
+            file_path,
+            failure_kind,
+            failed_attempts,
+        ): Self::Row,
     ) -> diesel::deserialize::Result<Self> {
         Ok(match download_status {
             DOWNLOAD_STATUS_NOT_STARTED => DownloadStatus::Pending,
-            DOWNLOAD_STATUS_FAILED => DownloadStatus::Failed(message),
+            DOWNLOAD_STATUS_FAILED => DownloadStatus::Failed {
+                message,
+                kind: FailureKind::from_db(failure_kind),
+                attempts: u32::try_from(failed_attempts)?,
+            },
             DOWNLOAD_STATUS_IN_PROGRESS => {
                 DownloadStatus::InProgress((downloaded_size as u64, file_size as u64))
             }
@@ -63,11 +121,88 @@ impl Queryable<(BigInt, BigInt, BigInt, Text, Binary), diesel::sqlite::Sqlite> f
     }
 }
 
+/// Nullable unsigned integer, which SQLite stores as a signed integer.
+pub struct NullableU64(Option<u64>);
+
+impl Queryable<Nullable<BigInt>, diesel::sqlite::Sqlite> for NullableU64 {
+    type Row = Option<i64>;
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(NullableU64(row.map(u64::try_from).transpose()?))
+    }
+}
+
+impl From<NullableU64> for Option<u64> {
+    fn from(value: NullableU64) -> Self {
+        value.0
+    }
+}
+
 pub const DOWNLOAD_STATUS_NOT_STARTED: i64 = 0;
 pub const DOWNLOAD_STATUS_FAILED: i64 = 1;
 pub const DOWNLOAD_STATUS_IN_PROGRESS: i64 = 2;
 pub const DOWNLOAD_STATUS_DOWNLOADED: i64 = 3;
 
+pub const FAILURE_KIND_UNKNOWN: i64 = 0;
+pub const FAILURE_KIND_NETWORK: i64 = 1;
+pub const FAILURE_KIND_CHECKSUM: i64 = 2;
+pub const FAILURE_KIND_STORAGE: i64 = 3;
+pub const FAILURE_KIND_INTERRUPTED: i64 = 4;
+
+pub const VIDEO_EVENT_QUEUED: i64 = 0;
+pub const VIDEO_EVENT_STARTED: i64 = 1;
+pub const VIDEO_EVENT_PROGRESS: i64 = 2;
+pub const VIDEO_EVENT_FAILED: i64 = 3;
+pub const VIDEO_EVENT_COMPLETED: i64 = 4;
+
+/// Kind of an event in the download history of a video
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoEventKind {
+    /// The video is waiting to be downloaded, either for the first time or after a reset
+    Queued,
+    /// A download of the video started
+    Started,
+    /// The download reached a milestone, given in the message of the event
+    Progress,
+    /// The download failed, with the error in the message of the event
+    Failed,
+    /// The video was downloaded
+    Completed,
+}
+
+impl VideoEventKind {
+    /// Value stored in the database
+    pub fn to_db(self) -> i64 {
+        match self {
+            VideoEventKind::Queued => VIDEO_EVENT_QUEUED,
+            VideoEventKind::Started => VIDEO_EVENT_STARTED,
+            VideoEventKind::Progress => VIDEO_EVENT_PROGRESS,
+            VideoEventKind::Failed => VIDEO_EVENT_FAILED,
+            VideoEventKind::Completed => VIDEO_EVENT_COMPLETED,
+        }
+    }
+
+    /// Parses the value stored in the database, or returns `None` if it is unknown.
+    pub fn from_db(value: i64) -> Option<Self> {
+        match value {
+            VIDEO_EVENT_QUEUED => Some(VideoEventKind::Queued),
+            VIDEO_EVENT_STARTED => Some(VideoEventKind::Started),
+            VIDEO_EVENT_PROGRESS => Some(VideoEventKind::Progress),
+            VIDEO_EVENT_FAILED => Some(VideoEventKind::Failed),
+            VIDEO_EVENT_COMPLETED => Some(VideoEventKind::Completed),
+            _ => None,
+        }
+    }
+}
+
+/// An event in the download history of a video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoEvent {
+    pub at: std::time::SystemTime,
+    pub kind: VideoEventKind,
+    pub message: String,
+}
+
 #[derive(Queryable, Debug, Clone, PartialEq, Eq)]
 #[diesel(table_name = schema::videos)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
@@ -84,6 +219,18 @@ pub struct Video {
 
     #[diesel(deserialize_as = i64)]
     pub view_count: u64,
+
+    #[diesel(deserialize_as = NullableU64)]
+    pub duration_secs: Option<u64>,
+
+    pub resolution: Option<String>,
+
+    /// Size of the downloaded file on disk, cached once it was first computed
+    #[diesel(deserialize_as = NullableU64)]
+    pub actual_size: Option<u64>,
+
+    /// Whether the video was pinned by a user, so that it is listed first and never deleted
+    pub pinned: bool,
 }
 
 impl Selectable<diesel::sqlite::Sqlite> for Video {
@@ -93,6 +240,10 @@ impl Selectable<diesel::sqlite::Sqlite> for Video {
         schema::videos::dsl::file_size,
         <DownloadStatus as Selectable<diesel::sqlite::Sqlite>>::SelectExpression,
         schema::videos::dsl::view_count,
+        schema::videos::dsl::duration_secs,
+        schema::videos::dsl::resolution,
+        schema::videos::dsl::actual_size,
+        schema::videos::dsl::pinned,
     );
 
     fn construct_selection() -> Self::SelectExpression {
@@ -102,6 +253,10 @@ impl Selectable<diesel::sqlite::Sqlite> for Video {
             schema::videos::dsl::file_size,
             <DownloadStatus as Selectable<diesel::sqlite::Sqlite>>::construct_selection(),
             schema::videos::dsl::view_count,
+            schema::videos::dsl::duration_secs,
+            schema::videos::dsl::resolution,
+            schema::videos::dsl::actual_size,
+            schema::videos::dsl::pinned,
         )
     }
 }
@@ -113,3 +268,27 @@ pub struct NewVideo {
     pub name: String,
     pub file_size: i64,
 }
+
+/// Media information of a video. Missing values are unknown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaInfo {
+    /// Duration in whole seconds
+    pub duration_secs: Option<u64>,
+
+    /// Resolution as `<width>x<height>` (e.g.: `1920x1080`)
+    pub resolution: Option<String>,
+}
+
+impl MediaInfo {
+    pub fn is_empty(&self) -> bool {
+        self.duration_secs.is_none() && self.resolution.is_none()
+    }
+
+    /// Fills the values missing in `self` with the ones from `other`.
+    pub fn or(self, other: MediaInfo) -> MediaInfo {
+        MediaInfo {
+            duration_secs: self.duration_secs.or(other.duration_secs),
+            resolution: self.resolution.or(other.resolution),
+        }
+    }
+}
@@ -10,5 +10,23 @@ diesel::table! {
         view_count -> BigInt,
         message -> Text,
         file_path -> Binary,
+        duration_secs -> Nullable<BigInt>,
+        resolution -> Nullable<Text>,
+        actual_size -> Nullable<BigInt>,
+        removed_at -> Nullable<BigInt>,
+        failure_kind -> BigInt,
+        failed_attempts -> BigInt,
+        pinned -> Bool,
+        retry_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    video_events (seq) {
+        seq -> Integer,
+        video_id -> Text,
+        at -> BigInt,
+        kind -> BigInt,
+        message -> Text,
     }
 }
@@ -1,71 +1,473 @@
 mod backend;
+mod checksum;
+pub mod guard;
+mod httpbackend;
+pub mod import;
+pub mod orphans;
+pub mod partial;
+mod probe;
 pub mod s3backend;
+pub mod status;
+pub mod streams;
 mod tasks;
+mod webhook;
 
 use std::{path::PathBuf, sync::Arc};
 
 use crate::{
     cfg::{DownloaderConfig, S3Config},
     db::Database,
+    manifest::ManifestFile,
+    store::ContentStore,
 };
 use backend::FileBackend;
+use httpbackend::HttpBackend;
 use s3backend::S3Backend;
+pub use status::DownloaderStatus;
 
-use tokio::sync::mpsc::UnboundedReceiver;
+use leap_api::types::ContentEvent;
+use tokio::sync::{broadcast, mpsc::UnboundedReceiver};
+
+pub use checksum::HashingPool;
+pub use tasks::partial_filepath;
 
 /// Commands received from users
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum UserCommand {
     /// User request to trigger an immediate manifest fetch
     FetchManifest,
+
+    /// User request to delete the local copy of a video and download it again
+    Redownload(uuid::Uuid),
+
+    /// User request to stop starting new downloads. Downloads in progress are completed.
+    Pause,
+
+    /// User request to start new downloads again after a pause
+    Resume,
+
+    /// Request to download the videos of the current manifest that are pending, such as the ones
+    /// restored by [`reconcile_current_manifest`]
+    DownloadPending,
+}
+
+/// Number of videos changed in the database by [`reconcile_current_manifest`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Videos of the manifest that were missing from the database
+    pub inserted: usize,
+    /// Videos that are no longer in the manifest, deleted along with their files
+    pub deleted: usize,
+}
+
+/// Reconciles the database with the published manifest, as done when a new manifest is adopted.
+/// Useful when both drifted apart, e.g.: after a manual edit of the database. Returns `None` if no
+/// manifest is published.
+pub async fn reconcile_current_manifest(
+    config: &DownloaderConfig,
+    db: &Database,
+) -> anyhow::Result<Option<Reconciliation>> {
+    let Some(manifest) = db.current_manifest().await.clone() else {
+        return Ok(None);
+    };
+    let inserted = tasks::initialize_video_entries(db, &manifest).await?;
+    let deleted =
+        tasks::remove_old_video_content(config, db, &manifest, std::time::SystemTime::now())
+            .await?;
+    tracing::info!(
+        "Reconciled the database with the manifest: {inserted} videos inserted, {deleted} deleted"
+    );
+    Ok(Some(Reconciliation { inserted, deleted }))
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("I/O error reading from backend: {0}")]
-    IoError(#[from] std::io::Error),
+    Io(#[from] std::io::Error),
+
+    #[error("The manifest exceeds the maximum size of {0} bytes")]
+    ManifestTooLarge(usize),
+
+    #[error("The manifest endpoint returned non-JSON content (content-type {0})")]
+    NotJsonManifest(String),
+
+    #[error("The offset {offset} exceeds the size of the resource ({size} bytes)")]
+    OffsetOutOfRange { offset: u64, size: u64 },
 }
 
 type DownloadJoinHandle = tokio::task::JoinHandle<anyhow::Result<()>>;
 
+/// Number of content events kept for subscribers that are slow to receive them. Once the buffer is
+/// full, the oldest events are dropped and lagging subscribers are notified about it, so that the
+/// downloader never waits for them.
+pub const CONTENT_EVENTS_CAPACITY: usize = 256;
+
+/// Sender of the changes in the download status of the videos.
+pub type EventSender = broadcast::Sender<ContentEvent>;
+
+/// Creates the channel used to broadcast the changes in the download status of the videos.
+pub fn content_events_channel() -> EventSender {
+    broadcast::channel(CONTENT_EVENTS_CAPACITY).0
+}
+
+/// Handles to the content of a channel, shared by its downloader and the HTTP handlers serving it.
+#[derive(Clone)]
+pub struct ContentHandles {
+    /// Storage of the downloaded files.
+    pub store: Arc<dyn ContentStore>,
+    /// Changes in the download status of the videos.
+    pub events: EventSender,
+    /// Offsets up to which the videos being downloaded can be streamed.
+    pub partial_writes: partial::PartialWrites,
+    /// Content streams being served, to which the downloads yield.
+    pub active_streams: streams::ActiveStreams,
+    /// Pool computing the checksums of the downloaded and imported files.
+    pub hashing: HashingPool,
+}
+
+impl ContentHandles {
+    /// Creates the handles of a channel storing its files in `store`, with a new events channel,
+    /// nothing being downloaded or streamed yet, and a pool hashing in `hashing_threads` threads.
+    pub fn new(store: Arc<dyn ContentStore>, hashing_threads: usize) -> Self {
+        Self {
+            store,
+            events: content_events_channel(),
+            partial_writes: Default::default(),
+            active_streams: Default::default(),
+            hashing: HashingPool::new(hashing_threads),
+        }
+    }
+}
+
+/// Generator of uniformly distributed random numbers in the `[0, 1)` range. It is part of the
+/// [`DownloadContext`] so that tests can make the backoff jitter deterministic.
+type Rng = Arc<dyn Fn() -> f64 + Send + Sync>;
+
+/// Returns a random number generator suitable for jitter, but not for cryptographic purposes.
+fn system_rng() -> Rng {
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // Each `RandomState` is randomly seeded, and hashing a counter makes sure that we never hash
+    // the same value twice.
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    Arc::new(|| {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        // Keep the 53 bits that fit in the mantissa of a f64
+        (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+/// Coalesces the progress events of each video, so that subscribers aren't flooded by fast
+/// downloads. Progress events are sent at most once per interval, and the other events are always
+/// sent right away.
+#[derive(Clone)]
+struct EventCoalescer {
+    interval: std::time::Duration,
+    /// Time at which the last progress event of each video in progress was sent
+    last_progress: Arc<std::sync::Mutex<std::collections::HashMap<uuid::Uuid, std::time::Instant>>>,
+}
+
+impl EventCoalescer {
+    fn new(interval: std::time::Duration) -> Self {
+        Self {
+            interval,
+            last_progress: Default::default(),
+        }
+    }
+
+    /// Returns whether an event with the given status of a video must be sent at `now`.
+    fn should_send(
+        &self,
+        id: uuid::Uuid,
+        status: &crate::db::DownloadStatus,
+        now: std::time::Instant,
+    ) -> bool {
+        let mut last_progress = self.last_progress.lock().expect("poisoned mutex");
+        if !matches!(status, crate::db::DownloadStatus::InProgress(_)) {
+            last_progress.remove(&id);
+            return true;
+        }
+        match last_progress.get(&id) {
+            Some(sent_at) if now.duration_since(*sent_at) < self.interval => false,
+            _ => {
+                last_progress.insert(id, now);
+                true
+            }
+        }
+    }
+}
+
+/// Collapses the repeated errors of the downloads, so that an unreachable backend doesn't flood the
+/// logs with the same error on every retry of every video. The first occurrence of an error is
+/// logged right away, and its repetitions within the interval are only counted. They are reported
+/// along with the next occurrence after the interval, or when the errors are flushed.
+#[derive(Clone)]
+struct ErrorLogLimiter {
+    interval: std::time::Duration,
+    /// Time at which each error was last logged, and number of repetitions since then
+    repeated: Arc<std::sync::Mutex<std::collections::HashMap<String, (std::time::Instant, u64)>>>,
+}
+
+impl ErrorLogLimiter {
+    fn new(interval: std::time::Duration) -> Self {
+        Self {
+            interval,
+            repeated: Default::default(),
+        }
+    }
+
+    /// Returns the line to log for an occurrence of the error at `now`, if any.
+    fn line(&self, msg: &str, now: std::time::Instant) -> Option<String> {
+        let mut repeated = self.repeated.lock().expect("poisoned mutex");
+        if let Some((logged_at, count)) = repeated.get_mut(msg)
+            && now.duration_since(*logged_at) < self.interval
+        {
+            *count += 1;
+            return None;
+        }
+        let count = repeated
+            .insert(msg.to_string(), (now, 0))
+            .map_or(0, |(_, count)| count);
+        Some(repeated_line(msg, count))
+    }
+
+    /// Logs an occurrence of the error, unless it was logged within the interval.
+    fn error(&self, msg: &str) {
+        if let Some(line) = self.line(msg, std::time::Instant::now()) {
+            tracing::error!("{line}");
+        }
+    }
+
+    /// Logs the last occurrence of the errors repeated since they were logged, and forgets about
+    /// all the errors.
+    fn flush(&self) {
+        let repeated = std::mem::take(&mut *self.repeated.lock().expect("poisoned mutex"));
+        for (msg, (_, count)) in repeated {
+            if count > 0 {
+                tracing::error!("{}", repeated_line(&msg, count));
+            }
+        }
+    }
+}
+
+fn repeated_line(msg: &str, count: u64) -> String {
+    match count {
+        0 => msg.to_string(),
+        count => format!("{msg} (repeated {count} times)"),
+    }
+}
+
 #[derive(Clone)]
 struct DownloadContext {
     config: Arc<DownloaderConfig>,
     backend: Arc<dyn backend::Backend>,
     db: Arc<Database>,
+    status: Arc<DownloaderStatus>,
+    store: Arc<dyn ContentStore>,
+    rng: Rng,
+    events: EventSender,
+    coalescer: EventCoalescer,
+    error_log: ErrorLogLimiter,
+    hashing: checksum::HashingPool,
+    resource_guard: Arc<dyn guard::ResourceGuard>,
+    partial_writes: partial::PartialWrites,
+    active_streams: streams::ActiveStreams,
+}
+
+impl DownloadContext {
+    /// Notifies the subscribers about a change in the download status of a video. Never blocks:
+    /// lagging subscribers lose the oldest events instead.
+    fn notify(&self, id: uuid::Uuid, status: crate::db::DownloadStatus) {
+        if !self
+            .coalescer
+            .should_send(id, &status, std::time::Instant::now())
+        {
+            return;
+        }
+        // Sending only fails when there are no subscribers, which is fine
+        let _ = self.events.send(ContentEvent {
+            id: id.to_string(),
+            status: status.into(),
+        });
+    }
+}
+
+/// Stops the pending download task, if any. Downloads of `manifest` that the task left in progress
+/// are marked as interrupted, so that they can be claimed again by the next task.
+async fn stop_pending_task(
+    ctx: &DownloadContext,
+    pending_task: &mut Option<DownloadJoinHandle>,
+    manifest: &ManifestFile,
+) -> anyhow::Result<()> {
+    let Some(old_task) = pending_task.take() else {
+        return Ok(());
+    };
+
+    if old_task.is_finished() {
+        return old_task.await?;
+    }
+
+    old_task.abort();
+    match old_task.await {
+        // This is a degenerate case in which the task is still able to finish even though
+        // we cancelled it. It can happen due to race conditions.
+        Ok(task_retval) => task_retval?,
+        Err(e) if e.is_cancelled() => {
+            tracing::info!("Canceled previous download task in favor of a new task");
+            ctx.error_log.flush();
+        }
+        Err(e) => {
+            return Err(e.into());
+        }
+    }
+
+    tasks::mark_interrupted_downloads(
+        &ctx.db,
+        manifest,
+        "Download interrupted by a newer download task",
+        crate::db::FailureKind::Interrupted,
+    )
+    .await
+}
+
+/// Result of checking the remote server for manifest updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpdateCheck {
+    /// A valid manifest was fetched, whether or not it was more recent than the current one
+    Fetched,
+
+    /// The manifest couldn't be fetched or had an invalid format
+    Failed,
+}
+
+/// Returns how long to wait before checking for manifest updates again. After a failed check, the
+/// manifest is fetched again sooner, backing off up to the regular update interval. Once the
+/// upstream is considered unreachable, it backs off further up to the maximum update interval.
+fn next_update_delay(config: &DownloaderConfig, consecutive_failures: u32) -> std::time::Duration {
+    if consecutive_failures == 0 {
+        return config.update_interval;
+    }
+    if let Some(unreachable_failures) =
+        consecutive_failures.checked_sub(config.unreachable_after_failures)
+    {
+        return config
+            .update_interval
+            .saturating_mul(2u32.saturating_pow(unreachable_failures.saturating_add(1)))
+            .min(config.max_update_interval)
+            .max(config.update_interval);
+    }
+    config
+        .manifest_retry_interval
+        .saturating_mul(2u32.saturating_pow(consecutive_failures - 1))
+        .min(config.update_interval)
 }
 
 #[tracing::instrument(name = "check_manifest_updates", skip(ctx, pending_task))]
 async fn check_updates(
     ctx: DownloadContext,
     pending_task: &mut Option<DownloadJoinHandle>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<UpdateCheck> {
     // Inspect new manifest file
-    let Ok(manifest_data) = ctx.backend.fetch_manifest().await.inspect_err(|err| {
-        tracing::error!("Error fetching manifest: {err}");
-    }) else {
-        return Ok(());
+    // The current manifest and its content keep being served when the new manifest can't be
+    // fetched, until a valid manifest is available again.
+    let manifest_data = match ctx
+        .backend
+        .fetch_manifest(ctx.config.max_manifest_bytes)
+        .await
+    {
+        Ok(manifest_data) => manifest_data,
+        Err(err) => {
+            tracing::error!("Error fetching manifest: {err}");
+            ctx.status
+                .set_upstream_error(Some(format!("Error fetching manifest: {err}")));
+            return Ok(UpdateCheck::Failed);
+        }
     };
 
-    let Ok(new_manifest) = serde_json::from_slice(&manifest_data).inspect_err(|err| {
-        tracing::error!("Received manifest with invalid format from the server: {err}");
-    }) else {
-        return Ok(());
+    let new_manifest = match crate::manifest::validate_manifest_bytes(
+        &manifest_data,
+        ctx.config.strict_manifest,
+    ) {
+        Ok(new_manifest) => new_manifest,
+        Err(err) => {
+            tracing::error!("Received manifest with invalid format from the server: {err}");
+            ctx.status.set_upstream_error(Some(format!(
+                "Received manifest with invalid format: {err}"
+            )));
+            return Ok(UpdateCheck::Failed);
+        }
     };
+    ctx.status.set_upstream_error(None);
+
+    if !new_manifest.version.is_supported() {
+        tracing::warn!(
+            "The manifest version {} is not supported by this server, which supports major \
+            versions {:?}. Parts of the manifest may be ignored or misinterpreted.",
+            new_manifest.version,
+            crate::manifest::SUPPORTED_MAJOR_VERSIONS
+        );
+    }
 
     let cur_manifest = ctx.db.current_manifest().await;
     let is_more_recent_manifest = cur_manifest
         .as_ref()
-        .is_none_or(|v| *v != new_manifest && v.date.cmp(&new_manifest.date).is_lt());
+        .is_none_or(|v| *v != new_manifest && new_manifest.is_newer_than(v));
 
     if !is_more_recent_manifest {
+        ctx.status.set_rejected_manifest(None);
         // Nothing to do, the manifest has not changed
         tracing::info!(
             "Current Manifest dated on {} is up to date",
             cur_manifest.as_ref().unwrap().date
         );
-        return Ok(());
+        // Removed videos retained for a grace period are deleted once it elapses, even if the
+        // manifest doesn't change anymore
+        if ctx.config.retain_removed_for.is_some() {
+            let manifest = cur_manifest.clone().unwrap();
+            drop(cur_manifest);
+            tasks::remove_old_video_content(
+                &ctx.config,
+                &ctx.db,
+                &manifest,
+                std::time::SystemTime::now(),
+            )
+            .await?;
+        }
+        return Ok(UpdateCheck::Fetched);
     }
+    // Manifests relying on newer features of the server are not adopted by older servers, which
+    // keep serving the current manifest
+    let server_version = crate::manifest::Version::of_server();
+    if !new_manifest.is_servable_by(&server_version) {
+        let reason = format!(
+            "The manifest dated on {} requires server version {} or later, but this server is {}",
+            new_manifest.date,
+            new_manifest.min_vds_version.as_ref().unwrap(),
+            server_version
+        );
+        ctx.error_log
+            .error(&format!("{reason}. Keeping the current manifest."));
+        ctx.status.set_rejected_manifest(Some(reason));
+        return Ok(UpdateCheck::Fetched);
+    }
+    ctx.status.set_rejected_manifest(None);
+    // Newer manifests may ask for a grace period before being adopted, in which the current one
+    // is still served. They are adopted by the first check after it elapses.
+    if !new_manifest.is_adoptable_at(chrono::Utc::now()) {
+        tracing::info!(
+            "Found updated manifest dated on {}, which won't be adopted until {}",
+            new_manifest.date,
+            new_manifest.adopt_after.unwrap()
+        );
+        return Ok(UpdateCheck::Fetched);
+    }
+    // Videos whose content changed upstream need to be downloaded again
+    let changed_videos = cur_manifest
+        .as_ref()
+        .map(|v| new_manifest.changed_videos(v))
+        .unwrap_or_default();
     drop(cur_manifest);
 
     tracing::info!("Found updated manifest dated on {}", new_manifest.date);
@@ -75,39 +477,210 @@ async fn check_updates(
     ctx.db.save_manifest_to_disk(&manifest_data).await?;
 
     // Stop existing tasks, given we found an even more recent task
-    if let Some(old_task) = pending_task.take() {
-        if old_task.is_finished() {
-            old_task.await??;
-        } else {
-            old_task.abort();
-            match old_task.await {
-                // This is a degenerate case in which the task is still able to finish even though
-                // we cancelled it. It can happen due to race conditions.
-                Ok(task_retval) => task_retval?,
-                Err(e) if e.is_cancelled() => {
-                    tracing::info!("Canceled previous download task in favor of a new task");
-                }
-                Err(e) => {
-                    return Err(e.into());
-                }
+    stop_pending_task(&ctx, pending_task, &new_manifest).await?;
+    tasks::reset_video_downloads(&ctx.config, &ctx.db, &changed_videos).await?;
+
+    let download_manifest_task = tasks::download_manifest_task(ctx, new_manifest);
+    pending_task.replace(tokio::task::spawn(download_manifest_task));
+
+    Ok(UpdateCheck::Fetched)
+}
+
+/// Returns the total size in bytes of the files in the content directory, including partial
+/// downloads and files that are not tracked by the database. Walks the whole directory, so callers
+/// should avoid calling it frequently.
+pub async fn content_disk_usage(config: &DownloaderConfig) -> std::io::Result<u64> {
+    fn dir_usage(path: &std::path::Path) -> std::io::Result<u64> {
+        let mut total = 0;
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            // Symlinks are not followed, they don't use space in the content directory
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                total += dir_usage(&entry.path())?;
+            } else if metadata.is_file() {
+                total += metadata.len();
             }
         }
+        Ok(total)
     }
 
-    let download_manifest_task = tasks::download_manifest_task(ctx, new_manifest);
+    let mut paths = vec![config.content_path.clone()];
+    let temp_download_path = config.temp_download_path();
+    if !temp_download_path.starts_with(&config.content_path) {
+        paths.push(temp_download_path);
+    }
+
+    tokio::task::spawn_blocking(move || {
+        paths
+            .iter()
+            .filter(|path| path.exists())
+            .map(|path| dir_usage(path))
+            .sum()
+    })
+    .await
+    .expect("Unexpected panic while walking the content directory")
+}
+
+/// Interval at which the free space of the content and database filesystems, and the resource
+/// guard, are checked.
+const RESOURCE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Returns the space available to unprivileged users in the filesystem of the given path.
+// The field types of statvfs differ between 32 and 64-bit targets
+#[allow(clippy::unnecessary_cast)]
+fn free_space(path: &std::path::Path) -> nix::Result<u64> {
+    let stat = nix::sys::statvfs::statvfs(path)?;
+    Ok((stat.blocks_available() as u64).saturating_mul(stat.fragment_size() as u64))
+}
+
+/// Pauses the downloads while the filesystems of the content or of the database have less free
+/// space than `min_free_bytes`, so that the database can still be written, and resumes them once
+/// there is enough space again.
+fn check_free_space(ctx: &DownloadContext) {
+    let paths = [
+        ctx.config.content_path.clone(),
+        ctx.config.temp_download_path(),
+        ctx.db.db_path(),
+    ];
+    let mut free_bytes = u64::MAX;
+    for path in paths {
+        match free_space(&path) {
+            Ok(bytes) => free_bytes = free_bytes.min(bytes),
+            Err(e) => tracing::warn!("Unable to read the free space of {path:?}: {e}"),
+        }
+    }
+    update_low_disk_space(ctx, free_bytes);
+}
+
+/// Updates whether the downloads are paused with the free space of the storage.
+fn update_low_disk_space(ctx: &DownloadContext, free_bytes: u64) {
+    let min_free_bytes = ctx.config.min_free_bytes;
+    let low = free_bytes < min_free_bytes;
+    if low && !ctx.status.low_disk_space() {
+        tracing::error!(
+            "Only {free_bytes} bytes are free, less than the minimum of {min_free_bytes}. Pausing downloads"
+        );
+    } else if !low && ctx.status.low_disk_space() {
+        tracing::info!("{free_bytes} bytes are free again. Resuming downloads");
+    }
+    ctx.status.set_low_disk_space(low);
+}
+
+/// Pauses the downloads while the resource guard reports a reason to, and resumes them once it
+/// doesn't anymore.
+fn check_resource_guard(ctx: &DownloadContext) {
+    let reason = ctx.resource_guard.pause_reason();
+    match (&reason, ctx.status.throttled()) {
+        (Some(reason), None) => tracing::warn!("Pausing downloads: {reason}"),
+        (None, Some(_)) => tracing::info!("Resuming downloads paused by the resource guard"),
+        _ => {}
+    }
+    ctx.status.set_throttled(reason);
+}
+
+/// Name of the file created to check that the download directories are writable.
+const WRITE_CHECK_FILENAME: &str = ".leap-write-check";
+
+/// Creates the download directories and checks that they are writable. Warns if the temporary
+/// download directory is not in the same filesystem as the content, because finished downloads
+/// can't be moved atomically then.
+fn prepare_download_paths(config: &DownloaderConfig) -> anyhow::Result<()> {
+    use anyhow::Context;
+    use std::os::unix::fs::MetadataExt;
+
+    let temp_download_path = config.temp_download_path();
+    let mut devices = vec![];
+    for path in [&config.content_path, &temp_download_path] {
+        std::fs::create_dir_all(path)
+            .with_context(|| format!("The download path {path:?} can't be created"))?;
+        // Fail early instead of failing every single download later on
+        let write_check = path.join(WRITE_CHECK_FILENAME);
+        std::fs::write(&write_check, b"")
+            .and_then(|_| std::fs::remove_file(&write_check))
+            .with_context(|| format!("The download path {path:?} is not writable"))?;
+        devices.push(
+            std::fs::metadata(path)
+                .with_context(|| format!("Reading metadata of {path:?}"))?
+                .dev(),
+        );
+    }
+
+    if devices[0] != devices[1] {
+        tracing::warn!(
+            "The temporary download path {temp_download_path:?} is not in the same filesystem as \
+            the content path {:?}. Finished downloads will be copied instead of atomically moved.",
+            config.content_path
+        );
+    }
+    Ok(())
+}
+
+/// Deletes the local copy of a video and restarts the download task of the current manifest, so
+/// that the video is downloaded again.
+#[tracing::instrument(name = "redownload_video", skip(ctx, pending_task))]
+async fn redownload_video(
+    ctx: DownloadContext,
+    pending_task: &mut Option<DownloadJoinHandle>,
+    id: uuid::Uuid,
+) -> anyhow::Result<()> {
+    let Some(cur_manifest) = ctx.db.current_manifest().await.clone() else {
+        tracing::error!("Unable to download video {id} again without a manifest");
+        return Ok(());
+    };
+
+    stop_pending_task(&ctx, pending_task, &cur_manifest).await?;
+    tasks::reset_video_downloads(&ctx.config, &ctx.db, &[id]).await?;
+
+    let download_manifest_task = tasks::download_manifest_task(ctx, cur_manifest);
     pending_task.replace(tokio::task::spawn(download_manifest_task));
 
     Ok(())
 }
 
-#[tracing::instrument(name = "run_downloader", skip(config, db, cmd_receiver))]
+/// Waits for the pending download task to finish, if any, and returns its result. The task is
+/// kept if the returned future is dropped before it finishes.
+async fn finish_pending_task(pending_task: &mut Option<DownloadJoinHandle>) -> anyhow::Result<()> {
+    let Some(task) = pending_task.as_mut() else {
+        return Ok(());
+    };
+    let task_retval = task.await;
+    pending_task.take();
+    task_retval?
+}
+
+/// Starts a download task of the current manifest to download its pending videos, e.g.: the ones
+/// whose files went missing. The pending task is left to finish first, so that the downloads in
+/// progress are not interrupted.
+async fn download_missing_videos(
+    ctx: DownloadContext,
+    pending_task: &mut Option<DownloadJoinHandle>,
+) -> anyhow::Result<()> {
+    finish_pending_task(pending_task).await?;
+    let Some(cur_manifest) = ctx.db.current_manifest().await.clone() else {
+        return Ok(());
+    };
+
+    let download_manifest_task = tasks::download_manifest_task(ctx, cur_manifest);
+    pending_task.replace(tokio::task::spawn(download_manifest_task));
+
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "run_downloader",
+    skip(config, db, status, content, cmd_receiver)
+)]
 pub async fn run_downloader(
     config: DownloaderConfig,
     s3_config: S3Config,
     db: Arc<Database>,
-    mut cmd_receiver: UnboundedReceiver<UserCommand>,
+    status: Arc<DownloaderStatus>,
+    content: ContentHandles,
+    cmd_receiver: UnboundedReceiver<UserCommand>,
 ) -> anyhow::Result<()> {
     let config = Arc::new(config);
+    prepare_download_paths(&config)?;
 
     // The backend can be either a local file path or an S3 bucket. We allow local filepaths
     // for simple testing of the server.
@@ -116,7 +689,7 @@ pub async fn run_downloader(
         None | Some("file") => {
             let path: PathBuf = config.remote_server.path().into();
             tracing::info!("Using file backend located at {path:?}");
-            Arc::new(FileBackend::new(&path))
+            Arc::new(FileBackend::new(&path).with_chunk_size(config.io_chunk_bytes))
         }
         Some("s3") => {
             let bucket = config
@@ -125,49 +698,393 @@ pub async fn run_downloader(
                 .ok_or_else(|| anyhow::anyhow!("S3 URI must specify a bucket name"))?;
             tracing::info!("Using S3 backend with bucket: {bucket}");
 
-            Arc::new(S3Backend::new(bucket, &s3_config).await?)
+            Arc::new(
+                S3Backend::new(bucket, &s3_config)
+                    .await?
+                    .verifying_manifest_content_type(config.verify_manifest_content_type),
+            )
+        }
+        // Used to mirror other LEAPs, with the URL of their exported manifest
+        Some("http" | "https") => {
+            tracing::info!(
+                "Using HTTP backend with manifest at {}",
+                config.remote_server
+            );
+            Arc::new(
+                HttpBackend::new(&config.remote_server, config.request_header_map()?)?
+                    .verifying_manifest_content_type(config.verify_manifest_content_type),
+            )
         }
         Some(scheme) => {
             anyhow::bail!("Unknown remote server URI scheme: {scheme}");
         }
     };
 
+    let resource_guard = guard::resource_guard(&config.resource_guard);
+    let ContentHandles {
+        store,
+        events,
+        partial_writes,
+        active_streams,
+        hashing,
+    } = content;
     let download_context = DownloadContext {
+        coalescer: EventCoalescer::new(config.progress_event_interval),
+        error_log: ErrorLogLimiter::new(config.error_log_interval),
+        hashing,
         config,
         backend,
         db,
+        status,
+        store,
+        rng: system_rng(),
+        events,
+        resource_guard,
+        partial_writes,
+        active_streams,
     };
+    run_update_loop(download_context, cmd_receiver).await
+}
 
+/// Periodically checks the remote server for manifest updates and handles the user commands.
+async fn run_update_loop(
+    download_context: DownloadContext,
+    mut cmd_receiver: UnboundedReceiver<UserCommand>,
+) -> anyhow::Result<()> {
     // We keep track of the last pending task so that we can cancel it if we discovered an
     // even-newer manifest
     let mut pending_task: Option<DownloadJoinHandle> = None;
+    let mut consecutive_failures = 0;
+    // The downloaded videos are checked in batches, starting after the last video checked. Videos
+    // whose files went missing are downloaded again once the pending task finishes.
+    let reconcile_interval = download_context.config.reconcile_interval;
+    let mut reconcile = tokio::time::interval_at(
+        tokio::time::Instant::now() + reconcile_interval,
+        reconcile_interval,
+    );
+    reconcile.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut reconcile_cursor = None;
+    let mut missing_videos = false;
+    // The resources are checked before resuming any download. The first tick of the interval
+    // completes right away, so it is consumed here.
+    let mut resource_check = tokio::time::interval(RESOURCE_CHECK_INTERVAL);
+    resource_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    check_free_space(&download_context);
+    check_resource_guard(&download_context);
+    resource_check.tick().await;
 
     // Because the system might have restarted while downloading the current manifest, we
     // have to spawn a download task to verify that it is actually downloaded, or fetch whatever
     // is remaining.
-    if let Some(cur_manifest) = download_context.db.current_manifest().await.clone() {
-        tasks::mark_interrupted_downloads(&download_context.db, &cur_manifest).await?;
+    if let Some(cur_manifest) = tasks::load_saved_manifest(&download_context.db).await? {
+        tasks::mark_interrupted_downloads(
+            &download_context.db,
+            &cur_manifest,
+            "Download interrupted due to system restart",
+            crate::db::FailureKind::Interrupted,
+        )
+        .await?;
         let download_manifest_task =
             tasks::download_manifest_task(download_context.clone(), cur_manifest);
         pending_task.replace(tokio::task::spawn(download_manifest_task));
     } else {
         // Trigger initial fetch
-        check_updates(download_context.clone(), &mut pending_task).await?;
+        let check = check_updates(download_context.clone(), &mut pending_task).await?;
+        consecutive_failures = count_failures(consecutive_failures, check);
     }
 
     loop {
-        let mut wait = std::pin::pin!(tokio::time::sleep(download_context.config.update_interval));
-        let cmd = tokio::select! {
-            _ = &mut wait => { None }
-            command = cmd_receiver.recv() => {
-                command
+        let delay = next_update_delay(&download_context.config, consecutive_failures);
+        if consecutive_failures > 0
+            && consecutive_failures == download_context.config.unreachable_after_failures
+        {
+            tracing::warn!(
+                "The upstream is unreachable after {consecutive_failures} failed fetches. Backing off up to {:?}",
+                download_context.config.max_update_interval
+            );
+        }
+        if consecutive_failures > 0 {
+            tracing::info!("Fetching the manifest again in {delay:?} after a failed fetch");
+        }
+        let mut wait = std::pin::pin!(tokio::time::sleep(delay));
+        let cmd = loop {
+            tokio::select! {
+                _ = &mut wait => { break None }
+                command = cmd_receiver.recv() => {
+                    break command
+                }
+                // Reconciling doesn't delay the next update check
+                _ = reconcile.tick() => {
+                    let demoted =
+                        tasks::reconcile_downloads(&download_context, &mut reconcile_cursor).await?;
+                    missing_videos |= !demoted.is_empty();
+                }
+                result = download_missing_videos(download_context.clone(), &mut pending_task),
+                    if missing_videos =>
+                {
+                    result?;
+                    missing_videos = false;
+                }
+                _ = resource_check.tick() => {
+                    check_free_space(&download_context);
+                    check_resource_guard(&download_context);
+                }
             }
         };
 
-        if let Some(UserCommand::FetchManifest) = cmd {
-            tracing::info!("Handling user-requested fetch");
+        match cmd {
+            Some(UserCommand::Redownload(id)) => {
+                tracing::info!("Handling user-requested redownload of video {id}");
+                redownload_video(download_context.clone(), &mut pending_task, id).await?;
+            }
+            Some(UserCommand::Pause) => {
+                tracing::info!("Pausing downloads on user request");
+                download_context.status.set_downloads_paused(true);
+            }
+            Some(UserCommand::Resume) => {
+                tracing::info!("Resuming downloads on user request");
+                download_context.status.set_downloads_paused(false);
+            }
+            Some(UserCommand::DownloadPending) => {
+                tracing::info!("Downloading the pending videos of the current manifest");
+                missing_videos = true;
+            }
+            Some(UserCommand::FetchManifest) => {
+                tracing::info!("Handling user-requested fetch");
+                let check = check_updates(download_context.clone(), &mut pending_task).await?;
+                consecutive_failures = count_failures(consecutive_failures, check);
+            }
+            None => {
+                let check = check_updates(download_context.clone(), &mut pending_task).await?;
+                consecutive_failures = count_failures(consecutive_failures, check);
+            }
+        }
+    }
+}
+
+/// Updates the number of consecutive failed update checks with the result of the latest check.
+fn count_failures(consecutive_failures: u32, check: UpdateCheck) -> u32 {
+    match check {
+        UpdateCheck::Fetched => 0,
+        UpdateCheck::Failed => consecutive_failures.saturating_add(1),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::cfg::RetryParams;
+    use googletest::prelude::*;
+    use std::time::Duration;
+
+    fn downloader_config(content_path: PathBuf) -> DownloaderConfig {
+        DownloaderConfig {
+            concurrent_downloads: 1,
+            solo_download_bytes: None,
+            streaming_concurrent_downloads: None,
+            yield_to_streams: crate::cfg::DEFAULT_YIELD_TO_STREAMS,
+            content_path,
+            temp_download_path: None,
+            retry_params: RetryParams {
+                initial_backoff: Duration::from_millis(100),
+                backoff_factor: 1.0,
+                max_backoff: Duration::from_millis(100),
+                backoff_jitter_fraction: 0.5,
+            },
+            remote_server: "/Invalid".try_into().unwrap(),
+            update_interval: Duration::from_secs(300),
+            max_manifest_bytes: crate::cfg::DEFAULT_MAX_MANIFEST_BYTES,
+            manual_fetch_cooldown: crate::cfg::DEFAULT_MANUAL_FETCH_COOLDOWN,
+            manifest_retry_interval: crate::cfg::DEFAULT_MANIFEST_RETRY_INTERVAL,
+            unreachable_after_failures: crate::cfg::DEFAULT_UNREACHABLE_AFTER_FAILURES,
+            max_update_interval: crate::cfg::DEFAULT_MAX_UPDATE_INTERVAL,
+            retain_removed_for: None,
+            completion_webhook_url: None,
+            existing_file_check: crate::cfg::ExistingFileCheck::default(),
+            reconcile_interval: crate::cfg::DEFAULT_RECONCILE_INTERVAL,
+            progress_event_interval: crate::cfg::DEFAULT_PROGRESS_EVENT_INTERVAL,
+            error_log_interval: crate::cfg::DEFAULT_ERROR_LOG_INTERVAL,
+            allowed_uri_schemes: None,
+            min_free_bytes: 0,
+            strict_manifest: false,
+            verify_manifest_content_type: true,
+            resource_guard: Default::default(),
+            io_chunk_bytes: crate::cfg::DEFAULT_IO_CHUNK_BYTES,
+            hashing_threads: crate::cfg::DEFAULT_HASHING_THREADS,
+            request_headers: Default::default(),
+            channels: Default::default(),
         }
+    }
+
+    #[googletest::test]
+    fn test_prepare_download_paths_not_writable() -> googletest::Result<()> {
+        // A directory can't be created inside a regular file, regardless of the permissions of the
+        // user running the test
+        let parent = tempfile::NamedTempFile::new().or_fail()?;
+        let config = downloader_config(parent.path().join("content"));
+
+        let result = prepare_download_paths(&config);
+        expect_that!(
+            result.map_err(|e| e.to_string()),
+            err(contains_substring("can't be created"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_prepare_download_paths_creates_directories() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let config = downloader_config(tempdir.path().join("content"));
+
+        prepare_download_paths(&config).or_fail()?;
+        expect_true!(config.temp_download_path().is_dir());
+        expect_false!(config.content_path.join(WRITE_CHECK_FILENAME).exists());
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_next_update_delay() -> googletest::Result<()> {
+        let mut config = downloader_config("/Invalid".into());
+        config.update_interval = Duration::from_secs(60);
+        config.manifest_retry_interval = Duration::from_secs(5);
+        // Never back off beyond the update interval
+        config.max_update_interval = config.update_interval;
+
+        expect_that!(next_update_delay(&config, 0), eq(Duration::from_secs(60)));
+        expect_that!(next_update_delay(&config, 1), eq(Duration::from_secs(5)));
+        expect_that!(next_update_delay(&config, 2), eq(Duration::from_secs(10)));
+        expect_that!(next_update_delay(&config, 4), eq(Duration::from_secs(40)));
+        expect_that!(next_update_delay(&config, 5), eq(Duration::from_secs(60)));
+        expect_that!(
+            next_update_delay(&config, u32::MAX),
+            eq(Duration::from_secs(60))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_next_update_delay_when_unreachable() -> googletest::Result<()> {
+        let mut config = downloader_config("/Invalid".into());
+        config.update_interval = Duration::from_secs(60);
+        config.manifest_retry_interval = Duration::from_secs(5);
+        config.unreachable_after_failures = 3;
+        config.max_update_interval = Duration::from_secs(600);
+
+        let mut consecutive_failures = 0;
+        let mut delays = vec![];
+        for _ in 0..7 {
+            consecutive_failures = count_failures(consecutive_failures, UpdateCheck::Failed);
+            delays.push(next_update_delay(&config, consecutive_failures).as_secs());
+        }
+        expect_that!(
+            delays,
+            elements_are![
+                eq(&5),
+                eq(&10),
+                eq(&120),
+                eq(&240),
+                eq(&480),
+                eq(&600),
+                eq(&600)
+            ]
+        );
+
+        // The first successful fetch resets the backoff
+        consecutive_failures = count_failures(consecutive_failures, UpdateCheck::Fetched);
+        expect_that!(
+            next_update_delay(&config, consecutive_failures),
+            eq(Duration::from_secs(60))
+        );
+        expect_that!(
+            next_update_delay(&config, count_failures(0, UpdateCheck::Failed)),
+            eq(Duration::from_secs(5))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_content_disk_usage() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let mut config = downloader_config(tempdir.path().join("content"));
+        expect_that!(content_disk_usage(&config).await.or_fail()?, eq(0));
+
+        // Downloaded, partial and orphan files are all accounted for
+        prepare_download_paths(&config).or_fail()?;
+        std::fs::write(config.content_path.join("video.mp4"), [0; 100]).or_fail()?;
+        std::fs::write(config.temp_download_path().join("partial.mp4"), [0; 20]).or_fail()?;
+        std::fs::write(config.content_path.join("orphan"), [0; 3]).or_fail()?;
+        expect_that!(content_disk_usage(&config).await.or_fail()?, eq(123));
+
+        // So is the temporary download directory, if it is elsewhere
+        let temp_download_path = tempdir.path().join("tmp");
+        std::fs::create_dir(&temp_download_path).or_fail()?;
+        std::fs::write(temp_download_path.join("partial.mp4"), [0; 7]).or_fail()?;
+        config.temp_download_path = Some(temp_download_path);
+        expect_that!(content_disk_usage(&config).await.or_fail()?, eq(130));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_progress_events_are_coalesced() {
+        use crate::db::{DownloadStatus, FailureKind};
+
+        let coalescer = EventCoalescer::new(Duration::from_millis(100));
+        let (first, second) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let start = std::time::Instant::now();
+
+        // A progress update every millisecond for a second
+        let sent: Vec<_> = (0..1000u64)
+            .filter(|&ms| {
+                let status = DownloadStatus::InProgress((ms, 1000));
+                coalescer.should_send(first, &status, start + Duration::from_millis(ms))
+            })
+            .collect();
+        expect_that!(sent, len(eq(10)));
+        expect_that!(sent, each(predicate(|ms: &u64| ms.is_multiple_of(100))));
+
+        // The progress of other videos is sent independently
+        let status = DownloadStatus::InProgress((1, 1000));
+        expect_true!(coalescer.should_send(second, &status, start + Duration::from_millis(999)));
+
+        // Terminal events are never dropped, and the next download starts reporting right away
+        let now = start + Duration::from_millis(999);
+        let downloaded = DownloadStatus::Downloaded("/content/video.mp4".into());
+        expect_true!(coalescer.should_send(first, &downloaded, now));
+        let failed = DownloadStatus::Failed {
+            message: "Network error".to_string(),
+            kind: FailureKind::Network,
+            attempts: 1,
+        };
+        expect_true!(coalescer.should_send(second, &failed, now));
+        expect_true!(coalescer.should_send(second, &failed, now));
+        expect_true!(coalescer.should_send(second, &status, now));
+    }
+
+    #[googletest::test]
+    fn test_repeated_errors_are_collapsed() {
+        let limiter = ErrorLogLimiter::new(Duration::from_millis(100));
+        let start = std::time::Instant::now();
+
+        // The same error every millisecond for a second is logged once per interval
+        let lines: Vec<_> = (0..1000u64)
+            .filter_map(|ms| limiter.line("Backend down", start + Duration::from_millis(ms)))
+            .collect();
+        expect_that!(lines, len(eq(10)));
+        expect_that!(lines[0], eq("Backend down"));
+        expect_that!(
+            lines[1..].to_vec(),
+            each(eq("Backend down (repeated 99 times)"))
+        );
+
+        // Other errors are logged independently
+        let now = start + Duration::from_millis(999);
+        expect_that!(limiter.line("Disk full", now), some(eq("Disk full")));
+        expect_that!(limiter.line("Disk full", now), none());
 
-        check_updates(download_context.clone(), &mut pending_task).await?;
+        // Flushing forgets the errors, so that they are logged right away again
+        limiter.flush();
+        expect_that!(limiter.line("Backend down", now), some(eq("Backend down")));
     }
 }
@@ -4,7 +4,7 @@ use std::pin::Pin;
 use crate::downloader::Error;
 
 use async_stream::stream;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio_stream::Stream;
 
 pub type ChunkResult = Result<Vec<u8>, Error>;
@@ -16,11 +16,41 @@ pub trait Backend: Sync + Send {
         &'a self,
         uri: &'b http::Uri,
     ) -> Pin<Box<dyn Stream<Item = ChunkResult> + Send + 'a>>
+    where
+        'b: 'a,
+    {
+        self.fetch_resource_from(uri, 0)
+    }
+
+    /// Fetches a resource from the given URI, skipping its first `offset` bytes, e.g. to resume an
+    /// interrupted download. The stream is empty if `offset` is the size of the resource, and
+    /// fails with [`Error::OffsetOutOfRange`] if it is larger.
+    fn fetch_resource_from<'a, 'b>(
+        &'a self,
+        uri: &'b http::Uri,
+        offset: u64,
+    ) -> Pin<Box<dyn Stream<Item = ChunkResult> + Send + 'a>>
     where
         'b: 'a;
 
-    /// Obtains the current manifest from the upstream
-    async fn fetch_manifest(&self) -> Result<Vec<u8>, Error>;
+    /// Obtains the current manifest from the upstream. Fails with [`Error::ManifestTooLarge`]
+    /// without reading the rest of the manifest once it exceeds `max_size` bytes.
+    async fn fetch_manifest(&self, max_size: usize) -> Result<Vec<u8>, Error>;
+}
+
+/// Fails with [`Error::NotJsonManifest`] if the manifest fetched from an endpoint is clearly not
+/// JSON, e.g. an HTML login or error page served by a misconfigured upstream. It is the case when
+/// its content type is HTML, or when its content doesn't start as a JSON object.
+pub fn check_manifest_content(content_type: Option<&str>, content: &[u8]) -> Result<(), Error> {
+    let not_json = || Error::NotJsonManifest(content_type.unwrap_or("unknown").to_string());
+    if content_type.is_some_and(|content_type| content_type.to_lowercase().contains("html")) {
+        return Err(not_json());
+    }
+    let content = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    match content.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{') | None => Ok(()),
+        Some(_) => Err(not_json()),
+    }
 }
 
 const DEFAULT_CHUNK_SIZE: usize = 1024;
@@ -38,13 +68,19 @@ impl FileBackend {
             chunk_size: DEFAULT_CHUNK_SIZE,
         }
     }
+
+    /// Reads the resources in chunks of `chunk_size` bytes.
+    pub fn with_chunk_size(self, chunk_size: usize) -> Self {
+        Self { chunk_size, ..self }
+    }
 }
 
 #[async_trait::async_trait]
 impl Backend for FileBackend {
-    fn fetch_resource<'a, 'b>(
+    fn fetch_resource_from<'a, 'b>(
         &'a self,
         uri: &'b http::Uri,
+        offset: u64,
     ) -> Pin<Box<dyn Stream<Item = ChunkResult> + Send + 'a>>
     where
         'b: 'a,
@@ -54,6 +90,13 @@ impl Backend for FileBackend {
             let path = self.base_path.join(relpath);
             let mut file = tokio::fs::File::open(path).await?;
 
+            let size = file.metadata().await?.len();
+            if offset > size {
+                yield Err(Error::OffsetOutOfRange { offset, size });
+                return;
+            }
+            file.seek(std::io::SeekFrom::Start(offset)).await?;
+
             loop {
                 let mut chunk = vec![0; self.chunk_size];
                 let n = file.read(&mut chunk[..]).await?;
@@ -66,9 +109,19 @@ impl Backend for FileBackend {
         })
     }
 
-    async fn fetch_manifest(&self) -> Result<Vec<u8>, Error> {
+    async fn fetch_manifest(&self, max_size: usize) -> Result<Vec<u8>, Error> {
         let manifest_path = self.base_path.join("manifest.json");
-        Ok(tokio::fs::read(manifest_path).await?)
+        let file = tokio::fs::File::open(manifest_path).await?;
+
+        // Read one byte past the limit to find out whether the manifest exceeds it
+        let mut data = Vec::new();
+        file.take(max_size as u64 + 1)
+            .read_to_end(&mut data)
+            .await?;
+        if data.len() > max_size {
+            return Err(Error::ManifestTooLarge(max_size));
+        }
+        Ok(data)
     }
 }
 
@@ -107,4 +160,120 @@ mod test {
 
         Ok(())
     }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn read_resource_with_custom_chunk_size() -> googletest::Result<()> {
+        use googletest::prelude::*;
+
+        let temp_dir = tempfile::TempDir::new().or_fail()?;
+        let v: Vec<u8> = (0..8321).map(|i| (i % 251) as u8).collect();
+        std::fs::write(temp_dir.path().join("video.mp4"), &v).or_fail()?;
+
+        for chunk_size in [1, 7, 4096, 100_000] {
+            let backend = FileBackend::new(temp_dir.path()).with_chunk_size(chunk_size);
+            let uri = Uri::from_static("/video.mp4");
+            let chunks: Vec<_> = backend.fetch_resource(&uri).collect().await;
+
+            let mut data = vec![];
+            for chunk in chunks.iter() {
+                let chunk = chunk.as_ref().or_fail()?;
+                expect_that!(chunk.len(), le(chunk_size), "chunk size {chunk_size}");
+                data.extend_from_slice(chunk);
+            }
+            expect_that!(data, eq(&v), "chunk size {chunk_size}");
+            expect_that!(chunks.len(), eq(v.len().div_ceil(chunk_size)));
+        }
+        Ok(())
+    }
+
+    /// Reads the resource at `offset` from a file backend serving `content`.
+    async fn read_resource_from(content: &[u8], offset: u64) -> googletest::Result<Vec<u8>> {
+        let temp_dir = tempfile::TempDir::new().or_fail()?;
+        std::fs::write(temp_dir.path().join("video.mp4"), content).or_fail()?;
+
+        let backend = FileBackend::new(temp_dir.path());
+        let uri = Uri::from_static("/video.mp4");
+        let mut stream = backend.fetch_resource_from(&uri, offset);
+
+        let mut data = vec![];
+        while let Some(chunk) = stream.next().await {
+            data.extend(chunk.or_fail()?);
+        }
+        Ok(data)
+    }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn read_resource_from_offset_using_file_backend() -> googletest::Result<()> {
+        use googletest::prelude::*;
+
+        let v: Vec<u8> = (0..8321).map(|i| (i % 251) as u8).collect();
+
+        expect_that!(read_resource_from(&v, 0).await, ok(eq(&v)));
+        // Offsets in the middle of a chunk
+        for offset in [1, 1500, 8000] {
+            expect_that!(
+                read_resource_from(&v, offset).await,
+                ok(eq(&v[offset as usize..])),
+                "offset {offset}"
+            );
+        }
+        // Offset at a chunk boundary
+        let offset = 2 * DEFAULT_CHUNK_SIZE;
+        expect_that!(
+            read_resource_from(&v, offset as u64).await,
+            ok(eq(&v[offset..]))
+        );
+        expect_that!(read_resource_from(&v, v.len() as u64).await, ok(is_empty()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn read_resource_beyond_end_using_file_backend() -> googletest::Result<()> {
+        use googletest::prelude::*;
+
+        let temp_dir = tempfile::TempDir::new().or_fail()?;
+        std::fs::write(temp_dir.path().join("video.mp4"), [0; 10]).or_fail()?;
+
+        let backend = FileBackend::new(temp_dir.path());
+        let uri = Uri::from_static("/video.mp4");
+        let chunks: Vec<_> = backend.fetch_resource_from(&uri, 11).collect().await;
+        expect_that!(chunks, len(eq(1)));
+        expect_true!(matches!(
+            chunks[0],
+            Err(Error::OffsetOutOfRange {
+                offset: 11,
+                size: 10
+            })
+        ));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn check_manifest_content_rejects_html() {
+        use googletest::prelude::*;
+
+        expect_that!(
+            check_manifest_content(Some("application/json"), b"{}"),
+            ok(anything())
+        );
+        // Object storages often serve the files with a generic content type
+        expect_that!(
+            check_manifest_content(Some("binary/octet-stream"), b"\xEF\xBB\xBF\n  {}"),
+            ok(anything())
+        );
+        expect_that!(
+            check_manifest_content(Some("text/html; charset=utf-8"), b"{}"),
+            err(displays_as(eq(
+                "The manifest endpoint returned non-JSON content (content-type text/html; \
+                 charset=utf-8)"
+            )))
+        );
+        expect_that!(
+            check_manifest_content(None, b"<!DOCTYPE html>"),
+            err(displays_as(contains_substring("content-type unknown")))
+        );
+    }
 }
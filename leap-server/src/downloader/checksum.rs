@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use sha2::Digest;
+use tokio::sync::Semaphore;
+
+use crate::manifest::Checksum;
+
+/// Size of the batches of data hashed in the pool. Larger batches spend less time handing the data
+/// over to the pool, at the cost of memory.
+const HASHING_BATCH_BYTES: usize = 1024 * 1024;
+
+/// Batches smaller than this are hashed right away, as handing them over to the pool would take
+/// longer than hashing them. Small files are hashed this way.
+const INLINE_HASHING_BYTES: usize = 64 * 1024;
+
+/// Incremental hasher computing the checksum of a file with the algorithm of the expected
+/// checksum, so that both can be compared.
+pub enum ChecksumHasher {
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl ChecksumHasher {
+    pub fn new(expected: &Checksum) -> Self {
+        match expected {
+            Checksum::Sha256(_) => ChecksumHasher::Sha256(sha2::Sha256::new()),
+            Checksum::Sha512(_) => ChecksumHasher::Sha512(sha2::Sha512::new()),
+            Checksum::Blake3(_) => ChecksumHasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            ChecksumHasher::Sha256(hasher) => hasher.update(data),
+            ChecksumHasher::Sha512(hasher) => hasher.update(data),
+            ChecksumHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> Checksum {
+        match self {
+            ChecksumHasher::Sha256(hasher) => Checksum::Sha256(
+                hasher
+                    .finalize()
+                    .as_slice()
+                    .try_into()
+                    .expect("Should have 32 bytes"),
+            ),
+            ChecksumHasher::Sha512(hasher) => Checksum::Sha512(
+                hasher
+                    .finalize()
+                    .as_slice()
+                    .try_into()
+                    .expect("Should have 64 bytes"),
+            ),
+            ChecksumHasher::Blake3(hasher) => Checksum::Blake3(
+                hasher
+                    .finalize()
+                    .as_bytes()
+                    .as_slice()
+                    .try_into()
+                    .expect("Should have 32 bytes"),
+            ),
+        }
+    }
+}
+
+/// Bounded pool of blocking threads computing the checksums, so that hashing large files doesn't
+/// block the worker threads of the async runtime, which also serve the HTTP requests.
+#[derive(Clone)]
+pub struct HashingPool {
+    permits: Arc<Semaphore>,
+}
+
+impl HashingPool {
+    /// Creates a pool hashing in at most `threads` threads at once.
+    pub fn new(threads: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(threads)),
+        }
+    }
+
+    /// Returns an incremental hasher computing the checksum in the pool.
+    pub fn hasher(&self, expected: &Checksum) -> PooledHasher {
+        PooledHasher {
+            permits: Arc::clone(&self.permits),
+            hasher: Some(ChecksumHasher::new(expected)),
+            pending: Vec::new(),
+        }
+    }
+}
+
+/// Incremental hasher of a [`HashingPool`]. The data is hashed in batches of
+/// [`HASHING_BATCH_BYTES`], in the order in which it is given.
+pub struct PooledHasher {
+    permits: Arc<Semaphore>,
+    // Only missing while a batch is being hashed in the pool
+    hasher: Option<ChecksumHasher>,
+    pending: Vec<u8>,
+}
+
+impl PooledHasher {
+    pub async fn update(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        if self.pending.len() >= HASHING_BATCH_BYTES {
+            self.hash_pending().await;
+        }
+    }
+
+    pub async fn finalize(mut self) -> Checksum {
+        self.hash_pending().await;
+        self.hasher
+            .take()
+            .expect("The hasher is only missing while hashing")
+            .finalize()
+    }
+
+    async fn hash_pending(&mut self) {
+        if self.pending.len() < INLINE_HASHING_BYTES {
+            self.hasher
+                .as_mut()
+                .expect("The hasher is only missing while hashing")
+                .update(&self.pending);
+            self.pending.clear();
+            return;
+        }
+
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .expect("The hashing semaphore is never closed");
+        let mut hasher = self
+            .hasher
+            .take()
+            .expect("The hasher is only missing while hashing");
+        let mut pending = std::mem::take(&mut self.pending);
+        let (hasher, pending) = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            hasher.update(&pending);
+            // The buffer is given back, so that the next batch reuses it
+            pending.clear();
+            (hasher, pending)
+        })
+        .await
+        .expect("Unexpected panic of a hashing thread");
+        self.hasher = Some(hasher);
+        self.pending = pending;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+
+    fn checksum(algo: &str, value: &str) -> googletest::Result<Checksum> {
+        serde_json::from_value(serde_json::json!({ "algo": algo, "value": value })).or_fail()
+    }
+
+    fn hash(expected: &Checksum, data: &[u8]) -> Checksum {
+        let mut hasher = ChecksumHasher::new(expected);
+        // Hash in several chunks, as the downloader does
+        for chunk in data.chunks(2) {
+            hasher.update(chunk);
+        }
+        hasher.finalize()
+    }
+
+    /// Checksums of `abc` with each of the algorithms
+    const ABC_CHECKSUMS: [(&str, &str); 3] = [
+        (
+            "sha256",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            "sha512",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+            2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        ),
+        (
+            "blake3",
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+        ),
+    ];
+
+    #[googletest::test]
+    fn test_checksum_algorithms() -> googletest::Result<()> {
+        for (algo, value) in ABC_CHECKSUMS {
+            let expected = checksum(algo, value)?;
+            expect_that!(hash(&expected, b"abc"), eq(&expected), "{algo}");
+            expect_that!(hash(&expected, b"abd"), not(eq(&expected)), "{algo}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_pooled_hasher_matches_inline_hashing() -> googletest::Result<()> {
+        let pool = HashingPool::new(1);
+        // Several batches, and a last partial one
+        let data: Vec<u8> = (0..5 * HASHING_BATCH_BYTES + 12345)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        for (algo, value) in ABC_CHECKSUMS {
+            // Small data is hashed inline
+            let abc = checksum(algo, value)?;
+            let mut hasher = pool.hasher(&abc);
+            hasher.update(b"abc").await;
+            expect_that!(hasher.finalize().await, eq(&abc), "{algo}");
+
+            let mut reference = ChecksumHasher::new(&abc);
+            reference.update(&data);
+            let expected = reference.finalize();
+            let mut hasher = pool.hasher(&expected);
+            // Chunks of the size yielded by the backends
+            for chunk in data.chunks(16 * 1024) {
+                hasher.update(chunk).await;
+            }
+            expect_that!(hasher.finalize().await, eq(&expected), "{algo}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! Conditions of the device under which the downloads are paused, such as being too hot or running
+//! on battery.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::cfg::ResourceGuardConfig;
+
+/// Reports conditions of the device under which the downloads must be paused. Consulted
+/// periodically by the downloader, so implementations should be cheap.
+pub trait ResourceGuard: Send + Sync {
+    /// Returns why the downloads must be paused, or `None` if they can run.
+    fn pause_reason(&self) -> Option<String>;
+}
+
+/// Never pauses the downloads.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopResourceGuard;
+
+impl ResourceGuard for NoopResourceGuard {
+    fn pause_reason(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Reads the temperature and the power supply of the device from the Linux sysfs.
+#[derive(Debug, Clone)]
+pub struct SysfsResourceGuard {
+    config: ResourceGuardConfig,
+    sys_path: PathBuf,
+}
+
+impl SysfsResourceGuard {
+    pub fn new(config: ResourceGuardConfig) -> Self {
+        Self::with_sys_path(config, "/sys")
+    }
+
+    fn with_sys_path(config: ResourceGuardConfig, sys_path: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            sys_path: sys_path.into(),
+        }
+    }
+
+    /// Returns the directories of the devices of a sysfs class, e.g. `thermal`.
+    fn class_devices(&self, class: &str) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(self.sys_path.join("class").join(class)) else {
+            return vec![];
+        };
+        entries.filter_map(|e| e.ok()).map(|e| e.path()).collect()
+    }
+
+    /// Returns the temperature of the hottest thermal zone, in degrees Celsius, if any can be read.
+    fn max_temperature(&self) -> Option<f64> {
+        self.class_devices("thermal")
+            .iter()
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("thermal_zone"))
+            })
+            .filter_map(|path| read_attribute(path, "temp"))
+            // In millidegrees Celsius
+            .filter_map(|temp| temp.parse::<f64>().ok())
+            .map(|temp| temp / 1000.0)
+            .reduce(f64::max)
+    }
+
+    /// Returns whether any battery of the device is discharging.
+    fn on_battery(&self) -> bool {
+        self.class_devices("power_supply").iter().any(|path| {
+            read_attribute(path, "type").as_deref() == Some("Battery")
+                && read_attribute(path, "status").as_deref() == Some("Discharging")
+        })
+    }
+}
+
+/// Reads an attribute of a sysfs device, without its trailing newline.
+fn read_attribute(device: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(device.join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+impl ResourceGuard for SysfsResourceGuard {
+    fn pause_reason(&self) -> Option<String> {
+        if let Some(max_temperature) = self.config.max_temperature_celsius
+            && let Some(temperature) = self.max_temperature()
+            && temperature > max_temperature
+        {
+            return Some(format!(
+                "The device is at {temperature:.1} °C, above {max_temperature} °C"
+            ));
+        }
+        if self.config.pause_on_battery && self.on_battery() {
+            return Some("The device is running on battery".to_string());
+        }
+        None
+    }
+}
+
+/// Returns the guard checking the configured conditions, which never pauses the downloads if no
+/// condition is configured.
+pub fn resource_guard(config: &ResourceGuardConfig) -> Arc<dyn ResourceGuard> {
+    if config.is_enabled() {
+        Arc::new(SysfsResourceGuard::new(config.clone()))
+    } else {
+        Arc::new(NoopResourceGuard)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+
+    fn write_device(
+        sys_path: &Path,
+        class: &str,
+        device: &str,
+        attributes: &[(&str, &str)],
+    ) -> googletest::Result<()> {
+        let path = sys_path.join("class").join(class).join(device);
+        std::fs::create_dir_all(&path).or_fail()?;
+        for (name, value) in attributes {
+            std::fs::write(path.join(name), format!("{value}\n")).or_fail()?;
+        }
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_sysfs_guard_temperature() -> googletest::Result<()> {
+        let sys_path = tempfile::TempDir::new().or_fail()?;
+        write_device(
+            sys_path.path(),
+            "thermal",
+            "thermal_zone0",
+            &[("temp", "48000")],
+        )?;
+        write_device(
+            sys_path.path(),
+            "thermal",
+            "thermal_zone1",
+            &[("temp", "81500")],
+        )?;
+        // Cooling devices are not thermal zones
+        write_device(
+            sys_path.path(),
+            "thermal",
+            "cooling_device0",
+            &[("temp", "99000")],
+        )?;
+
+        let guard = |max_temperature_celsius| {
+            SysfsResourceGuard::with_sys_path(
+                ResourceGuardConfig {
+                    max_temperature_celsius: Some(max_temperature_celsius),
+                    pause_on_battery: false,
+                },
+                sys_path.path(),
+            )
+        };
+        expect_that!(
+            guard(80.0).pause_reason(),
+            some(contains_substring("81.5 °C"))
+        );
+        expect_that!(guard(85.0).pause_reason(), none());
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_sysfs_guard_battery() -> googletest::Result<()> {
+        let sys_path = tempfile::TempDir::new().or_fail()?;
+        write_device(sys_path.path(), "power_supply", "AC", &[("type", "Mains")])?;
+        write_device(
+            sys_path.path(),
+            "power_supply",
+            "BAT0",
+            &[("type", "Battery"), ("status", "Charging")],
+        )?;
+        let config = ResourceGuardConfig {
+            max_temperature_celsius: None,
+            pause_on_battery: true,
+        };
+        let guard = SysfsResourceGuard::with_sys_path(config, sys_path.path());
+        expect_that!(guard.pause_reason(), none());
+
+        write_device(
+            sys_path.path(),
+            "power_supply",
+            "BAT0",
+            &[("status", "Discharging")],
+        )?;
+        expect_that!(guard.pause_reason(), some(contains_substring("battery")));
+
+        // Batteries are ignored unless configured
+        let guard =
+            SysfsResourceGuard::with_sys_path(ResourceGuardConfig::default(), sys_path.path());
+        expect_that!(guard.pause_reason(), none());
+        Ok(())
+    }
+}
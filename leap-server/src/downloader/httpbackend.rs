@@ -0,0 +1,286 @@
+use std::pin::Pin;
+
+use crate::downloader::Error;
+use crate::downloader::backend::{Backend, ChunkResult, check_manifest_content};
+
+use async_stream::stream;
+use tokio_stream::Stream;
+
+/// Backend reading from an HTTP server, such as another LEAP exporting its catalog with
+/// `GET api/manifest/export`. The remote server URI is the URL of the manifest, and the videos are
+/// fetched from their URIs in the manifest.
+pub struct HttpBackend {
+    client: reqwest::Client,
+    manifest_url: String,
+    verify_manifest_content_type: bool,
+}
+
+impl HttpBackend {
+    /// Creates a backend sending the given `headers` with both the manifest and content requests.
+    pub fn new(manifest_url: &http::Uri, headers: http::HeaderMap) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .default_headers(headers)
+                .build()?,
+            manifest_url: manifest_url.to_string(),
+            verify_manifest_content_type: false,
+        })
+    }
+
+    /// Rejects the manifests that are not JSON, as checked by [`check_manifest_content`].
+    pub fn verifying_manifest_content_type(self, verify_manifest_content_type: bool) -> Self {
+        Self {
+            verify_manifest_content_type,
+            ..self
+        }
+    }
+
+    /// Sends a GET request to `url`, failing if the server doesn't respond with a success status.
+    async fn get(&self, url: &str, range: Option<String>) -> Result<reqwest::Response, Error> {
+        tracing::debug!("Fetching {url}");
+
+        let mut request = self.client.get(url);
+        if let Some(range) = range {
+            request = request.header(reqwest::header::RANGE, range);
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::Io(std::io::Error::other(format!("Failed to get {url}: {e}"))))
+    }
+}
+
+#[async_trait::async_trait]
+impl Backend for HttpBackend {
+    fn fetch_resource_from<'a, 'b>(
+        &'a self,
+        uri: &'b http::Uri,
+        offset: u64,
+    ) -> Pin<Box<dyn Stream<Item = ChunkResult> + Send + 'a>>
+    where
+        'b: 'a,
+    {
+        Box::pin(stream! {
+            let url = uri.to_string();
+            let range = (offset > 0).then(|| format!("bytes={offset}-"));
+            let mut response = match self.get(&url, range).await {
+                Ok(response) => response,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+
+            loop {
+                match response.chunk().await {
+                    Ok(Some(bytes)) => yield Ok(bytes.to_vec()),
+                    Ok(None) => break,
+                    Err(e) => {
+                        yield Err(Error::Io(std::io::Error::other(format!(
+                            "Error reading {url}: {e}"
+                        ))));
+                        return;
+                    }
+                }
+            }
+        })
+    }
+
+    async fn fetch_manifest(&self, max_size: usize) -> Result<Vec<u8>, Error> {
+        tracing::info!("Fetching manifest from {}", self.manifest_url);
+
+        let mut response = self.get(&self.manifest_url, None).await?;
+
+        // Fail early if the manifest is known to be too large. We still check the size while
+        // reading, because the content length is not always available.
+        if response
+            .content_length()
+            .is_some_and(|len| len > max_size as u64)
+        {
+            return Err(Error::ManifestTooLarge(max_size));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        let mut data = Vec::new();
+        while let Some(bytes) = response.chunk().await.map_err(|e| {
+            Error::Io(std::io::Error::other(format!(
+                "Failed to read manifest body: {e}"
+            )))
+        })? {
+            if data.len() + bytes.len() > max_size {
+                return Err(Error::ManifestTooLarge(max_size));
+            }
+            data.extend_from_slice(&bytes);
+        }
+        if self.verify_manifest_content_type {
+            check_manifest_content(content_type.as_deref(), &data)?;
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use googletest::prelude::*;
+    use tokio_stream::StreamExt;
+
+    type RecordedHeaders = Arc<Mutex<Vec<Vec<(String, String)>>>>;
+
+    /// Spawns an HTTP server responding with `content` to every request, and recording the
+    /// headers of the requests.
+    fn spawn_server(
+        content: &'static [u8],
+    ) -> googletest::Result<(std::net::SocketAddr, RecordedHeaders)> {
+        spawn_server_with_content_type(content, "application/octet-stream")
+    }
+
+    /// Same as [`spawn_server`], responding with the given content type.
+    fn spawn_server_with_content_type(
+        content: &'static [u8],
+        content_type: &'static str,
+    ) -> googletest::Result<(std::net::SocketAddr, RecordedHeaders)> {
+        use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").or_fail()?;
+        let address = listener.local_addr().or_fail()?;
+        let requests = RecordedHeaders::default();
+        let recorded = requests.clone();
+        let server = HttpServer::new(move || {
+            let recorded = recorded.clone();
+            App::new().default_service(web::to(move |request: HttpRequest| {
+                let headers = request
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = value.to_str().unwrap_or_default();
+                        (name.to_string(), value.to_string())
+                    })
+                    .collect();
+                recorded.lock().expect("poisoned mutex").push(headers);
+                async move { HttpResponse::Ok().content_type(content_type).body(content) }
+            }))
+        })
+        .workers(1)
+        .listen(listener)
+        .or_fail()?
+        .run();
+        tokio::spawn(server);
+        Ok((address, requests))
+    }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn test_configured_headers_are_sent() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let (address, requests) = spawn_server(b"content")?;
+        let mut config = crate::api::test::test_config(tempdir.path()).downloader_config;
+        config.request_headers = [("Authorization", "Bearer token"), ("X-Cdn-Token", "signed")]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let headers = config.request_header_map().or_fail()?;
+        // The configured values are never logged
+        expect_true!(headers["authorization"].is_sensitive());
+
+        let manifest_url = format!("http://{address}/api/manifest/export")
+            .parse()
+            .or_fail()?;
+        let backend = HttpBackend::new(&manifest_url, headers).or_fail()?;
+        let manifest = backend.fetch_manifest(1024).await.or_fail()?;
+        expect_that!(manifest, eq(b"content"));
+        let video_url = format!("http://{address}/api/content/video")
+            .parse()
+            .or_fail()?;
+        let chunks: Vec<_> = backend.fetch_resource(&video_url).collect().await;
+        expect_that!(chunks, each(ok(anything())));
+
+        let requests = requests.lock().expect("poisoned mutex").clone();
+        expect_that!(requests, len(eq(2)));
+        expect_that!(
+            requests,
+            each(all![
+                contains(eq(&(
+                    "authorization".to_string(),
+                    "Bearer token".to_string()
+                ))),
+                contains(eq(&("x-cdn-token".to_string(), "signed".to_string()))),
+                contains(eq(&(
+                    "user-agent".to_string(),
+                    crate::cfg::DEFAULT_USER_AGENT.to_string()
+                ))),
+            ])
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn test_configured_user_agent_overrides_default() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let (address, requests) = spawn_server(b"content")?;
+        let mut config = crate::api::test::test_config(tempdir.path()).downloader_config;
+        config
+            .request_headers
+            .insert("User-Agent".to_string(), "custom-agent".to_string());
+
+        let manifest_url = format!("http://{address}/manifest.json")
+            .parse()
+            .or_fail()?;
+        let backend =
+            HttpBackend::new(&manifest_url, config.request_header_map().or_fail()?).or_fail()?;
+        backend.fetch_manifest(1024).await.or_fail()?;
+
+        let requests = requests.lock().expect("poisoned mutex").clone();
+        expect_that!(
+            requests,
+            elements_are![contains(eq(&(
+                "user-agent".to_string(),
+                "custom-agent".to_string()
+            )))]
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn test_html_manifest_is_rejected() -> googletest::Result<()> {
+        let (address, _) = spawn_server_with_content_type(
+            b"<!DOCTYPE html><html><body>Please log in</body></html>",
+            "text/html; charset=utf-8",
+        )?;
+        let manifest_url = format!("http://{address}/manifest.json")
+            .parse()
+            .or_fail()?;
+        let backend = HttpBackend::new(&manifest_url, http::HeaderMap::new())
+            .or_fail()?
+            .verifying_manifest_content_type(true);
+
+        let result = backend.fetch_manifest(1024).await;
+        expect_that!(
+            result,
+            err(displays_as(eq(
+                "The manifest endpoint returned non-JSON content (content-type text/html; \
+                 charset=utf-8)"
+            )))
+        );
+
+        // Manifests served with a generic content type are still accepted
+        let (address, _) = spawn_server(b"{}")?;
+        let manifest_url = format!("http://{address}/manifest.json")
+            .parse()
+            .or_fail()?;
+        let backend = HttpBackend::new(&manifest_url, http::HeaderMap::new())
+            .or_fail()?
+            .verifying_manifest_content_type(true);
+        expect_that!(backend.fetch_manifest(1024).await, ok(eq(b"{}")));
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Import of video files from a local directory, such as a USB drive, into the content directory.
+//! Files are matched to the videos of the current manifest by their checksum, so that LEAPs can be
+//! provisioned without downloading their content.
+
+use std::path::{Path, PathBuf};
+
+use tokio::io::AsyncReadExt;
+
+use super::checksum::HashingPool;
+use super::tasks::{content_filepath, partial_filepath};
+use crate::{
+    cfg::DownloaderConfig,
+    db::{Database, DownloadStatus},
+    manifest::Video,
+    store::ContentStore,
+};
+
+/// A file imported as the content of a video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedFile {
+    /// Path of the file in the source directory
+    pub path: PathBuf,
+    pub id: uuid::Uuid,
+    /// Path of the copy of the file in the content directory
+    pub content_path: PathBuf,
+}
+
+/// The result of importing the files of a directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Files imported, once per video whose content they are
+    pub imported: Vec<ImportedFile>,
+    /// Files that don't match any video still missing its content
+    pub unmatched: Vec<PathBuf>,
+}
+
+/// Returns the videos of the manifest that still need their content: the pending and failed ones.
+/// Videos listed several times are returned once.
+async fn videos_missing_content(
+    db: &Database,
+    manifest: &crate::manifest::ManifestFile,
+) -> crate::db::Result<Vec<Video>> {
+    let mut videos: Vec<Video> = vec![];
+    for video in manifest.sections.iter().flat_map(|s| s.content.iter()) {
+        if videos.iter().any(|v| v.id == video.id) {
+            continue;
+        }
+        if matches!(
+            db.find_video(video.id).await?.download_status,
+            DownloadStatus::Pending | DownloadStatus::Failed { .. }
+        ) {
+            videos.push(video.clone());
+        }
+    }
+    Ok(videos)
+}
+
+/// Returns the videos whose checksum matches the content of the file. Only the checksums of the
+/// candidates are computed in the pool, reading the file once.
+async fn matching_videos(
+    hashing: &HashingPool,
+    path: &Path,
+    candidates: Vec<&Video>,
+) -> std::io::Result<Vec<Video>> {
+    let mut hashers: Vec<_> = candidates
+        .iter()
+        .map(|video| hashing.hasher(&video.checksum))
+        .collect();
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]).await;
+        }
+    }
+    let mut matching = vec![];
+    for (video, hasher) in candidates.into_iter().zip(hashers) {
+        if hasher.finalize().await == video.checksum {
+            matching.push(video.clone());
+        }
+    }
+    Ok(matching)
+}
+
+/// Copies the file into the content directory as the content of the video. The video is claimed
+/// first, so that the downloader doesn't download it meanwhile. Returns whether it was imported.
+async fn import_file(
+    config: &DownloaderConfig,
+    db: &Database,
+    store: &dyn ContentStore,
+    path: &Path,
+    id: uuid::Uuid,
+) -> anyhow::Result<bool> {
+    if !db.claim_for_download(id).await? {
+        return Ok(false);
+    }
+    let partial_path = partial_filepath(config, id);
+    let target_path = content_filepath(config, id);
+    let copied = async {
+        tokio::fs::create_dir_all(config.temp_download_path()).await?;
+        tokio::fs::copy(path, &partial_path).await?;
+        store.persist(&partial_path, &target_path).await
+    }
+    .await;
+    if let Err(e) = copied {
+        let _ = tokio::fs::remove_file(&partial_path).await;
+        db.reset_download(id).await?;
+        return Err(e.into());
+    }
+    db.set_downloaded(id, &target_path).await?;
+    tracing::info!("Imported {path:?} as the content of video {id}");
+    Ok(true)
+}
+
+/// Imports the files of the source directory whose checksum matches a video of the current
+/// manifest that is not downloaded yet. The files are copied, so the source is left untouched.
+/// Returns `None` if no manifest is being served.
+pub async fn import_directory(
+    config: &DownloaderConfig,
+    db: &Database,
+    store: &dyn ContentStore,
+    hashing: &HashingPool,
+    source: &Path,
+) -> anyhow::Result<Option<ImportReport>> {
+    let Some(manifest) = db.current_manifest().await.clone() else {
+        return Ok(None);
+    };
+    let mut missing = videos_missing_content(db, &manifest).await?;
+
+    let mut paths = vec![];
+    let mut entries = tokio::fs::read_dir(source).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.metadata().await?.is_file() {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+
+    let mut report = ImportReport::default();
+    for path in paths {
+        // Most files can be discarded by their size, without reading them
+        let size = tokio::fs::metadata(&path).await?.len();
+        let candidates: Vec<_> = missing.iter().filter(|v| v.file_size == size).collect();
+        let matches = if candidates.is_empty() {
+            vec![]
+        } else {
+            matching_videos(hashing, &path, candidates).await?
+        };
+
+        let mut imported = false;
+        for video in matches {
+            if import_file(config, db, store, &path, video.id).await? {
+                report.imported.push(ImportedFile {
+                    path: path.clone(),
+                    id: video.id,
+                    content_path: content_filepath(config, video.id),
+                });
+                imported = true;
+            }
+            missing.retain(|v| v.id != video.id);
+        }
+        if !imported {
+            tracing::info!("The file {path:?} doesn't match any video missing its content");
+            report.unmatched.push(path);
+        }
+    }
+    Ok(Some(report))
+}
@@ -0,0 +1,125 @@
+//! Detection of the video files in the download directories which don't belong to any video of
+//! the database anymore, e.g. because the server was interrupted while deleting them.
+
+use std::path::PathBuf;
+
+use crate::{
+    cfg::DownloaderConfig,
+    db::{Database, DownloadStatus},
+};
+
+/// A video file that doesn't belong to any video of the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Kind of the video files in the download directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    /// A downloaded video, named `<id>.mp4` in the content directory
+    Downloaded,
+    /// A partial download, named `<id>.mp4.part` in the temporary download directory
+    Partial,
+}
+
+/// A video file found in the download directories.
+struct VideoFile {
+    id: uuid::Uuid,
+    kind: FileKind,
+    file: OrphanFile,
+}
+
+/// Lists the video files in the download directories. Files not named after a video are ignored.
+async fn list_video_files(config: &DownloaderConfig) -> std::io::Result<Vec<VideoFile>> {
+    let mut files = vec![];
+    for (dir, suffix, kind) in [
+        (config.content_path.clone(), ".mp4", FileKind::Downloaded),
+        (config.temp_download_path(), ".mp4.part", FileKind::Partial),
+    ] {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(suffix))
+                .and_then(|id| uuid::Uuid::parse_str(id).ok())
+            else {
+                continue;
+            };
+            files.push(VideoFile {
+                id,
+                kind,
+                file: OrphanFile {
+                    path: entry.path(),
+                    size: metadata.len(),
+                },
+            });
+        }
+    }
+    files.sort_by(|a, b| a.file.path.cmp(&b.file.path));
+    Ok(files)
+}
+
+/// Returns whether a video file doesn't belong to its video. The files of videos being downloaded
+/// are never orphans, since the download job may still be using them.
+async fn is_orphan(db: &Database, file: &VideoFile) -> crate::db::Result<bool> {
+    let video = match db.find_video(file.id).await {
+        Ok(video) => video,
+        Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    Ok(match (file.kind, video.download_status) {
+        (_, DownloadStatus::InProgress(_)) => false,
+        (FileKind::Downloaded, DownloadStatus::Downloaded(path)) => path != file.file.path,
+        _ => true,
+    })
+}
+
+/// Returns the video files in the download directories that don't belong to any video.
+pub async fn find_orphan_files(
+    config: &DownloaderConfig,
+    db: &Database,
+) -> anyhow::Result<Vec<OrphanFile>> {
+    let mut orphans = vec![];
+    for file in list_video_files(config).await? {
+        if is_orphan(db, &file).await? {
+            orphans.push(file.file);
+        }
+    }
+    Ok(orphans)
+}
+
+/// Deletes the video files in the download directories that don't belong to any video, and
+/// returns them. Each file is checked right before deleting it, so that downloads started
+/// in the meantime keep their files.
+pub async fn remove_orphan_files(
+    config: &DownloaderConfig,
+    db: &Database,
+) -> anyhow::Result<Vec<OrphanFile>> {
+    let mut removed = vec![];
+    for file in list_video_files(config).await? {
+        if !is_orphan(db, &file).await? {
+            continue;
+        }
+        match tokio::fs::remove_file(&file.file.path).await {
+            Ok(()) => {
+                tracing::info!("Removed orphan file {:?}", file.file.path);
+                removed.push(file.file);
+            }
+            // Removed concurrently, e.g. by the downloader
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(removed)
+}
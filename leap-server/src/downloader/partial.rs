@@ -0,0 +1,112 @@
+//! Coordination between the downloads writing the partial files of the videos and the requests
+//! streaming them while they are downloaded.
+//!
+//! Each download publishes the offset of its partial file up to which the content is confirmed to
+//! be written, i.e.: the writes before it were flushed to the file. The invariant is that the
+//! content before the published offset is complete and is never rewritten while the offset is
+//! published, so it can be read without seeing torn writes. Content past the offset may be
+//! partially written, e.g.: a range of the file allocated but not filled yet, and must never be
+//! read until the offset moves past it or the download completes.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// Confirmed offsets of the partial files being written, by path. Paths are used instead of the
+/// video ids because each channel downloads its videos to its own partial files.
+#[derive(Debug, Clone, Default)]
+pub struct PartialWrites {
+    offsets: Arc<Mutex<HashMap<PathBuf, Arc<AtomicU64>>>>,
+}
+
+impl PartialWrites {
+    /// Starts publishing the confirmed offset of the partial file at `path`, which is written from
+    /// its beginning. The offset stops being published when the returned writer is dropped.
+    pub fn start(&self, path: &Path) -> PartialWrite {
+        let offset = Arc::new(AtomicU64::new(0));
+        self.offsets
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), Arc::clone(&offset));
+        PartialWrite {
+            writes: self.clone(),
+            path: path.to_path_buf(),
+            offset,
+        }
+    }
+
+    /// Returns the offset of the partial file at `path` up to which its content can be read, or
+    /// `None` if it isn't being written.
+    pub fn confirmed_offset(&self, path: &Path) -> Option<u64> {
+        self.offsets
+            .lock()
+            .unwrap()
+            .get(path)
+            .map(|offset| offset.load(Ordering::Acquire))
+    }
+}
+
+/// Publishes the confirmed offset of a partial file while it is written.
+#[derive(Debug)]
+pub struct PartialWrite {
+    writes: PartialWrites,
+    path: PathBuf,
+    offset: Arc<AtomicU64>,
+}
+
+impl PartialWrite {
+    /// Confirms that the content of the partial file up to `offset` is written. Must only be
+    /// called once the writes before `offset` are flushed to the file.
+    pub fn confirm(&self, offset: u64) {
+        self.offset.fetch_max(offset, Ordering::Release);
+    }
+}
+
+impl Drop for PartialWrite {
+    fn drop(&mut self) {
+        let mut offsets = self.writes.offsets.lock().unwrap();
+        // A new download of the same file may have started already, e.g.: after a retry
+        if offsets
+            .get(&self.path)
+            .is_some_and(|offset| Arc::ptr_eq(offset, &self.offset))
+        {
+            offsets.remove(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn test_confirmed_offset_is_published_while_writing() {
+        let writes = PartialWrites::default();
+        let path = Path::new("/tmp/.tmp/video.mp4");
+        expect_that!(writes.confirmed_offset(path), none());
+
+        let write = writes.start(path);
+        expect_that!(writes.confirmed_offset(path), some(eq(0)));
+        write.confirm(10);
+        expect_that!(writes.confirmed_offset(path), some(eq(10)));
+        // The confirmed content never shrinks while the file is written
+        write.confirm(5);
+        expect_that!(writes.confirmed_offset(path), some(eq(10)));
+
+        // A restarted download is written from the beginning, and stays published when the
+        // previous one is dropped
+        let restarted = writes.start(path);
+        expect_that!(writes.confirmed_offset(path), some(eq(0)));
+        drop(write);
+        expect_that!(writes.confirmed_offset(path), some(eq(0)));
+        drop(restarted);
+        expect_that!(writes.confirmed_offset(path), none());
+    }
+}
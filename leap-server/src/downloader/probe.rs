@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use crate::db::MediaInfo;
+
+/// Obtains the media information of a downloaded video with `ffprobe`. Values that can't be
+/// probed are left empty, e.g. when `ffprobe` is not installed or the server was built without the
+/// `ffprobe` feature.
+#[cfg(feature = "ffprobe")]
+pub async fn probe_media_info(path: &Path) -> MediaInfo {
+    match run_ffprobe(path).await {
+        Ok(media_info) => media_info,
+        Err(e) => {
+            tracing::warn!("Unable to probe the media information of {path:?}: {e}");
+            MediaInfo::default()
+        }
+    }
+}
+
+#[cfg(not(feature = "ffprobe"))]
+pub async fn probe_media_info(_path: &Path) -> MediaInfo {
+    MediaInfo::default()
+}
+
+#[cfg(feature = "ffprobe")]
+async fn run_ffprobe(path: &Path) -> anyhow::Result<MediaInfo> {
+    #[derive(serde::Deserialize)]
+    struct FfprobeOutput {
+        #[serde(default)]
+        streams: Vec<Stream>,
+        format: Option<Format>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Stream {
+        width: Option<u64>,
+        height: Option<u64>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Format {
+        /// Duration in seconds, as a decimal string
+        duration: Option<String>,
+    }
+
+    let result = tokio::process::Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height:format=duration"])
+        .args(["-of", "json"])
+        .arg(path)
+        .output()
+        .await?;
+    if !result.status.success() {
+        anyhow::bail!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+
+    let output: FfprobeOutput = serde_json::from_slice(&result.stdout)?;
+    let resolution = output
+        .streams
+        .first()
+        .and_then(|s| Some(format!("{}x{}", s.width?, s.height?)));
+    let duration_secs = output
+        .format
+        .and_then(|f| f.duration)
+        .and_then(|d| d.parse::<f64>().ok())
+        .map(|d| d.round() as u64);
+
+    Ok(MediaInfo {
+        duration_secs,
+        resolution,
+    })
+}
@@ -2,7 +2,7 @@ use std::pin::Pin;
 
 use crate::cfg::S3Config;
 use crate::downloader::Error;
-use crate::downloader::backend::{Backend, ChunkResult};
+use crate::downloader::backend::{Backend, ChunkResult, check_manifest_content};
 
 use async_stream::stream;
 use aws_sdk_s3::Client;
@@ -20,6 +20,7 @@ struct ResolvedS3Config {
 pub struct S3Backend {
     client: Client,
     bucket: String,
+    verify_manifest_content_type: bool,
 }
 
 impl S3Backend {
@@ -103,9 +104,18 @@ impl S3Backend {
         Ok(Self {
             client,
             bucket: bucket.to_string(),
+            verify_manifest_content_type: false,
         })
     }
 
+    /// Rejects the manifests that are not JSON, as checked by [`check_manifest_content`].
+    pub fn verifying_manifest_content_type(self, verify_manifest_content_type: bool) -> Self {
+        Self {
+            verify_manifest_content_type,
+            ..self
+        }
+    }
+
     /// Checks that we can access the bucket using the given credentials. This might be used, for
     /// instance, to check that we have access to the bucket after the user has provisioned the
     /// system with the given credentials.
@@ -128,9 +138,37 @@ impl S3Backend {
         Ok(())
     }
 
+    /// Obtains the size of an S3 object without fetching its content.
+    async fn s3_object_size(&self, key: &str) -> Result<u64, Error> {
+        let head = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| {
+                Error::Io(std::io::Error::other(format!(
+                    "Failed to get S3 object metadata s3://{}/{}: {}",
+                    self.bucket, key, e
+                )))
+            })?;
+        head.content_length()
+            .and_then(|len| u64::try_from(len).ok())
+            .ok_or_else(|| {
+                Error::Io(std::io::Error::other(format!(
+                    "Unknown size of S3 object s3://{}/{}",
+                    self.bucket, key
+                )))
+            })
+    }
+
+    /// Fetches an S3 object. When `range` is set, only the given byte range is fetched, in the
+    /// format of the HTTP `Range` header.
     async fn get_s3_object(
         &self,
         key: &str,
+        range: Option<String>,
     ) -> Result<aws_sdk_s3::operation::get_object::GetObjectOutput, Error> {
         tracing::debug!("Fetching S3 object: s3://{}/{}", self.bucket, key);
 
@@ -138,6 +176,7 @@ impl S3Backend {
             .get_object()
             .bucket(&self.bucket)
             .key(key)
+            .set_range(range)
             .send()
             .await
             .map_err(|e| {
@@ -154,7 +193,7 @@ impl S3Backend {
                     key,
                     e
                 );
-                Error::IoError(std::io::Error::other(format!(
+                Error::Io(std::io::Error::other(format!(
                     "Failed to get S3 object s3://{}/{}: {}",
                     self.bucket, key, e
                 )))
@@ -164,9 +203,10 @@ impl S3Backend {
 
 #[async_trait::async_trait]
 impl Backend for S3Backend {
-    fn fetch_resource<'a, 'b>(
+    fn fetch_resource_from<'a, 'b>(
         &'a self,
         uri: &'b http::Uri,
+        offset: u64,
     ) -> Pin<Box<dyn Stream<Item = ChunkResult> + Send + 'a>>
     where
         'b: 'a,
@@ -174,7 +214,28 @@ impl Backend for S3Backend {
         Box::pin(stream! {
             let key = uri.path().trim_start_matches('/');
 
-            let object = match self.get_s3_object(key).await {
+            // S3 rejects ranges starting at the end of the object, so the size is checked first
+            let range = if offset > 0 {
+                let size = match self.s3_object_size(key).await {
+                    Ok(size) => size,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                if offset > size {
+                    yield Err(Error::OffsetOutOfRange { offset, size });
+                    return;
+                }
+                if offset == size {
+                    return;
+                }
+                Some(format!("bytes={offset}-"))
+            } else {
+                None
+            };
+
+            let object = match self.get_s3_object(key, range).await {
                 Ok(obj) => {
                     tracing::info!("Successfully initiated download of s3://{}/{}", self.bucket, key);
                     obj
@@ -194,7 +255,7 @@ impl Backend for S3Backend {
                     }
                     Some(Err(e)) => {
                         tracing::error!("Error reading S3 stream for s3://{}/{}: {}", self.bucket, key, e);
-                        yield Err(Error::IoError(std::io::Error::other(
+                        yield Err(Error::Io(std::io::Error::other(
                             format!("Error reading S3 stream: {}", e)
                         )));
                         return;
@@ -208,20 +269,129 @@ impl Backend for S3Backend {
         })
     }
 
-    async fn fetch_manifest(&self) -> Result<Vec<u8>, Error> {
+    async fn fetch_manifest(&self, max_size: usize) -> Result<Vec<u8>, Error> {
         tracing::info!("Fetching manifest from s3://{}/manifest.json", self.bucket);
 
-        let result = self.get_s3_object("manifest.json").await?;
+        let result = self.get_s3_object("manifest.json", None).await?;
+
+        // Fail early if the object is known to be too large. We still check the size while
+        // reading, because the content length is not always available.
+        if result
+            .content_length()
+            .is_some_and(|len| len > max_size as i64)
+        {
+            tracing::error!("The manifest exceeds the maximum size of {max_size} bytes");
+            return Err(Error::ManifestTooLarge(max_size));
+        }
+        let content_type = result.content_type().map(str::to_string);
 
-        let data = result.body.collect().await.map_err(|e| {
-            tracing::error!("Failed to read manifest body: {}", e);
-            Error::IoError(std::io::Error::other(format!(
-                "Failed to read manifest body: {}",
-                e
-            )))
-        })?;
+        let mut body = result.body;
+        let mut data = Vec::new();
+        while let Some(bytes) = body.next().await {
+            let bytes = bytes.map_err(|e| {
+                tracing::error!("Failed to read manifest body: {}", e);
+                Error::Io(std::io::Error::other(format!(
+                    "Failed to read manifest body: {}",
+                    e
+                )))
+            })?;
+            if data.len() + bytes.len() > max_size {
+                tracing::error!("The manifest exceeds the maximum size of {max_size} bytes");
+                return Err(Error::ManifestTooLarge(max_size));
+            }
+            data.extend_from_slice(&bytes);
+        }
+        if self.verify_manifest_content_type {
+            check_manifest_content(content_type.as_deref(), &data)?;
+        }
 
         tracing::info!("Successfully fetched manifest from S3");
-        Ok(data.into_bytes().to_vec())
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use googletest::prelude::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    /// Starts a minimal S3-compatible server answering every request with `body`. The request
+    /// line of each received request is sent through the returned channel.
+    async fn mock_s3_server(body: String) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let _ = sender.send(request.lines().next().unwrap_or_default().to_string());
+
+                let response = format!(
+                    concat!(
+                        "HTTP/1.1 200 OK\r\n",
+                        "Content-Type: application/json\r\n",
+                        "Content-Length: {}\r\n",
+                        "Connection: close\r\n\r\n{}"
+                    ),
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (format!("http://{address}"), receiver)
+    }
+
+    fn s3_config(endpoint_url: String) -> S3Config {
+        S3Config {
+            endpoint_url: Some(endpoint_url),
+            force_path_style: true,
+            access_key_id: Some(SecretString::from("minio")),
+            secret_access_key: Some(SecretString::from("minio-secret")),
+            region: "us-east-1".to_string(),
+        }
+    }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn fetch_manifest_from_custom_endpoint() -> googletest::Result<()> {
+        let (endpoint_url, mut requests) = mock_s3_server("{}".to_string()).await;
+        let backend = S3Backend::new("leap-bucket", &s3_config(endpoint_url))
+            .await
+            .or_fail()?;
+        let manifest = backend.fetch_manifest(1024).await.or_fail()?;
+
+        expect_that!(manifest, eq(b"{}"));
+        expect_that!(
+            requests.recv().await,
+            some(starts_with("GET /leap-bucket/manifest.json"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    #[tokio::test]
+    async fn fetch_oversized_manifest() -> googletest::Result<()> {
+        let (endpoint_url, _) = mock_s3_server("x".repeat(4096)).await;
+        let backend = S3Backend::new("leap-bucket", &s3_config(endpoint_url))
+            .await
+            .or_fail()?;
+
+        let manifest = backend.fetch_manifest(1024).await;
+        expect_true!(matches!(manifest, Err(Error::ManifestTooLarge(1024))));
+        Ok(())
     }
 }
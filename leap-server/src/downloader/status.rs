@@ -0,0 +1,135 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::watch;
+
+/// Runtime state of the downloader that is reported by the API. Unlike the database, none of it is
+/// persisted: it is rebuilt by the downloader after a restart.
+#[derive(Debug, Default)]
+pub struct DownloaderStatus {
+    // Error of the last manifest fetch, if it failed. The current manifest keeps being served
+    // meanwhile, even though it may be outdated.
+    upstream_error: Mutex<Option<String>>,
+    // Why the newest manifest fetched was not adopted, if it wasn't. The current manifest keeps
+    // being served meanwhile.
+    rejected_manifest: Mutex<Option<String>>,
+    // Whether the operator paused the background downloads
+    downloads_paused: watch::Sender<bool>,
+    // Whether the downloads are paused because the storage is running out of free space
+    low_disk_space: watch::Sender<bool>,
+    // Why the resource guard paused the downloads, if it did
+    throttled: watch::Sender<Option<String>>,
+    // When the current manifest was published and when all its content was cached
+    cache_timing: Mutex<CacheTiming>,
+}
+
+/// Times at which the current manifest was published and fully cached.
+#[derive(Debug, Default)]
+struct CacheTiming {
+    published_at: Option<Instant>,
+    cached_at: Option<Instant>,
+}
+
+impl DownloaderStatus {
+    /// Records the result of the last manifest fetch: the error if it failed, or `None` if a valid
+    /// manifest was fetched.
+    pub fn set_upstream_error(&self, error: Option<String>) {
+        *self.upstream_error.lock().expect("poisoned mutex") = error;
+    }
+
+    /// Returns the error of the last manifest fetch, if it failed. The current manifest is stale
+    /// while the error is set.
+    pub fn upstream_error(&self) -> Option<String> {
+        self.upstream_error.lock().expect("poisoned mutex").clone()
+    }
+
+    /// Records why the newest manifest fetched can't be adopted by this server, or `None` once
+    /// the fetched manifest can be served.
+    pub fn set_rejected_manifest(&self, reason: Option<String>) {
+        *self.rejected_manifest.lock().expect("poisoned mutex") = reason;
+    }
+
+    /// Returns why the newest manifest fetched was not adopted, if it wasn't.
+    pub fn rejected_manifest(&self) -> Option<String> {
+        self.rejected_manifest
+            .lock()
+            .expect("poisoned mutex")
+            .clone()
+    }
+
+    /// Pauses or resumes the background downloads. Downloads already in progress are not affected.
+    pub fn set_downloads_paused(&self, paused: bool) {
+        self.downloads_paused.send_replace(paused);
+    }
+
+    /// Returns true if the background downloads are paused.
+    pub fn downloads_paused(&self) -> bool {
+        *self.downloads_paused.borrow()
+    }
+
+    /// Returns a receiver notified whenever the background downloads are paused or resumed.
+    pub fn subscribe_downloads_paused(&self) -> watch::Receiver<bool> {
+        self.downloads_paused.subscribe()
+    }
+
+    /// Sets whether the storage is running out of free space. The background downloads are paused
+    /// meanwhile, independently of [`Self::set_downloads_paused`].
+    pub fn set_low_disk_space(&self, low: bool) {
+        self.low_disk_space.send_replace(low);
+    }
+
+    /// Returns true if the storage is running out of free space.
+    pub fn low_disk_space(&self) -> bool {
+        *self.low_disk_space.borrow()
+    }
+
+    /// Returns a receiver notified whenever the storage runs out of free space or recovers it.
+    pub fn subscribe_low_disk_space(&self) -> watch::Receiver<bool> {
+        self.low_disk_space.subscribe()
+    }
+
+    /// Sets why the background downloads are paused due to the conditions of the device, such as
+    /// its temperature, or `None` to resume them. Independent of [`Self::set_downloads_paused`].
+    pub fn set_throttled(&self, reason: Option<String>) {
+        self.throttled.send_replace(reason);
+    }
+
+    /// Returns why the background downloads are paused due to the conditions of the device, if
+    /// they are.
+    pub fn throttled(&self) -> Option<String> {
+        self.throttled.borrow().clone()
+    }
+
+    /// Returns a receiver notified whenever the downloads are paused or resumed due to the
+    /// conditions of the device.
+    pub fn subscribe_throttled(&self) -> watch::Receiver<Option<String>> {
+        self.throttled.subscribe()
+    }
+
+    /// Records that a manifest was just published. The time to cache its content is measured from
+    /// now on.
+    pub fn set_manifest_published(&self) {
+        *self.cache_timing.lock().expect("poisoned mutex") = CacheTiming {
+            published_at: Some(Instant::now()),
+            cached_at: None,
+        };
+    }
+
+    /// Records that all the content of the current manifest is cached. Only the first call after
+    /// publishing a manifest is recorded.
+    pub fn set_fully_cached(&self) {
+        let mut timing = self.cache_timing.lock().expect("poisoned mutex");
+        if timing.published_at.is_some() && timing.cached_at.is_none() {
+            timing.cached_at = Some(Instant::now());
+        }
+    }
+
+    /// Returns the time it took to cache all the content of the current manifest after it was
+    /// published, or `None` while it is not fully cached.
+    pub fn time_to_cache(&self) -> Option<Duration> {
+        let timing = self.cache_timing.lock().expect("poisoned mutex");
+        Some(timing.cached_at? - timing.published_at?)
+    }
+}
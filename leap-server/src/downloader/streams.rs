@@ -0,0 +1,52 @@
+//! Count of the content streams being served, so that the downloads can yield to them on devices
+//! that can't serve and download at full speed at the same time.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Number of content streams being served, shared by the HTTP handlers and the downloaders.
+#[derive(Debug, Clone)]
+pub struct ActiveStreams {
+    count: Arc<watch::Sender<usize>>,
+}
+
+impl Default for ActiveStreams {
+    fn default() -> Self {
+        Self {
+            count: Arc::new(watch::Sender::new(0)),
+        }
+    }
+}
+
+impl ActiveStreams {
+    /// Registers a new content stream, which is active until the returned guard is dropped.
+    pub fn start(&self) -> ActiveStream {
+        self.count.send_modify(|count| *count += 1);
+        ActiveStream {
+            count: Arc::clone(&self.count),
+        }
+    }
+
+    /// Returns the number of content streams being served.
+    pub fn count(&self) -> usize {
+        *self.count.borrow()
+    }
+
+    /// Returns a receiver notified when the number of content streams changes.
+    pub fn subscribe(&self) -> watch::Receiver<usize> {
+        self.count.subscribe()
+    }
+}
+
+/// A content stream being served, counted by its [`ActiveStreams`].
+#[derive(Debug)]
+pub struct ActiveStream {
+    count: Arc<watch::Sender<usize>>,
+}
+
+impl Drop for ActiveStream {
+    fn drop(&mut self) {
+        self.count.send_modify(|count| *count -= 1);
+    }
+}
@@ -1,35 +1,78 @@
 use crate::{
-    db::{Database, DownloadStatus},
+    cfg::{DownloaderConfig, ExistingFileCheck},
+    db::{Database, DownloadStatus, FailureKind, MediaInfo},
     manifest::{ManifestFile, Video},
 };
 
-use super::DownloadContext;
+use super::{DownloadContext, probe, webhook};
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, sync::Arc};
 
-use sha2::Digest;
-use tokio::{io::AsyncWriteExt, task::JoinSet};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    sync::Semaphore,
+    task::JoinSet,
+};
 use tokio_stream::StreamExt;
 
+/// Maximum number of characters of the video names stored in the database. Longer names are
+/// truncated with an ellipsis.
+const MAX_VIDEO_NAME_CHARS: usize = 200;
+
+/// Returns the name of a video as shown to the users. The names are free text from the manifest,
+/// so control characters are replaced by spaces, and overlong names are truncated to
+/// [`MAX_VIDEO_NAME_CHARS`].
+fn sanitize_video_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let name = name.trim();
+    if name.chars().count() <= MAX_VIDEO_NAME_CHARS {
+        return name.to_string();
+    }
+    let truncated: String = name.chars().take(MAX_VIDEO_NAME_CHARS - 1).collect();
+    format!("{}\u{2026}", truncated.trim_end())
+}
+
 /// Makes sure that all manifest videos are present in the database with their corresponding state.
-/// Creates entries for missing videos.
+/// Creates entries for missing videos, and returns how many were created.
 #[tracing::instrument(name = "initialize_video_entries", skip(database, new_manifest))]
 pub async fn initialize_video_entries(
     database: &Database,
     new_manifest: &ManifestFile,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<usize> {
+    let ids: Vec<_> = new_manifest
+        .sections
+        .iter()
+        .flat_map(|s| s.content.iter().map(|v| v.id))
+        .collect();
+    let mut existing = database.existing_video_ids(&ids).await?;
+    let mut inserted = 0;
     for video in new_manifest.sections.iter().flat_map(|s| s.content.iter()) {
-        match database.find_video(video.id).await {
-            Ok(_) => {}
-            Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
-                database
-                    .insert_video(video.id, &video.name, video.file_size)
-                    .await?
-            }
-            Err(e) => return Err(e.into()),
+        // Videos listed several times are inserted once
+        if existing.insert(video.id) {
+            database
+                .insert_video(video.id, &sanitize_video_name(&video.name), video.file_size)
+                .await?;
+            inserted += 1;
+        }
+
+        // The media information of the manifest takes precedence over the probed one
+        let media_info = manifest_media_info(video);
+        if !media_info.is_empty() {
+            database.update_media_info(video.id, media_info).await?;
         }
     }
-    Ok(())
+    Ok(inserted)
+}
+
+/// Returns the media information of a video given by the manifest.
+fn manifest_media_info(video: &Video) -> MediaInfo {
+    MediaInfo {
+        duration_secs: video.duration_secs,
+        resolution: video.resolution.clone(),
+    }
 }
 
 #[tracing::instrument(name = "publish_manifest", skip(db, new_manifest), fields(manifest_date = %new_manifest.date))]
@@ -37,15 +80,54 @@ pub async fn publish_manifest(db: &Database, new_manifest: &ManifestFile) {
     db.publish_manifest(new_manifest).await;
 }
 
+/// Publishes the manifest saved by a previous run, once its videos are in the database. Returns
+/// the manifest, or `None` if no valid manifest was saved.
+#[tracing::instrument(name = "load_saved_manifest", skip(db))]
+pub async fn load_saved_manifest(db: &Database) -> anyhow::Result<Option<ManifestFile>> {
+    let Some(manifest) = db.saved_manifest().await else {
+        return Ok(None);
+    };
+    initialize_video_entries(db, &manifest).await?;
+    publish_manifest(db, &manifest).await;
+    Ok(Some(manifest))
+}
+
+/// Returns the path of the file where a video is stored once downloaded.
+pub fn content_filepath(config: &DownloaderConfig, id: uuid::Uuid) -> std::path::PathBuf {
+    config.content_path.join(format!("{id}.mp4"))
+}
+
+/// Returns the path of the file where a video is stored while being downloaded.
+pub fn partial_filepath(config: &DownloaderConfig, id: uuid::Uuid) -> std::path::PathBuf {
+    config.temp_download_path().join(format!("{id}.mp4.part"))
+}
+
+/// Removes the files of a video which is not fully downloaded. The files might not exist if the
+/// download never started. Therefore we don't error out and do best effort deletion here.
+async fn remove_partial_download(config: &DownloaderConfig, id: uuid::Uuid) {
+    let _ = tokio::fs::remove_file(partial_filepath(config, id)).await;
+    // Older versions downloaded the videos directly to their final path
+    let _ = tokio::fs::remove_file(content_filepath(config, id)).await;
+}
+
 /// Iterates through the on-disk video entries, deleting video content that is not present in the current
 /// manifest. This is a cleanup action that is deferred until the new manifest has been fully
-/// adopted.
-#[tracing::instrument(name = "remove_old_video_content", skip(database, new_manifest))]
+/// adopted. Returns the number of deleted videos.
+///
+/// When `retain_removed_for` is configured, downloaded videos are only marked as removed at
+/// `now`, and deleted once the grace period has elapsed since they were first marked.
+///
+/// Pinned videos are never deleted, until they are unpinned.
+#[tracing::instrument(
+    name = "remove_old_video_content",
+    skip(config, database, new_manifest)
+)]
 pub async fn remove_old_video_content(
-    content_path: &std::path::Path,
+    config: &DownloaderConfig,
     database: &Database,
     new_manifest: &ManifestFile,
-) -> anyhow::Result<()> {
+    now: std::time::SystemTime,
+) -> anyhow::Result<usize> {
     let in_manifest = |id| {
         new_manifest
             .sections
@@ -54,22 +136,67 @@ pub async fn remove_old_video_content(
             .any(|v| v.id == id)
     };
 
+    let mut deleted = 0;
     for video in database.list_all_videos().await? {
-        if !in_manifest(video.id) {
-            database.delete_video(video.id).await?;
-            if let DownloadStatus::Downloaded(path) = video.download_status {
-                tokio::fs::remove_file(path).await?;
-            } else {
-                // Try to remove it from the current runtime_path. Not only fully downloaded videos
-                // need to be deleted.
-                let path = content_path.join(format!("{}.mp4", video.id));
-                // The file might already not exist, if the download never started. Therefore we
-                // don't error out and do best effort deletion here.
-                let _ = tokio::fs::remove_file(path).await;
+        if in_manifest(video.id) {
+            // The video might have been removed by a previous manifest
+            if config.retain_removed_for.is_some() {
+                database.clear_removed(video.id).await?;
+            }
+            continue;
+        }
+        if video.pinned {
+            tracing::debug!(
+                "Keeping pinned video {} removed from the manifest",
+                video.id
+            );
+            continue;
+        }
+        if let (Some(grace_period), DownloadStatus::Downloaded(_)) =
+            (config.retain_removed_for, &video.download_status)
+        {
+            let removed_at = database.mark_removed(video.id, now).await?;
+            if now.duration_since(removed_at).unwrap_or_default() < grace_period {
+                continue;
             }
+            tracing::info!("Grace period of removed video {} elapsed", video.id);
+        }
+
+        database.delete_video(video.id).await?;
+        deleted += 1;
+        if let DownloadStatus::Downloaded(path) = video.download_status {
+            tokio::fs::remove_file(path).await?;
+        } else {
+            // Not only fully downloaded videos need to be deleted.
+            remove_partial_download(config, video.id).await;
         }
     }
 
+    Ok(deleted)
+}
+
+/// Deletes the local content of the given videos and marks them as pending, so that they are
+/// downloaded again. Videos unknown to the database are ignored.
+#[tracing::instrument(name = "reset_video_downloads", skip(config, database))]
+pub async fn reset_video_downloads(
+    config: &DownloaderConfig,
+    database: &Database,
+    ids: &[uuid::Uuid],
+) -> anyhow::Result<()> {
+    for &id in ids {
+        let video = match database.find_video(id).await {
+            Ok(video) => video,
+            Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        database.reset_download(id).await?;
+
+        match video.download_status {
+            DownloadStatus::Downloaded(path) => tokio::fs::remove_file(path).await?,
+            _ => remove_partial_download(config, id).await,
+        }
+        tracing::info!("Video {id} will be downloaded again");
+    }
     Ok(())
 }
 
@@ -77,12 +204,41 @@ pub async fn remove_old_video_content(
 struct Job {
     backoff_time: std::time::Duration,
     video: Video,
+    /// Other videos with the same source as `video`. The source is only downloaded once, and
+    /// copied to each of them.
+    duplicates: Vec<Video>,
+}
+
+impl Job {
+    /// Returns the video downloaded by the job, followed by its duplicates.
+    fn videos(&self) -> impl Iterator<Item = &Video> {
+        std::iter::once(&self.video).chain(&self.duplicates)
+    }
 }
 
-/// When the leap-server command is interrupted, downloads that might have been previously in
-/// progress are now lost. In order to more clearly report the download state after a reboot of the
-/// server, we mark them as failed with a corresponding reason, instead of saying they are
-/// downloading with some fake progress.
+/// Computes the time at which a failed job should be retried, randomizing its backoff time by
+/// the configured jitter fraction. Also increments the backoff time of the job for the next
+/// failure, up to the configured maximum.
+fn retry_wakeup_time(
+    ctx: &DownloadContext,
+    job: &mut Job,
+    now: tokio::time::Instant,
+) -> tokio::time::Instant {
+    let retry_params = &ctx.config.retry_params;
+    let jitter_fraction = retry_params.backoff_jitter_fraction.clamp(0.0, 1.0);
+    let jitter = jitter_fraction * (2.0 * (ctx.rng)() - 1.0);
+    let wakeup_time = now + job.backoff_time.mul_f64(1.0 + jitter);
+    job.backoff_time = job
+        .backoff_time
+        .mul_f64(retry_params.backoff_factor)
+        .min(retry_params.max_backoff);
+    wakeup_time
+}
+
+/// When the leap-server command or a download task is interrupted, downloads that might have been
+/// previously in progress are now lost. In order to more clearly report the download state after
+/// an interruption, we mark them as failed with the given reason, instead of saying they are
+/// downloading with some fake progress. This also allows them to be claimed again for download.
 #[tracing::instrument(
     name = "mark_interrupted_downloads",
     skip(database, manifest),
@@ -91,6 +247,8 @@ struct Job {
 pub async fn mark_interrupted_downloads(
     database: &Database,
     manifest: &ManifestFile,
+    reason: &str,
+    kind: FailureKind,
 ) -> anyhow::Result<()> {
     for video in manifest.sections.iter().flat_map(|s| s.content.iter()) {
         match database.find_video(video.id).await {
@@ -98,9 +256,7 @@ pub async fn mark_interrupted_downloads(
                 download_status: crate::db::DownloadStatus::InProgress(_),
                 ..
             }) => {
-                database
-                    .set_download_failed(video.id, "Download interrupted due to system restart")
-                    .await?;
+                database.set_download_failed(video.id, reason, kind).await?;
             }
             Ok(_) | Err(crate::db::Error::Diesel(diesel::result::Error::NotFound)) => {
                 // Nothing to do, these are handled separately when starting to fetch
@@ -130,54 +286,146 @@ pub async fn download_manifest_task(
     // After the video entries for the current manifest have been populated, we are ready to
     // publish the manifest and make it visible to the HTTP clients.
     publish_manifest(&ctx.db, &new_manifest).await;
+    ctx.status.set_manifest_published();
 
     // Mark older content for deletion
-    remove_old_video_content(&ctx.config.content_path, &ctx.db, &new_manifest).await?;
+    remove_old_video_content(
+        &ctx.config,
+        &ctx.db,
+        &new_manifest,
+        std::time::SystemTime::now(),
+    )
+    .await?;
+
+    // Videos already on disk don't need to be downloaded again
+    adopt_existing_files(&ctx, &new_manifest).await?;
 
-    // Collect the content that we need to download
+    // Collect the content that we need to download. Claiming the videos makes sure that no one
+    // else downloads them concurrently, and that videos listed twice are only downloaded once.
+    // The first video of each section is downloaded first, as it is the one played when opening
+    // the playlist of the section.
     let mut pending_downloads: VecDeque<Job> = VecDeque::new();
-    for video in new_manifest.sections.iter().flat_map(|s| s.content.iter()) {
-        let already_downloaded = ctx
-            .db
-            .find_video(video.id)
-            .await
-            .unwrap()
-            .download_status
-            .is_downloaded();
-        if pending_downloads.iter().all(|j| video.id != j.video.id) && !already_downloaded {
-            pending_downloads.push_back(Job {
-                video: video.clone(),
-                backoff_time: ctx.config.retry_params.initial_backoff,
-            });
+    let mut backoff_list: VecDeque<(tokio::time::Instant, Job)> = VecDeque::new();
+    let first_videos = new_manifest
+        .sections
+        .iter()
+        .filter_map(|s| s.content.first());
+    let other_videos = new_manifest
+        .sections
+        .iter()
+        .flat_map(|s| s.content.iter().skip(1));
+    for video in first_videos.chain(other_videos) {
+        // Failed downloads keep waiting for the rest of their backoff after a restart. They are
+        // not claimed while waiting, like the videos backing off before the restart.
+        if let Some(remaining) = remaining_backoff(&ctx.db, video.id).await? {
+            if let Some((_, job)) = backoff_list
+                .iter_mut()
+                .find(|(_, job)| job.video.uri == video.uri && job.video.checksum == video.checksum)
+            {
+                if job.videos().all(|v| v.id != video.id) {
+                    job.duplicates.push(video.clone());
+                }
+                continue;
+            }
+            tracing::info!(
+                "Video {} will reattempt download in {remaining:?}",
+                video.id
+            );
+            let wakeup_time = tokio::time::Instant::now() + remaining;
+            let position = backoff_list.partition_point(|(t, _)| *t <= wakeup_time);
+            backoff_list.insert(
+                position,
+                (
+                    wakeup_time,
+                    Job {
+                        video: video.clone(),
+                        backoff_time: ctx.config.retry_params.initial_backoff,
+                        duplicates: vec![],
+                    },
+                ),
+            );
+            continue;
+        }
+        if !ctx.db.claim_for_download(video.id).await? {
+            continue;
+        }
+        if !ctx.config.is_uri_allowed(&video.uri) {
+            let message = format!(
+                "URI scheme not allowed: {}, expected one of {:?}",
+                video.uri.scheme_str().unwrap_or_default(),
+                ctx.config.allowed_uri_schemes()
+            );
+            tracing::warn!("Not downloading video {}: {message}", video.id);
+            set_failed(&ctx, video.id, &message, FailureKind::Unknown).await?;
+            continue;
+        }
+        // Different videos with the same source file are also downloaded once
+        if let Some(job) = pending_downloads
+            .iter_mut()
+            .find(|job| job.video.uri == video.uri && job.video.checksum == video.checksum)
+        {
+            job.duplicates.push(video.clone());
+            continue;
         }
+        pending_downloads.push_back(Job {
+            video: video.clone(),
+            backoff_time: ctx.config.retry_params.initial_backoff,
+            duplicates: vec![],
+        });
     }
 
     tracing::debug!("Videos pending download: {pending_downloads:?}");
 
     // Because we do not want to ovewhelm the network, we limit the number of concurrent downloads
-    // we perform. This limit is configurable via the configuration file.
+    // we perform. This limit is configurable via the configuration file. Each download holds a
+    // permit, except for large videos that hold all of them to be downloaded alone.
+    let download_permits = Arc::new(Semaphore::new(ctx.config.concurrent_downloads));
     let mut inprogress_videos = JoinSet::new();
-    let mut backoff_list = VecDeque::new();
+    let mut paused = ctx.status.subscribe_downloads_paused();
+    let mut low_disk_space = ctx.status.subscribe_low_disk_space();
+    let mut throttled = ctx.status.subscribe_throttled();
+    let mut active_streams = ctx.active_streams.subscribe();
 
     loop {
         if inprogress_videos.is_empty() && backoff_list.is_empty() && pending_downloads.is_empty() {
             break;
         }
 
-        // Try to start more downloads while we have some
-        while inprogress_videos.len() < ctx.config.concurrent_downloads {
-            let Some(current_job) = pending_downloads.pop_front() else {
+        // Try to start more downloads while we have some, unless the downloads are paused, the
+        // storage is running out of space or the resource guard paused them. Jobs are started in
+        // order, so a large video waiting for all the permits is not overtaken.
+        while !*paused.borrow_and_update()
+            && !*low_disk_space.borrow_and_update()
+            && throttled.borrow_and_update().is_none()
+        {
+            let Some(current_job) = pending_downloads.front() else {
+                break;
+            };
+            if inprogress_videos.len() >= max_downloads(&ctx, *active_streams.borrow_and_update()) {
+                break;
+            }
+            let Ok(permits) = download_permits
+                .clone()
+                .try_acquire_many_owned(download_permits_of(&ctx, current_job))
+            else {
                 break;
             };
+            let current_job = pending_downloads.pop_front().expect("Pending job");
 
-            let job = download_job_task(ctx.clone(), current_job.clone());
-            inprogress_videos.spawn(job);
+            let job = download_job_task(ctx.clone(), current_job);
+            inprogress_videos.spawn(async move {
+                let _permits = permits;
+                job.await
+            });
         }
 
-        // We have 2 situations to wait for here.
+        // We have 3 situations to wait for here.
         //  1. A download finished, which opens up a new slot to start another download
         //  2. A failed video which was held has now completed the backoff duration and can be
         //     scheduled again.
+        //  3. The downloads were paused or resumed, by the operator, due to the free space or by
+        //     the resource guard.
+        //  4. Videos started or stopped being streamed, which may change the number of downloads.
         let first_backoff_video = async {
             let Some(wakeup_time) = backoff_list
                 .iter()
@@ -201,14 +449,49 @@ pub async fn download_manifest_task(
                 pending_downloads.push_back(job);
             }
 
+            Ok(()) = paused.changed() => {
+                tracing::info!("Downloads paused: {}", *paused.borrow());
+            }
+
+            Ok(()) = low_disk_space.changed() => {
+                tracing::info!("Downloads paused due to low disk space: {}", *low_disk_space.borrow());
+            }
+
+            Ok(()) = throttled.changed() => {
+                tracing::info!("Downloads paused by the resource guard: {:?}", *throttled.borrow());
+            }
+
+            Ok(()) = active_streams.changed() => {
+                tracing::debug!("Videos being streamed: {}", *active_streams.borrow());
+            }
+
             Some(finished_video) = inprogress_videos.join_next() => {
                 match finished_video? {
                     Ok(()) => { }
                     Err(DownloadJobError::ShouldRetry(mut job)) => {
-                        tracing::error!("Video {} failed. Backing off for {:?}", job.video.id, job.backoff_time);
-                        let wakeup_time = tokio::time::Instant::now() + job.backoff_time;
-                        job.backoff_time = job.backoff_time .mul_f64( ctx.config.retry_params.backoff_factor);
-                        backoff_list.push_back((wakeup_time, job));
+                        let now = tokio::time::Instant::now();
+                        let wakeup_time = retry_wakeup_time(&ctx, &mut job, now);
+                        ctx.error_log.error(&format!("Video {} failed. Backing off", job.video.id));
+                        tracing::debug!("Video {} will be retried in {:?}", job.video.id, wakeup_time - now);
+                        let retry_at = std::time::SystemTime::now() + (wakeup_time - now);
+                        for video in job.videos() {
+                            if let Err(e) = ctx.db.set_retry_at(video.id, retry_at).await {
+                                tracing::warn!("Unable to record the retry time of video {}: {e}", video.id);
+                            }
+                        }
+                        // Due to the jitter, the backoff list is not ordered by insertion time.
+                        let position = backoff_list.partition_point(|(t, _)| *t <= wakeup_time);
+                        backoff_list.insert(position, (wakeup_time, job));
+                    }
+                    Err(DownloadJobError::PathNotWritable(path)) => {
+                        // All the other downloads would fail the same way, so we stop them and
+                        // report the error once
+                        let msg = format!("Download path {path:?} is not writable");
+                        tracing::error!(msg);
+                        inprogress_videos.shutdown().await;
+                        mark_interrupted_downloads(&ctx.db, &new_manifest, &msg, FailureKind::Storage)
+                            .await?;
+                        anyhow::bail!(msg);
                     }
                     Err(DownloadJobError::Unrecoverable(job)) => {
                         let msg = format!("Unrecoverable download error for video: {}", job.video.id);
@@ -219,14 +502,250 @@ pub async fn download_manifest_task(
             }
         }
     }
+    ctx.error_log.flush();
+
+    if ctx.db.stats().await?.is_fully_cached() {
+        ctx.status.set_fully_cached();
+        if let Some(time_to_cache) = ctx.status.time_to_cache() {
+            tracing::info!("All the content of the manifest was cached in {time_to_cache:?}");
+        }
+    }
+
+    if let Some(url) = ctx.config.completion_webhook_url.clone() {
+        let payload = webhook::completion_payload(&ctx.db, &new_manifest).await?;
+        // The notification is sent in the background, so that it does not delay the download of
+        // newer manifests
+        tokio::spawn(webhook::notify_completion(
+            url,
+            ctx.config.retry_params.clone(),
+            payload,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns how long the failed download of a video still has to wait before being retried,
+/// according to the retry time recorded before a restart. Returns `None` if the video can be
+/// downloaded right away.
+async fn remaining_backoff(
+    db: &Database,
+    id: uuid::Uuid,
+) -> crate::db::Result<Option<std::time::Duration>> {
+    let Some(retry_at) = db.retry_at(id).await? else {
+        return Ok(None);
+    };
+    if !matches!(
+        db.find_video(id).await?.download_status,
+        DownloadStatus::Failed { .. }
+    ) {
+        return Ok(None);
+    }
+    Ok(retry_at
+        .duration_since(std::time::SystemTime::now())
+        .ok()
+        .filter(|remaining| !remaining.is_zero()))
+}
+
+/// Returns the maximum number of concurrent downloads while `streams` videos are streamed:
+/// `streaming_concurrent_downloads` from `yield_to_streams` streams on, and otherwise only the
+/// limit of the download permits.
+fn max_downloads(ctx: &DownloadContext, streams: usize) -> usize {
+    match ctx.config.streaming_concurrent_downloads {
+        Some(limit) if streams >= ctx.config.yield_to_streams => limit,
+        _ => usize::MAX,
+    }
+}
+
+/// Returns the number of download permits held while downloading a job: all of them for videos
+/// larger than `solo_download_bytes`, so that they are downloaded alone, and one otherwise.
+fn download_permits_of(ctx: &DownloadContext, job: &Job) -> u32 {
+    let concurrent_downloads = ctx.config.concurrent_downloads.max(1) as u32;
+    match ctx.config.solo_download_bytes {
+        Some(threshold) if job.video.file_size > threshold => concurrent_downloads,
+        _ => 1,
+    }
+}
+
+/// Records a failed download attempt of a video, and notifies the subscribers about it.
+async fn set_failed(
+    ctx: &DownloadContext,
+    id: uuid::Uuid,
+    message: &str,
+    kind: FailureKind,
+) -> crate::db::Result<()> {
+    ctx.db.set_download_failed(id, message, kind).await?;
+    // The number of attempts is only known by the database
+    ctx.notify(id, ctx.db.find_video(id).await?.download_status);
+    Ok(())
+}
+
+/// Probes the media information of a downloaded video that is missing from the manifest.
+async fn store_media_info(ctx: &DownloadContext, video: &Video, path: &std::path::Path) {
+    // Missing media information is not worth failing the download
+    let manifest_media_info = manifest_media_info(video);
+    if manifest_media_info.duration_secs.is_none() || manifest_media_info.resolution.is_none() {
+        let media_info = manifest_media_info.or(probe::probe_media_info(path).await);
+        if let Err(e) = ctx.db.update_media_info(video.id, media_info).await {
+            tracing::warn!(
+                "Error storing the media information of video {}: {e}",
+                video.id
+            );
+        }
+    }
+}
+
+/// Returns whether the file in `path` has the size and, if configured, the checksum declared by
+/// the manifest for `video`. Missing files don't match.
+async fn existing_file_matches(
+    ctx: &DownloadContext,
+    video: &Video,
+    path: &std::path::Path,
+) -> std::io::Result<bool> {
+    let size = match ctx.store.size(path).await {
+        Ok(size) => size,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if size != video.file_size {
+        tracing::info!(
+            "Ignoring existing file {path:?} of {size} bytes, expected {} bytes",
+            video.file_size
+        );
+        return Ok(false);
+    }
+    if ctx.config.existing_file_check != ExistingFileCheck::Checksum {
+        return Ok(true);
+    }
+
+    let mut reader = ctx.store.open_range(path, 0).await?;
+    let mut hasher = ctx.hashing.hasher(&video.checksum);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]).await;
+    }
+    let hash = hasher.finalize().await;
+    if hash != video.checksum {
+        tracing::info!(
+            "Ignoring existing file {path:?} with hash {hash}, expected {}",
+            video.checksum
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Marks the videos whose file is already in the content directory as downloaded, so that they
+/// are not downloaded again. This is the case when the content directory was seeded from
+/// external storage. Only videos that were not downloaded yet are checked.
+#[tracing::instrument(
+    name = "adopt_existing_files",
+    skip(ctx, manifest),
+    fields(manifest_date = %manifest.date)
+)]
+async fn adopt_existing_files(
+    ctx: &DownloadContext,
+    manifest: &ManifestFile,
+) -> anyhow::Result<()> {
+    if ctx.config.existing_file_check == ExistingFileCheck::Disabled {
+        return Ok(());
+    }
+    for video in manifest.sections.iter().flat_map(|s| s.content.iter()) {
+        let db_video = ctx.db.find_video(video.id).await?;
+        if !matches!(
+            db_video.download_status,
+            DownloadStatus::Pending | DownloadStatus::Failed { .. }
+        ) {
+            continue;
+        }
+        let path = content_filepath(&ctx.config, video.id);
+        match existing_file_matches(ctx, video, &path).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            // Unusable files are downloaded again, which reports the errors of their storage
+            Err(e) => {
+                tracing::warn!("Unable to check the existing file {path:?}: {e}");
+                continue;
+            }
+        }
 
+        ctx.db.set_downloaded(video.id, &path).await?;
+        store_media_info(ctx, video, &path).await;
+        ctx.notify(video.id, DownloadStatus::Downloaded(path.clone()));
+        tracing::info!("Using the existing file {path:?} of video {}", video.id);
+    }
     Ok(())
 }
 
+/// Maximum number of downloaded videos checked by each run of [`reconcile_downloads`], to avoid
+/// bursts of disk I/O.
+pub const RECONCILE_FILES_PER_RUN: usize = 16;
+
+/// Checks that the files of the downloaded videos following `cursor` are still on disk with the
+/// size declared by the manifest. Videos whose files were deleted or truncated behind our back are
+/// marked as pending. The cursor is advanced so that the next run checks the following videos,
+/// starting over once all of them were checked. Returns the ids of the videos marked as pending.
+#[tracing::instrument(name = "reconcile_downloads", skip(ctx))]
+pub async fn reconcile_downloads(
+    ctx: &DownloadContext,
+    cursor: &mut Option<uuid::Uuid>,
+) -> anyhow::Result<Vec<uuid::Uuid>> {
+    let videos = ctx
+        .db
+        .downloaded_videos_after(*cursor, RECONCILE_FILES_PER_RUN)
+        .await?;
+    *cursor = if videos.len() < RECONCILE_FILES_PER_RUN {
+        None
+    } else {
+        videos.last().map(|video| video.id)
+    };
+
+    let mut demoted = vec![];
+    for video in videos {
+        let DownloadStatus::Downloaded(path) = &video.download_status else {
+            continue;
+        };
+        let mismatch = match ctx.store.size(path).await {
+            Ok(size) if size == video.file_size => continue,
+            Ok(size) => format!("has {size} bytes instead of {}", video.file_size),
+            Err(e) => format!("can't be read: {e}"),
+        };
+        tracing::warn!(
+            "The file {path:?} of video {} {mismatch}, downloading it again",
+            video.id
+        );
+        ctx.db.reset_download(video.id).await?;
+        ctx.notify(video.id, DownloadStatus::Pending);
+        demoted.push(video.id);
+    }
+    Ok(demoted)
+}
+
 #[derive(Debug)]
 enum DownloadJobError {
     ShouldRetry(Job),
     Unrecoverable(Job),
+    /// The download directories can't be written to. Retrying is pointless until an operator
+    /// fixes the filesystem.
+    PathNotWritable(std::path::PathBuf),
+}
+
+impl DownloadJobError {
+    /// Classifies an error writing to the download directories.
+    fn from_write_error(job: &Job, path: &std::path::Path, e: &std::io::Error) -> Self {
+        use std::io::ErrorKind;
+
+        match e.kind() {
+            ErrorKind::PermissionDenied
+            | ErrorKind::ReadOnlyFilesystem
+            | ErrorKind::NotADirectory => Self::PathNotWritable(path.to_path_buf()),
+            _ => Self::ShouldRetry(job.clone()),
+        }
+    }
 }
 
 /// download job task
@@ -241,19 +760,23 @@ async fn download_job_task(ctx: DownloadContext, job: Job) -> Result<(), Downloa
     let video = &job.video;
     let mut stream = ctx.backend.fetch_resource(&video.uri);
 
-    let target_filepath = ctx.config.content_path.join(format!("{}.mp4", video.id));
-    if let Some(dir) = target_filepath.parent() {
-        tokio::fs::create_dir_all(dir).await.map_err(|e| {
-            tracing::error!("Error creating directory: {dir:?}. Error: {e}");
-            DownloadJobError::ShouldRetry(job.clone())
-        })?;
-    }
-    let mut target_file = tokio::fs::File::create(&target_filepath)
+    // Videos are downloaded to a temporary directory, so that partial downloads never live next
+    // to the served content
+    let target_filepath = content_filepath(&ctx.config, video.id);
+    let partial_filepath = partial_filepath(&ctx.config, video.id);
+    let partial_file = ctx
+        .store
+        .write_stream(&partial_filepath)
         .await
         .map_err(|e| {
-            tracing::error!("Error creating file: {target_filepath:?}. Error: {e}");
-            DownloadJobError::ShouldRetry(job.clone())
+            tracing::error!("Error creating file: {partial_filepath:?}. Error: {e}");
+            DownloadJobError::from_write_error(&job, &partial_filepath, &e)
         })?;
+    // The backends may yield small chunks, which are written in chunks of the configured size
+    let mut partial_file =
+        tokio::io::BufWriter::with_capacity(ctx.config.io_chunk_bytes, partial_file);
+    // The partial file may be streamed while it is written, but only up to the confirmed offset
+    let partial_write = ctx.partial_writes.start(&partial_filepath);
 
     let translate_error = |e: crate::db::Result<()>| {
         e.map_err(|e| {
@@ -264,9 +787,10 @@ async fn download_job_task(ctx: DownloadContext, job: Job) -> Result<(), Downloa
         })
     };
 
-    let mut hasher = sha2::Sha256::new();
+    let mut hasher = ctx.hashing.hasher(&video.checksum);
 
     let mut total_size = 0;
+    let mut confirmed_size = 0;
     while let Some(chunk) = stream.next().await {
         let chunk = match chunk {
             Ok(v) => v,
@@ -275,20 +799,34 @@ async fn download_job_task(ctx: DownloadContext, job: Job) -> Result<(), Downloa
                     "Error fetching file with id: {}, name: {}. path: {}. Error: {}.",
                     video.id, video.name, video.uri, err
                 );
-                tracing::error!("{error_msg}");
+                ctx.error_log.error(&error_msg);
 
-                translate_error(ctx.db.set_download_failed(video.id, &error_msg).await)?;
+                for video in job.videos() {
+                    translate_error(
+                        set_failed(&ctx, video.id, &error_msg, FailureKind::Network).await,
+                    )?;
+                }
 
                 return Err(DownloadJobError::ShouldRetry(job.clone()));
             }
         };
 
-        hasher.update(&chunk[..]);
-        target_file.write_all(&chunk[..]).await.map_err(|e| {
-            tracing::error!("Error writing file: {target_filepath:?}. Error: {e}");
-            DownloadJobError::ShouldRetry(job.clone())
+        hasher.update(&chunk[..]).await;
+        partial_file.write_all(&chunk[..]).await.map_err(|e| {
+            tracing::error!("Error writing file: {partial_filepath:?}. Error: {e}");
+            DownloadJobError::from_write_error(&job, &partial_filepath, &e)
         })?;
         total_size += chunk.len();
+        // Written content is only confirmed once flushed, as writes to the file complete in the
+        // background. Flushing once per chunk of the configured size keeps the writes batched.
+        if total_size - confirmed_size >= ctx.config.io_chunk_bytes {
+            partial_file.flush().await.map_err(|e| {
+                tracing::error!("Error flushing file: {partial_filepath:?}. Error: {e}");
+                DownloadJobError::from_write_error(&job, &partial_filepath, &e)
+            })?;
+            partial_write.confirm(total_size as u64);
+            confirmed_size = total_size;
+        }
 
         tracing::trace!(
             "Got chunk of {} bytes. Progress: {:.2} %",
@@ -296,29 +834,94 @@ async fn download_job_task(ctx: DownloadContext, job: Job) -> Result<(), Downloa
             (total_size as f64) / (job.video.file_size as f64) * 100.0
         );
 
-        translate_error(
-            ctx.db
-                .update_download_progress(video.id, total_size as u64)
-                .await,
-        )?;
+        for video in job.videos() {
+            translate_error(
+                ctx.db
+                    .update_download_progress(video.id, total_size as u64)
+                    .await,
+            )?;
+            ctx.notify(
+                video.id,
+                DownloadStatus::InProgress((total_size as u64, video.file_size)),
+            );
+        }
     }
 
-    let hash = hasher.finalize();
-    let hash = hash.as_slice();
-    let expected_hash = video.sha256.as_bytes();
-    if hash != &expected_hash[..] {
-        let hash: crate::manifest::Sha256 = hash.try_into().expect("Should have 32 bytes");
-        let err_msg = &format!("Got hash: {hash}. Expected: {}", video.sha256);
-        translate_error(ctx.db.set_download_failed(video.id, err_msg).await)?;
-        tracing::error!("{}", err_msg);
+    let hash = hasher.finalize().await;
+    if hash != video.checksum {
+        let err_msg = &format!("Got hash: {hash}. Expected: {}", video.checksum);
+        for video in job.videos() {
+            translate_error(set_failed(&ctx, video.id, err_msg, FailureKind::Checksum).await)?;
+        }
+        ctx.error_log.error(err_msg);
         return Err(DownloadJobError::ShouldRetry(job.clone()));
     }
 
+    let moved = async {
+        partial_file.shutdown().await?;
+        drop(partial_file);
+        partial_write.confirm(total_size as u64);
+        ctx.store.persist(&partial_filepath, &target_filepath).await
+    };
+    moved.await.map_err(|e| {
+        tracing::error!(
+            "Error moving file {partial_filepath:?} to {target_filepath:?}. Error: {e}"
+        );
+        DownloadJobError::from_write_error(&job, &target_filepath, &e)
+    })?;
+
     translate_error(ctx.db.set_downloaded(video.id, &target_filepath).await)?;
+    store_media_info(&ctx, video, &target_filepath).await;
+    ctx.notify(
+        video.id,
+        DownloadStatus::Downloaded(target_filepath.clone()),
+    );
     tracing::info!("Video downloaded successfully to: {target_filepath:?}");
+
+    for duplicate in &job.duplicates {
+        let filepath = copy_content(&ctx, &target_filepath, duplicate.id)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Error copying {target_filepath:?} to video {}. Error: {e}",
+                    duplicate.id
+                );
+                DownloadJobError::from_write_error(
+                    &job,
+                    &content_filepath(&ctx.config, duplicate.id),
+                    &e,
+                )
+            })?;
+        translate_error(ctx.db.set_downloaded(duplicate.id, &filepath).await)?;
+        store_media_info(&ctx, duplicate, &filepath).await;
+        ctx.notify(duplicate.id, DownloadStatus::Downloaded(filepath.clone()));
+        tracing::info!("Video with the same source copied to: {filepath:?}");
+    }
     Ok(())
 }
 
+/// Copies the downloaded content at `from` to the content path of the video `id`, which has the
+/// same source. Returns the content path of the video.
+async fn copy_content(
+    ctx: &DownloadContext,
+    from: &std::path::Path,
+    id: uuid::Uuid,
+) -> std::io::Result<std::path::PathBuf> {
+    let target_filepath = content_filepath(&ctx.config, id);
+    let partial_filepath = partial_filepath(&ctx.config, id);
+
+    let reader = ctx.store.open_range(from, 0).await?;
+    let mut reader = tokio::io::BufReader::with_capacity(ctx.config.io_chunk_bytes, reader);
+    let mut writer = ctx.store.write_stream(&partial_filepath).await?;
+    tokio::io::copy_buf(&mut reader, &mut writer).await?;
+    writer.shutdown().await?;
+    drop(writer);
+    ctx.store
+        .persist(&partial_filepath, &target_filepath)
+        .await?;
+    Ok(target_filepath)
+}
+
 #[cfg(test)]
 pub mod test {
     use std::{str::FromStr, sync::Arc, time::Duration};
@@ -338,6 +941,9 @@ pub mod test {
         Ok(ManifestFile {
             name: "manifest".to_string(),
             date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
             version: Version {
                 major: 2,
                 minor: 0,
@@ -346,55 +952,65 @@ pub mod test {
             sections: vec![
                 Section {
                     name: "".to_string(),
+                    description: None,
                     content: vec![
                         Video {
                             name: "Linear equations".to_string(),
                             id: uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799")
                                 .or_fail()?,
                             uri: "s3://bucket/linear-equations.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123456,
+                            duration_secs: None,
+                            resolution: None,
                         },
                         Video {
                             name: "Quadratic equations".to_string(),
                             id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a")
                                 .or_fail()?,
                             uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123457,
+                            duration_secs: None,
+                            resolution: None,
                         },
                     ],
                 },
                 Section {
                     name: "Integration".to_string(),
+                    description: None,
                     content: vec![
                         Video {
                             name: "Riemann sum".to_string(),
                             id: uuid::Uuid::from_str("eddb4450-a9ff-4a4b-ad81-2a8b78998405")
                                 .or_fail()?,
                             uri: "s3://bucket/riemann-sum.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "a6d3b80cd14f78b21ffbf5995bbda38ad8834459557782d245ed720134d36fc4"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123459,
+                            duration_secs: None,
+                            resolution: None,
                         },
                         Video {
                             name: "List of integrals".to_string(),
                             id: uuid::Uuid::from_str("f47e6cdc-1bcf-439a-9ea4-038dc7153648")
                                 .or_fail()?,
                             uri: "s3://bucket/list-of-integrals.mp4".parse().or_fail()?,
-                            sha256:
+                            checksum:
                                 "98780990e94fb55d0b88ebcd78fe82f069eac547731a4b0822332d826c970aec"
                                     .try_into()
                                     .or_fail()?,
                             file_size: 123460,
+                            duration_secs: None,
+                            resolution: None,
                         },
                     ],
                 },
@@ -406,6 +1022,9 @@ pub mod test {
         Ok(ManifestFile {
             name: "manifest 2".to_string(),
             date: chrono::NaiveDate::from_str("2025-10-11").or_fail()?,
+            published_at: None,
+            adopt_after: None,
+            min_vds_version: None,
             version: Version {
                 major: 2,
                 minor: 0,
@@ -413,26 +1032,33 @@ pub mod test {
             },
             sections: vec![Section {
                 name: "Section with a name".to_string(),
+                description: None,
                 content: vec![
                     Video {
                         name: "Quadratic equations".to_string(),
                         id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a")
                             .or_fail()?,
                         uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
-                        sha256: "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
-                            .try_into()
-                            .or_fail()?,
+                        checksum:
+                            "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
+                                .try_into()
+                                .or_fail()?,
                         file_size: 123457,
+                        duration_secs: None,
+                        resolution: None,
                     },
                     Video {
                         name: "Riemann sum".to_string(),
                         id: uuid::Uuid::from_str("eddb4450-a9ff-4a4b-ad81-2a8b78998405")
                             .or_fail()?,
                         uri: "s3://bucket/riemann-sum.mp4".parse().or_fail()?,
-                        sha256: "a6d3b80cd14f78b21ffbf5995bbda38ad8834459557782d245ed720134d36fc4"
-                            .try_into()
-                            .or_fail()?,
+                        checksum:
+                            "a6d3b80cd14f78b21ffbf5995bbda38ad8834459557782d245ed720134d36fc4"
+                                .try_into()
+                                .or_fail()?,
                         file_size: 123459,
+                        duration_secs: None,
+                        resolution: None,
                     },
                 ],
             }],
@@ -452,14 +1078,39 @@ pub mod test {
         let content_path = tempfile::TempDir::new().unwrap();
         let downloader_config = Arc::new(DownloaderConfig {
             concurrent_downloads: 2,
+            solo_download_bytes: None,
+            streaming_concurrent_downloads: None,
+            yield_to_streams: crate::cfg::DEFAULT_YIELD_TO_STREAMS,
             content_path: content_path.path().to_path_buf(),
+            temp_download_path: None,
             retry_params: RetryParams {
                 initial_backoff: Duration::from_millis(100),
                 backoff_factor: 1.0,
                 max_backoff: Duration::from_millis(100),
+                backoff_jitter_fraction: 0.5,
             },
             remote_server: "/Invalid".try_into().unwrap(),
             update_interval: Duration::from_secs(300),
+            max_manifest_bytes: crate::cfg::DEFAULT_MAX_MANIFEST_BYTES,
+            manual_fetch_cooldown: crate::cfg::DEFAULT_MANUAL_FETCH_COOLDOWN,
+            manifest_retry_interval: Duration::from_millis(100),
+            unreachable_after_failures: crate::cfg::DEFAULT_UNREACHABLE_AFTER_FAILURES,
+            max_update_interval: crate::cfg::DEFAULT_MAX_UPDATE_INTERVAL,
+            retain_removed_for: None,
+            completion_webhook_url: None,
+            existing_file_check: crate::cfg::ExistingFileCheck::default(),
+            reconcile_interval: Duration::from_secs(3600),
+            progress_event_interval: Duration::ZERO,
+            error_log_interval: crate::cfg::DEFAULT_ERROR_LOG_INTERVAL,
+            allowed_uri_schemes: None,
+            min_free_bytes: 0,
+            strict_manifest: false,
+            verify_manifest_content_type: true,
+            resource_guard: Default::default(),
+            io_chunk_bytes: crate::cfg::DEFAULT_IO_CHUNK_BYTES,
+            hashing_threads: crate::cfg::DEFAULT_HASHING_THREADS,
+            request_headers: Default::default(),
+            channels: Default::default(),
         });
 
         let runtime_path = tempfile::TempDir::new().unwrap();
@@ -475,9 +1126,25 @@ pub mod test {
         let dummy_backend = Arc::new(DummyBackend::default());
 
         let download_ctx = DownloadContext {
+            coalescer: crate::downloader::EventCoalescer::new(
+                downloader_config.progress_event_interval,
+            ),
+            error_log: crate::downloader::ErrorLogLimiter::new(
+                downloader_config.error_log_interval,
+            ),
+            hashing: crate::downloader::checksum::HashingPool::new(
+                downloader_config.hashing_threads,
+            ),
             config: downloader_config,
             backend: dummy_backend.clone(),
             db,
+            status: Default::default(),
+            store: Arc::new(crate::store::LocalFsStore),
+            rng: sequence_rng(vec![0.5]),
+            events: crate::downloader::content_events_channel(),
+            resource_guard: Arc::new(crate::downloader::guard::NoopResourceGuard),
+            partial_writes: Default::default(),
+            active_streams: Default::default(),
         };
 
         TestContext {
@@ -488,19 +1155,68 @@ pub mod test {
         }
     }
 
+    /// Deterministic random number generator that cycles through the given values.
+    fn sequence_rng(values: Vec<f64>) -> crate::downloader::Rng {
+        let index = std::sync::atomic::AtomicUsize::new(0);
+        Arc::new(move || {
+            values[index.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % values.len()]
+        })
+    }
+
     struct BackendFile {
         uri: Uri,
         content: Vec<u8>,
     }
 
+    /// Faults injected in the fetches of a file of the [`DummyBackend`].
+    #[derive(Debug, Default, Clone)]
+    struct Faults {
+        /// Number of upcoming fetches that fail after yielding `fail_after_bytes` of the content
+        failing_fetches: usize,
+        fail_after_bytes: usize,
+        /// Size of the chunks in which the content is yielded. Yielded at once if missing.
+        chunk_size: Option<usize>,
+        /// Time taken by each chunk
+        chunk_delay: Duration,
+    }
+
     struct DummyBackend {
         files: tokio::sync::Mutex<Vec<BackendFile>>,
+        /// Files whose download stalls forever after the first half of their content
+        stalled: tokio::sync::Mutex<Vec<Uri>>,
+        /// Faults injected in the fetches of each file
+        faults: std::sync::Mutex<Vec<(Uri, Faults)>>,
+        /// Starts of all the resource fetches, including the failed ones, in order
+        fetch_starts: std::sync::Mutex<Vec<(Uri, std::time::Instant)>>,
+        manifest: tokio::sync::Mutex<Option<Vec<u8>>>,
+        /// Number of upcoming manifest fetches that fail before the manifest is returned
+        failing_manifest_fetches: std::sync::atomic::AtomicUsize,
+        /// Number of manifest fetches, including the failed ones
+        manifest_fetches: std::sync::atomic::AtomicUsize,
+        /// Number of resource fetches, including the failed ones
+        resource_fetches: std::sync::atomic::AtomicUsize,
+        /// Time taken by each successful resource fetch
+        fetch_delay: std::sync::Mutex<Duration>,
+        /// Starts (`true`) and ends (`false`) of the successful resource fetches, in order
+        fetch_log: std::sync::Mutex<Vec<(Uri, bool)>>,
+        /// Barriers that the successful fetches of each file wait for once started
+        fetch_barriers: std::sync::Mutex<Vec<(Uri, Arc<tokio::sync::Barrier>)>>,
     }
 
     impl Default for DummyBackend {
         fn default() -> Self {
             Self {
                 files: tokio::sync::Mutex::new(vec![]),
+                stalled: tokio::sync::Mutex::new(vec![]),
+                faults: std::sync::Mutex::new(vec![]),
+                fetch_starts: std::sync::Mutex::new(vec![]),
+                manifest: tokio::sync::Mutex::new(None),
+                failing_manifest_fetches: std::sync::atomic::AtomicUsize::new(0),
+                manifest_fetches: std::sync::atomic::AtomicUsize::new(0),
+                resource_fetches: std::sync::atomic::AtomicUsize::new(0),
+                fetch_delay: std::sync::Mutex::new(Duration::ZERO),
+                fetch_log: std::sync::Mutex::new(vec![]),
+                fetch_barriers: std::sync::Mutex::new(vec![]),
             }
         }
     }
@@ -510,31 +1226,136 @@ pub mod test {
             let mut files = self.files.lock().await;
             files.push(file);
         }
+
+        async fn set_manifest(&self, manifest: &ManifestFile) {
+            let data = serde_json::to_vec(manifest).expect("Serializable manifest");
+            self.manifest.lock().await.replace(data);
+        }
+
+        /// Injects faults in the upcoming fetches of the file, replacing the previous ones.
+        fn inject_faults(&self, uri: &Uri, faults: Faults) {
+            let mut all_faults = self.faults.lock().unwrap();
+            all_faults.retain(|(u, _)| u != uri);
+            all_faults.push((uri.clone(), faults));
+        }
+
+        /// Returns the faults of the next fetch of the file, counting it as one of the failing
+        /// fetches. Returns whether the fetch fails along with them.
+        fn next_fetch_faults(&self, uri: &Uri) -> (Faults, bool) {
+            let mut all_faults = self.faults.lock().unwrap();
+            let Some((_, faults)) = all_faults.iter_mut().find(|(u, _)| u == uri) else {
+                return (Faults::default(), false);
+            };
+            let fails = faults.failing_fetches > 0;
+            faults.failing_fetches = faults.failing_fetches.saturating_sub(1);
+            (faults.clone(), fails)
+        }
+
+        /// Returns the times elapsed between consecutive fetches of the file.
+        fn fetch_intervals(&self, uri: &Uri) -> Vec<Duration> {
+            let starts: Vec<_> = self
+                .fetch_starts
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(u, _)| u == uri)
+                .map(|(_, start)| *start)
+                .collect();
+            starts.windows(2).map(|w| w[1] - w[0]).collect()
+        }
     }
 
     #[async_trait::async_trait]
     impl Backend for DummyBackend {
-        fn fetch_resource<'a, 'b>(
+        fn fetch_resource_from<'a, 'b>(
             &'a self,
             uri: &'b http::Uri,
+            offset: u64,
         ) -> std::pin::Pin<Box<dyn tokio_stream::Stream<Item = backend::ChunkResult> + Send + 'a>>
         where
             'b: 'a,
         {
+            self.resource_fetches
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.fetch_starts
+                .lock()
+                .unwrap()
+                .push((uri.clone(), std::time::Instant::now()));
+            let (faults, fails) = self.next_fetch_faults(uri);
             Box::pin(async_stream::stream! {
                 let files = self.files.lock().await;
                 let Some(file) = files.iter().find(|f| f.uri == *uri) else {
-                    yield Err(crate::downloader::Error::IoError(std::io::Error::new(std::io::ErrorKind::NotFound, "")));
+                    yield Err(crate::downloader::Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "")));
                     return;
                 };
+                let size = file.content.len() as u64;
+                if offset > size {
+                    yield Err(crate::downloader::Error::OffsetOutOfRange { offset, size });
+                    return;
+                }
+                let content = file.content[offset as usize..].to_vec();
+                drop(files);
+
+                if self.stalled.lock().await.contains(uri) {
+                    yield Ok(content[..content.len() / 2].to_vec());
+                    std::future::pending::<()>().await;
+                }
+
+                if fails {
+                    let prefix = &content[..faults.fail_after_bytes.min(content.len())];
+                    for chunk in prefix.chunks(faults.chunk_size.unwrap_or(prefix.len()).max(1)) {
+                        tokio::time::sleep(faults.chunk_delay).await;
+                        yield Ok(chunk.to_vec());
+                    }
+                    yield Err(crate::downloader::Error::Io(std::io::Error::new(
+                        std::io::ErrorKind::ConnectionReset,
+                        "injected fault",
+                    )));
+                    return;
+                }
 
-                yield Ok(file.content.clone());
+                self.fetch_log.lock().unwrap().push((uri.clone(), true));
+                let barrier = self
+                    .fetch_barriers
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .find(|(u, _)| u == uri)
+                    .map(|(_, barrier)| Arc::clone(barrier));
+                if let Some(barrier) = barrier {
+                    barrier.wait().await;
+                }
+                let delay = *self.fetch_delay.lock().unwrap();
+                tokio::time::sleep(delay).await;
+                for chunk in content.chunks(faults.chunk_size.unwrap_or(content.len()).max(1)) {
+                    tokio::time::sleep(faults.chunk_delay).await;
+                    yield Ok(chunk.to_vec());
+                }
+                self.fetch_log.lock().unwrap().push((uri.clone(), false));
             })
         }
 
-        async fn fetch_manifest(&self) -> std::result::Result<Vec<u8>, crate::downloader::Error> {
-            // Not needed for these tests
-            unimplemented!()
+        async fn fetch_manifest(
+            &self,
+            _max_size: usize,
+        ) -> std::result::Result<Vec<u8>, crate::downloader::Error> {
+            use std::sync::atomic::Ordering;
+
+            self.manifest_fetches.fetch_add(1, Ordering::Relaxed);
+            let failing = self.failing_manifest_fetches.fetch_update(
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+                |n| n.checked_sub(1),
+            );
+            if failing.is_ok() {
+                return Err(crate::downloader::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionRefused,
+                    "",
+                )));
+            }
+            self.manifest.lock().await.clone().ok_or_else(|| {
+                crate::downloader::Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, ""))
+            })
         }
     }
 
@@ -558,6 +1379,10 @@ pub mod test {
                     file_size: video.file_size,
                     download_status: crate::db::DownloadStatus::Pending,
                     view_count: 0,
+                    duration_secs: video.duration_secs,
+                    resolution: video.resolution.clone(),
+                    actual_size: None,
+                    pinned: false,
                 })
             );
         }
@@ -587,9 +1412,14 @@ pub mod test {
             tokio::fs::write(p, b"Dummy content").await.or_fail()?;
         }
 
-        remove_old_video_content(&ctx.download_ctx.config.content_path, db, &new_manifest)
-            .await
-            .or_fail()?;
+        remove_old_video_content(
+            &ctx.download_ctx.config,
+            db,
+            &new_manifest,
+            std::time::SystemTime::now(),
+        )
+        .await
+        .or_fail()?;
 
         for video in manifest.sections.iter().flat_map(|s| s.content.iter()) {
             let db_video = db.find_video(video.id).await;
@@ -615,6 +1445,10 @@ pub mod test {
                         file_size: video.file_size,
                         download_status: crate::db::DownloadStatus::Pending,
                         view_count: 0,
+                        duration_secs: None,
+                        resolution: None,
+                        actual_size: None,
+                        pinned: false,
                     }))
                 );
                 let content = tokio::fs::read_to_string(p).await.or_fail()?;
@@ -635,52 +1469,175 @@ pub mod test {
 
     #[tokio::test]
     #[googletest::test]
-    async fn test_download_job_task_recoverable_io_failure() -> googletest::Result<()> {
+    async fn test_remove_old_video_content_retains_removed_videos() -> googletest::Result<()> {
         let ctx = create_context().await;
-        let id = uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?;
-
-        initialize_video_entries(&ctx.download_ctx.db, &manifest_for_test().or_fail()?)
-            .await
-            .or_fail()?;
+        let db = &ctx.download_ctx.db;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.retain_removed_for = Some(Duration::from_secs(3600));
 
-        let result = download_job_task(
-            ctx.download_ctx.clone(),
-            Job {
-                backoff_time: ctx.download_ctx.config.retry_params.initial_backoff,
-                video: Video {
-                    name: "Quadratic equations".to_string(),
-                    id,
-                    uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
-                    sha256: "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
-                        .try_into()
-                        .or_fail()?,
-                    file_size: 123457,
-                },
-            },
-        )
-        .await;
+        let manifest = manifest_for_test()?;
+        let new_manifest = manifest_for_test2()?;
+        initialize_video_entries(db, &manifest).await.or_fail()?;
 
-        assert_that!(
-            result,
-            err(matches_pattern!(DownloadJobError::ShouldRetry(
-                matches_pattern!(Job {
-                    video: matches_pattern!(Video { id: &id, .. }),
-                    backoff_time: &ctx.download_ctx.config.retry_params.initial_backoff,
-                })
-            )))
-        );
+        let removed_id = manifest
+            .sections
+            .iter()
+            .flat_map(|s| s.content.iter())
+            .map(|v| v.id)
+            .find(|id| {
+                !new_manifest
+                    .sections
+                    .iter()
+                    .flat_map(|s| s.content.iter())
+                    .any(|v| v.id == *id)
+            })
+            .or_fail()?;
+        let path = config.content_path.join(format!("{removed_id}.mp4"));
+        tokio::fs::write(&path, b"Dummy content").await.or_fail()?;
+        db.set_downloaded(removed_id, &path).await.or_fail()?;
+
+        // The removed video keeps being served during the grace period
+        let now = std::time::SystemTime::now();
+        for elapsed in [Duration::ZERO, Duration::from_secs(1800)] {
+            remove_old_video_content(&config, db, &new_manifest, now + elapsed)
+                .await
+                .or_fail()?;
+            expect_that!(
+                db.find_video(removed_id).await,
+                ok(field!(
+                    &crate::db::Video.download_status,
+                    ref eq(&crate::db::DownloadStatus::Downloaded(path.clone()))
+                ))
+            );
+            expect_true!(path.exists());
+        }
 
-        // Check that file is available in the database
-        let db_video = ctx.download_ctx.db.find_video(id).await.or_fail()?;
+        // And is deleted once it elapsed since it was first removed
+        remove_old_video_content(&config, db, &new_manifest, now + Duration::from_secs(3600))
+            .await
+            .or_fail()?;
         expect_that!(
-            db_video,
-            matches_pattern!(crate::db::Video {
-                id: &id,
-                download_status: matches_pattern!(crate::db::DownloadStatus::Failed(eq(
-                    "Error fetching file with id: 5eb9e089-79cf-478d-9121-9ca3e7bb1d4a, name: Quadratic equations. path: s3://bucket/quadratic-equations.mp4. Error: I/O error reading from backend: ."
-                ))),
-                ..
-            })
+            db.find_video(removed_id).await,
+            err(matches_pattern!(crate::db::Error::Diesel(
+                matches_pattern!(diesel::result::Error::NotFound)
+            )))
+        );
+        expect_false!(path.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_remove_old_video_content_keeps_pinned_videos() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let db = &ctx.download_ctx.db;
+        let config = &ctx.download_ctx.config;
+
+        let manifest = manifest_for_test()?;
+        let new_manifest = manifest_for_test2()?;
+        initialize_video_entries(db, &manifest).await.or_fail()?;
+
+        let removed_id = manifest
+            .sections
+            .iter()
+            .flat_map(|s| s.content.iter())
+            .map(|v| v.id)
+            .find(|id| {
+                !new_manifest
+                    .sections
+                    .iter()
+                    .flat_map(|s| s.content.iter())
+                    .any(|v| v.id == *id)
+            })
+            .or_fail()?;
+        let path = config.content_path.join(format!("{removed_id}.mp4"));
+        tokio::fs::write(&path, b"Dummy content").await.or_fail()?;
+        db.set_downloaded(removed_id, &path).await.or_fail()?;
+        db.set_pinned(removed_id, true).await.or_fail()?;
+
+        let now = std::time::SystemTime::now();
+        remove_old_video_content(config, db, &new_manifest, now)
+            .await
+            .or_fail()?;
+        expect_that!(
+            db.find_video(removed_id).await,
+            ok(field!(&crate::db::Video.pinned, eq(true)))
+        );
+        expect_true!(path.exists());
+
+        // Unpinned videos are deleted as any other removed video
+        db.set_pinned(removed_id, false).await.or_fail()?;
+        remove_old_video_content(config, db, &new_manifest, now)
+            .await
+            .or_fail()?;
+        expect_that!(
+            db.find_video(removed_id).await,
+            err(matches_pattern!(crate::db::Error::Diesel(
+                matches_pattern!(diesel::result::Error::NotFound)
+            )))
+        );
+        expect_false!(path.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_job_task_recoverable_io_failure() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let id = uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?;
+
+        initialize_video_entries(&ctx.download_ctx.db, &manifest_for_test().or_fail()?)
+            .await
+            .or_fail()?;
+
+        let result = download_job_task(
+            ctx.download_ctx.clone(),
+            Job {
+                backoff_time: ctx.download_ctx.config.retry_params.initial_backoff,
+                video: Video {
+                    name: "Quadratic equations".to_string(),
+                    id,
+                    uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
+                    checksum: "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
+                        .try_into()
+                        .or_fail()?,
+                    file_size: 123457,
+                    duration_secs: None,
+                    resolution: None,
+                },
+                duplicates: vec![],
+            },
+        )
+        .await;
+
+        assert_that!(
+            result,
+            err(matches_pattern!(DownloadJobError::ShouldRetry(
+                matches_pattern!(Job {
+                    video: matches_pattern!(Video { id: &id, .. }),
+                    backoff_time: &ctx.download_ctx.config.retry_params.initial_backoff,
+                    ..
+                })
+            )))
+        );
+
+        // Check that file is available in the database
+        let db_video = ctx.download_ctx.db.find_video(id).await.or_fail()?;
+        expect_that!(
+            db_video,
+            matches_pattern!(crate::db::Video {
+                id: &id,
+                download_status: matches_pattern!(crate::db::DownloadStatus::Failed {
+                    message: eq(
+                        "Error fetching file with id: 5eb9e089-79cf-478d-9121-9ca3e7bb1d4a, name: Quadratic equations. path: s3://bucket/quadratic-equations.mp4. Error: I/O error reading from backend: ."
+                    ),
+                    kind: eq(&crate::db::FailureKind::Network),
+                    attempts: eq(&1),
+                }),
+                ..
+            })
         );
 
         Ok(())
@@ -713,11 +1670,14 @@ pub mod test {
                     name: name.clone(),
                     id,
                     uri,
-                    sha256: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                    checksum: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
                         .try_into()
                         .or_fail()?,
                     file_size: 4,
+                    duration_secs: None,
+                    resolution: None,
                 },
+                duplicates: vec![],
             },
         )
         .await;
@@ -744,6 +1704,63 @@ pub mod test {
         let data = tokio::fs::read(video_fs_path).await.or_fail()?;
         assert_that!(data, eq(&vec![1, 2, 3, 4]));
 
+        // Check that the partial download was moved to its final path
+        expect_false!(partial_filepath(&ctx.download_ctx.config, id).exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_job_task_custom_temp_download_path() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let temp_download_path = tempfile::TempDir::new().or_fail()?;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.temp_download_path = Some(temp_download_path.path().join("partial"));
+        ctx.download_ctx.config = Arc::new(config);
+
+        let id = uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?;
+        let uri: Uri = "s3://bucket/quadratic-equations.mp4".parse().or_fail()?;
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri: uri.clone(),
+                content: vec![1, 2, 3, 4],
+            })
+            .await;
+        initialize_video_entries(&ctx.download_ctx.db, &manifest_for_test().or_fail()?)
+            .await
+            .or_fail()?;
+
+        let result = download_job_task(
+            ctx.download_ctx.clone(),
+            Job {
+                backoff_time: ctx.download_ctx.config.retry_params.initial_backoff,
+                video: Video {
+                    name: "Quadratic equations".to_string(),
+                    id,
+                    uri,
+                    checksum: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                        .try_into()
+                        .or_fail()?,
+                    file_size: 4,
+                    duration_secs: None,
+                    resolution: None,
+                },
+                duplicates: vec![],
+            },
+        )
+        .await;
+        assert_that!(result, ok(anything()));
+
+        let video_fs_path = content_filepath(&ctx.download_ctx.config, id);
+        let data = tokio::fs::read(&video_fs_path).await.or_fail()?;
+        expect_that!(data, eq(&vec![1, 2, 3, 4]));
+        expect_that!(
+            std::fs::read_dir(temp_download_path.path().join("partial"))
+                .or_fail()?
+                .count(),
+            eq(0)
+        );
         Ok(())
     }
 
@@ -774,11 +1791,14 @@ pub mod test {
                     name: name.clone(),
                     id,
                     uri,
-                    sha256: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                    checksum: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
                         .try_into()
                         .or_fail()?,
                     file_size: 4,
+                    duration_secs: None,
+                    resolution: None,
                 },
+                duplicates: vec![],
             },
         )
         .await;
@@ -789,6 +1809,7 @@ pub mod test {
                 matches_pattern!(Job {
                     video: matches_pattern!(Video { id: &id, .. }),
                     backoff_time: &ctx.download_ctx.config.retry_params.initial_backoff,
+                    ..
                 })
             )))
         );
@@ -799,13 +1820,1877 @@ pub mod test {
             db_video,
             matches_pattern!(crate::db::Video {
                 id: &id,
-                download_status: matches_pattern!(crate::db::DownloadStatus::Failed(eq(
-                    "Got hash: 1571902abec0a45661de965dbe90cb0177b98c49fc58a5aabfa1edb6c678d972. Expected: 9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
-                ))),
+                download_status: matches_pattern!(crate::db::DownloadStatus::Failed {
+                    message: eq(
+                        "Got hash: 1571902abec0a45661de965dbe90cb0177b98c49fc58a5aabfa1edb6c678d972. Expected: 9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                    ),
+                    kind: eq(&crate::db::FailureKind::Checksum),
+                    ..
+                }),
                 ..
             })
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_retry_wakeup_time_jitter() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        ctx.download_ctx.rng = sequence_rng(vec![0.0, 0.75]);
+        let manifest = manifest_for_test()?;
+        let videos: Vec<_> = manifest
+            .sections
+            .iter()
+            .flat_map(|s| s.content.iter())
+            .take(2)
+            .collect();
+
+        let backoff_time = ctx.download_ctx.config.retry_params.initial_backoff;
+        let mut job_a = Job {
+            backoff_time,
+            video: videos[0].clone(),
+            duplicates: vec![],
+        };
+        let mut job_b = Job {
+            backoff_time,
+            video: videos[1].clone(),
+            duplicates: vec![],
+        };
+
+        // Both jobs fail at the same instant
+        let now = tokio::time::Instant::now();
+        let wakeup_a = retry_wakeup_time(&ctx.download_ctx, &mut job_a, now);
+        let wakeup_b = retry_wakeup_time(&ctx.download_ctx, &mut job_b, now);
+
+        expect_that!(wakeup_a, not(eq(wakeup_b)));
+        // The jitter fraction is 0.5, so the backoff is randomized within [50ms, 150ms)
+        expect_that!(wakeup_a - now, eq(Duration::from_millis(50)));
+        expect_that!(wakeup_b - now, eq(Duration::from_millis(125)));
+        // The jitter does not accumulate in the backoff time of the job
+        expect_that!(job_a.backoff_time, eq(backoff_time));
+        expect_that!(job_b.backoff_time, eq(backoff_time));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_redownload_changed_video() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let id = uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?;
+        let uri: Uri = "s3://bucket/quadratic-equations.mp4".parse().or_fail()?;
+        let video_fs_path = ctx
+            .download_ctx
+            .config
+            .content_path
+            .join(format!("{id}.mp4"));
+
+        let manifest_with_video = |sha256: &str, file_size| -> googletest::Result<ManifestFile> {
+            let mut manifest = manifest_for_test2()?;
+            manifest.sections[0].content = vec![Video {
+                name: "Quadratic equations".to_string(),
+                id,
+                uri: uri.clone(),
+                checksum: sha256.try_into().or_fail()?,
+                file_size,
+                duration_secs: None,
+                resolution: None,
+            }];
+            Ok(manifest)
+        };
+        let old_manifest = manifest_with_video(
+            "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a",
+            4,
+        )?;
+        let new_manifest = manifest_with_video(
+            "1571902abec0a45661de965dbe90cb0177b98c49fc58a5aabfa1edb6c678d972",
+            4,
+        )?;
+
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri: uri.clone(),
+                content: vec![1, 2, 3, 4],
+            })
+            .await;
+        download_manifest_task(ctx.download_ctx.clone(), old_manifest.clone())
+            .await
+            .or_fail()?;
+        let data = tokio::fs::read(&video_fs_path).await.or_fail()?;
+        expect_that!(data, eq(&vec![1, 2, 3, 4]));
+
+        // The publisher replaces the content of the video, keeping its id
+        ctx.dummy_backend.files.lock().await[0].content = vec![1, 2, 3, 5];
+        let changed = new_manifest.changed_videos(&old_manifest);
+        expect_that!(changed, eq(&vec![id]));
+
+        reset_video_downloads(&ctx.download_ctx.config, &ctx.download_ctx.db, &changed)
+            .await
+            .or_fail()?;
+        expect_false!(video_fs_path.exists());
+
+        download_manifest_task(ctx.download_ctx.clone(), new_manifest)
+            .await
+            .or_fail()?;
+        let db_video = ctx.download_ctx.db.find_video(id).await.or_fail()?;
+        expect_that!(
+            db_video.download_status,
+            eq(&crate::db::DownloadStatus::Downloaded(
+                video_fs_path.clone()
+            ))
+        );
+        let data = tokio::fs::read(&video_fs_path).await.or_fail()?;
+        expect_that!(data, eq(&vec![1, 2, 3, 5]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_manifest_task_path_not_writable() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        // A directory can't be created inside a regular file, regardless of the permissions of the
+        // user running the test
+        let parent = tempfile::NamedTempFile::new().or_fail()?;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.content_path = parent.path().join("content");
+        ctx.download_ctx.config = Arc::new(config);
+
+        let manifest = manifest_for_test().or_fail()?;
+        let result = download_manifest_task(ctx.download_ctx.clone(), manifest.clone()).await;
+        expect_that!(
+            result.map_err(|e| e.to_string()),
+            err(contains_substring("is not writable"))
+        );
+
+        // No video is left waiting for a retry
+        for video in manifest.sections.iter().flat_map(|s| s.content.iter()) {
+            let db_video = ctx.download_ctx.db.find_video(video.id).await.or_fail()?;
+            expect_that!(
+                db_video.download_status,
+                matches_pattern!(crate::db::DownloadStatus::Failed {
+                    message: contains_substring("is not writable"),
+                    kind: eq(&crate::db::FailureKind::Storage),
+                    ..
+                })
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_manifest_task_cancelled_by_newer_manifest() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+
+        // The old manifest has a video kept by the new manifest and a video removed from it
+        let kept = Video {
+            name: "Quadratic equations".to_string(),
+            id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?,
+            uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
+            checksum: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                .try_into()
+                .or_fail()?,
+            file_size: 4,
+            duration_secs: None,
+            resolution: None,
+        };
+        let removed = Video {
+            name: "Linear equations".to_string(),
+            id: uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799").or_fail()?,
+            uri: "s3://bucket/linear-equations.mp4".parse().or_fail()?,
+            checksum: "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                .try_into()
+                .or_fail()?,
+            file_size: 4,
+            duration_secs: None,
+            resolution: None,
+        };
+        let added = Video {
+            name: "Derivatives".to_string(),
+            id: uuid::Uuid::from_str("0ff3a4d4-4a11-4b0c-a46e-2dcbca8b2a55").or_fail()?,
+            uri: "s3://bucket/derivatives.mp4".parse().or_fail()?,
+            checksum: "1571902abec0a45661de965dbe90cb0177b98c49fc58a5aabfa1edb6c678d972"
+                .try_into()
+                .or_fail()?,
+            file_size: 4,
+            duration_secs: None,
+            resolution: None,
+        };
+
+        let mut old_manifest = manifest_for_test()?;
+        old_manifest.sections.truncate(1);
+        old_manifest.sections[0].content = vec![kept.clone(), removed.clone()];
+        let mut new_manifest = manifest_for_test2()?;
+        new_manifest.sections.truncate(1);
+        new_manifest.sections[0].content = vec![kept.clone(), added.clone()];
+        expect_true!(new_manifest.is_newer_than(&old_manifest));
+
+        for (video, content) in [
+            (&kept, vec![1, 2, 3, 4]),
+            (&removed, vec![9, 9, 9, 9]),
+            (&added, vec![1, 2, 3, 5]),
+        ] {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+        *ctx.dummy_backend.stalled.lock().await = vec![kept.uri.clone(), removed.uri.clone()];
+
+        // Wait until both downloads of the old manifest are halfway through
+        let mut pending_task = Some(tokio::spawn(download_manifest_task(
+            ctx.download_ctx.clone(),
+            old_manifest.clone(),
+        )));
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let mut halfway = true;
+                for video in [&kept, &removed] {
+                    halfway &= matches!(
+                        db.find_video(video.id).await.map(|v| v.download_status),
+                        Ok(crate::db::DownloadStatus::InProgress((2, _)))
+                    );
+                }
+                if halfway {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .or_fail()?;
+        expect_true!(partial_filepath(config, kept.id).exists());
+
+        // The newer manifest cancels the stalled downloads
+        ctx.dummy_backend.stalled.lock().await.clear();
+        ctx.dummy_backend.set_manifest(&new_manifest).await;
+        crate::downloader::check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+
+        for (video, content) in [(&kept, vec![1, 2, 3, 4]), (&added, vec![1, 2, 3, 5])] {
+            let path = content_filepath(config, video.id);
+            expect_that!(
+                db.find_video(video.id).await.or_fail()?.download_status,
+                eq(&crate::db::DownloadStatus::Downloaded(path.clone()))
+            );
+            expect_that!(tokio::fs::read(&path).await.or_fail()?, eq(&content));
+        }
+
+        // No leftovers of the cancelled downloads
+        expect_true!(db.find_video(removed.id).await.is_err());
+        expect_false!(content_filepath(config, removed.id).exists());
+        let partial_files = std::fs::read_dir(config.temp_download_path())
+            .or_fail()?
+            .collect::<std::io::Result<Vec<_>>>()
+            .or_fail()?;
+        expect_that!(partial_files, is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_initialize_video_entries_media_info() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let db = &ctx.download_ctx.db;
+
+        let mut manifest = manifest_for_test()?;
+        let video = &mut manifest.sections[0].content[0];
+        video.duration_secs = Some(754);
+        video.resolution = Some("1280x720".to_string());
+        let id = video.id;
+        initialize_video_entries(db, &manifest).await.or_fail()?;
+
+        let meta = leap_api::types::LocalVideoMeta::from(db.find_video(id).await.or_fail()?);
+        expect_that!(meta.duration_secs, some(eq(754)));
+        expect_that!(meta.resolution, some(eq("1280x720")));
+
+        // Videos without media information in the manifest are left unknown
+        let other_id = manifest.sections[0].content[1].id;
+        let other = db.find_video(other_id).await.or_fail()?;
+        expect_that!(other.duration_secs, none());
+        expect_that!(other.resolution, none());
+
+        // The manifest information is kept when the entries are initialized again
+        manifest.sections[0].content[0].resolution = None;
+        initialize_video_entries(db, &manifest).await.or_fail()?;
+        let video = db.find_video(id).await.or_fail()?;
+        expect_that!(video.resolution, some(eq("1280x720")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_job_task_checksum_algorithms() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let id = uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?;
+        let uri: Uri = "s3://bucket/quadratic-equations.mp4".parse().or_fail()?;
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri: uri.clone(),
+                content: b"abc".to_vec(),
+            })
+            .await;
+        initialize_video_entries(&ctx.download_ctx.db, &manifest_for_test().or_fail()?)
+            .await
+            .or_fail()?;
+
+        let job = |checksum: serde_json::Value| -> googletest::Result<Job> {
+            Ok(Job {
+                backoff_time: ctx.download_ctx.config.retry_params.initial_backoff,
+                video: Video {
+                    name: "Quadratic equations".to_string(),
+                    id,
+                    uri: uri.clone(),
+                    checksum: serde_json::from_value(checksum).or_fail()?,
+                    file_size: 3,
+                    duration_secs: None,
+                    resolution: None,
+                },
+                duplicates: vec![],
+            })
+        };
+
+        // The hash of the content matches, but with a different algorithm
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let result = download_job_task(
+            ctx.download_ctx.clone(),
+            job(serde_json::json!({ "algo": "blake3", "value": sha256 }))?,
+        )
+        .await;
+        expect_true!(matches!(result, Err(DownloadJobError::ShouldRetry(_))));
+
+        let sha512 = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+            2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+        let result = download_job_task(
+            ctx.download_ctx.clone(),
+            job(serde_json::json!({ "algo": "sha512", "value": sha512 }))?,
+        )
+        .await;
+        expect_that!(result, ok(anything()));
+        expect_true!(
+            ctx.download_ctx
+                .db
+                .find_video(id)
+                .await
+                .or_fail()?
+                .download_status
+                .is_downloaded()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_manifest_fetch_retried_after_failure() -> googletest::Result<()> {
+        use std::sync::atomic::Ordering;
+
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        expect_that!(config.manifest_retry_interval, lt(config.update_interval));
+
+        // The upstream server is unreachable once, then serves the manifest
+        ctx.dummy_backend
+            .failing_manifest_fetches
+            .store(1, Ordering::Relaxed);
+        ctx.dummy_backend.set_manifest(&manifest_for_test()?).await;
+
+        let (_cmd_sender, cmd_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let update_loop = tokio::spawn(crate::downloader::run_update_loop(
+            ctx.download_ctx.clone(),
+            cmd_receiver,
+        ));
+
+        // The manifest is published long before the regular update interval elapses
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while ctx.download_ctx.db.current_manifest().await.is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .or_fail()?;
+        expect_that!(
+            ctx.dummy_backend.manifest_fetches.load(Ordering::Relaxed),
+            eq(2)
+        );
+
+        update_loop.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_invalid_manifest_keeps_serving_current_manifest() -> googletest::Result<()> {
+        use crate::downloader::{UpdateCheck, check_updates};
+
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+
+        let video = Video {
+            name: "Quadratic equations".to_string(),
+            id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?,
+            uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
+            checksum: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                .try_into()
+                .or_fail()?,
+            file_size: 4,
+            duration_secs: None,
+            resolution: None,
+        };
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri: video.uri.clone(),
+                content: vec![1, 2, 3, 4],
+            })
+            .await;
+        let mut manifest = manifest_for_test()?;
+        manifest.sections.truncate(1);
+        manifest.sections[0].content = vec![video.clone()];
+        ctx.dummy_backend.set_manifest(&manifest).await;
+
+        let mut pending_task = None;
+        let check = check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        expect_that!(check, eq(UpdateCheck::Fetched));
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+
+        let current_manifest_name =
+            async || db.current_manifest().await.as_ref().map(|m| m.name.clone());
+        let path = content_filepath(config, video.id);
+        let expect_content_served = async || {
+            expect_that!(
+                db.find_video(video.id).await.map(|v| v.download_status),
+                ok(eq(&crate::db::DownloadStatus::Downloaded(path.clone())))
+            );
+            expect_that!(tokio::fs::read(&path).await, ok(eq(&vec![1, 2, 3, 4])));
+        };
+
+        // The upstream server serves a corrupt manifest
+        *ctx.dummy_backend.manifest.lock().await = Some(b"{\"name\": \"manifest".to_vec());
+        let check = check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        expect_that!(check, eq(UpdateCheck::Failed));
+        expect_true!(pending_task.is_none());
+        expect_that!(current_manifest_name().await, some(eq("manifest")));
+        expect_that!(
+            ctx.download_ctx.status.upstream_error(),
+            some(contains_substring("invalid format"))
+        );
+        expect_content_served().await;
+
+        // Recovers once a valid manifest is served again
+        let mut new_manifest = manifest_for_test2()?;
+        new_manifest.sections.truncate(1);
+        new_manifest.sections[0].content = vec![video.clone()];
+        ctx.dummy_backend.set_manifest(&new_manifest).await;
+        let check = check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        expect_that!(check, eq(UpdateCheck::Fetched));
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+        expect_that!(current_manifest_name().await, some(eq("manifest 2")));
+        expect_that!(ctx.download_ctx.status.upstream_error(), none());
+        expect_content_served().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_unsupported_manifest_version_is_used() -> googletest::Result<()> {
+        use crate::downloader::{UpdateCheck, check_updates};
+
+        let ctx = create_context().await;
+        let db = &ctx.download_ctx.db;
+
+        let mut manifest = manifest_for_test()?;
+        manifest.version.major = crate::manifest::SUPPORTED_MAJOR_VERSIONS.end() + 1;
+        // Without content, so that the download task finishes right away
+        manifest.sections.iter_mut().for_each(|s| s.content.clear());
+        expect_false!(manifest.version.is_supported());
+        ctx.dummy_backend.set_manifest(&manifest).await;
+
+        // The manifest is used on a best-effort basis
+        let mut pending_task = None;
+        let check = check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        expect_that!(check, eq(UpdateCheck::Fetched));
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+        expect_that!(
+            db.current_manifest()
+                .await
+                .as_ref()
+                .map(|m| m.version.clone()),
+            some(eq(&manifest.version))
+        );
+        expect_that!(ctx.download_ctx.status.upstream_error(), none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_manifest_adopted_after_its_adoption_time() -> googletest::Result<()> {
+        use crate::downloader::{UpdateCheck, check_updates};
+
+        let ctx = create_context().await;
+        let db = &ctx.download_ctx.db;
+
+        let mut old_manifest = manifest_for_test()?;
+        old_manifest
+            .sections
+            .iter_mut()
+            .for_each(|s| s.content.clear());
+        ctx.dummy_backend.set_manifest(&old_manifest).await;
+        let mut pending_task = None;
+        check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+
+        let mut new_manifest = manifest_with_known_content()?;
+        new_manifest.date = old_manifest.date.succ_opt().or_fail()?;
+        new_manifest.adopt_after = Some(chrono::Utc::now() + chrono::Duration::hours(1));
+        ctx.dummy_backend.set_manifest(&new_manifest).await;
+        add_known_content(&ctx, &new_manifest).await;
+
+        // The old manifest keeps being served until the adoption time
+        let check = check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        expect_that!(check, eq(UpdateCheck::Fetched));
+        expect_true!(pending_task.is_none());
+        expect_that!(db.current_manifest().await.clone(), some(eq(&old_manifest)));
+        expect_that!(db.saved_manifest().await, some(eq(&old_manifest)));
+        let new_id = new_manifest.sections[0].content[0].id;
+        expect_true!(db.find_video(new_id).await.is_err());
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed),
+            eq(0)
+        );
+
+        new_manifest.adopt_after = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+        ctx.dummy_backend.set_manifest(&new_manifest).await;
+        check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+        expect_that!(db.current_manifest().await.clone(), some(eq(&new_manifest)));
+        for video in &new_manifest.sections[0].content {
+            expect_true!(
+                db.find_video(video.id)
+                    .await
+                    .or_fail()?
+                    .download_status
+                    .is_downloaded()
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_manifest_requiring_newer_server_is_not_adopted() -> googletest::Result<()> {
+        use crate::downloader::{UpdateCheck, check_updates};
+        use crate::manifest::Version;
+
+        let ctx = create_context().await;
+        let db = &ctx.download_ctx.db;
+
+        let mut old_manifest = manifest_for_test()?;
+        old_manifest
+            .sections
+            .iter_mut()
+            .for_each(|s| s.content.clear());
+        ctx.dummy_backend.set_manifest(&old_manifest).await;
+        let mut pending_task = None;
+        check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+
+        let server_version = Version::of_server();
+        let mut new_manifest = manifest_with_known_content()?;
+        new_manifest.date = old_manifest.date.succ_opt().or_fail()?;
+        new_manifest.min_vds_version = Some(Version {
+            major: server_version.major + 1,
+            ..server_version.clone()
+        });
+        ctx.dummy_backend.set_manifest(&new_manifest).await;
+        add_known_content(&ctx, &new_manifest).await;
+
+        // The old manifest keeps being served, and the reason is reported
+        let check = check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        expect_that!(check, eq(UpdateCheck::Fetched));
+        expect_true!(pending_task.is_none());
+        expect_that!(db.current_manifest().await.clone(), some(eq(&old_manifest)));
+        expect_that!(db.saved_manifest().await, some(eq(&old_manifest)));
+        expect_that!(
+            ctx.download_ctx.status.rejected_manifest(),
+            some(contains_substring("requires server version"))
+        );
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed),
+            eq(0)
+        );
+
+        // Manifests requiring this version of the server are adopted
+        new_manifest.min_vds_version = Some(server_version);
+        ctx.dummy_backend.set_manifest(&new_manifest).await;
+        check_updates(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        pending_task.take().or_fail()?.await.or_fail()?.or_fail()?;
+        expect_that!(db.current_manifest().await.clone(), some(eq(&new_manifest)));
+        expect_that!(ctx.download_ctx.status.rejected_manifest(), none());
+        Ok(())
+    }
+
+    /// Adds the content of the videos of [`manifest_with_known_content`] to the backend.
+    async fn add_known_content(ctx: &TestContext, manifest: &ManifestFile) {
+        for (video, content) in manifest.sections[0]
+            .content
+            .iter()
+            .zip([vec![1, 2, 3, 4], vec![1, 2, 3, 5]])
+        {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_repeated_download_errors_are_logged_once() -> googletest::Result<()> {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mut ctx = create_context().await;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.retry_params = RetryParams {
+            initial_backoff: Duration::from_millis(1),
+            backoff_factor: 1.0,
+            max_backoff: Duration::from_millis(1),
+            backoff_jitter_fraction: 0.0,
+        };
+        ctx.download_ctx.config = Arc::new(config);
+        let manifest = manifest_with_known_content()?;
+        add_known_content(&ctx, &manifest).await;
+        let video = &manifest.sections[0].content[0];
+        ctx.dummy_backend.inject_faults(
+            &video.uri,
+            Faults {
+                failing_fetches: 20,
+                fail_after_bytes: 0,
+                chunk_size: None,
+                chunk_delay: Duration::ZERO,
+            },
+        );
+
+        // The logs are written as in the log file read by the status page
+        let logdir = tempfile::TempDir::new().or_fail()?;
+        let logfile =
+            crate::logfile::RotatingFile::open(&logdir.path().join("leap.log"), u64::MAX, 0)
+                .or_fail()?;
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_bunyan_formatter::JsonStorageLayer)
+            .with(tracing_bunyan_formatter::BunyanFormattingLayer::new(
+                "leap-server".into(),
+                move || logfile.clone(),
+            ));
+        {
+            let _guard = tracing::subscriber::set_default(subscriber);
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone())
+                .await
+                .or_fail()?;
+        }
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::SeqCst),
+            eq(22)
+        );
+
+        // Only the first and the last of the identical errors are logged
+        let log = std::fs::read_to_string(logdir.path().join("leap.log")).or_fail()?;
+        let fetch_errors: Vec<_> = log
+            .lines()
+            .filter(|line| line.contains("Error fetching file"))
+            .collect();
+        expect_that!(
+            fetch_errors,
+            elements_are![
+                not(contains_substring("repeated")),
+                contains_substring("(repeated 19 times)"),
+            ]
+        );
+        let backoffs = log.lines().filter(|line| line.contains("Backing off"));
+        expect_that!(backoffs.count(), eq(2));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_restarts_after_mid_stream_failure() -> googletest::Result<()> {
+        use crate::db::VideoEventKind;
+
+        let ctx = create_context().await;
+        let db = &ctx.download_ctx.db;
+        let manifest = manifest_with_known_content()?;
+        add_known_content(&ctx, &manifest).await;
+        let video = &manifest.sections[0].content[0];
+        // The first fetch fails after writing half of the content to the partial file
+        ctx.dummy_backend.inject_faults(
+            &video.uri,
+            Faults {
+                failing_fetches: 1,
+                fail_after_bytes: 2,
+                chunk_size: Some(1),
+                chunk_delay: Duration::from_millis(5),
+            },
+        );
+
+        download_manifest_task(ctx.download_ctx.clone(), manifest.clone())
+            .await
+            .or_fail()?;
+
+        // The retry fetches the whole content again, without keeping the partial content
+        let path = content_filepath(&ctx.download_ctx.config, video.id);
+        expect_that!(
+            db.find_video(video.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::Downloaded(path.clone()))
+        );
+        expect_that!(
+            tokio::fs::read(&path).await.or_fail()?,
+            eq(&vec![1, 2, 3, 4])
+        );
+        expect_false!(partial_filepath(&ctx.download_ctx.config, video.id).exists());
+        expect_that!(ctx.dummy_backend.fetch_intervals(&video.uri), len(eq(1)));
+
+        let events = db.video_events(video.id).await.or_fail()?;
+        expect_that!(
+            events,
+            contains(matches_pattern!(crate::db::VideoEvent {
+                kind: eq(&VideoEventKind::Failed),
+                message: contains_substring("injected fault"),
+                ..
+            }))
+        );
+        expect_that!(
+            events.last().map(|e| e.kind),
+            some(eq(VideoEventKind::Completed))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_backoff_escalates_up_to_max_backoff() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.retry_params = RetryParams {
+            initial_backoff: Duration::from_millis(50),
+            backoff_factor: 4.0,
+            max_backoff: Duration::from_millis(100),
+            backoff_jitter_fraction: 0.0,
+        };
+        ctx.download_ctx.config = Arc::new(config);
+        let manifest = manifest_with_known_content()?;
+        add_known_content(&ctx, &manifest).await;
+        let video = &manifest.sections[0].content[0];
+        // Flaky source, slow to fail
+        ctx.dummy_backend.inject_faults(
+            &video.uri,
+            Faults {
+                failing_fetches: 3,
+                fail_after_bytes: 1,
+                chunk_size: Some(1),
+                chunk_delay: Duration::from_millis(5),
+            },
+        );
+
+        download_manifest_task(ctx.download_ctx.clone(), manifest.clone())
+            .await
+            .or_fail()?;
+        expect_true!(
+            ctx.download_ctx
+                .db
+                .find_video(video.id)
+                .await
+                .or_fail()?
+                .download_status
+                .is_downloaded()
+        );
+
+        // Each retry waits longer than the previous one, until reaching the maximum backoff. The
+        // third retry would wait 800ms without the maximum.
+        let intervals = ctx.dummy_backend.fetch_intervals(&video.uri);
+        assert_that!(intervals, len(eq(3)));
+        expect_that!(intervals[0], ge(Duration::from_millis(50)));
+        expect_that!(intervals[1], ge(Duration::from_millis(100)));
+        expect_that!(intervals[2], ge(Duration::from_millis(100)));
+        expect_that!(intervals[2], lt(Duration::from_millis(400)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_retry_wakeup_time_escalation() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.retry_params = RetryParams {
+            initial_backoff: Duration::from_millis(100),
+            backoff_factor: 2.0,
+            max_backoff: Duration::from_millis(300),
+            backoff_jitter_fraction: 0.0,
+        };
+        ctx.download_ctx.config = Arc::new(config);
+        let mut job = Job {
+            backoff_time: ctx.download_ctx.config.retry_params.initial_backoff,
+            video: manifest_with_known_content()?.sections[0].content[0].clone(),
+            duplicates: vec![],
+        };
+
+        let now = tokio::time::Instant::now();
+        let mut backoffs = vec![];
+        for _ in 0..4 {
+            backoffs.push(retry_wakeup_time(&ctx.download_ctx, &mut job, now) - now);
+        }
+        expect_that!(
+            backoffs,
+            elements_are![
+                eq(&Duration::from_millis(100)),
+                eq(&Duration::from_millis(200)),
+                eq(&Duration::from_millis(300)),
+                eq(&Duration::from_millis(300)),
+            ]
+        );
+        Ok(())
+    }
+
+    /// Returns a manifest with two videos, whose content is `[1, 2, 3, 4]` and `[1, 2, 3, 5]`.
+    fn manifest_with_known_content() -> googletest::Result<ManifestFile> {
+        let mut manifest = manifest_for_test()?;
+        manifest.sections.truncate(1);
+        manifest.sections[0].content = vec![
+            Video {
+                name: "Quadratic equations".to_string(),
+                id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?,
+                uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
+                checksum: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                    .try_into()
+                    .or_fail()?,
+                file_size: 4,
+                duration_secs: None,
+                resolution: None,
+            },
+            Video {
+                name: "Derivatives".to_string(),
+                id: uuid::Uuid::from_str("0ff3a4d4-4a11-4b0c-a46e-2dcbca8b2a55").or_fail()?,
+                uri: "s3://bucket/derivatives.mp4".parse().or_fail()?,
+                checksum: "1571902abec0a45661de965dbe90cb0177b98c49fc58a5aabfa1edb6c678d972"
+                    .try_into()
+                    .or_fail()?,
+                file_size: 4,
+                duration_secs: None,
+                resolution: None,
+            },
+        ];
+        Ok(manifest)
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_existing_files_with_checksum_are_not_downloaded() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+        let manifest = manifest_with_known_content()?;
+        let (seeded, corrupted) = (
+            &manifest.sections[0].content[0],
+            &manifest.sections[0].content[1],
+        );
+
+        tokio::fs::write(content_filepath(config, seeded.id), [1, 2, 3, 4])
+            .await
+            .or_fail()?;
+        tokio::fs::write(content_filepath(config, corrupted.id), [1, 2, 3, 6])
+            .await
+            .or_fail()?;
+        // Only the corrupted video can be downloaded. Downloading the seeded one would fail
+        // forever.
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri: corrupted.uri.clone(),
+                content: vec![1, 2, 3, 5],
+            })
+            .await;
+
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await
+        .or_fail()?
+        .or_fail()?;
+
+        for (video, content) in [(seeded, vec![1, 2, 3, 4]), (corrupted, vec![1, 2, 3, 5])] {
+            let path = content_filepath(config, video.id);
+            expect_that!(
+                db.find_video(video.id).await.or_fail()?.download_status,
+                eq(&crate::db::DownloadStatus::Downloaded(path.clone()))
+            );
+            expect_that!(tokio::fs::read(&path).await.or_fail()?, eq(&content));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_existing_files_with_size_only() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.existing_file_check = ExistingFileCheck::Size;
+        ctx.download_ctx.config = Arc::new(config);
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+        let manifest = manifest_with_known_content()?;
+        let (truncated, corrupted) = (
+            &manifest.sections[0].content[0],
+            &manifest.sections[0].content[1],
+        );
+
+        tokio::fs::write(content_filepath(config, truncated.id), [1, 2, 3])
+            .await
+            .or_fail()?;
+        tokio::fs::write(content_filepath(config, corrupted.id), [1, 2, 3, 6])
+            .await
+            .or_fail()?;
+
+        initialize_video_entries(db, &manifest).await.or_fail()?;
+        adopt_existing_files(&ctx.download_ctx, &manifest)
+            .await
+            .or_fail()?;
+
+        // The checksum is not checked, only the size
+        expect_that!(
+            db.find_video(truncated.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::Pending)
+        );
+        expect_that!(
+            db.find_video(corrupted.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::Downloaded(content_filepath(
+                config,
+                corrupted.id
+            )))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_paused_downloads_are_not_started() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+        let manifest = manifest_with_known_content()?;
+        for (video, content) in manifest.sections[0]
+            .content
+            .iter()
+            .zip([vec![1, 2, 3, 4], vec![1, 2, 3, 5]])
+        {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+
+        ctx.download_ctx.status.set_downloads_paused(true);
+        let mut task = tokio::spawn(download_manifest_task(
+            ctx.download_ctx.clone(),
+            manifest.clone(),
+        ));
+
+        // No download starts while paused
+        let paused = tokio::time::timeout(Duration::from_millis(300), &mut task).await;
+        expect_true!(paused.is_err());
+        for video in &manifest.sections[0].content {
+            expect_that!(
+                db.find_video(video.id).await.or_fail()?.download_status,
+                eq(&crate::db::DownloadStatus::InProgress((0, 4)))
+            );
+            expect_false!(content_filepath(config, video.id).exists());
+        }
+
+        // Resuming downloads the pending videos
+        ctx.download_ctx.status.set_downloads_paused(false);
+        tokio::time::timeout(Duration::from_secs(10), task)
+            .await
+            .or_fail()?
+            .or_fail()?
+            .or_fail()?;
+        for video in &manifest.sections[0].content {
+            expect_that!(
+                db.find_video(video.id).await.or_fail()?.download_status,
+                eq(&crate::db::DownloadStatus::Downloaded(content_filepath(
+                    config, video.id
+                )))
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_reconcile_downloads_requeues_missing_files() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+        let manifest = manifest_with_known_content()?;
+        let (deleted, kept) = (
+            &manifest.sections[0].content[0],
+            &manifest.sections[0].content[1],
+        );
+        for (video, content) in [(deleted, vec![1, 2, 3, 4]), (kept, vec![1, 2, 3, 5])] {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await
+        .or_fail()?
+        .or_fail()?;
+
+        // Nothing to do while the files are on disk
+        let mut cursor = None;
+        expect_that!(
+            reconcile_downloads(&ctx.download_ctx, &mut cursor)
+                .await
+                .or_fail()?,
+            is_empty()
+        );
+
+        tokio::fs::remove_file(content_filepath(config, deleted.id))
+            .await
+            .or_fail()?;
+        expect_that!(
+            reconcile_downloads(&ctx.download_ctx, &mut cursor)
+                .await
+                .or_fail()?,
+            elements_are![eq(&deleted.id)]
+        );
+
+        // The missing video is downloaded again
+        let mut pending_task = None;
+        crate::downloader::download_missing_videos(ctx.download_ctx.clone(), &mut pending_task)
+            .await
+            .or_fail()?;
+        tokio::time::timeout(Duration::from_secs(10), pending_task.take().or_fail()?)
+            .await
+            .or_fail()?
+            .or_fail()?
+            .or_fail()?;
+        for (video, content) in [(deleted, vec![1, 2, 3, 4]), (kept, vec![1, 2, 3, 5])] {
+            let path = content_filepath(config, video.id);
+            expect_that!(
+                db.find_video(video.id).await.or_fail()?.download_status,
+                eq(&crate::db::DownloadStatus::Downloaded(path.clone()))
+            );
+            expect_that!(tokio::fs::read(&path).await.or_fail()?, eq(&content));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_missing_files_dont_interrupt_downloads() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+        let manifest = manifest_with_known_content()?;
+        let (deleted, stalled) = (
+            &manifest.sections[0].content[0],
+            &manifest.sections[0].content[1],
+        );
+        for (video, content) in [(deleted, vec![1, 2, 3, 4]), (stalled, vec![1, 2, 3, 5])] {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+        *ctx.dummy_backend.stalled.lock().await = vec![stalled.uri.clone()];
+
+        // Wait until a video is downloaded while the other one is halfway through
+        let mut pending_task = Some(tokio::spawn(download_manifest_task(
+            ctx.download_ctx.clone(),
+            manifest.clone(),
+        )));
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let deleted_status = db.find_video(deleted.id).await.map(|v| v.download_status);
+                let stalled_status = db.find_video(stalled.id).await.map(|v| v.download_status);
+                if matches!(deleted_status, Ok(crate::db::DownloadStatus::Downloaded(_)))
+                    && matches!(
+                        stalled_status,
+                        Ok(crate::db::DownloadStatus::InProgress((2, _)))
+                    )
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .or_fail()?;
+
+        tokio::fs::remove_file(content_filepath(config, deleted.id))
+            .await
+            .or_fail()?;
+        let mut cursor = None;
+        expect_that!(
+            reconcile_downloads(&ctx.download_ctx, &mut cursor)
+                .await
+                .or_fail()?,
+            elements_are![eq(&deleted.id)]
+        );
+
+        // The missing video waits for the download in progress, which is not interrupted
+        let download_missing =
+            crate::downloader::download_missing_videos(ctx.download_ctx.clone(), &mut pending_task);
+        expect_true!(
+            tokio::time::timeout(Duration::from_millis(200), download_missing)
+                .await
+                .is_err()
+        );
+        expect_false!(pending_task.as_ref().or_fail()?.is_finished());
+        expect_that!(
+            db.find_video(stalled.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::InProgress((2, 4)))
+        );
+        expect_that!(
+            db.find_video(deleted.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::Pending)
+        );
+
+        pending_task.take().or_fail()?.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_videos_with_the_same_source_are_downloaded_once() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+        let mut manifest = manifest_with_known_content()?;
+        let first = manifest.sections[0].content[0].clone();
+        let second = &mut manifest.sections[0].content[1];
+        second.uri = first.uri.clone();
+        second.checksum = first.checksum.clone();
+        let second = second.clone();
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri: first.uri.clone(),
+                content: vec![1, 2, 3, 4],
+            })
+            .await;
+
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await
+        .or_fail()?
+        .or_fail()?;
+
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed),
+            eq(1)
+        );
+        for video in [&first, &second] {
+            let path = content_filepath(config, video.id);
+            expect_that!(
+                db.find_video(video.id).await.or_fail()?.download_status,
+                eq(&crate::db::DownloadStatus::Downloaded(path.clone()))
+            );
+            expect_that!(
+                tokio::fs::read(&path).await.or_fail()?,
+                eq(&vec![1, 2, 3, 4])
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_saved_manifest_is_published_after_restart() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let manifest = manifest_for_test()?;
+        // The process stops right after saving a new manifest, before creating its videos
+        ctx.download_ctx
+            .db
+            .save_manifest_to_disk(&serde_json::to_vec(&manifest).or_fail()?)
+            .await
+            .or_fail()?;
+
+        let db = Database::open(DbConfig {
+            busy_timeout: Duration::from_secs(2),
+            runtime_path: ctx._runtime_path.path().to_path_buf(),
+            pool_size: 16,
+        })
+        .await
+        .or_fail()?;
+        db.apply_pending_migrations().await.or_fail()?;
+        // The manifest is not served before its videos are in the database
+        expect_true!(db.current_manifest().await.is_none());
+        expect_that!(db.current_manifest_sections().await, ok(is_empty()));
+
+        let loaded = load_saved_manifest(&db).await.or_fail()?;
+        expect_that!(loaded, some(eq(&manifest)));
+        let sections = db.current_manifest_sections().await.or_fail()?;
+        expect_that!(
+            sections.iter().map(|s| s.videos.len()).sum::<usize>(),
+            eq(db.count_videos().await)
+        );
+        expect_that!(sections.len(), eq(manifest.sections.len()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_large_videos_are_downloaded_alone() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.concurrent_downloads = 2;
+        config.solo_download_bytes = Some(4);
+        ctx.download_ctx.config = Arc::new(config);
+
+        let mut manifest = manifest_with_known_content()?;
+        let small = manifest.sections[0].content.clone();
+        // The fetches of the small videos only complete once both of them started, so that they
+        // overlap regardless of the time taken by each download
+        let barrier = Arc::new(tokio::sync::Barrier::new(2));
+        for video in &small[..2] {
+            ctx.dummy_backend
+                .fetch_barriers
+                .lock()
+                .unwrap()
+                .push((video.uri.clone(), Arc::clone(&barrier)));
+        }
+        let large_video =
+            |name: &str, content: Vec<u8>, sha256: &str| -> googletest::Result<Video> {
+                Ok(Video {
+                    name: name.to_string(),
+                    id: uuid::Uuid::new_v4(),
+                    uri: format!("s3://bucket/{name}.mp4").parse().or_fail()?,
+                    checksum: sha256.try_into().or_fail()?,
+                    file_size: content.len() as u64,
+                    duration_secs: None,
+                    resolution: None,
+                })
+            };
+        let large = [
+            large_video(
+                "first-large",
+                (1..=8).collect(),
+                "66840dda154e8a113c31dd0ad32f7f3a366a80e8136979d8f5a101d3d29d6f72",
+            )?,
+            large_video(
+                "second-large",
+                (2..=9).collect(),
+                "117c9035800fb3497693c4c08747397e1c69e3b3b799ed286adb2978df427f49",
+            )?,
+        ];
+        manifest.sections[0].content = vec![
+            large[0].clone(),
+            small[0].clone(),
+            small[1].clone(),
+            large[1].clone(),
+        ];
+        for (video, content) in [
+            (&large[0], (1..=8).collect()),
+            (&small[0], vec![1, 2, 3, 4]),
+            (&small[1], vec![1, 2, 3, 5]),
+            (&large[1], (2..=9).collect()),
+        ] {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await
+        .or_fail()?
+        .or_fail()?;
+
+        // Replay the fetches, collecting the other fetches in progress when each one started
+        let mut active = vec![];
+        let mut overlapping = vec![];
+        for (uri, started) in ctx.dummy_backend.fetch_log.lock().unwrap().iter() {
+            if *started {
+                overlapping.push((uri.clone(), active.clone()));
+                active.push(uri.clone());
+            } else {
+                active.retain(|u| u != uri);
+            }
+        }
+        expect_that!(
+            overlapping,
+            elements_are![
+                eq(&(large[0].uri.clone(), vec![])),
+                // The small videos are fetched at the same time, starting in any order
+                any!(
+                    eq(&(small[0].uri.clone(), vec![])),
+                    eq(&(small[1].uri.clone(), vec![]))
+                ),
+                any!(
+                    eq(&(small[1].uri.clone(), vec![small[0].uri.clone()])),
+                    eq(&(small[0].uri.clone(), vec![small[1].uri.clone()]))
+                ),
+                eq(&(large[1].uri.clone(), vec![])),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_downloads_yield_to_streams() -> googletest::Result<()> {
+        // Downloads the known content, returning the maximum number of fetches in progress at once
+        let max_concurrent_fetches = async |streams: usize| -> googletest::Result<usize> {
+            let mut ctx = create_context().await;
+            let mut config = (*ctx.download_ctx.config).clone();
+            config.concurrent_downloads = 2;
+            config.streaming_concurrent_downloads = Some(1);
+            config.yield_to_streams = 1;
+            ctx.download_ctx.config = Arc::new(config);
+            *ctx.dummy_backend.fetch_delay.lock().unwrap() = Duration::from_millis(100);
+            let manifest = manifest_with_known_content()?;
+            add_known_content(&ctx, &manifest).await;
+
+            let _streams: Vec<_> = (0..streams)
+                .map(|_| ctx.download_ctx.active_streams.start())
+                .collect();
+            tokio::time::timeout(
+                Duration::from_secs(10),
+                download_manifest_task(ctx.download_ctx.clone(), manifest),
+            )
+            .await
+            .or_fail()?
+            .or_fail()?;
+
+            let mut active = 0;
+            let mut max_active = 0;
+            for (_, started) in ctx.dummy_backend.fetch_log.lock().unwrap().iter() {
+                if *started {
+                    active += 1;
+                    max_active = max_active.max(active);
+                } else {
+                    active -= 1;
+                }
+            }
+            Ok(max_active)
+        };
+
+        expect_that!(max_concurrent_fetches(0).await, ok(eq(&2)));
+        expect_that!(max_concurrent_fetches(1).await, ok(eq(&1)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_videos_with_disallowed_uri_schemes_are_not_downloaded() -> googletest::Result<()>
+    {
+        let ctx = create_context().await;
+        let config = &ctx.download_ctx.config;
+        let db = &ctx.download_ctx.db;
+        let mut manifest = manifest_with_known_content()?;
+        let allowed = manifest.sections[0].content[0].clone();
+        let disallowed = &mut manifest.sections[0].content[1];
+        disallowed.uri = "https://example.com/quadratic-equations.mp4"
+            .parse()
+            .or_fail()?;
+        disallowed.checksum = allowed.checksum.clone();
+        let disallowed = disallowed.clone();
+        for video in [&allowed, &disallowed] {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content: vec![1, 2, 3, 4],
+                })
+                .await;
+        }
+
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await
+        .or_fail()?
+        .or_fail()?;
+
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed),
+            eq(1)
+        );
+        expect_that!(
+            db.find_video(allowed.id).await.or_fail()?.download_status,
+            eq(&crate::db::DownloadStatus::Downloaded(content_filepath(
+                config, allowed.id
+            )))
+        );
+        expect_that!(
+            db.find_video(disallowed.id)
+                .await
+                .or_fail()?
+                .download_status,
+            matches_pattern!(crate::db::DownloadStatus::Failed {
+                message: contains_substring("URI scheme not allowed: https"),
+                kind: eq(&crate::db::FailureKind::Unknown),
+                ..
+            })
+        );
+        expect_that!(
+            tokio::fs::try_exists(content_filepath(config, disallowed.id))
+                .await
+                .or_fail()?,
+            eq(false)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_time_to_cache_is_recorded() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let status = &ctx.download_ctx.status;
+        let manifest = manifest_with_known_content()?;
+        for (video, content) in manifest.sections[0]
+            .content
+            .iter()
+            .zip([vec![1, 2, 3, 4], vec![1, 2, 3, 5]])
+        {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+        expect_that!(status.time_to_cache(), none());
+
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await
+        .or_fail()?
+        .or_fail()?;
+        expect_that!(
+            ctx.download_ctx
+                .db
+                .stats()
+                .await
+                .or_fail()?
+                .is_fully_cached(),
+            eq(true)
+        );
+        expect_that!(status.time_to_cache(), some(lt(Duration::from_secs(10))));
+
+        // A new manifest resets the time
+        status.set_manifest_published();
+        expect_that!(status.time_to_cache(), none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_initialize_video_entries_sanitizes_names() -> googletest::Result<()> {
+        let ctx = create_context().await;
+        let db = &ctx.download_ctx.db;
+        let mut manifest = manifest_with_known_content()?;
+        manifest.sections[0].content[0].name = "x".repeat(1000);
+        manifest.sections[0].content[1].name = "Derivatives\n\u{7}<b>part 1</b>\t".to_string();
+
+        initialize_video_entries(db, &manifest).await.or_fail()?;
+
+        let overlong = db
+            .find_video(manifest.sections[0].content[0].id)
+            .await
+            .or_fail()?;
+        expect_that!(overlong.name.chars().count(), eq(MAX_VIDEO_NAME_CHARS));
+        expect_that!(overlong.name, ends_with("x\u{2026}"));
+        // Markup is escaped by the site, so it is kept
+        let control = db
+            .find_video(manifest.sections[0].content[1].id)
+            .await
+            .or_fail()?;
+        expect_that!(control.name, eq("Derivatives  <b>part 1</b>"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_first_videos_of_sections_are_downloaded_first() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.concurrent_downloads = 1;
+        ctx.download_ctx.config = Arc::new(config);
+
+        let mut manifest = manifest_with_known_content()?;
+        let first_section = manifest.sections[0].content.clone();
+        let second_section = Video {
+            name: "Riemann sum".to_string(),
+            id: uuid::Uuid::new_v4(),
+            uri: "s3://bucket/riemann-sum.mp4".parse().or_fail()?,
+            checksum: "66840dda154e8a113c31dd0ad32f7f3a366a80e8136979d8f5a101d3d29d6f72"
+                .try_into()
+                .or_fail()?,
+            file_size: 8,
+            duration_secs: None,
+            resolution: None,
+        };
+        manifest.sections.push(Section {
+            name: "Integration".to_string(),
+            description: None,
+            content: vec![second_section.clone()],
+        });
+        for (video, content) in [
+            (&first_section[0], vec![1, 2, 3, 4]),
+            (&first_section[1], vec![1, 2, 3, 5]),
+            (&second_section, (1..=8).collect()),
+        ] {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await
+        .or_fail()?
+        .or_fail()?;
+
+        let started: Vec<Uri> = ctx
+            .dummy_backend
+            .fetch_log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, started)| *started)
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        expect_that!(
+            started,
+            elements_are![
+                eq(&first_section[0].uri),
+                eq(&second_section.uri),
+                eq(&first_section[1].uri),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_backoff_is_honored_after_restart() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.retry_params.initial_backoff = Duration::from_secs(3600);
+        config.retry_params.max_backoff = Duration::from_secs(3600);
+        ctx.download_ctx.config = Arc::new(config);
+        let db = &ctx.download_ctx.db;
+        let fetches = || {
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed)
+        };
+
+        // Only the second video can be fetched, the first one backs off
+        let manifest = manifest_with_known_content()?;
+        let (failing, available) = (
+            &manifest.sections[0].content[0],
+            &manifest.sections[0].content[1],
+        );
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri: available.uri.clone(),
+                content: vec![1, 2, 3, 5],
+            })
+            .await;
+        let result = tokio::time::timeout(
+            Duration::from_secs(1),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await;
+        expect_true!(result.is_err());
+        expect_that!(fetches(), eq(2));
+        let retry_at = db.retry_at(failing.id).await.or_fail()?.or_fail()?;
+        expect_that!(
+            retry_at,
+            gt(std::time::SystemTime::now() + Duration::from_secs(3000))
+        );
+
+        // The restarted task keeps waiting instead of fetching the video again right away
+        let result = tokio::time::timeout(
+            Duration::from_secs(1),
+            download_manifest_task(ctx.download_ctx.clone(), manifest.clone()),
+        )
+        .await;
+        expect_true!(result.is_err());
+        expect_that!(fetches(), eq(2));
+        expect_that!(
+            db.find_video(failing.id).await.or_fail()?.download_status,
+            matches_pattern!(crate::db::DownloadStatus::Failed { .. })
+        );
+
+        // Resetting the video clears its retry time
+        db.reset_download(failing.id).await.or_fail()?;
+        expect_that!(db.retry_at(failing.id).await.or_fail()?, none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_downloads_pause_while_low_on_disk_space() -> googletest::Result<()> {
+        let mut ctx = create_context().await;
+        let manifest = manifest_with_known_content()?;
+        for (video, content) in manifest.sections[0]
+            .content
+            .iter()
+            .zip([vec![1, 2, 3, 4], vec![1, 2, 3, 5]])
+        {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+
+        // No filesystem has that much free space
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.min_free_bytes = u64::MAX;
+        ctx.download_ctx.config = Arc::new(config);
+        crate::downloader::check_free_space(&ctx.download_ctx);
+        expect_true!(ctx.download_ctx.status.low_disk_space());
+
+        let mut task = tokio::spawn(download_manifest_task(
+            ctx.download_ctx.clone(),
+            manifest.clone(),
+        ));
+        let paused = tokio::time::timeout(Duration::from_millis(300), &mut task).await;
+        expect_true!(paused.is_err());
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed),
+            eq(0)
+        );
+
+        // The downloads resume once there is enough free space
+        let mut config = (*ctx.download_ctx.config).clone();
+        config.min_free_bytes = 0;
+        ctx.download_ctx.config = Arc::new(config);
+        crate::downloader::check_free_space(&ctx.download_ctx);
+        expect_false!(ctx.download_ctx.status.low_disk_space());
+        tokio::time::timeout(Duration::from_secs(10), task)
+            .await
+            .or_fail()?
+            .or_fail()?
+            .or_fail()?;
+        for video in &manifest.sections[0].content {
+            expect_that!(
+                ctx.download_ctx
+                    .db
+                    .find_video(video.id)
+                    .await
+                    .or_fail()?
+                    .download_status,
+                eq(&crate::db::DownloadStatus::Downloaded(content_filepath(
+                    &ctx.download_ctx.config,
+                    video.id
+                )))
+            );
+        }
+        Ok(())
+    }
+
+    /// Resource guard whose pause reason is set by the tests.
+    #[derive(Default)]
+    struct FakeResourceGuard(std::sync::Mutex<Option<String>>);
+
+    impl crate::downloader::guard::ResourceGuard for FakeResourceGuard {
+        fn pause_reason(&self) -> Option<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_downloads_pause_while_resource_guard_reports_a_reason() -> googletest::Result<()>
+    {
+        let mut ctx = create_context().await;
+        let guard = Arc::new(FakeResourceGuard::default());
+        ctx.download_ctx.resource_guard = guard.clone();
+        let manifest = manifest_with_known_content()?;
+        for (video, content) in manifest.sections[0]
+            .content
+            .iter()
+            .zip([vec![1, 2, 3, 4], vec![1, 2, 3, 5]])
+        {
+            ctx.dummy_backend
+                .add_file(BackendFile {
+                    uri: video.uri.clone(),
+                    content,
+                })
+                .await;
+        }
+
+        *guard.0.lock().unwrap() = Some("Too hot".to_string());
+        crate::downloader::check_resource_guard(&ctx.download_ctx);
+        expect_that!(ctx.download_ctx.status.throttled(), some(eq("Too hot")));
+
+        let mut task = tokio::spawn(download_manifest_task(
+            ctx.download_ctx.clone(),
+            manifest.clone(),
+        ));
+        let paused = tokio::time::timeout(Duration::from_millis(300), &mut task).await;
+        expect_true!(paused.is_err());
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed),
+            eq(0)
+        );
+
+        // The downloads resume once the guard doesn't report a reason anymore
+        *guard.0.lock().unwrap() = None;
+        crate::downloader::check_resource_guard(&ctx.download_ctx);
+        expect_that!(ctx.download_ctx.status.throttled(), none());
+        tokio::time::timeout(Duration::from_secs(10), task)
+            .await
+            .or_fail()?
+            .or_fail()?
+            .or_fail()?;
+        expect_that!(
+            ctx.dummy_backend
+                .resource_fetches
+                .load(std::sync::atomic::Ordering::Relaxed),
+            eq(2)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_download_events_after_a_failure() -> googletest::Result<()> {
+        use crate::db::VideoEventKind;
+
+        let ctx = create_context().await;
+        let id = uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a").or_fail()?;
+        let uri: Uri = "s3://bucket/quadratic-equations.mp4".parse().or_fail()?;
+        initialize_video_entries(&ctx.download_ctx.db, &manifest_for_test().or_fail()?)
+            .await
+            .or_fail()?;
+        let job = Job {
+            backoff_time: ctx.download_ctx.config.retry_params.initial_backoff,
+            video: Video {
+                name: "Quadratic equations".to_string(),
+                id,
+                uri: uri.clone(),
+                checksum: "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a"
+                    .try_into()
+                    .or_fail()?,
+                file_size: 4,
+                duration_secs: None,
+                resolution: None,
+            },
+            duplicates: vec![],
+        };
+
+        // The first attempt fails because the backend doesn't have the file yet
+        expect_true!(ctx.download_ctx.db.claim_for_download(id).await.or_fail()?);
+        let result = download_job_task(ctx.download_ctx.clone(), job.clone()).await;
+        expect_that!(
+            result,
+            err(matches_pattern!(DownloadJobError::ShouldRetry(anything())))
+        );
+
+        ctx.dummy_backend
+            .add_file(BackendFile {
+                uri,
+                content: vec![1, 2, 3, 4],
+            })
+            .await;
+        expect_true!(ctx.download_ctx.db.claim_for_download(id).await.or_fail()?);
+        let result = download_job_task(ctx.download_ctx.clone(), job).await;
+        expect_that!(result, ok(anything()));
+
+        let events = ctx.download_ctx.db.video_events(id).await.or_fail()?;
+        expect_that!(
+            events.iter().map(|e| e.kind).collect::<Vec<_>>(),
+            elements_are![
+                eq(&VideoEventKind::Queued),
+                eq(&VideoEventKind::Started),
+                eq(&VideoEventKind::Failed),
+                eq(&VideoEventKind::Started),
+                eq(&VideoEventKind::Completed),
+            ]
+        );
+        expect_that!(
+            events[2].message,
+            contains_substring("Error fetching file with id: 5eb9e089")
+        );
+        expect_true!(events.windows(2).all(|w| w[0].at <= w[1].at));
+        Ok(())
+    }
 }
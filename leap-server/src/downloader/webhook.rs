@@ -0,0 +1,157 @@
+//! Notification of external systems once the content of a manifest is fully cached.
+
+use crate::{
+    cfg::RetryParams,
+    db::{Database, DownloadStatus},
+    manifest::ManifestFile,
+};
+
+/// Number of times the webhook is called before giving up on a notification.
+const WEBHOOK_ATTEMPTS: u32 = 5;
+
+/// The JSON body posted to the completion webhook.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CompletionPayload {
+    /// Date of the cached manifest
+    pub manifest_date: chrono::NaiveDate,
+    /// Version of the cached manifest
+    pub manifest_version: String,
+    /// Total size of the cached videos, in bytes
+    pub total_bytes: u64,
+    /// Number of videos of the manifest that failed to download
+    pub failed_count: usize,
+}
+
+/// Summarizes the download state of the videos of a manifest.
+pub async fn completion_payload(
+    db: &Database,
+    manifest: &ManifestFile,
+) -> crate::db::Result<CompletionPayload> {
+    let mut payload = CompletionPayload {
+        manifest_date: manifest.date,
+        manifest_version: manifest.version.to_string(),
+        total_bytes: 0,
+        failed_count: 0,
+    };
+    for video in manifest.sections.iter().flat_map(|s| s.content.iter()) {
+        match db.find_video(video.id).await?.download_status {
+            DownloadStatus::Downloaded(_) => payload.total_bytes += video.file_size,
+            DownloadStatus::Failed { .. } => payload.failed_count += 1,
+            DownloadStatus::Pending | DownloadStatus::InProgress(_) => {}
+        }
+    }
+    Ok(payload)
+}
+
+/// Posts the payload to the webhook, retrying with the download backoff when it fails. Failures
+/// are only logged, since caching does not depend on the notification.
+#[tracing::instrument(name = "notify_completion", skip(retry_params, payload))]
+pub async fn notify_completion(url: String, retry_params: RetryParams, payload: CompletionPayload) {
+    let client = reqwest::Client::new();
+    let mut backoff = retry_params.initial_backoff;
+    for attempt in 1..=WEBHOOK_ATTEMPTS {
+        let result = client
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => {
+                tracing::info!(
+                    "Notified the completion of manifest {}",
+                    payload.manifest_date
+                );
+                return;
+            }
+            Err(e) if attempt < WEBHOOK_ATTEMPTS => {
+                tracing::warn!(
+                    "Unable to notify the completion webhook, retrying in {backoff:?}: {e}"
+                );
+            }
+            Err(e) => {
+                tracing::error!("Unable to notify the completion webhook, giving up: {e}");
+                return;
+            }
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = backoff
+            .mul_f64(retry_params.backoff_factor)
+            .min(retry_params.max_backoff);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{sync::Mutex, time::Duration};
+
+    use actix_web::{App, HttpResponse, HttpServer, web};
+    use googletest::prelude::*;
+
+    /// Received payloads, and number of requests that are still to be rejected
+    struct MockEndpoint {
+        payloads: Mutex<Vec<CompletionPayload>>,
+        failures: Mutex<usize>,
+    }
+
+    async fn mock_handler(
+        endpoint: web::Data<MockEndpoint>,
+        payload: web::Json<CompletionPayload>,
+    ) -> HttpResponse {
+        let mut failures = endpoint.failures.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            return HttpResponse::ServiceUnavailable().finish();
+        }
+        endpoint.payloads.lock().unwrap().push(payload.into_inner());
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_notify_completion() -> googletest::Result<()> {
+        let endpoint = web::Data::new(MockEndpoint {
+            payloads: Mutex::new(vec![]),
+            failures: Mutex::new(2),
+        });
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").or_fail()?;
+        let url = format!("http://{}/hook", listener.local_addr().or_fail()?);
+        let server = {
+            let endpoint = endpoint.clone();
+            HttpServer::new(move || {
+                App::new()
+                    .app_data(endpoint.clone())
+                    .route("/hook", web::post().to(mock_handler))
+            })
+            .workers(1)
+            .listen(listener)
+            .or_fail()?
+            .run()
+        };
+        actix_web::rt::spawn(server);
+
+        let payload = CompletionPayload {
+            manifest_date: chrono::NaiveDate::from_ymd_opt(2025, 10, 10).or_fail()?,
+            manifest_version: "v2.0.0".to_string(),
+            total_bytes: 1234,
+            failed_count: 1,
+        };
+        let retry_params = RetryParams {
+            initial_backoff: Duration::from_millis(10),
+            backoff_factor: 2.0,
+            max_backoff: Duration::from_millis(100),
+            backoff_jitter_fraction: 0.0,
+        };
+
+        // The payload is delivered after the failed attempts
+        notify_completion(url, retry_params, payload.clone()).await;
+        expect_that!(
+            *endpoint.payloads.lock().unwrap(),
+            elements_are![eq(&payload)]
+        );
+        expect_that!(*endpoint.failures.lock().unwrap(), eq(0));
+        Ok(())
+    }
+}
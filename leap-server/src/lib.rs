@@ -4,13 +4,18 @@ use tokio::sync::{Mutex, mpsc};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use std::{io::stdout, net::TcpListener, path::Path, sync::Arc};
+use std::{collections::BTreeMap, io::stdout, net::TcpListener, sync::Arc};
 
-use crate::{api::ProvisionApiData, cfg::LeapConfig};
+use crate::{
+    api::ProvisionApiData,
+    cfg::{HttpServerConfig, LeapConfig},
+};
 
 pub mod build_info;
 pub mod cfg;
 pub mod db;
+pub mod logfile;
+pub mod store;
 
 mod api;
 mod downloader;
@@ -18,7 +23,30 @@ mod manifest;
 mod provision;
 mod static_files;
 
-pub async fn init_logging(logfile: Option<&Path>, debug: bool) {
+pub use manifest::lint_manifest;
+
+/// Field of the log records holding the name of the instance.
+const INSTANCE_LOG_FIELD: &str = "instance";
+
+/// Formats the log records as JSON lines, all of them tagged with the name of the instance. The
+/// name is a default field of the records rather than a field of a root span, as the spans are not
+/// inherited by the tasks spawned in other threads.
+fn formatting_layer<W>(instance_name: &str, make_writer: W) -> BunyanFormattingLayer<W>
+where
+    W: for<'a> tracing_subscriber::fmt::MakeWriter<'a> + 'static,
+{
+    let fields = std::collections::HashMap::from([(
+        INSTANCE_LOG_FIELD.to_string(),
+        serde_json::Value::from(instance_name),
+    )]);
+    BunyanFormattingLayer::with_default_fields("leap-server".into(), make_writer, fields)
+}
+
+pub async fn init_logging(
+    logfile: Option<logfile::RotatingFile>,
+    debug: bool,
+    instance_name: &str,
+) {
     let layered = tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
@@ -27,28 +55,13 @@ pub async fn init_logging(logfile: Option<&Path>, debug: bool) {
             }),
         )
         .with(JsonStorageLayer)
-        .with(BunyanFormattingLayer::new("leap-server".into(), stdout));
+        .with(formatting_layer(instance_name, stdout));
 
     if let Some(logfile) = logfile {
-        let logfile = logfile.to_owned();
-        let open_logfile = {
-            move || -> Box<dyn std::io::Write> {
-                Box::new(
-                    std::fs::File::options()
-                        .create(true)
-                        .append(true)
-                        .open(&logfile)
-                        .map_err(|e| format!("Unable to open logfile {logfile:?}: {e}"))
-                        .unwrap(),
-                )
-            }
-        };
+        let open_logfile = move || logfile.clone();
 
         layered
-            .with(BunyanFormattingLayer::new(
-                "leap-server".into(),
-                open_logfile,
-            ))
+            .with(formatting_layer(instance_name, open_logfile))
             .init();
     } else {
         layered.init();
@@ -70,7 +83,38 @@ pub async fn run_provisioning(listener: TcpListener) -> anyhow::Result<()> {
     Ok(server.await?)
 }
 
+/// Creates the HTTP server of the LEAP application, serving the API and the site.
+fn create_server(
+    listener: TcpListener,
+    config: &HttpServerConfig,
+    api_data: web::Data<api::ApiData>,
+) -> anyhow::Result<actix_web::dev::Server> {
+    config.validate()?;
+
+    let http_config = config.clone();
+    let site_files = static_files::register_site_files(config.base_path());
+    let mut server = HttpServer::new(move || {
+        App::new()
+            .app_data(api_data.clone())
+            .wrap(tracing_actix_web::TracingLogger::default())
+            .configure(api::register_handlers(&http_config))
+            .configure(site_files.clone())
+    })
+    .on_connect(api::register_connection);
+
+    if let Some(workers) = config.http_workers {
+        server = server.workers(workers);
+    }
+    if let Some(keep_alive_secs) = config.keep_alive_secs {
+        server = server.keep_alive(std::time::Duration::from_secs(keep_alive_secs));
+    }
+
+    Ok(server.listen(listener)?.run())
+}
+
 pub async fn run_app(listener: TcpListener, config: LeapConfig) -> anyhow::Result<()> {
+    config.downloader_config.validate()?;
+
     let database = Arc::new(
         db::Database::open(config.db_config.clone())
             .await
@@ -80,35 +124,83 @@ pub async fn run_app(listener: TcpListener, config: LeapConfig) -> anyhow::Resul
     database.apply_pending_migrations().await?;
 
     let (user_command_sender, user_command_receiver) = mpsc::unbounded_channel();
+    let status = Arc::new(downloader::DownloaderStatus::default());
+    let content = downloader::ContentHandles::new(
+        store::content_store(&config.http_config),
+        config.downloader_config.hashing_threads,
+    );
 
     let downloader = downloader::run_downloader(
         config.downloader_config.clone(),
         config.s3_config.clone(),
         Arc::clone(&database),
+        Arc::clone(&status),
+        content.clone(),
         user_command_receiver,
     );
 
+    // Each additional channel has its own database and downloader, so that their content is
+    // isolated from the default channel
+    let mut channels = BTreeMap::new();
+    let mut channel_downloaders = tokio::task::JoinSet::new();
+    for (name, channel) in &config.downloader_config.channels {
+        let channel_database = Arc::new(
+            db::Database::open(config.db_config.channel_config(name))
+                .await
+                .with_context(|| format!("While initializing database of channel {name}"))?,
+        );
+        channel_database.apply_pending_migrations().await?;
+
+        let channel_config = config.downloader_config.channel_config(channel);
+        let channel_status = Arc::new(downloader::DownloaderStatus::default());
+        let (command_sender, command_receiver) = mpsc::unbounded_channel();
+        // The downloads of all the channels yield to the streams of any of them, and share the
+        // threads computing the checksums
+        let channel_content = downloader::ContentHandles {
+            events: downloader::content_events_channel(),
+            ..content.clone()
+        };
+        let events = channel_content.events.clone();
+        channel_downloaders.spawn(downloader::run_downloader(
+            channel_config.clone(),
+            config.s3_config.clone(),
+            Arc::clone(&channel_database),
+            Arc::clone(&channel_status),
+            channel_content,
+            command_receiver,
+        ));
+        channels.insert(
+            name.clone(),
+            api::Channel::new(
+                channel_database,
+                channel_status,
+                command_sender,
+                events,
+                channel_config,
+            ),
+        );
+    }
+
     let api_data = web::Data::new(api::ApiData::new(
         config.clone(),
         Arc::clone(&database),
+        status,
+        content,
         user_command_sender,
+        channels,
     ));
 
-    let server = HttpServer::new(move || {
-        App::new()
-            .app_data(api_data.clone())
-            .wrap(tracing_actix_web::TracingLogger::default())
-            .configure(api::register_handlers)
-            .configure(static_files::register_site_files)
-    })
-    .listen(listener)?
-    .run();
+    let server = create_server(listener, &config.http_config, api_data)?;
 
     tokio::select! {
         downloader = downloader => {
             downloader?;
             panic!("Unexpected downloader task exit.");
         }
+        Some(downloader) = channel_downloaders.join_next() => {
+            downloader??;
+            panic!("Unexpected downloader task exit.");
+        }
         server = server => {
             server?;
             // the server can exit due to SIGINT. Using join for these 2 futures would not
@@ -118,3 +210,563 @@ pub async fn run_app(listener: TcpListener, config: LeapConfig) -> anyhow::Resul
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_create_server_with_custom_workers() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = api::test::create_api_data(tempdir.path()).await;
+        let config = HttpServerConfig {
+            http_workers: Some(2),
+            keep_alive_secs: Some(1),
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").or_fail()?;
+        let address = listener.local_addr().or_fail()?;
+        let server = create_server(listener, &config, api_data).or_fail()?;
+        let handle = server.handle();
+        let server = tokio::spawn(server);
+
+        let mut stream = tokio::net::TcpStream::connect(address).await.or_fail()?;
+        stream
+            .write_all(b"GET /api/version HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .or_fail()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.or_fail()?;
+        expect_that!(response, starts_with("HTTP/1.1 200 OK"));
+
+        handle.stop(true).await;
+        server.await.or_fail()?.or_fail()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_create_server_rejects_zero_workers() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let api_data = api::test::create_api_data(tempdir.path()).await;
+        let config = HttpServerConfig {
+            http_workers: Some(0),
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").or_fail()?;
+        expect_true!(create_server(listener, &config, api_data).is_err());
+        Ok(())
+    }
+
+    /// Configuration of the whole application, serving the content of a file backend in
+    /// `backend_path` and storing all its data in `runtime_path`.
+    fn app_config(
+        runtime_path: &std::path::Path,
+        backend_path: &std::path::Path,
+    ) -> googletest::Result<LeapConfig> {
+        let mut config = api::test::test_config(runtime_path);
+        config.downloader_config.remote_server =
+            backend_path.to_str().or_fail()?.try_into().or_fail()?;
+        config.downloader_config.update_interval = std::time::Duration::from_millis(100);
+        config.downloader_config.manifest_retry_interval = std::time::Duration::from_millis(100);
+        Ok(config)
+    }
+
+    /// Starts the whole application. Returns the address of the server.
+    fn spawn_app(config: LeapConfig) -> googletest::Result<std::net::SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0").or_fail()?;
+        let address = listener.local_addr().or_fail()?;
+        tokio::spawn(run_app(listener, config));
+        Ok(address)
+    }
+
+    /// Creates a file backend in `backend_path`, with a manifest listing a single video.
+    fn write_backend(
+        backend_path: &std::path::Path,
+        video_id: &str,
+        content: &[u8],
+        sha256: &str,
+    ) -> googletest::Result<()> {
+        std::fs::create_dir(backend_path).or_fail()?;
+        std::fs::write(backend_path.join("video.mp4"), content).or_fail()?;
+        let manifest = serde_json::json!({
+            "name": "manifest",
+            "date": "2025-10-10",
+            "version": "v2.0.0",
+            "sections": [{
+                "name": "Section",
+                "content": [{
+                    "name": "Video",
+                    "id": video_id,
+                    "uri": "s3://bucket/video.mp4",
+                    "sha256": sha256,
+                    "file_size": content.len(),
+                }],
+            }],
+        });
+        std::fs::write(
+            backend_path.join("manifest.json"),
+            serde_json::to_vec(&manifest).or_fail()?,
+        )
+        .or_fail()?;
+        Ok(())
+    }
+
+    /// Requests the content at `url` until it is served, once the downloader cached it.
+    async fn wait_for_content(
+        client: &reqwest::Client,
+        url: &str,
+    ) -> googletest::Result<actix_web::web::Bytes> {
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                if let Ok(response) = client.get(url).send().await
+                    && response.status() == reqwest::StatusCode::OK
+                {
+                    return response.bytes().await;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .or_fail()?
+        .or_fail()
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_app_serves_downloaded_content() -> googletest::Result<()> {
+        const VIDEO_ID: &str = "5eb9e089-79cf-478d-9121-9ca3e7bb1d4a";
+        const CONTENT: [u8; 4] = [1, 2, 3, 4];
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let backend_path = tempdir.path().join("backend");
+        write_backend(
+            &backend_path,
+            VIDEO_ID,
+            &CONTENT,
+            "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a",
+        )?;
+
+        let address = spawn_app(app_config(&tempdir.path().join("runtime"), &backend_path)?)?;
+
+        // The content can be requested as soon as the server is up, but it is only served once
+        // the downloader cached it
+        let client = reqwest::Client::new();
+        let content =
+            wait_for_content(&client, &format!("http://{address}/api/content/{VIDEO_ID}")).await?;
+        expect_that!(content.as_ref(), eq(&CONTENT[..]));
+
+        let response = client
+            .get(format!("http://{address}/api/content/meta/{VIDEO_ID}"))
+            .send()
+            .await
+            .or_fail()?;
+        let body: leap_api::api::content::meta::id::get::Response =
+            response.json().await.or_fail()?;
+        expect_that!(
+            body.meta,
+            some(field!(
+                &leap_api::types::LocalVideoMeta.status,
+                ref eq(&leap_api::types::VideoStatus::Downloaded)
+            ))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_app_isolates_channels() -> googletest::Result<()> {
+        const DEFAULT_VIDEO_ID: &str = "5eb9e089-79cf-478d-9121-9ca3e7bb1d4a";
+        const CHANNEL_VIDEO_ID: &str = "0ff3a4d4-4a11-4b0c-a46e-2dcbca8b2a55";
+        const ORPHAN_ID: &str = "7d0a3c5e-2b8f-4c1e-9a6d-4f5b8e2c1d3a";
+        const ADMIN_API_KEY: &str = "admin key";
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let default_backend = tempdir.path().join("backend");
+        write_backend(
+            &default_backend,
+            DEFAULT_VIDEO_ID,
+            &[1, 2, 3, 4],
+            "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a",
+        )?;
+        let channel_backend = tempdir.path().join("classroom_backend");
+        write_backend(
+            &channel_backend,
+            CHANNEL_VIDEO_ID,
+            &[1, 2, 3, 5],
+            "1571902abec0a45661de965dbe90cb0177b98c49fc58a5aabfa1edb6c678d972",
+        )?;
+
+        let mut config = app_config(&tempdir.path().join("runtime"), &default_backend)?;
+        config.downloader_config.channels.insert(
+            "classroom".to_string(),
+            cfg::ChannelConfig {
+                remote_server: channel_backend.to_str().or_fail()?.try_into().or_fail()?,
+                content_subdir: "classroom".into(),
+            },
+        );
+        config.http_config.admin_api_key = Some(secrecy::SecretString::from(ADMIN_API_KEY));
+        let content_path = config.downloader_config.content_path.clone();
+        let address = spawn_app(config)?;
+
+        let client = reqwest::Client::new();
+        let content = wait_for_content(
+            &client,
+            &format!("http://{address}/api/content/{CHANNEL_VIDEO_ID}?channel=classroom"),
+        )
+        .await?;
+        expect_that!(content.as_ref(), eq(&[1u8, 2, 3, 5][..]));
+        expect_true!(
+            content_path
+                .join("classroom")
+                .join(format!("{CHANNEL_VIDEO_ID}.mp4"))
+                .exists()
+        );
+        wait_for_content(
+            &client,
+            &format!("http://{address}/api/content/{DEFAULT_VIDEO_ID}"),
+        )
+        .await?;
+
+        // Each channel only lists the videos of its own manifest
+        for (query, video_id) in [
+            ("", DEFAULT_VIDEO_ID),
+            ("?channel=classroom", CHANNEL_VIDEO_ID),
+        ] {
+            let body: leap_api::api::content::meta::get::Response = client
+                .get(format!("http://{address}/api/content/meta{query}"))
+                .send()
+                .await
+                .or_fail()?
+                .json()
+                .await
+                .or_fail()?;
+            expect_that!(
+                body.videos,
+                elements_are![field!(
+                    &leap_api::types::GroupedSection.content,
+                    ref elements_are![field!(&leap_api::types::LocalVideoMeta.id, ref eq(video_id))]
+                )]
+            );
+        }
+
+        // The rest of the content endpoints are scoped to the channel as well
+        let orphan_path = content_path
+            .join("classroom")
+            .join(format!("{ORPHAN_ID}.mp4"));
+        std::fs::write(&orphan_path, [1, 2]).or_fail()?;
+        for (query, video_id, orphans) in [
+            ("", DEFAULT_VIDEO_ID, vec![]),
+            (
+                "?channel=classroom",
+                CHANNEL_VIDEO_ID,
+                vec![orphan_path.to_string_lossy().into_owned()],
+            ),
+        ] {
+            let summary: leap_api::api::content::summary::get::Response = client
+                .get(format!("http://{address}/api/content/summary{query}"))
+                .send()
+                .await
+                .or_fail()?
+                .json()
+                .await
+                .or_fail()?;
+            expect_that!(summary.downloaded, eq(1));
+
+            let sections: leap_api::api::content::sections::get::Response = client
+                .get(format!("http://{address}/api/content/sections{query}"))
+                .send()
+                .await
+                .or_fail()?
+                .json()
+                .await
+                .or_fail()?;
+            expect_that!(
+                sections.sections,
+                elements_are![field!(&leap_api::types::SectionStatus.ready, eq(true))]
+            );
+
+            let section: leap_api::api::content::sections::name::status::get::Response = client
+                .get(format!(
+                    "http://{address}/api/content/sections/Section/status{query}"
+                ))
+                .send()
+                .await
+                .or_fail()?
+                .json()
+                .await
+                .or_fail()?;
+            expect_that!(section.first_playable_id, some(eq(video_id)));
+
+            let export = client
+                .get(format!("http://{address}/api/content/export{query}"))
+                .send()
+                .await
+                .or_fail()?
+                .text()
+                .await
+                .or_fail()?;
+            let line: leap_api::api::content::export::get::Line =
+                serde_json::from_str(export.trim_end()).or_fail()?;
+            expect_that!(line.id, eq(video_id));
+
+            let response = client
+                .post(format!(
+                    "http://{address}/api/content/{video_id}/view{query}"
+                ))
+                .send()
+                .await
+                .or_fail()?;
+            expect_that!(response.status(), eq(reqwest::StatusCode::OK));
+
+            let response = client
+                .get(format!(
+                    "http://{address}/api/content/{video_id}/progress/stream{query}"
+                ))
+                .send()
+                .await
+                .or_fail()?;
+            expect_that!(response.status(), eq(reqwest::StatusCode::OK));
+
+            let response = client
+                .put(format!(
+                    "http://{address}/api/content/local/pin?id={video_id}&pinned=true{}",
+                    query.replace('?', "&")
+                ))
+                .send()
+                .await
+                .or_fail()?;
+            expect_that!(response.status(), eq(reqwest::StatusCode::NO_CONTENT));
+
+            let orphans_response: leap_api::api::admin::orphans::get::Response = client
+                .get(format!("http://{address}/api/admin/orphans{query}"))
+                .bearer_auth(ADMIN_API_KEY)
+                .send()
+                .await
+                .or_fail()?
+                .json()
+                .await
+                .or_fail()?;
+            expect_that!(
+                orphans_response
+                    .files
+                    .into_iter()
+                    .map(|file| file.path)
+                    .collect::<Vec<_>>(),
+                eq(&orphans)
+            );
+
+            let reconciliation: leap_api::api::admin::reconcile::post::Response = client
+                .post(format!("http://{address}/api/admin/reconcile{query}"))
+                .bearer_auth(ADMIN_API_KEY)
+                .send()
+                .await
+                .or_fail()?
+                .json()
+                .await
+                .or_fail()?;
+            expect_that!(
+                reconciliation,
+                eq(&leap_api::api::admin::reconcile::post::Response {
+                    inserted: 0,
+                    deleted: 0,
+                })
+            );
+        }
+
+        // The videos of a channel are not served by the other channels
+        for (method, url) in [
+            (
+                reqwest::Method::GET,
+                format!("http://{address}/api/content/{CHANNEL_VIDEO_ID}"),
+            ),
+            (
+                reqwest::Method::GET,
+                format!("http://{address}/api/content/{DEFAULT_VIDEO_ID}?channel=classroom"),
+            ),
+            (
+                reqwest::Method::POST,
+                format!("http://{address}/api/content/{CHANNEL_VIDEO_ID}/view"),
+            ),
+            (
+                reqwest::Method::GET,
+                format!("http://{address}/api/content/{CHANNEL_VIDEO_ID}/progress/stream"),
+            ),
+            (
+                reqwest::Method::GET,
+                format!("http://{address}/api/content/{CHANNEL_VIDEO_ID}/events"),
+            ),
+            (
+                reqwest::Method::PUT,
+                format!("http://{address}/api/content/local/pin?id={CHANNEL_VIDEO_ID}&pinned=true"),
+            ),
+            (
+                reqwest::Method::POST,
+                format!("http://{address}/api/content/local/redownload?id={CHANNEL_VIDEO_ID}"),
+            ),
+            (
+                reqwest::Method::POST,
+                format!(
+                    "http://{address}/api/content/local/redownload?id={DEFAULT_VIDEO_ID}&channel=classroom"
+                ),
+            ),
+        ] {
+            let response = client.request(method, &url).send().await.or_fail()?;
+            expect_that!(response.status(), eq(reqwest::StatusCode::NOT_FOUND));
+        }
+
+        // Unknown channels are rejected by all the content endpoints
+        for endpoint in [
+            "content/meta",
+            "content/summary",
+            "content/sections",
+            "content/sections/Section/status",
+            "content/events",
+            "content/export",
+            "admin/orphans",
+        ] {
+            let response = client
+                .get(format!("http://{address}/api/{endpoint}?channel=unknown"))
+                .bearer_auth(ADMIN_API_KEY)
+                .send()
+                .await
+                .or_fail()?;
+            expect_that!(response.status(), eq(reqwest::StatusCode::NOT_FOUND));
+        }
+        for endpoint in ["admin/reconcile?", "admin/import?path=/&"] {
+            let response = client
+                .post(format!("http://{address}/api/{endpoint}channel=unknown"))
+                .bearer_auth(ADMIN_API_KEY)
+                .send()
+                .await
+                .or_fail()?;
+            expect_that!(response.status(), eq(reqwest::StatusCode::NOT_FOUND));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_app_mirrors_another_app() -> googletest::Result<()> {
+        const VIDEO_ID: &str = "5eb9e089-79cf-478d-9121-9ca3e7bb1d4a";
+        const CONTENT: [u8; 4] = [1, 2, 3, 4];
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let backend_path = tempdir.path().join("backend");
+        write_backend(
+            &backend_path,
+            VIDEO_ID,
+            &CONTENT,
+            "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a",
+        )?;
+        let upstream = spawn_app(app_config(&tempdir.path().join("upstream"), &backend_path)?)?;
+
+        // The downstream app downloads the content exported by the upstream app
+        let mut config = app_config(&tempdir.path().join("downstream"), &backend_path)?;
+        config.downloader_config.remote_server = format!("http://{upstream}/api/manifest/export")
+            .try_into()
+            .or_fail()?;
+        let downstream = spawn_app(config)?;
+
+        let content = wait_for_content(
+            &reqwest::Client::new(),
+            &format!("http://{downstream}/api/content/{VIDEO_ID}"),
+        )
+        .await?;
+        expect_that!(content.as_ref(), eq(&CONTENT[..]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_app_closes_stalled_content_connections() -> googletest::Result<()> {
+        use sha2::Digest;
+
+        const VIDEO_ID: &str = "5eb9e089-79cf-478d-9121-9ca3e7bb1d4a";
+        // Larger than the socket buffers, so that the server waits for the client to read
+        let content = vec![7u8; 32 * 1024 * 1024];
+        let sha256 =
+            manifest::Sha256::try_from(sha2::Sha256::digest(&content).as_slice()).or_fail()?;
+
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let backend_path = tempdir.path().join("backend");
+        write_backend(&backend_path, VIDEO_ID, &content, &sha256.to_string())?;
+        let mut config = app_config(&tempdir.path().join("runtime"), &backend_path)?;
+        config.http_config.content_idle_timeout_secs = Some(1);
+        let address = spawn_app(config)?;
+
+        // Clients reading the content are not affected
+        let client = reqwest::Client::new();
+        let served =
+            wait_for_content(&client, &format!("http://{address}/api/content/{VIDEO_ID}")).await?;
+        expect_that!(served.len(), eq(content.len()));
+
+        let mut stream = tokio::net::TcpStream::connect(address).await.or_fail()?;
+        stream
+            .write_all(
+                format!(
+                    "GET /api/content/{VIDEO_ID} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+                )
+                .as_bytes(),
+            )
+            .await
+            .or_fail()?;
+        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+        // The connection was closed before the whole content was sent
+        let mut response = vec![];
+        tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            stream.read_to_end(&mut response),
+        )
+        .await
+        .or_fail()?
+        .ok();
+        expect_true!(response.starts_with(b"HTTP/1.1 200 OK"));
+        expect_that!(response.len(), lt(content.len()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_logs_are_tagged_with_the_instance_name() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let path = tempdir.path().join("leap.log");
+        let logfile = logfile::RotatingFile::open(&path, u64::MAX, 0).or_fail()?;
+        let subscriber = tracing_subscriber::registry()
+            .with(JsonStorageLayer)
+            .with(formatting_layer("classroom", move || logfile.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("request", id = 1).in_scope(|| tracing::info!("Serving content"));
+        });
+
+        let log = std::fs::read_to_string(&path).or_fail()?;
+        let records = log
+            .lines()
+            .map(serde_json::from_str::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()
+            .or_fail()?;
+        // The events of a span are logged as `[SPAN - EVENT] message`
+        expect_that!(
+            records.iter().find(|record| {
+                record["msg"]
+                    .as_str()
+                    .is_some_and(|msg| msg.ends_with("Serving content"))
+            }),
+            some(anything())
+        );
+        // Every record is tagged, including the ones of the spans
+        expect_that!(
+            records,
+            each(predicate(|record: &serde_json::Value| {
+                record[INSTANCE_LOG_FIELD] == "classroom"
+            }))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A log file that is rotated once it grows past a maximum size. The active file is always the one
+/// at the given path, and rotated files are renamed to `<path>.1`, `<path>.2`, ..., with `<path>.1`
+/// being the most recent one. Only `max_files` rotated files are kept.
+///
+/// Cloning a `RotatingFile` returns a handle to the same underlying file.
+#[derive(Clone)]
+pub struct RotatingFile {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let file = Self::open_file(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                path: path.to_path_buf(),
+                file,
+                size,
+                max_bytes,
+                max_files,
+            })),
+        })
+    }
+
+    fn open_file(path: &Path) -> std::io::Result<File> {
+        File::options().create(true).append(true).open(path)
+    }
+}
+
+impl Inner {
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            // The oldest file is overwritten by the next one
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = RotatingFile::open_file(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut inner = self.inner.lock().expect("Poisoned log file lock");
+        // Records are never split between files, so a file may only exceed the maximum size when
+        // a single record is larger than it.
+        if inner.size > 0 && inner.size + buf.len() as u64 > inner.max_bytes {
+            inner.rotate()?;
+        }
+        let written = inner.file.write(buf)?;
+        inner.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner
+            .lock()
+            .expect("Poisoned log file lock")
+            .file
+            .flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn test_rotation() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let path = tempdir.path().join("leap.log");
+        let mut file = RotatingFile::open(&path, 10, 2).or_fail()?;
+
+        for record in ["aaaaaa\n", "bbbbbb\n", "cccccc\n", "dddddd\n"] {
+            file.write_all(record.as_bytes()).or_fail()?;
+        }
+
+        expect_that!(std::fs::read_to_string(&path).or_fail()?, eq("dddddd\n"));
+        expect_that!(
+            std::fs::read_to_string(tempdir.path().join("leap.log.1")).or_fail()?,
+            eq("cccccc\n")
+        );
+        expect_that!(
+            std::fs::read_to_string(tempdir.path().join("leap.log.2")).or_fail()?,
+            eq("bbbbbb\n")
+        );
+        expect_false!(tempdir.path().join("leap.log.3").exists());
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_rotation_takes_existing_size_into_account() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let path = tempdir.path().join("leap.log");
+        std::fs::write(&path, "aaaaaa\n").or_fail()?;
+
+        let mut file = RotatingFile::open(&path, 10, 1).or_fail()?;
+        file.write_all(b"bbbbbb\n").or_fail()?;
+
+        expect_that!(std::fs::read_to_string(&path).or_fail()?, eq("bbbbbb\n"));
+        expect_that!(
+            std::fs::read_to_string(tempdir.path().join("leap.log.1")).or_fail()?,
+            eq("aaaaaa\n")
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use std::{net::TcpListener, path::PathBuf};
+use std::{
+    net::TcpListener,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
 
@@ -27,6 +30,22 @@ struct Args {
     /// Displays version information.
     #[arg(short, long)]
     version: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Checks a manifest file as the LEAPs do when fetching it, e.g.: before uploading it.
+    ValidateManifest {
+        /// Path to the manifest file
+        path: PathBuf,
+
+        /// Reject the unknown keys too, as the LEAPs configured with `strict_manifest` do.
+        #[arg(long)]
+        strict: bool,
+    },
 }
 
 fn default_config_path() -> PathBuf {
@@ -56,6 +75,25 @@ fn print_version_info() {
     println!("\tFeatures: {}", info.features);
 }
 
+/// Prints the errors of the manifest at `path`, or a summary of it if it is valid. Fails if the
+/// manifest is not valid.
+fn validate_manifest(path: &Path, strict: bool) -> anyhow::Result<()> {
+    let data = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Unable to read the manifest {path:?}: {e}"))?;
+    match leap_server::lint_manifest(&data, strict) {
+        Ok(summary) => {
+            println!("{path:?} is a valid manifest: {summary}");
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{error}");
+            }
+            anyhow::bail!("{path:?} is not a valid manifest")
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 enum AppError {
     #[error("The LEAP configuration could not be loaded: {0}")]
@@ -68,7 +106,16 @@ async fn start_leap_server(args: &Args) -> Result<(), AppError> {
     let config =
         leap_server::cfg::get_config(args.config.as_ref().unwrap_or(&default_config_path()))
             .map_err(AppError::InvalidConfiguration)?;
-    leap_server::init_logging(Some(&config.db_config.logfile()), config.debug).await;
+    let logfile = config.db_config.logfile();
+    let logfile = leap_server::logfile::RotatingFile::open(
+        &logfile,
+        config.log_max_bytes,
+        config.log_max_files,
+    )
+    .map_err(|e| {
+        AppError::RuntimeError(anyhow::anyhow!("Unable to open logfile {logfile:?}: {e}"))
+    })?;
+    leap_server::init_logging(Some(logfile), config.debug, &config.instance_name()).await;
 
     let listener = TcpListener::bind(format!("{}:{}", args.address, args.port))
         .map_err(|e| AppError::RuntimeError(e.into()))?;
@@ -86,7 +133,7 @@ async fn start_leap_server(args: &Args) -> Result<(), AppError> {
 }
 
 async fn start_leap_provisioning(args: &Args) -> anyhow::Result<()> {
-    leap_server::init_logging(None, false).await;
+    leap_server::init_logging(None, false, &leap_server::cfg::default_instance_name()).await;
     let listener = TcpListener::bind(format!("{}:{}", args.address, args.port))?;
     leap_server::run_provisioning(listener).await?;
     Ok(())
@@ -99,6 +146,9 @@ async fn main() -> anyhow::Result<()> {
         print_version_info();
         return Ok(());
     }
+    if let Some(Command::ValidateManifest { path, strict }) = &args.command {
+        return validate_manifest(path, *strict);
+    }
 
     if args.provision {
         start_leap_provisioning(&args).await?;
@@ -1,80 +1,216 @@
 use std::{fmt::Display, ops::Deref};
 
+/// Major versions of the manifest understood by this server. Manifests with other major versions
+/// are still parsed on a best-effort basis, but their incompatible changes may go unnoticed.
+pub const SUPPORTED_MAJOR_VERSIONS: std::ops::RangeInclusive<u32> = 1..=2;
+
 /// Version data type made of major, minor and revision numbers.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Version {
     pub major: u32,
     pub minor: u32,
     pub revision: u32,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Sha256(String);
+impl Version {
+    /// Returns true if the major version is understood by this server.
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_MAJOR_VERSIONS.contains(&self.major)
+    }
 
-impl Display for Sha256 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+    /// Returns the version of this server, which manifests may require with `min_vds_version`.
+    pub fn of_server() -> Self {
+        let number = |value: &str| value.parse().expect("Cargo versions are numeric");
+        Self {
+            major: number(env!("CARGO_PKG_VERSION_MAJOR")),
+            minor: number(env!("CARGO_PKG_VERSION_MINOR")),
+            revision: number(env!("CARGO_PKG_VERSION_PATCH")),
+        }
     }
 }
 
-impl Sha256 {
-    pub fn as_bytes(&self) -> [u8; 32] {
-        (0..32)
-            .map(|byte_idx| {
-                u8::from_str_radix(&self.0[2 * byte_idx..2 * byte_idx + 2], 16)
-                    .expect("Sha256 should be a valid hex string of 64 chars")
-            })
-            .collect::<Vec<u8>>()
-            .try_into()
-            .expect("Sha256 can only be constructed with 64 characters")
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.revision)
     }
 }
 
-impl TryFrom<&[u8]> for Sha256 {
-    type Error = String;
+/// Defines a hash type stored as a lowercase hex string of the given number of bytes.
+macro_rules! hex_hash {
+    ($(#[$meta:meta])* $name:ident, $bytes:literal, $display_name:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, PartialEq, Eq, Clone)]
+        pub struct $name(String);
 
-    fn try_from(v: &[u8]) -> Result<Self, String> {
-        if v.len() != 32 {
-            return Err(format!(
-                "Sha256 can only be constructed from a 32-byte slice. Got {} bytes",
-                v.len()
-            ));
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
         }
 
-        Ok(Sha256(
-            v.iter()
-                .flat_map(|byte| {
-                    let msb = char::from_digit((byte >> 4) as u32, 16).unwrap();
-                    let lsb = char::from_digit((byte & 0x0f) as u32, 16).unwrap();
-                    std::iter::once(msb).chain(std::iter::once(lsb))
-                })
-                .collect(),
-        ))
+        impl $name {
+            pub fn as_bytes(&self) -> [u8; $bytes] {
+                (0..$bytes)
+                    .map(|byte_idx| {
+                        u8::from_str_radix(&self.0[2 * byte_idx..2 * byte_idx + 2], 16).expect(
+                            concat!($display_name, " should be a valid hex string"),
+                        )
+                    })
+                    .collect::<Vec<u8>>()
+                    .try_into()
+                    .expect(concat!(
+                        $display_name,
+                        " can only be constructed from ",
+                        $bytes,
+                        " bytes"
+                    ))
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = String;
+
+            fn try_from(v: &[u8]) -> Result<Self, String> {
+                if v.len() != $bytes {
+                    return Err(format!(
+                        concat!(
+                            $display_name,
+                            " can only be constructed from a ",
+                            $bytes,
+                            "-byte slice. Got {} bytes"
+                        ),
+                        v.len()
+                    ));
+                }
+
+                Ok($name(
+                    v.iter()
+                        .flat_map(|byte| {
+                            let msb = char::from_digit((byte >> 4) as u32, 16).unwrap();
+                            let lsb = char::from_digit((byte & 0x0f) as u32, 16).unwrap();
+                            std::iter::once(msb).chain(std::iter::once(lsb))
+                        })
+                        .collect(),
+                ))
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = String;
+
+            fn try_from(v: &str) -> Result<Self, String> {
+                let is_lowercase_hex = |c: char| c.is_ascii_digit() || ('a'..='f').contains(&c);
+                if v.len() != $bytes * 2 || !v.chars().all(is_lowercase_hex) {
+                    return Err(format!(concat!("\"{}\" is not a valid ", $display_name), v));
+                };
+
+                Ok(Self(v.to_string()))
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let v = String::deserialize(deserializer)?;
+                v.as_str().try_into().map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_str(self)
+            }
+        }
+    };
+}
+
+hex_hash!(
+    /// SHA-256 hash, as a hex string of 64 characters.
+    Sha256,
+    32,
+    "SHA-256"
+);
+hex_hash!(
+    /// SHA-512 hash, as a hex string of 128 characters.
+    Sha512,
+    64,
+    "SHA-512"
+);
+hex_hash!(
+    /// BLAKE3 hash, as a hex string of 64 characters.
+    Blake3,
+    32,
+    "BLAKE3"
+);
+
+/// Checksum of a video file, tagged by the algorithm used to compute it. It is serialized as
+/// `{ "algo": "sha256", "value": "<hex>" }`. For backwards compatibility, a bare hex string is
+/// deserialized as a SHA-256 checksum.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+#[serde(tag = "algo", content = "value", rename_all = "lowercase")]
+pub enum Checksum {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Blake3),
+}
+
+impl Display for Checksum {
+    /// Displays the hex value of the checksum, without the algorithm.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Checksum::Sha256(v) => write!(f, "{v}"),
+            Checksum::Sha512(v) => write!(f, "{v}"),
+            Checksum::Blake3(v) => write!(f, "{v}"),
+        }
     }
 }
 
-impl TryFrom<&str> for Sha256 {
+/// Interprets the string as a SHA-256 checksum, like bare hex strings in the manifest.
+impl TryFrom<&str> for Checksum {
     type Error = String;
 
     fn try_from(v: &str) -> Result<Self, String> {
-        use regex::Regex;
-        use std::sync::LazyLock;
-        static SHA_REGEX: LazyLock<Regex> = std::sync::LazyLock::new(|| {
-            regex::Regex::new("^[0-9a-f]{64}$").expect("Invalid sha256 regex")
-        });
-
-        if !SHA_REGEX.is_match(v) {
-            return Err(format!("\"{v}\" is not a valid SHA-256"));
-        };
-
-        Ok(Self(v.to_string()))
+        Ok(Checksum::Sha256(v.try_into()?))
     }
 }
 
-impl Deref for Sha256 {
-    type Target = str;
-    fn deref(&self) -> &Self::Target {
-        &self.0
+impl<'de> serde::Deserialize<'de> for Checksum {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(tag = "algo", content = "value", rename_all = "lowercase")]
+        enum Tagged {
+            Sha256(Sha256),
+            Sha512(Sha512),
+            Blake3(Blake3),
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bare(Sha256),
+            Tagged(Tagged),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Bare(v) | Repr::Tagged(Tagged::Sha256(v)) => Checksum::Sha256(v),
+            Repr::Tagged(Tagged::Sha512(v)) => Checksum::Sha512(v),
+            Repr::Tagged(Tagged::Blake3(v)) => Checksum::Blake3(v),
+        })
     }
 }
 
@@ -92,11 +228,21 @@ pub struct Video {
     #[serde(serialize_with = "serialize_uri")]
     pub uri: http::Uri,
 
-    /// SHA-256 of the video file
-    pub sha256: Sha256,
+    /// Checksum of the video file
+    #[serde(alias = "sha256")]
+    pub checksum: Checksum,
 
     /// File size in bytes
     pub file_size: u64,
+
+    /// Duration of the video in whole seconds. Probed after the download if missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+
+    /// Resolution of the video as `<width>x<height>` (e.g.: `1920x1080`). Probed after the
+    /// download if missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
 }
 
 /// A section of content that groups together a number of videos
@@ -105,26 +251,267 @@ pub struct Section {
     /// Name of the section
     pub name: String,
 
+    /// Optional description of the section, shown alongside its name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
     /// Content within the section. Ordered as displayed
     pub content: Vec<Video>,
 }
 
+/// Keys of the objects of the manifest, including aliases. Other keys are rejected in strict mode.
+const MANIFEST_KEYS: &[&str] = &[
+    "name",
+    "date",
+    "published_at",
+    "adopt_after",
+    "min_vds_version",
+    "version",
+    "sections",
+];
+const SECTION_KEYS: &[&str] = &["name", "description", "content"];
+const VIDEO_KEYS: &[&str] = &[
+    "name",
+    "id",
+    "uri",
+    "checksum",
+    "sha256",
+    "file_size",
+    "duration_secs",
+    "resolution",
+];
+const CHECKSUM_KEYS: &[&str] = &["algo", "value"];
+
+/// Parses a manifest received from the upstream, checking that it has the expected format.
+/// Unknown keys are ignored, so that manifests with newer fields can be used, unless `strict` is
+/// set. Strict parsing rejects them, so that misspelled keys are not mistaken for missing ones.
+pub fn validate_manifest_bytes(data: &[u8], strict: bool) -> serde_json::Result<ManifestFile> {
+    let manifest = serde_json::from_slice(data)?;
+    if strict {
+        let value: serde_json::Value = serde_json::from_slice(data)?;
+        if let Some(key) = unknown_manifest_keys(&value).first() {
+            return Err(serde::de::Error::custom(format!(
+                "unknown manifest field `{key}`"
+            )));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Returns the paths of the keys of a manifest that are not part of its format, such as
+/// `sections[0].content[1].sha26`.
+fn unknown_manifest_keys(manifest: &serde_json::Value) -> Vec<String> {
+    fn unknown_keys(
+        value: &serde_json::Value,
+        known: &[&str],
+        path: &str,
+        unknown: &mut Vec<String>,
+    ) {
+        let Some(object) = value.as_object() else {
+            return;
+        };
+        for key in object.keys().filter(|key| !known.contains(&key.as_str())) {
+            unknown.push(if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            });
+        }
+    }
+
+    let mut unknown = vec![];
+    unknown_keys(manifest, MANIFEST_KEYS, "", &mut unknown);
+    let sections = manifest["sections"].as_array().into_iter().flatten();
+    for (section_idx, section) in sections.enumerate() {
+        let path = format!("sections[{section_idx}]");
+        unknown_keys(section, SECTION_KEYS, &path, &mut unknown);
+        let videos = section["content"].as_array().into_iter().flatten();
+        for (video_idx, video) in videos.enumerate() {
+            let path = format!("{path}.content[{video_idx}]");
+            unknown_keys(video, VIDEO_KEYS, &path, &mut unknown);
+            for key in ["checksum", "sha256"] {
+                unknown_keys(
+                    &video[key],
+                    CHECKSUM_KEYS,
+                    &format!("{path}.{key}"),
+                    &mut unknown,
+                );
+            }
+        }
+    }
+    unknown
+}
+
+/// Checks a manifest file as the servers do when fetching it, so that publishers can check it
+/// before uploading it. Returns a summary of the manifest if it is valid, or the description of
+/// its errors otherwise, with the lines of the manifest where they were found.
+pub fn lint_manifest(data: &[u8], strict: bool) -> Result<String, Vec<String>> {
+    let manifest = validate_manifest_bytes(data, strict).map_err(|err| {
+        // The validation stops at the first unknown key, but all of them are worth fixing at once
+        if strict && let Ok(value) = serde_json::from_slice::<serde_json::Value>(data) {
+            let unknown = unknown_manifest_keys(&value);
+            if !unknown.is_empty() {
+                return unknown
+                    .iter()
+                    .map(|key| format!("unknown manifest field `{key}`"))
+                    .collect();
+            }
+        }
+        vec![with_line_context(data, &err)]
+    })?;
+
+    let mut videos = std::collections::HashMap::new();
+    for video in manifest.sections.iter().flat_map(|s| &s.content) {
+        // Videos listed in several sections are only downloaded once
+        videos.insert(video.id, video.file_size);
+    }
+    let mut summary = format!(
+        "{} sections, {} videos, {} bytes in total",
+        manifest.sections.len(),
+        videos.len(),
+        videos.values().sum::<u64>()
+    );
+    if !manifest.version.is_supported() {
+        summary.push_str(&format!(
+            ". The manifest version {} is not supported by this server, which supports major \
+            versions {SUPPORTED_MAJOR_VERSIONS:?}",
+            manifest.version
+        ));
+    }
+    Ok(summary)
+}
+
+/// Describes a parsing error of a manifest, followed by the line of the manifest where it was
+/// found, if known.
+fn with_line_context(data: &[u8], err: &serde_json::Error) -> String {
+    let text = String::from_utf8_lossy(data);
+    let Some(line) = err
+        .line()
+        .checked_sub(1)
+        .and_then(|idx| text.lines().nth(idx))
+    else {
+        return err.to_string();
+    };
+    format!(
+        "{err}\n{:>5} | {line}\n      | {}^",
+        err.line(),
+        " ".repeat(err.column().saturating_sub(1))
+    )
+}
+
 /// Describes the set of videos and sections to be shown in the LEAP.
 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone)]
 pub struct ManifestFile {
     /// Name of the distribution list
     pub name: String,
 
-    /// Date in which this manifest was released
+    /// Date in which this manifest was released. It has no timezone, and is always interpreted as
+    /// a date in UTC.
     pub date: chrono::NaiveDate,
 
-    /// Version of the manifest. At the moment only version 1.0.0 is supported
+    /// Exact time at which this manifest was released, as an RFC 3339 timestamp. Its offset is
+    /// taken into account, so that timestamps generated in different timezones can be compared.
+    /// Optional, allows distinguishing manifests released on the same date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Time before which the LEAPs must not adopt this manifest, as an RFC 3339 timestamp. The
+    /// previous manifest keeps being served and no content of this one is downloaded until then,
+    /// so that publishers can still revert it. Adopted as soon as it is fetched if missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adopt_after: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Minimum version of the server required to serve this manifest, e.g.: because its content
+    /// relies on a newer feature. Older servers don't adopt it, and keep serving the previous
+    /// manifest instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_vds_version: Option<Version>,
+
+    /// Version of the manifest. See [`SUPPORTED_MAJOR_VERSIONS`] for the supported versions
     pub version: Version,
 
     /// Sections in the manifest. Ordered as displayed
     pub sections: Vec<Section>,
 }
 
+impl ManifestFile {
+    /// Returns the UTC date in which this manifest was released. The date of the publication
+    /// timestamp takes precedence over `date`, which may have been generated in another timezone.
+    pub fn release_date(&self) -> chrono::NaiveDate {
+        self.published_at
+            .map(|published_at| published_at.date_naive())
+            .unwrap_or(self.date)
+    }
+
+    /// Returns true if this manifest was released after `other`. Uses the publication timestamps
+    /// when both manifests have one, and falls back to comparing the UTC release date and then the
+    /// version otherwise. The decision never depends on the timezone of the server.
+    pub fn is_newer_than(&self, other: &ManifestFile) -> bool {
+        match (self.published_at, other.published_at) {
+            (Some(this), Some(other)) => this > other,
+            _ => (self.release_date(), &self.version) > (other.release_date(), &other.version),
+        }
+    }
+
+    /// Returns true if a server of the given version can serve this manifest.
+    pub fn is_servable_by(&self, server: &Version) -> bool {
+        self.min_vds_version
+            .as_ref()
+            .is_none_or(|min_version| min_version <= server)
+    }
+
+    /// Returns true if the manifest can be adopted at the given time.
+    pub fn is_adoptable_at(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.adopt_after
+            .is_none_or(|adopt_after| adopt_after <= now)
+    }
+
+    /// Returns a copy of the manifest whose videos are downloaded from the content endpoint of the
+    /// LEAP at `base_url` (e.g.: `http://leap.local:8080`), so that other LEAPs can mirror it.
+    pub fn with_content_base_url(
+        &self,
+        base_url: &str,
+    ) -> Result<ManifestFile, http::uri::InvalidUri> {
+        let base_url = base_url.trim_end_matches('/');
+        let mut manifest = self.clone();
+        for video in manifest
+            .sections
+            .iter_mut()
+            .flat_map(|s| s.content.iter_mut())
+        {
+            video.uri = format!("{base_url}/api/content/{}", video.id).parse()?;
+        }
+        Ok(manifest)
+    }
+
+    /// Returns the IDs of the videos listed in both manifests whose content differs, i.e.: whose
+    /// checksum changed.
+    pub fn changed_videos(&self, other: &ManifestFile) -> Vec<uuid::Uuid> {
+        let other_videos: std::collections::HashMap<_, _> = other
+            .sections
+            .iter()
+            .flat_map(|s| s.content.iter())
+            .map(|v| (v.id, &v.checksum))
+            .collect();
+
+        let mut changed: Vec<_> = self
+            .sections
+            .iter()
+            .flat_map(|s| s.content.iter())
+            .filter(|v| {
+                other_videos
+                    .get(&v.id)
+                    .is_some_and(|checksum| **checksum != v.checksum)
+            })
+            .map(|v| v.id)
+            .collect();
+        // Videos may be listed in several sections
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
+
 fn serialize_uri<S>(uri: &http::Uri, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -154,25 +541,7 @@ impl serde::Serialize for Version {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&format!("v{}.{}.{}", self.major, self.minor, self.revision))
-    }
-}
-
-impl<'de> serde::Deserialize<'de> for Sha256 {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        deserializer.deserialize_str(sha256::Visitor {})
-    }
-}
-
-impl serde::Serialize for Sha256 {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(self)
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -243,25 +612,6 @@ mod version {
     }
 }
 
-mod sha256 {
-    pub struct Visitor {}
-
-    impl<'de> serde::de::Visitor<'de> for Visitor {
-        type Value = super::Sha256;
-
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str("A Sha256 hash with 64 alfanumeric characters.")
-        }
-
-        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-        where
-            E: serde::de::Error,
-        {
-            v.try_into().map_err(|e| E::custom(e))
-        }
-    }
-}
-
 #[cfg(test)]
 pub mod test {
     use std::str::FromStr;
@@ -364,6 +714,70 @@ pub mod test {
         Ok(())
     }
 
+    #[googletest::gtest]
+    fn deserialize_checksum() -> googletest::Result<()> {
+        let sha256 = "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327";
+        let sha512 = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+            2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+
+        let checksum = serde_json::from_value::<Checksum>(serde_json::json!(sha256)).or_fail()?;
+        expect_that!(checksum, eq(&Checksum::Sha256(Sha256(sha256.to_string()))));
+
+        let checksum = serde_json::from_value::<Checksum>(
+            serde_json::json!({ "algo": "sha256", "value": sha256 }),
+        )
+        .or_fail()?;
+        expect_that!(checksum, eq(&Checksum::Sha256(Sha256(sha256.to_string()))));
+
+        let checksum = serde_json::from_value::<Checksum>(
+            serde_json::json!({ "algo": "sha512", "value": sha512 }),
+        )
+        .or_fail()?;
+        expect_that!(checksum, eq(&Checksum::Sha512(Sha512(sha512.to_string()))));
+
+        let checksum = serde_json::from_value::<Checksum>(
+            serde_json::json!({ "algo": "blake3", "value": sha256 }),
+        )
+        .or_fail()?;
+        expect_that!(checksum, eq(&Checksum::Blake3(Blake3(sha256.to_string()))));
+
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn deserialize_checksum_incorrect_format() -> googletest::Result<()> {
+        let sha256 = "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327";
+        let testcases = [
+            // Unknown algorithm
+            serde_json::json!({ "algo": "md5", "value": "0b88b2dec2be5e2ef74022ef6a802323" }),
+            // Value of the wrong length for the algorithm
+            serde_json::json!({ "algo": "sha512", "value": sha256 }),
+            // Missing value
+            serde_json::json!({ "algo": "sha256" }),
+        ];
+
+        for testcase in testcases {
+            expect_that!(
+                serde_json::from_value::<Checksum>(testcase),
+                err(anything())
+            );
+        }
+
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn serialize_checksum() -> googletest::Result<()> {
+        let sha256 = "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327";
+        let checksum =
+            serde_json::to_value(Checksum::Blake3(Blake3(sha256.to_string()))).or_fail()?;
+        expect_that!(
+            checksum,
+            eq(&serde_json::json!({ "algo": "blake3", "value": sha256 }))
+        );
+        Ok(())
+    }
+
     #[googletest::gtest]
     fn deserialize_video() -> googletest::Result<()> {
         let serialized = r#"{
@@ -381,10 +795,12 @@ pub mod test {
                 name: "Linear equations".to_string(),
                 id: uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799").or_fail()?,
                 uri: "s3://bucket/linear-equations.mp4".parse().or_fail()?,
-                sha256: Sha256(
+                checksum: Checksum::Sha256(Sha256(
                     "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327".to_string()
-                ),
+                )),
                 file_size: 123456,
+                duration_secs: None,
+                resolution: None,
             })
         );
         Ok(())
@@ -424,39 +840,46 @@ pub mod test {
             section,
             eq(&Section {
                 name: "Equations".to_string(),
+                description: None,
                 content: vec![
                     Video {
                         name: "Linear equations".to_string(),
                         id: uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799")
                             .or_fail()?,
                         uri: "s3://bucket/linear-equations.mp4".parse().or_fail()?,
-                        sha256: Sha256(
+                        checksum: Checksum::Sha256(Sha256(
                             "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
                                 .to_string()
-                        ),
+                        )),
                         file_size: 123456,
+                        duration_secs: None,
+                        resolution: None,
                     },
                     Video {
                         name: "Quadratic equations".to_string(),
                         id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a")
                             .or_fail()?,
                         uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
-                        sha256: Sha256(
+                        checksum: Checksum::Sha256(Sha256(
                             "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
                                 .to_string()
-                        ),
+                        )),
                         file_size: 123457,
+                        duration_secs: None,
+                        resolution: None,
                     },
                     Video {
                         name: "Cubic equations".to_string(),
                         id: uuid::Uuid::from_str("9e0f44b6-3dc6-4f56-8c9f-7e28feac1d03")
                             .or_fail()?,
                         uri: "s3://bucket/cubic-equations.mp4".parse().or_fail()?,
-                        sha256: Sha256(
+                        checksum: Checksum::Sha256(Sha256(
                             "8b9522ce42fb02dd100b575714d935a4502872afccee80f7a65d466389a5bef8"
                                 .to_string()
-                        ),
+                        )),
                         file_size: 123458,
+                        duration_secs: None,
+                        resolution: None,
                     },
                 ]
             })
@@ -464,6 +887,153 @@ pub mod test {
         Ok(())
     }
 
+    #[googletest::gtest]
+    fn section_description_roundtrip() -> googletest::Result<()> {
+        let section: Section = serde_json::from_str(
+            r#"{"name": "Equations", "description": "Solving for x", "content": []}"#,
+        )
+        .or_fail()?;
+        expect_that!(section.description, some(eq("Solving for x")));
+        let roundtrip: Section =
+            serde_json::from_str(&serde_json::to_string(&section).or_fail()?).or_fail()?;
+        expect_that!(roundtrip, eq(&section));
+
+        // Sections without a description are serialized without the key
+        let section = Section {
+            description: None,
+            ..section
+        };
+        let serialized = serde_json::to_string(&section).or_fail()?;
+        expect_that!(serialized, not(contains_substring("description")));
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn manifest_min_vds_version() -> googletest::Result<()> {
+        let manifest: ManifestFile = serde_json::from_str(
+            r#"{"name": "manifest", "date": "2025-10-10", "min_vds_version": "v1.2.0", "version": "v2.0.0", "sections": []}"#,
+        )
+        .or_fail()?;
+        let version = |major, minor, revision| Version {
+            major,
+            minor,
+            revision,
+        };
+        expect_that!(manifest.min_vds_version, some(eq(&version(1, 2, 0))));
+        expect_true!(manifest.is_servable_by(&version(1, 2, 0)));
+        expect_true!(manifest.is_servable_by(&version(2, 0, 0)));
+        expect_false!(manifest.is_servable_by(&version(1, 1, 9)));
+
+        let manifest = ManifestFile {
+            min_vds_version: None,
+            ..manifest
+        };
+        expect_true!(manifest.is_servable_by(&version(0, 0, 1)));
+        let serialized = serde_json::to_string(&manifest).or_fail()?;
+        expect_that!(serialized, not(contains_substring("min_vds_version")));
+        Ok(())
+    }
+
+    /// Manifest listing a video in two sections, to be linted
+    const LINTED_MANIFEST: &str = r#"{
+    "name": "Linted manifest",
+    "date": "2025-10-10",
+    "version": "v2.0.0",
+    "sections": [
+        {
+            "name": "Equations",
+            "content": [
+                {
+                    "name": "Linear equations",
+                    "id": "bf978778-1c5d-44b3-b2c1-1cc253563799",
+                    "uri": "s3://bucket/linear-equations.mp4",
+                    "sha256": "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327",
+                    "file_size": 1000
+                },
+                {
+                    "name": "Quadratic equations",
+                    "id": "5eb9e089-79cf-478d-9121-9ca3e7bb1d4a",
+                    "uri": "s3://bucket/quadratic-equations.mp4",
+                    "sha256": "8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f",
+                    "file_size": 2000
+                }
+            ]
+        },
+        {
+            "name": "Review",
+            "content": [
+                {
+                    "name": "Linear equations",
+                    "id": "bf978778-1c5d-44b3-b2c1-1cc253563799",
+                    "uri": "s3://bucket/linear-equations.mp4",
+                    "sha256": "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327",
+                    "file_size": 1000
+                }
+            ]
+        }
+    ]
+}"#;
+
+    #[googletest::gtest]
+    fn lint_valid_manifest() {
+        expect_that!(
+            lint_manifest(LINTED_MANIFEST.as_bytes(), true),
+            ok(eq("2 sections, 2 videos, 3000 bytes in total"))
+        );
+
+        let unsupported = LINTED_MANIFEST.replace("v2.0.0", "v9.0.0");
+        expect_that!(
+            lint_manifest(unsupported.as_bytes(), false),
+            ok(contains_substring("version v9.0.0 is not supported"))
+        );
+    }
+
+    #[googletest::gtest]
+    fn lint_invalid_manifests() {
+        // Syntax errors point to their line
+        let missing_comma =
+            LINTED_MANIFEST.replace(r#""file_size": 2000"#, r#""file_size": 2000 "x""#);
+        expect_that!(
+            lint_manifest(missing_comma.as_bytes(), false),
+            err(elements_are![all!(
+                contains_substring("line 21 column"),
+                contains_substring(r#"   21 |                     "file_size": 2000 "x""#),
+                ends_with("^"),
+            )])
+        );
+
+        let missing_field = LINTED_MANIFEST.replace(r#""name": "Review","#, "");
+        expect_that!(
+            lint_manifest(missing_field.as_bytes(), false),
+            err(elements_are![contains_substring("missing field `name`")])
+        );
+
+        let invalid_checksum = LINTED_MANIFEST.replace("0b88b2dec2be", "0b88");
+        expect_that!(
+            lint_manifest(invalid_checksum.as_bytes(), false),
+            err(elements_are![contains_substring("line 13 column")])
+        );
+
+        // Unknown keys are only rejected in strict mode, all of them at once
+        let unknown_keys = LINTED_MANIFEST
+            .replace(r#""file_size": 2000"#, r#""file_size": 2000, "sha26": "x""#)
+            .replace(
+                r#""name": "Review","#,
+                r#""name": "Review", "descripton": "x","#,
+            );
+        expect_that!(
+            lint_manifest(unknown_keys.as_bytes(), false),
+            ok(anything())
+        );
+        expect_that!(
+            lint_manifest(unknown_keys.as_bytes(), true),
+            err(elements_are![
+                eq("unknown manifest field `sections[0].content[1].sha26`"),
+                eq("unknown manifest field `sections[1].descripton`"),
+            ])
+        );
+    }
+
     #[googletest::gtest]
     fn deserialize_manifest() -> googletest::Result<()> {
         let serialized = r#"{
@@ -525,65 +1095,80 @@ pub mod test {
             eq(&ManifestFile {
                 name: "High school video distribution list".to_string(),
                 date: chrono::NaiveDate::from_str("2025-10-10").or_fail()?,
+                published_at: None,
+                adopt_after: None,
+                min_vds_version: None,
                 version: new_version(1, 0, 0),
                 sections: vec![
                     Section {
                         name: "Equations".to_string(),
+                        description: None,
                         content: vec![
                         Video {
                             name: "Linear equations".to_string(),
                             id: uuid::Uuid::from_str("bf978778-1c5d-44b3-b2c1-1cc253563799")
                                 .or_fail()?,
                             uri: "s3://bucket/linear-equations.mp4".parse().or_fail()?,
-                            sha256:
-                                Sha256("0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
-                                    .to_string()),
+                            checksum:
+                                Checksum::Sha256(Sha256("0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                                    .to_string())),
                             file_size: 123456,
+                            duration_secs: None,
+                            resolution: None,
                         },
                         Video {
                             name: "Quadratic equations".to_string(),
                             id: uuid::Uuid::from_str("5eb9e089-79cf-478d-9121-9ca3e7bb1d4a")
                                 .or_fail()?,
                             uri: "s3://bucket/quadratic-equations.mp4".parse().or_fail()?,
-                            sha256:
-                                Sha256("8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
-                                    .to_string()),
+                            checksum:
+                                Checksum::Sha256(Sha256("8f9e3a4ae7d86c4abdf731a947fc90b607b82a0362da0b312e3b644defedb81f"
+                                    .to_string())),
                             file_size: 123457,
+                            duration_secs: None,
+                            resolution: None,
                         },
                         Video {
                             name: "Cubic equations".to_string(),
                             id: uuid::Uuid::from_str("9e0f44b6-3dc6-4f56-8c9f-7e28feac1d03")
                                 .or_fail()?,
                             uri: "s3://bucket/cubic-equations.mp4".parse().or_fail()?,
-                            sha256:
-                                Sha256("8b9522ce42fb02dd100b575714d935a4502872afccee80f7a65d466389a5bef8"
-                                    .to_string()),
+                            checksum:
+                                Checksum::Sha256(Sha256("8b9522ce42fb02dd100b575714d935a4502872afccee80f7a65d466389a5bef8"
+                                    .to_string())),
                             file_size: 123458,
+                            duration_secs: None,
+                            resolution: None,
                         },
                     ]
                     },
                     Section {
                         name: "Integration".to_string(),
+                        description: None,
                         content: vec![
                         Video {
                             name: "Riemann sum".to_string(),
                             id: uuid::Uuid::from_str("eddb4450-a9ff-4a4b-ad81-2a8b78998405")
                                 .or_fail()?,
                             uri: "s3://bucket/riemann-sum.mp4".parse().or_fail()?,
-                            sha256:
-                                Sha256("a6d3b80cd14f78b21ffbf5995bbda38ad8834459557782d245ed720134d36fc4"
-                                    .to_string()),
+                            checksum:
+                                Checksum::Sha256(Sha256("a6d3b80cd14f78b21ffbf5995bbda38ad8834459557782d245ed720134d36fc4"
+                                    .to_string())),
                             file_size: 123459,
+                            duration_secs: None,
+                            resolution: None,
                         },
                         Video {
                             name: "List of integrals".to_string(),
                             id: uuid::Uuid::from_str("f47e6cdc-1bcf-439a-9ea4-038dc7153648")
                                 .or_fail()?,
                             uri: "s3://bucket/list-of-integrals.mp4".parse().or_fail()?,
-                            sha256:
-                                Sha256("98780990e94fb55d0b88ebcd78fe82f069eac547731a4b0822332d826c970aec"
-                                    .to_string()),
+                            checksum:
+                                Checksum::Sha256(Sha256("98780990e94fb55d0b88ebcd78fe82f069eac547731a4b0822332d826c970aec"
+                                    .to_string())),
                             file_size: 123460,
+                            duration_secs: None,
+                            resolution: None,
                         },
                     ]
                     }
@@ -592,4 +1177,226 @@ pub mod test {
         );
         Ok(())
     }
+
+    fn empty_manifest(
+        date: &str,
+        published_at: Option<&str>,
+        version: Version,
+    ) -> googletest::Result<ManifestFile> {
+        Ok(ManifestFile {
+            name: "manifest".to_string(),
+            date: chrono::NaiveDate::from_str(date).or_fail()?,
+            published_at: published_at
+                .map(chrono::DateTime::<chrono::Utc>::from_str)
+                .transpose()
+                .or_fail()?,
+            adopt_after: None,
+            min_vds_version: None,
+            version,
+            sections: vec![],
+        })
+    }
+
+    #[googletest::gtest]
+    fn deserialize_manifest_published_at() -> googletest::Result<()> {
+        let serialized = r#"{
+            "name": "manifest",
+            "date": "2025-10-10",
+            "published_at": "2025-10-10T14:30:00+02:00",
+            "version": "v1.0.0",
+            "sections": []
+        }"#;
+
+        let manifest: ManifestFile = serde_json::from_str(serialized).or_fail()?;
+        expect_that!(
+            manifest,
+            eq(&empty_manifest(
+                "2025-10-10",
+                Some("2025-10-10T12:30:00Z"),
+                new_version(1, 0, 0)
+            )?)
+        );
+
+        let serialized = serde_json::to_string(&manifest).or_fail()?;
+        let roundtrip: ManifestFile = serde_json::from_str(&serialized).or_fail()?;
+        expect_that!(roundtrip, eq(&manifest));
+
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn serialize_manifest_without_published_at() -> googletest::Result<()> {
+        let manifest = empty_manifest("2025-10-10", None, new_version(1, 0, 0))?;
+        let serialized = serde_json::to_string(&manifest).or_fail()?;
+        expect_that!(serialized, not(contains_substring("published_at")));
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn manifest_freshness_uses_published_at() -> googletest::Result<()> {
+        let morning = empty_manifest(
+            "2025-10-10",
+            Some("2025-10-10T08:00:00Z"),
+            new_version(1, 0, 0),
+        )?;
+        let evening = empty_manifest(
+            "2025-10-10",
+            Some("2025-10-10T20:00:00Z"),
+            new_version(1, 0, 0),
+        )?;
+
+        expect_true!(evening.is_newer_than(&morning));
+        expect_false!(morning.is_newer_than(&evening));
+        expect_false!(morning.is_newer_than(&morning));
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn manifest_freshness_falls_back_to_date_and_version() -> googletest::Result<()> {
+        let old = empty_manifest("2025-10-10", None, new_version(1, 0, 0))?;
+        let newer_date = empty_manifest("2025-10-11", None, new_version(1, 0, 0))?;
+        let newer_version = empty_manifest("2025-10-10", None, new_version(1, 0, 1))?;
+        let timestamped = empty_manifest(
+            "2025-10-09",
+            Some("2025-10-09T23:00:00Z"),
+            new_version(1, 0, 0),
+        )?;
+
+        expect_true!(newer_date.is_newer_than(&old));
+        expect_true!(newer_version.is_newer_than(&old));
+        expect_false!(old.is_newer_than(&newer_version));
+        expect_true!(newer_date.is_newer_than(&newer_version));
+        // Only one of the manifests has a timestamp, so the date decides.
+        expect_true!(old.is_newer_than(&timestamped));
+        expect_false!(timestamped.is_newer_than(&old));
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn manifest_freshness_uses_published_at_offsets() -> googletest::Result<()> {
+        // Released at 23:30 UTC, although it is already the next day in UTC+2
+        let ahead = empty_manifest(
+            "2025-10-11",
+            Some("2025-10-11T01:30:00+02:00"),
+            new_version(1, 0, 0),
+        )?;
+        // Released at 01:00 UTC of the next day, although it is still the same day in UTC-5
+        let behind = empty_manifest(
+            "2025-10-10",
+            Some("2025-10-10T20:00:00-05:00"),
+            new_version(1, 0, 0),
+        )?;
+
+        expect_that!(
+            ahead.release_date(),
+            eq(chrono::NaiveDate::from_str("2025-10-10").or_fail()?)
+        );
+        expect_that!(
+            behind.release_date(),
+            eq(chrono::NaiveDate::from_str("2025-10-11").or_fail()?)
+        );
+        expect_true!(behind.is_newer_than(&ahead));
+        expect_false!(ahead.is_newer_than(&behind));
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn manifest_freshness_compares_utc_dates() -> googletest::Result<()> {
+        // Same UTC date as the manifest without timestamp, even though its local date is later
+        let timestamped = empty_manifest(
+            "2025-10-11",
+            Some("2025-10-11T01:30:00+02:00"),
+            new_version(1, 0, 0),
+        )?;
+        let newer_version = empty_manifest("2025-10-10", None, new_version(1, 0, 1))?;
+        let next_day = empty_manifest("2025-10-11", None, new_version(1, 0, 0))?;
+
+        expect_true!(newer_version.is_newer_than(&timestamped));
+        expect_false!(timestamped.is_newer_than(&newer_version));
+        expect_true!(next_day.is_newer_than(&timestamped));
+        expect_false!(timestamped.is_newer_than(&next_day));
+
+        // The same timestamp in any offset leads to the same decisions
+        for offset_hours in [14, 2, 0, -5, -12] {
+            let offset = chrono::FixedOffset::east_opt(offset_hours * 3600).or_fail()?;
+            let instant = timestamped.published_at.or_fail()?.with_timezone(&offset);
+            let serialized = format!(
+                r#"{{"name": "manifest", "date": "{}", "published_at": "{}", "version": "v1.0.0", "sections": []}}"#,
+                instant.date_naive(),
+                instant.to_rfc3339(),
+            );
+            let manifest: ManifestFile = serde_json::from_str(&serialized).or_fail()?;
+            expect_that!(manifest.published_at, eq(timestamped.published_at));
+            expect_true!(newer_version.is_newer_than(&manifest));
+            expect_true!(next_day.is_newer_than(&manifest));
+        }
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn misspelled_manifest_keys_are_rejected_in_strict_mode() -> googletest::Result<()> {
+        let data = br#"{
+            "name": "manifest",
+            "date": "2025-10-10",
+            "version": "v1.0.0",
+            "sections": [{
+                "name": "section",
+                "content": [{
+                    "name": "video",
+                    "id": "bf978778-1c5d-44b3-b2c1-1cc253563799",
+                    "uri": "s3://bucket/video.mp4",
+                    "sha256": "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327",
+                    "file_size": 100,
+                    "duration_sec": 60
+                }]
+            }]
+        }"#;
+
+        // The misspelled key is ignored by default
+        let manifest = validate_manifest_bytes(data, false).or_fail()?;
+        expect_that!(manifest.sections[0].content[0].duration_secs, none());
+
+        expect_that!(
+            validate_manifest_bytes(data, true).map_err(|e| e.to_string()),
+            err(contains_substring(
+                "unknown manifest field `sections[0].content[0].duration_sec`"
+            ))
+        );
+        Ok(())
+    }
+
+    #[googletest::gtest]
+    fn manifest_keys_are_accepted_in_strict_mode() -> googletest::Result<()> {
+        let data = br#"{
+            "name": "manifest",
+            "date": "2025-10-10",
+            "published_at": "2025-10-10T10:00:00Z",
+            "version": "v1.0.0",
+            "sections": [{
+                "name": "section",
+                "description": "Lessons of the section",
+                "content": [{
+                    "name": "video",
+                    "id": "bf978778-1c5d-44b3-b2c1-1cc253563799",
+                    "uri": "s3://bucket/video.mp4",
+                    "checksum": {
+                        "algo": "sha256",
+                        "value": "0b88b2dec2be5e2ef74022ef6a8023232e28374d67e917b76f9bb607e691f327"
+                    },
+                    "file_size": 100,
+                    "duration_secs": 60,
+                    "resolution": "1920x1080"
+                }]
+            }]
+        }"#;
+
+        let manifest = validate_manifest_bytes(data, true).or_fail()?;
+        // Serializing the manifest must not produce keys that strict parsing rejects
+        let serialized = serde_json::to_vec(&manifest).or_fail()?;
+        expect_that!(
+            validate_manifest_bytes(&serialized, true),
+            ok(eq(&manifest))
+        );
+        Ok(())
+    }
 }
@@ -5,7 +5,13 @@ use std::time::Duration;
 
 use super::{CONTENT_PATH, MOUNT_PATH, RUNTIME_PATH};
 use crate::cfg::{
-    DEFAULT_CONFIG_PATH, DbConfig, DownloaderConfig, LeapConfig, RetryParams, S3Config,
+    DEFAULT_BACKOFF_JITTER_FRACTION, DEFAULT_CONFIG_PATH, DEFAULT_ERROR_LOG_INTERVAL,
+    DEFAULT_HASHING_THREADS, DEFAULT_IO_CHUNK_BYTES, DEFAULT_LOG_MAX_BYTES, DEFAULT_LOG_MAX_FILES,
+    DEFAULT_MANIFEST_RETRY_INTERVAL, DEFAULT_MANUAL_FETCH_COOLDOWN, DEFAULT_MAX_MANIFEST_BYTES,
+    DEFAULT_MAX_UPDATE_INTERVAL, DEFAULT_MIN_FREE_BYTES, DEFAULT_PROGRESS_EVENT_INTERVAL,
+    DEFAULT_RECONCILE_INTERVAL, DEFAULT_UNREACHABLE_AFTER_FAILURES, DEFAULT_YIELD_TO_STREAMS,
+    DbConfig, DownloaderConfig, ExistingFileCheck, HttpServerConfig, LeapConfig, RetryParams,
+    S3Config,
 };
 
 impl From<&leap_api::provision::config::post::LeapConfig> for LeapConfig {
@@ -13,6 +19,9 @@ impl From<&leap_api::provision::config::post::LeapConfig> for LeapConfig {
         let has_custom_endpoint = value.s3_config.endpoint_url.is_some();
         Self {
             debug: false,
+            log_max_bytes: DEFAULT_LOG_MAX_BYTES,
+            log_max_files: DEFAULT_LOG_MAX_FILES,
+            instance_name: None,
             db_config: DbConfig {
                 // These parameters are not considered to be user-configurable.
                 busy_timeout: Duration::from_secs(10),
@@ -36,15 +45,41 @@ impl From<&leap_api::provision::config::post::LeapConfig> for LeapConfig {
             },
             downloader_config: DownloaderConfig {
                 concurrent_downloads: value.downloader_config.concurrent_downloads,
+                solo_download_bytes: None,
+                streaming_concurrent_downloads: None,
+                yield_to_streams: DEFAULT_YIELD_TO_STREAMS,
                 remote_server: value.s3_config.bucket.clone(),
                 update_interval: value.downloader_config.update_interval,
                 content_path: CONTENT_PATH.into(),
+                temp_download_path: None,
                 retry_params: RetryParams {
                     initial_backoff: value.downloader_config.retry_params.initial_backoff,
                     backoff_factor: value.downloader_config.retry_params.backoff_factor,
                     max_backoff: value.downloader_config.retry_params.max_backoff,
+                    backoff_jitter_fraction: DEFAULT_BACKOFF_JITTER_FRACTION,
                 },
+                max_manifest_bytes: DEFAULT_MAX_MANIFEST_BYTES,
+                manual_fetch_cooldown: DEFAULT_MANUAL_FETCH_COOLDOWN,
+                manifest_retry_interval: DEFAULT_MANIFEST_RETRY_INTERVAL,
+                unreachable_after_failures: DEFAULT_UNREACHABLE_AFTER_FAILURES,
+                max_update_interval: DEFAULT_MAX_UPDATE_INTERVAL,
+                retain_removed_for: None,
+                completion_webhook_url: None,
+                existing_file_check: ExistingFileCheck::default(),
+                reconcile_interval: DEFAULT_RECONCILE_INTERVAL,
+                progress_event_interval: DEFAULT_PROGRESS_EVENT_INTERVAL,
+                error_log_interval: DEFAULT_ERROR_LOG_INTERVAL,
+                allowed_uri_schemes: None,
+                min_free_bytes: DEFAULT_MIN_FREE_BYTES,
+                strict_manifest: false,
+                verify_manifest_content_type: true,
+                resource_guard: Default::default(),
+                io_chunk_bytes: DEFAULT_IO_CHUNK_BYTES,
+                hashing_threads: DEFAULT_HASHING_THREADS,
+                request_headers: Default::default(),
+                channels: Default::default(),
             },
+            http_config: HttpServerConfig::default(),
         }
     }
 }
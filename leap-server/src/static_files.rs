@@ -1,4 +1,10 @@
-use actix_web::web;
+use std::{collections::HashMap, sync::Arc};
+
+use actix_web::{
+    HttpResponse, guard,
+    http::header::{self, HeaderMap},
+    web,
+};
 
 mod site_files {
     #![allow(
@@ -22,16 +28,242 @@ mod provisioning_files {
     include!(concat!(env!("OUT_DIR"), "/provisioning/generated.rs"));
 }
 
+/// Page served instead of a site which wasn't embedded at build time, so that users get an
+/// explanation rather than a 404.
+const MISSING_SITE_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head><meta charset="utf-8"><title>LEAP</title></head>
+  <body>
+    <h1>The frontend is not available</h1>
+    <p>This server was built without its frontend, so only the API is served. Build the site
+    before building the server to bundle it.</p>
+  </body>
+</html>
+"#;
+
 pub fn register_provisioning_files(app: &mut web::ServiceConfig) {
-    let generated = provisioning_files::generate();
-    app.service(
-        actix_web_static_files::ResourceFiles::new("/", generated).resolve_not_found_to_root(),
-    );
+    register_files(app, provisioning_files::generate(), "provisioning site");
+}
+
+/// Returns a function serving the site under `base_path`, the path prefix of a reverse proxy (e.g.:
+/// `/leap`). The site resolves its links and API requests against the `<base>` of its index page,
+/// so the prefix is added to it and to the root-absolute paths of the assets.
+pub fn register_site_files(base_path: &str) -> impl Fn(&mut web::ServiceConfig) + Clone + use<> {
+    // The rewritten page lives as long as the server, and it is only built once for all workers
+    let index_page: Option<&'static [u8]> = (!base_path.is_empty())
+        .then(|| {
+            site_files::generate()
+                .get("index.html")
+                .map(|index| index.data)
+        })
+        .flatten()
+        .map(|data| {
+            &*Box::leak(
+                with_base_path(data, base_path)
+                    .into_bytes()
+                    .into_boxed_slice(),
+            )
+        });
+
+    move |app| {
+        let mut files = site_files::generate();
+        if let (Some(page), Some(index)) = (index_page, files.get_mut("index.html")) {
+            index.data = page;
+        }
+        register_files(app, files, "site");
+    }
 }
 
-pub fn register_site_files(app: &mut web::ServiceConfig) {
-    let generated = site_files::generate();
-    app.service(
-        actix_web_static_files::ResourceFiles::new("/", generated).resolve_not_found_to_root(),
-    );
+/// Prefixes the root-absolute paths of an index page generated by Trunk with `base_path`, including
+/// its `<base>` and the paths of the scripts loading the site.
+fn with_base_path(page: &[u8], base_path: &str) -> String {
+    String::from_utf8_lossy(page)
+        .replace("=\"/", &format!("=\"{base_path}/"))
+        .replace("'/", &format!("'{base_path}/"))
+}
+
+/// Serves the embedded `files`, or [`MISSING_SITE_PAGE`] if none were embedded at build time.
+fn register_files(
+    app: &mut web::ServiceConfig,
+    files: HashMap<&'static str, static_files::Resource>,
+    name: &str,
+) {
+    if files.is_empty() {
+        tracing::warn!("The {name} was not bundled at build time, serving a fallback page instead");
+        app.service(web::resource("/{path:.*}").to(|| async {
+            HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .body(MISSING_SITE_PAGE)
+        }));
+        return;
+    }
+
+    // Precompressed variants of the assets (e.g.: `site.wasm.gz` for `site.wasm`) are served to the
+    // clients accepting them, which saves most of the transfer of the bundle on slow links
+    let gzipped: HashMap<String, (&'static [u8], &'static str)> = files
+        .iter()
+        .filter_map(|(name, resource)| {
+            let original = files.get(name.strip_suffix(".gz")?)?;
+            let path = format!("/{}", name.strip_suffix(".gz")?);
+            Some((path, (resource.data, original.mime_type)))
+        })
+        .collect();
+    if !gzipped.is_empty() {
+        let gzipped = Arc::new(gzipped);
+        let guarded = gzipped.clone();
+        app.service(
+            web::resource("/{path:.*}")
+                .guard(guard::fn_guard(move |ctx| {
+                    accepts_gzip(&ctx.head().headers) && guarded.contains_key(ctx.head().uri.path())
+                }))
+                .to(move |request: actix_web::HttpRequest| {
+                    let gzipped = gzipped.clone();
+                    async move {
+                        let Some((data, mime_type)) = gzipped.get(request.path()) else {
+                            return HttpResponse::NotFound().finish();
+                        };
+                        HttpResponse::Ok()
+                            .content_type(*mime_type)
+                            .insert_header((header::CONTENT_ENCODING, "gzip"))
+                            .insert_header((header::VARY, "Accept-Encoding"))
+                            .body(*data)
+                    }
+                }),
+        );
+    }
+    app.service(actix_web_static_files::ResourceFiles::new("/", files).resolve_not_found_to_root());
+}
+
+/// Returns whether the `Accept-Encoding` header of a request accepts gzip.
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|encoding| {
+            let mut params = encoding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            let rejected = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && !rejected
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{App, http::StatusCode, test};
+    use googletest::prelude::*;
+
+    use super::*;
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_missing_site_serves_fallback_page() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let app = test::init_service(
+            App::new()
+                .app_data(crate::api::test::create_api_data(tempdir.path()).await)
+                .configure(crate::api::register_handlers(&Default::default()))
+                .configure(|app| register_files(app, HashMap::new(), "site")),
+        )
+        .await;
+
+        for path in ["/", "/playlist/1"] {
+            let resp =
+                test::call_service(&app, test::TestRequest::get().uri(path).to_request()).await;
+            expect_that!(resp.status(), eq(StatusCode::OK));
+            let body = test::read_body(resp).await;
+            expect_that!(
+                std::str::from_utf8(&body).or_fail()?,
+                contains_substring("The frontend is not available")
+            );
+        }
+
+        // The API is still served
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/api/health").to_request(),
+        )
+        .await;
+        expect_that!(resp.status(), eq(StatusCode::OK));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn test_index_page_with_base_path() -> googletest::Result<()> {
+        let page = br#"<!DOCTYPE html><html><head>
+<meta charset="utf-8"/>
+<base href="/"/>
+<link rel="stylesheet" href="/index-1234.css"/>
+<script type="module">import init from '/leap-site-1234.js';init({ module_or_path: '/leap-site-1234_bg.wasm' });</script>
+</head><body></body></html>"#;
+
+        let page = with_base_path(page, "/leap");
+        expect_that!(page, contains_substring(r#"<base href="/leap/"/>"#));
+        expect_that!(page, contains_substring(r#"href="/leap/index-1234.css""#));
+        expect_that!(page, contains_substring("from '/leap/leap-site-1234.js'"));
+        expect_that!(
+            page,
+            contains_substring("module_or_path: '/leap/leap-site-1234_bg.wasm'")
+        );
+        expect_that!(page, contains_substring(r#"<meta charset="utf-8"/>"#));
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[googletest::test]
+    async fn test_precompressed_assets() -> googletest::Result<()> {
+        let resource = |data: &'static [u8], mime_type| static_files::Resource {
+            data,
+            modified: 0,
+            mime_type,
+        };
+        let files = || {
+            HashMap::from([
+                ("index.html", resource(b"<html></html>", "text/html")),
+                ("site.wasm", resource(b"wasm", "application/wasm")),
+                (
+                    "site.wasm.gz",
+                    resource(b"gzipped wasm", "application/gzip"),
+                ),
+            ])
+        };
+        let app = test::init_service(
+            App::new().configure(move |app| register_files(app, files(), "site")),
+        )
+        .await;
+        let get_wasm = |accept_encoding: Option<&str>| {
+            let mut req = test::TestRequest::get().uri("/site.wasm");
+            if let Some(accept_encoding) = accept_encoding {
+                req = req.insert_header((header::ACCEPT_ENCODING, accept_encoding));
+            }
+            req.to_request()
+        };
+
+        for accept_encoding in ["gzip", "br, gzip;q=0.8", "*"] {
+            let resp = test::call_service(&app, get_wasm(Some(accept_encoding))).await;
+            expect_that!(resp.status(), eq(StatusCode::OK));
+            expect_that!(
+                resp.headers().get(header::CONTENT_ENCODING),
+                some(eq(&header::HeaderValue::from_static("gzip")))
+            );
+            expect_that!(
+                resp.headers().get(header::CONTENT_TYPE),
+                some(eq(&header::HeaderValue::from_static("application/wasm")))
+            );
+            expect_that!(test::read_body(resp).await, eq(&b"gzipped wasm"[..]));
+        }
+
+        for accept_encoding in [None, Some("br"), Some("gzip;q=0")] {
+            let resp = test::call_service(&app, get_wasm(accept_encoding)).await;
+            expect_that!(resp.status(), eq(StatusCode::OK));
+            expect_that!(resp.headers().get(header::CONTENT_ENCODING), none());
+            expect_that!(test::read_body(resp).await, eq(&b"wasm"[..]));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,188 @@
+//! Persistence of the video content. The content is accessed through the [`ContentStore`] trait,
+//! so that downloading and serving it does not depend on where it is stored. [`LocalFsStore`],
+//! which stores the content in the local filesystem, is used by default.
+
+pub mod cache;
+
+use std::{path::Path, pin::Pin, sync::Arc};
+
+use tokio::io::{AsyncRead, AsyncSeekExt, AsyncWrite};
+
+use crate::cfg::HttpServerConfig;
+
+/// Reader of stored content
+pub type ContentReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Writer of stored content
+pub type ContentWriter = Pin<Box<dyn AsyncWrite + Send>>;
+
+/// Storage of the video content. Content is identified by its path, which stores may map to
+/// other kinds of keys.
+#[async_trait::async_trait]
+pub trait ContentStore: Send + Sync {
+    /// Returns a writer replacing the content at `path`. The writer must be shut down once all
+    /// the content is written. The content is only guaranteed to be durable once it is moved to
+    /// its final path with [`ContentStore::persist`].
+    async fn write_stream(&self, path: &Path) -> std::io::Result<ContentWriter>;
+
+    /// Durably moves the content written at `from` to `to`, replacing the content at `to`.
+    async fn persist(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+
+    /// Returns a reader of the content at `path`, starting at byte `offset`.
+    async fn open_range(&self, path: &Path, offset: u64) -> std::io::Result<ContentReader>;
+
+    /// Deletes the content at `path`.
+    async fn delete(&self, path: &Path) -> std::io::Result<()>;
+
+    /// Returns the size of the content at `path`, in bytes.
+    async fn size(&self, path: &Path) -> std::io::Result<u64>;
+}
+
+/// Stores the content as files in the local filesystem.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalFsStore;
+
+#[async_trait::async_trait]
+impl ContentStore for LocalFsStore {
+    async fn write_stream(&self, path: &Path) -> std::io::Result<ContentWriter> {
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        Ok(Box::pin(tokio::fs::File::create(path).await?))
+    }
+
+    /// The move is atomic as long as both paths are in the same filesystem. Otherwise, the file
+    /// is first copied next to its final path.
+    async fn persist(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        tokio::fs::File::open(from).await?.sync_all().await?;
+        if let Some(dir) = to.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        match tokio::fs::rename(from, to).await {
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                tracing::warn!(
+                    "Copying {from:?} to {to:?}, because they are in different filesystems"
+                );
+                let mut copy_path = to.as_os_str().to_owned();
+                copy_path.push(".part");
+                tokio::fs::copy(from, &copy_path).await?;
+                tokio::fs::rename(&copy_path, to).await?;
+                tokio::fs::remove_file(from).await
+            }
+            result => result,
+        }
+    }
+
+    async fn open_range(&self, path: &Path, offset: u64) -> std::io::Result<ContentReader> {
+        let mut file = tokio::fs::File::open(path).await?;
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        Ok(Box::pin(file))
+    }
+
+    async fn delete(&self, path: &Path) -> std::io::Result<()> {
+        tokio::fs::remove_file(path).await
+    }
+
+    async fn size(&self, path: &Path) -> std::io::Result<u64> {
+        Ok(tokio::fs::metadata(path).await?.len())
+    }
+}
+
+/// Returns the store of the content, which keeps small files in memory if configured.
+pub fn content_store(config: &HttpServerConfig) -> Arc<dyn ContentStore> {
+    match config.content_cache_bytes() {
+        0 => Arc::new(LocalFsStore),
+        capacity => Arc::new(cache::MemoryCachedStore::new(
+            LocalFsStore,
+            capacity,
+            config.content_cache_max_file_bytes(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn write(store: &LocalFsStore, path: &Path, content: &[u8]) -> std::io::Result<()> {
+        let partial_path = path.with_extension("part");
+        let mut writer = store.write_stream(&partial_path).await?;
+        writer.write_all(content).await?;
+        writer.shutdown().await?;
+        store.persist(&partial_path, path).await
+    }
+
+    async fn read(store: &LocalFsStore, path: &Path, offset: u64) -> std::io::Result<Vec<u8>> {
+        let mut content = vec![];
+        store
+            .open_range(path, offset)
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        Ok(content)
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_local_fs_store_write() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let store = LocalFsStore;
+        let path = tempdir.path().join("content").join("video.mp4");
+
+        write(&store, &path, b"video content").await.or_fail()?;
+        expect_that!(std::fs::read(&path), ok(eq(b"video content")));
+        expect_that!(store.size(&path).await, ok(eq(&13)));
+        expect_false!(path.with_extension("part").exists());
+
+        // Writing again replaces the content
+        write(&store, &path, b"new").await.or_fail()?;
+        expect_that!(read(&store, &path, 0).await, ok(eq(b"new")));
+        expect_that!(store.size(&path).await, ok(eq(&3)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_local_fs_store_open_range() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let store = LocalFsStore;
+        let path = tempdir.path().join("video.mp4");
+        write(&store, &path, b"video content").await.or_fail()?;
+
+        expect_that!(read(&store, &path, 0).await, ok(eq(b"video content")));
+        expect_that!(read(&store, &path, 6).await, ok(eq(b"content")));
+        expect_that!(read(&store, &path, 13).await, ok(is_empty()));
+
+        let missing = tempdir.path().join("missing.mp4");
+        expect_that!(
+            read(&store, &missing, 0).await.map_err(|e| e.kind()),
+            err(eq(&std::io::ErrorKind::NotFound))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_local_fs_store_delete() -> googletest::Result<()> {
+        let tempdir = tempfile::TempDir::new().or_fail()?;
+        let store = LocalFsStore;
+        let path = tempdir.path().join("video.mp4");
+        write(&store, &path, b"video content").await.or_fail()?;
+
+        store.delete(&path).await.or_fail()?;
+        expect_false!(path.exists());
+        expect_that!(
+            store.size(&path).await.map_err(|e| e.kind()),
+            err(eq(std::io::ErrorKind::NotFound))
+        );
+        expect_that!(
+            store.delete(&path).await.map_err(|e| e.kind()),
+            err(eq(std::io::ErrorKind::NotFound))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+//! In-memory cache of small content files, so that frequently played short videos are not read
+//! from the storage on every request.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tokio::io::AsyncReadExt;
+
+use super::{ContentReader, ContentStore, ContentWriter};
+
+/// Content cached in memory.
+struct Entry {
+    content: Arc<[u8]>,
+    /// Value of [`Entries::clock`] when the content was last used
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_path: HashMap<PathBuf, Entry>,
+    /// Total size of the cached content, in bytes
+    size: u64,
+    /// Incremented on each use of the cache, to find the least recently used content
+    clock: u64,
+    /// Incremented each time some content is invalidated, so that content read before it is not
+    /// cached after it
+    generation: u64,
+    /// Paths being written, e.g. by a download in progress. Their content is incomplete, so it is
+    /// not cached until it is persisted.
+    writing: HashSet<PathBuf>,
+}
+
+impl Entries {
+    fn get(&mut self, path: &Path) -> Option<Arc<[u8]>> {
+        self.clock += 1;
+        let entry = self.by_path.get_mut(path)?;
+        entry.last_used = self.clock;
+        Some(Arc::clone(&entry.content))
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(entry) = self.by_path.remove(path) {
+            self.size -= entry.content.len() as u64;
+        }
+    }
+
+    /// Caches the content, evicting the least recently used content until it fits in `capacity`.
+    /// The content must not be larger than `capacity`.
+    fn insert(&mut self, path: PathBuf, content: Arc<[u8]>, capacity: u64) {
+        self.remove(&path);
+        let size = content.len() as u64;
+        while self.size + size > capacity {
+            let Some(lru) = self
+                .by_path
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                return;
+            };
+            self.remove(&lru);
+        }
+        self.clock += 1;
+        self.size += size;
+        self.by_path.insert(
+            path,
+            Entry {
+                content,
+                last_used: self.clock,
+            },
+        );
+    }
+}
+
+/// Serves the content of another store, keeping the files up to a given size in memory. The
+/// cached content never exceeds the capacity of the cache, the least recently used files being
+/// evicted first. Larger files are always read from the other store.
+///
+/// The content is invalidated when it is written, moved or deleted through this store, so all the
+/// changes to the content must go through it.
+pub struct MemoryCachedStore<S> {
+    inner: S,
+    /// Maximum size of the cached content, in bytes
+    capacity: u64,
+    /// Maximum size of a cached file, in bytes
+    max_file_bytes: u64,
+    entries: Mutex<Entries>,
+}
+
+impl<S> MemoryCachedStore<S> {
+    pub fn new(inner: S, capacity: u64, max_file_bytes: u64) -> Self {
+        Self {
+            inner,
+            capacity,
+            max_file_bytes: max_file_bytes.min(capacity),
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Returns the total size of the cached content, in bytes.
+    pub fn cached_bytes(&self) -> u64 {
+        self.entries.lock().unwrap().size
+    }
+
+    /// Removes the content at `path` from the cache, also marking whether it is being written.
+    fn invalidate(&self, path: &Path, writing: bool) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        entries.remove(path);
+        if writing {
+            entries.writing.insert(path.to_path_buf());
+        } else {
+            entries.writing.remove(path);
+        }
+    }
+}
+
+impl<S: ContentStore> MemoryCachedStore<S> {
+    /// Reads the whole content at `path` into the cache.
+    async fn load(&self, path: &Path, size: u64) -> std::io::Result<Arc<[u8]>> {
+        let generation = self.entries.lock().unwrap().generation;
+        let mut content = Vec::with_capacity(size as usize);
+        self.inner
+            .open_range(path, 0)
+            .await?
+            .take(size)
+            .read_to_end(&mut content)
+            .await?;
+        let content: Arc<[u8]> = content.into();
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation == generation
+            && content.len() as u64 == size
+            && !entries.writing.contains(path)
+        {
+            entries.insert(path.to_path_buf(), Arc::clone(&content), self.capacity);
+        }
+        Ok(content)
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: ContentStore> ContentStore for MemoryCachedStore<S> {
+    async fn write_stream(&self, path: &Path) -> std::io::Result<ContentWriter> {
+        self.invalidate(path, true);
+        self.inner.write_stream(path).await
+    }
+
+    async fn persist(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        let result = self.inner.persist(from, to).await;
+        self.invalidate(from, false);
+        self.invalidate(to, false);
+        result
+    }
+
+    async fn open_range(&self, path: &Path, offset: u64) -> std::io::Result<ContentReader> {
+        let (cached, writing) = {
+            let mut entries = self.entries.lock().unwrap();
+            (entries.get(path), entries.writing.contains(path))
+        };
+        let content = match cached {
+            Some(content) => content,
+            None => {
+                let size = self.inner.size(path).await?;
+                if writing || size > self.max_file_bytes {
+                    return self.inner.open_range(path, offset).await;
+                }
+                self.load(path, size).await?
+            }
+        };
+        let mut reader = Cursor::new(content);
+        reader.set_position(offset);
+        Ok(Box::pin(reader))
+    }
+
+    async fn delete(&self, path: &Path) -> std::io::Result<()> {
+        self.invalidate(path, false);
+        self.inner.delete(path).await
+    }
+
+    async fn size(&self, path: &Path) -> std::io::Result<u64> {
+        let cached = self.entries.lock().unwrap().get(path);
+        match cached {
+            Some(content) => Ok(content.len() as u64),
+            None => self.inner.size(path).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use googletest::prelude::*;
+    use tokio::io::AsyncWriteExt;
+
+    use crate::store::LocalFsStore;
+
+    async fn read(store: &dyn ContentStore, path: &Path, offset: u64) -> std::io::Result<Vec<u8>> {
+        let mut content = vec![];
+        store
+            .open_range(path, offset)
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        Ok(content)
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_memory_cached_store_evicts_least_recently_used() -> googletest::Result<()> {
+        let dir = tempfile::TempDir::new().or_fail()?;
+        let store = MemoryCachedStore::new(LocalFsStore, 10, 6);
+        let [a, b, c, large] = ["a", "b", "c", "large"].map(|name| dir.path().join(name));
+        std::fs::write(&a, b"aaaa").or_fail()?;
+        std::fs::write(&b, b"bbbb").or_fail()?;
+        std::fs::write(&c, b"cccc").or_fail()?;
+        std::fs::write(&large, b"larger than 6").or_fail()?;
+
+        expect_that!(read(&store, &a, 0).await.or_fail()?, eq(b"aaaa"));
+        expect_that!(read(&store, &b, 2).await.or_fail()?, eq(b"bb"));
+        expect_that!(store.cached_bytes(), eq(8));
+        // Files above the size threshold are not cached
+        expect_that!(
+            read(&store, &large, 0).await.or_fail()?,
+            eq(b"larger than 6")
+        );
+        expect_that!(store.cached_bytes(), eq(8));
+
+        // `a` was used more recently than `b`, which is evicted to make room for `c`
+        expect_that!(store.size(&a).await.or_fail()?, eq(4));
+        expect_that!(read(&store, &c, 0).await.or_fail()?, eq(b"cccc"));
+        expect_that!(store.cached_bytes(), eq(8));
+        std::fs::remove_file(&a).or_fail()?;
+        std::fs::remove_file(&b).or_fail()?;
+        expect_that!(read(&store, &a, 0).await.or_fail()?, eq(b"aaaa"));
+        expect_that!(read(&store, &b, 0).await, err(anything()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[googletest::test]
+    async fn test_memory_cached_store_invalidates_replaced_content() -> googletest::Result<()> {
+        let dir = tempfile::TempDir::new().or_fail()?;
+        let store = MemoryCachedStore::new(LocalFsStore, 10, 10);
+        let path = dir.path().join("video.mp4");
+        let partial_path = dir.path().join("video.part");
+        std::fs::write(&path, b"old").or_fail()?;
+        expect_that!(read(&store, &path, 0).await.or_fail()?, eq(b"old"));
+
+        let mut writer = store.write_stream(&partial_path).await.or_fail()?;
+        writer.write_all(b"ne").await.or_fail()?;
+        writer.flush().await.or_fail()?;
+        // Content being written is not cached
+        expect_that!(read(&store, &partial_path, 0).await.or_fail()?, eq(b"ne"));
+        writer.write_all(b"w").await.or_fail()?;
+        writer.shutdown().await.or_fail()?;
+        expect_that!(read(&store, &partial_path, 0).await.or_fail()?, eq(b"new"));
+        store.persist(&partial_path, &path).await.or_fail()?;
+        expect_that!(read(&store, &path, 0).await.or_fail()?, eq(b"new"));
+
+        store.delete(&path).await.or_fail()?;
+        expect_that!(store.cached_bytes(), eq(0));
+        expect_that!(read(&store, &path, 0).await, err(anything()));
+        Ok(())
+    }
+}
@@ -28,6 +28,8 @@
               runHook preBuild
               pushd leap-site
               trunk build --release --offline
+              # The server serves these precompressed variants to the clients accepting gzip
+              gzip --best --keep dist/*.wasm dist/*.js dist/*.css
               popd
               runHook postBuild
             ";
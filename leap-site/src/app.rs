@@ -1,7 +1,7 @@
 use yew::prelude::*;
 use yew_router::prelude::*;
 
-use crate::context::ContentProvider;
+use crate::context::{ContentProvider, ErrorBanner};
 use crate::pages::dashboard::Dashboard;
 use crate::pages::player::VideoPlayer;
 use crate::pages::status::StatusDashboard;
@@ -60,6 +60,7 @@ fn switch(route: Route) -> Html {
 pub fn app() -> Html {
     html! {
         <ContentProvider>
+            <ErrorBanner />
             <BrowserRouter>
                 <Switch<Route> render={switch} />
             </BrowserRouter>
@@ -1,5 +1,6 @@
 use gloo_net::http::Request;
 use std::rc::Rc;
+use std::time::Duration;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
@@ -22,6 +23,59 @@ impl Reducible for ContentContext {
 
 pub type ContentContextHandle = UseReducerHandle<ContentContext>;
 
+/// Time between automatic retries while the server is unreachable.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Reachability of the server, shared by the components fetching their data from it. Components
+/// report the result of their fetches, and fetch again whenever `attempt` changes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerStatus {
+    /// Whether the last fetch failed
+    pub unreachable: bool,
+
+    /// Number of retries requested since the page was loaded
+    pub attempt: u32,
+}
+
+pub enum ServerStatusAction {
+    FetchSucceeded,
+    FetchFailed,
+    Retry,
+}
+
+impl Reducible for ServerStatus {
+    type Action = ServerStatusAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        match action {
+            ServerStatusAction::FetchSucceeded if !self.unreachable => self,
+            ServerStatusAction::FetchSucceeded => Rc::new(Self {
+                unreachable: false,
+                ..*self
+            }),
+            ServerStatusAction::FetchFailed => Rc::new(Self {
+                unreachable: true,
+                ..*self
+            }),
+            ServerStatusAction::Retry => Rc::new(Self {
+                attempt: self.attempt + 1,
+                ..*self
+            }),
+        }
+    }
+}
+
+pub type ServerStatusHandle = UseReducerHandle<ServerStatus>;
+
+/// Reports the result of a fetch to the shared server status.
+pub fn report_fetch<T>(server_status: &ServerStatusHandle, result: &Option<T>) {
+    server_status.dispatch(if result.is_some() {
+        ServerStatusAction::FetchSucceeded
+    } else {
+        ServerStatusAction::FetchFailed
+    });
+}
+
 #[derive(Properties, PartialEq)]
 pub struct ContentProviderProps {
     #[prop_or_default]
@@ -31,14 +85,18 @@ pub struct ContentProviderProps {
 #[function_component(ContentProvider)]
 pub fn content_provider(props: &ContentProviderProps) -> Html {
     let context = use_reducer(|| ContentContext { sections: None });
+    let server_status = use_reducer(ServerStatus::default);
 
     {
         let context = context.clone();
-        use_effect_with((), move |_| {
+        let server_status = server_status.clone();
+        use_effect_with(server_status.attempt, move |_| {
             if context.sections.is_none() {
                 let context = context.clone();
                 spawn_local(async move {
-                    if let Some(sections) = fetch_sections().await {
+                    let sections = fetch_sections().await;
+                    report_fetch(&server_status, &sections);
+                    if let Some(sections) = sections {
                         context.dispatch(sections);
                     }
                 });
@@ -47,29 +105,146 @@ pub fn content_provider(props: &ContentProviderProps) -> Html {
         });
     }
 
+    // Keep retrying while the server is unreachable
+    {
+        let server_status = server_status.clone();
+        use_effect_with(
+            (server_status.unreachable, server_status.attempt),
+            move |(unreachable, _)| {
+                if *unreachable {
+                    spawn_local(async move {
+                        yew::platform::time::sleep(RETRY_INTERVAL).await;
+                        server_status.dispatch(ServerStatusAction::Retry);
+                    });
+                }
+                || ()
+            },
+        );
+    }
+
     html! {
-        <ContextProvider<ContentContextHandle> context={context}>
-            { props.children.clone() }
-        </ContextProvider<ContentContextHandle>>
+        <ContextProvider<ServerStatusHandle> context={server_status}>
+            <ContextProvider<ContentContextHandle> context={context}>
+                { props.children.clone() }
+            </ContextProvider<ContentContextHandle>>
+        </ContextProvider<ServerStatusHandle>>
     }
 }
 
+/// Fetches the videos of all sections, following the pages of the server.
 async fn fetch_sections() -> Option<Vec<GroupedSection>> {
-    let response = match Request::get("/api/content/meta").send().await {
-        Ok(v) => v,
-        Err(e) => {
-            log::error!("Failed to fetch content meta. Error performing HTTP request: {e:?}");
-            return None;
+    let mut sections = Vec::new();
+    let mut offset = 0;
+    loop {
+        let url = format!("api/content/meta?offset={offset}");
+        let response = match Request::get(&url).send().await {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("Failed to fetch content meta. Error performing HTTP request: {e:?}");
+                return None;
+            }
+        };
+
+        let response = match response.json::<Response>().await {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("Failed to fetch videos. Error decoding json: {e:?}");
+                return None;
+            }
+        };
+
+        let page_len: usize = response.videos.iter().map(|s| s.content.len()).sum();
+        merge_page(&mut sections, response.videos);
+        offset += page_len;
+        // Stop on empty pages too, in case the catalog shrinks while paging
+        if page_len == 0 || offset >= response.total {
+            return Some(sections);
         }
-    };
+    }
+}
 
-    let response = match response.json::<Response>().await {
-        Ok(v) => v,
-        Err(e) => {
-            log::error!("Failed to fetch videos. Error decoding json: {e:?}");
-            return None;
+/// Appends a page of sections to the ones already fetched. A section split between pages is
+/// returned in both, and its videos are joined.
+fn merge_page(sections: &mut Vec<GroupedSection>, page: Vec<GroupedSection>) {
+    let mut page = page.into_iter();
+    if let Some(first) = page.next() {
+        match sections.last_mut() {
+            Some(last) if last.name == first.name => last.content.extend(first.content),
+            _ => sections.push(first),
         }
+    }
+    sections.extend(page);
+}
+
+/// Banner shown while the server is unreachable, allowing users to retry right away.
+#[function_component(ErrorBanner)]
+pub fn error_banner() -> Html {
+    let server_status = use_context::<ServerStatusHandle>().expect("ServerStatus not found");
+
+    if !server_status.unreachable {
+        return html! {};
+    }
+
+    let on_retry = {
+        let server_status = server_status.clone();
+        Callback::from(move |_| server_status.dispatch(ServerStatusAction::Retry))
     };
 
-    Some(response.videos)
+    html! {
+        <div class="error-banner" role="alert">
+            <span>{ "Couldn't reach the server \u{2014} retrying\u{2026}" }</span>
+            <button onclick={on_retry} class="btn btn-primary">{ "Retry now" }</button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use googletest::prelude::*;
+    use leap_api::types::{LocalVideoMeta, VideoStatus};
+
+    fn section(name: &str, ids: &[&str]) -> GroupedSection {
+        GroupedSection {
+            name: name.to_string(),
+            description: None,
+            content: ids
+                .iter()
+                .map(|id| LocalVideoMeta {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    declared_size: 100,
+                    actual_size: None,
+                    status: VideoStatus::Pending,
+                    view_count: 0,
+                    duration_secs: None,
+                    resolution: None,
+                    pinned: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[googletest::test]
+    fn test_merge_page() {
+        let mut sections = vec![];
+        merge_page(
+            &mut sections,
+            vec![section("a", &["1"]), section("b", &["2"])],
+        );
+        merge_page(
+            &mut sections,
+            vec![section("b", &["3"]), section("c", &["4"])],
+        );
+        merge_page(&mut sections, vec![section("d", &["5"])]);
+        expect_that!(
+            sections,
+            eq(&vec![
+                section("a", &["1"]),
+                section("b", &["2", "3"]),
+                section("c", &["4"]),
+                section("d", &["5"]),
+            ])
+        );
+    }
 }
@@ -1,14 +1,24 @@
+use gloo_net::http::Request;
 use std::hash::{DefaultHasher, Hasher};
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
-use crate::context::ContentContextHandle;
+use leap_api::api::content::meta::get::VideoStatus;
+use leap_api::api::content::summary::get::{ContentSummary, Response};
+use leap_api::api::version::get::BuildInfo;
+
+use crate::context::{ContentContextHandle, ServerStatusHandle, report_fetch};
 
 #[derive(yew::Properties, PartialEq)]
 pub struct PlaylistCardProps {
     pub playlist_id: usize,
     pub playlist_name: String,
+    /// Description of the playlist, shown under its name
+    pub playlist_description: Option<String>,
     pub num_videos: usize,
+    /// First downloaded video of the playlist, opened when clicking the card
+    pub first_playable_id: Option<String>,
 }
 
 #[function_component(PlaylistCard)]
@@ -16,16 +26,25 @@ pub fn playlist_card(
     PlaylistCardProps {
         playlist_id,
         playlist_name,
+        playlist_description,
         num_videos,
+        first_playable_id,
     }: &PlaylistCardProps,
 ) -> Html {
     let navigator = use_navigator();
 
     let onclick = if *num_videos > 0 {
         let playlist_id = *playlist_id;
+        let route = match first_playable_id {
+            Some(video_id) => crate::app::Route::Video {
+                playlist_id,
+                video_id: video_id.clone(),
+            },
+            None => crate::app::Route::Playlist { playlist_id },
+        };
         Callback::from(move |_| {
             if let Some(navigator) = &navigator {
-                navigator.push(&crate::app::Route::Playlist { playlist_id });
+                navigator.push(&route);
             }
         })
     } else {
@@ -42,7 +61,10 @@ pub fn playlist_card(
         <div {onclick} class={classes!("card", (*num_videos == 0).then_some("unavailable"))}>
             <div class="icon" style={icon_style}>{ first_letter }</div>
             <div class="details">
-                <h3>{ playlist_name }</h3>
+                <h3 title={playlist_name.clone()}>{ playlist_name }</h3>
+                if let Some(description) = playlist_description {
+                    <p class="description" title={description.clone()}>{ description }</p>
+                }
                 <span>{ format!("{} videos", num_videos) }</span>
             </div>
             <div class="arrow"> { "\u{203A}" }</div>
@@ -70,7 +92,12 @@ pub fn playlists_list() -> Html {
                 {
                     sections.iter().enumerate().map(|(index, section)| {
                         let num_videos = section.content.len();
-                        html! { <PlaylistCard playlist_id={index} playlist_name={section.name.clone()} num_videos={num_videos} /> }
+                        let first_playable_id = section
+                            .content
+                            .iter()
+                            .find(|v| v.status == VideoStatus::Downloaded)
+                            .map(|v| v.id.clone());
+                        html! { <PlaylistCard playlist_id={index} playlist_name={section.name.clone()} playlist_description={section.description.clone()} num_videos={num_videos} first_playable_id={first_playable_id} /> }
                     }).collect::<Html>()
                 }
                 </div>
@@ -78,12 +105,121 @@ pub fn playlists_list() -> Html {
     }
 }
 
+async fn fetch_summary() -> Option<ContentSummary> {
+    let response = match Request::get("api/content/summary").send().await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("Failed to fetch content summary. Error performing HTTP request: {e:?}");
+            return None;
+        }
+    };
+
+    match response.json::<Response>().await {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::error!("Failed to fetch content summary. Error decoding json: {e:?}");
+            None
+        }
+    }
+}
+
+async fn fetch_instance_name() -> Option<String> {
+    let response = match Request::get("api/version").send().await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("Failed to fetch version info. Error performing HTTP request: {e:?}");
+            return None;
+        }
+    };
+
+    match response.json::<BuildInfo>().await {
+        Ok(v) => v.instance_name,
+        Err(e) => {
+            log::error!("Failed to fetch version info. Error decoding json: {e:?}");
+            None
+        }
+    }
+}
+
+/// Name of the server, telling it apart from the others of a fleet.
+#[function_component(InstanceName)]
+pub fn instance_name() -> Html {
+    let instance_name = use_state(|| None);
+
+    {
+        let instance_name = instance_name.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                if let Some(v) = fetch_instance_name().await {
+                    instance_name.set(Some(v));
+                }
+            });
+            || ()
+        });
+    }
+
+    match &*instance_name {
+        Some(name) => html! { <span class="instance-name" title="Server name">{ name }</span> },
+        None => html! {},
+    }
+}
+
+#[function_component(CacheBadge)]
+pub fn cache_badge() -> Html {
+    let summary = use_state(|| None);
+    let server_status = use_context::<ServerStatusHandle>().expect("ServerStatus not found");
+
+    {
+        let summary = summary.clone();
+        let server_status = server_status.clone();
+        use_effect_with(server_status.attempt, move |_| {
+            if summary.is_none() {
+                spawn_local(async move {
+                    let v = fetch_summary().await;
+                    report_fetch(&server_status, &v);
+                    if let Some(v) = v {
+                        summary.set(Some(v));
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    let Some(summary) = *summary else {
+        return html! {};
+    };
+
+    let (class, text) = if summary.fully_cached {
+        ("badge-ok", "All content available".to_string())
+    } else if summary.failed > 0 {
+        (
+            "badge-error",
+            format!("{} of {} videos failed", summary.failed, summary.total),
+        )
+    } else {
+        (
+            "badge-warning",
+            format!(
+                "{} of {} videos available",
+                summary.downloaded, summary.total
+            ),
+        )
+    };
+
+    html! {
+        <span class={classes!("badge", class)}>{ text }</span>
+    }
+}
+
 #[function_component(Dashboard)]
 pub fn dashboard() -> Html {
     html! {
         <div class="page dashboard-page">
             <header class="header">
                 <h1>{ "Playlists" }</h1>
+                <InstanceName/>
+                <CacheBadge/>
             </header>
             <PlaylistsList/>
         </div>
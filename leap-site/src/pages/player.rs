@@ -20,6 +20,31 @@ pub fn video_player(
 ) -> Html {
     let context = use_context::<ContentContextHandle>().expect("ContentContext not found");
     let navigator = use_navigator().expect("Navigator not found");
+    let availability = use_state(|| None::<leap_api::api::content::id::available::get::Response>);
+
+    {
+        let availability = availability.clone();
+        use_effect_with(video_id.clone(), move |video_id| {
+            availability.set(None);
+            if let Some(video_id) = video_id.clone() {
+                spawn_local(async move {
+                    let Ok(resp) = Request::get(&format!("api/content/{video_id}/available"))
+                        .send()
+                        .await
+                    else {
+                        return;
+                    };
+                    if !resp.ok() {
+                        return;
+                    }
+                    if let Ok(response) = resp.json().await {
+                        availability.set(Some(response));
+                    }
+                });
+            }
+            || ()
+        });
+    }
 
     {
         let context = context.clone();
@@ -40,7 +65,7 @@ pub fn video_player(
                     let context = context.clone();
 
                     spawn_local(async move {
-                        let Ok(resp) = Request::post(&format!("/api/content/{video_id}/view"))
+                        let Ok(resp) = Request::post(&format!("api/content/{video_id}/view"))
                             .send()
                             .await
                         else {
@@ -50,6 +75,11 @@ pub fn video_player(
                         if !resp.ok() {
                             return;
                         }
+                        let Ok(leap_api::api::content::id::view::post::Response { view_count }) =
+                            resp.json().await
+                        else {
+                            return;
+                        };
 
                         let Some(sections) = context.sections.as_ref() else {
                             return;
@@ -60,7 +90,7 @@ pub fn video_player(
                             .get_mut(playlist_id)
                             .and_then(|s| s.content.iter_mut().find(|v| v.id == video_id))
                         {
-                            video.view_count += 1;
+                            video.view_count = view_count;
                             context.dispatch(new_sections);
                         }
                     });
@@ -134,10 +164,35 @@ pub fn video_player(
                     </button>
                     <h1>{ &section.name }</h1>
                 </header>
+                if let Some(description) = &section.description {
+                    <p class="playlist-description">{ description }</p>
+                }
 
                 {
-                    if let Some(active_video) = active_video && active_video.status == Downloaded {
-                        let video_path = format!("/api/content/{}", active_video.id);
+                    // The file of the video is checked before rendering the player, as the playlist
+                    // can be outdated
+                    if let Some(active_video) = active_video
+                        && let Some(availability) = availability.as_ref()
+                        && !availability.available
+                    {
+                        let status_text = match &availability.status {
+                            Downloaded => "The video file is not available".to_string(),
+                            Downloading(progress) => format!("Downloading ({:.0}%)", progress.0 * 100.0),
+                            Pending => "Waiting for the download to start".to_string(),
+                            Failed { message, .. } => format!("Download failed: {message}"),
+                        };
+                        html!{
+                            <div>
+                                <div class="video-player unavailable">
+                                    <p>{ status_text }</p>
+                                </div>
+                                <h2>{ &active_video.name }</h2>
+                            </div>
+                        }
+                    } else if let Some(active_video) = active_video
+                        && availability.as_ref().is_some_and(|a| a.available)
+                    {
+                        let video_path = format!("api/content/{}", active_video.id);
                         html!{
                             <div>
                                 <video key={active_video.id.clone()} controls=true autoplay=true class="video-player">
@@ -148,6 +203,15 @@ pub fn video_player(
 
                                 <div class={"details"}>
                                     <span>{ format!("{} views", active_video.view_count) }</span>
+                                    if let Some(duration_secs) = active_video.duration_secs {
+                                        <span>{ format!("{}:{:02}", duration_secs / 60, duration_secs % 60) }</span>
+                                    }
+                                    if let Some(resolution) = &active_video.resolution {
+                                        <span>{ resolution }</span>
+                                    }
+                                    if active_video.actual_size.is_some_and(|size| size != active_video.declared_size) {
+                                        <span class={"size-mismatch"}>{ "The downloaded file size doesn't match the manifest" }</span>
+                                    }
                                 </div>
                             </div>
                         }
@@ -169,7 +233,12 @@ pub fn video_player(
                             Downloaded => (true, format!("{} views", video.view_count)),
                             Downloading(progress) => (false, format!("Downloading ({:.0}%)", progress.0 * 100.0)),
                             Pending => (false, "Pending".to_string()),
-                            Failed(_) => (false, "Download failed".to_string()),
+                            Failed { .. } => (false, "Download failed".to_string()),
+                        };
+                        // The reason of a failure is shown when hovering the card
+                        let failure_reason = match &video.status {
+                            Failed { message, .. } => Some(message.clone()),
+                            _ => None,
                         };
 
                         let is_active = active_video.is_some_and(|active| active.id == video.id) && is_downloaded;
@@ -192,10 +261,10 @@ pub fn video_player(
                         };
 
                         html! {
-                            <div {onclick} class={classes!("card", is_active.then_some("active"), (!is_downloaded).then_some("unavailable"))}>
+                            <div {onclick} title={failure_reason} class={classes!("card", is_active.then_some("active"), (!is_downloaded).then_some("unavailable"))}>
                                 <div class="icon">{ icon }</div>
                                 <div class="details">
-                                    <h3>{ &video.name }</h3>
+                                    <h3 title={video.name.clone()}>{ &video.name }</h3>
                                     <span>{ status_text }</span>
                                 </div>
                             </div>
@@ -1,13 +1,13 @@
-use crate::context::ContentContextHandle;
+use crate::context::{ContentContextHandle, ServerStatusAction, ServerStatusHandle};
 
 use gloo_net::http::Request;
-use leap_api::api::content::meta::get::VideoStatus;
+use leap_api::api::content::meta::get::{FailureKind, VideoStatus};
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
 use leap_api::api::version::get::BuildInfo;
 
-#[derive(PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct DownloadItem {
     pub id: String,
     pub name: String,
@@ -148,13 +148,45 @@ pub fn manifest_status(ManifestStatusProps { manifest, on_fetch }: &ManifestStat
                 </div>
                 <div class="actions">
                     <button onclick={on_fetch.clone()} class="btn btn-primary">{ "Check manifest updates" }</button>
-                    <a href="/api/manifest/latest" download="manifest.json" class={ classes!("btn", "btn-primary", "no-underline", (!has_manifest).then_some("disabled"))}>{ "Download manifest" }</a>
+                    <a href="api/manifest/latest" download="manifest.json" class={ classes!("btn", "btn-primary", "no-underline", (!has_manifest).then_some("disabled"))}>{ "Download manifest" }</a>
                 </div>
             </div>
         </div>
     }
 }
 
+/// Describes a failed download. Older servers don't report the cause nor the number of attempts.
+fn failure_text(message: &str, kind: FailureKind, attempts: u32) -> String {
+    match (kind, attempts) {
+        (FailureKind::Unknown, 0) => format!("Failed: {message}"),
+        (kind, 0 | 1) => format!("Failed ({kind}): {message}"),
+        (kind, attempts) => format!("Failed ({kind}, {attempts} attempts): {message}"),
+    }
+}
+
+/// Orders the downloads so that the list doesn't jump around between refreshes: the ongoing
+/// downloads first, the most advanced ones first, followed by the pending and the failed ones. The
+/// downloads are otherwise ordered by name.
+fn sort_downloads(downloads: &mut [DownloadItem]) {
+    let rank = |item: &DownloadItem| match item.status {
+        VideoStatus::Downloading(_) => 0,
+        VideoStatus::Pending => 1,
+        VideoStatus::Failed { .. } => 2,
+        VideoStatus::Downloaded => 3,
+    };
+    let progress = |item: &DownloadItem| match &item.status {
+        VideoStatus::Downloading(progress) => progress.0,
+        _ => 0.0,
+    };
+    downloads.sort_by(|a, b| {
+        rank(a)
+            .cmp(&rank(b))
+            .then(progress(b).total_cmp(&progress(a)))
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
 #[derive(Properties, PartialEq)]
 pub struct DownloadsListProps {
     pub downloads: Vec<DownloadItem>,
@@ -173,17 +205,17 @@ pub fn downloads_list(DownloadsListProps { downloads }: &DownloadsListProps) ->
                     for downloads.iter().map(|item| html! {
                         <div class="card download-card">
                              <div class="details">
-                                <h3>{ &item.name }</h3>
+                                <h3 title={item.name.clone()}>{ &item.name }</h3>
                                 <span class={match item.status {
                                     VideoStatus::Pending => "status-pending",
                                     VideoStatus::Downloading(_) => "status-downloading",
-                                    VideoStatus::Failed(_) => "status-failed",
+                                    VideoStatus::Failed { .. } => "status-failed",
                                     VideoStatus::Downloaded => "status-downloaded",
                                 }}>
                                     { match &item.status {
                                         VideoStatus::Pending => "Pending".to_string(),
                                         VideoStatus::Downloading(p) => format!("Downloading ({:.0}%)", p.0 * 100.0),
-                                        VideoStatus::Failed(msg) => format!("Failed: {msg}"),
+                                        VideoStatus::Failed { message, kind, attempts } => failure_text(message, *kind, *attempts),
                                     VideoStatus::Downloaded => "Downloaded".to_string(),
                                     }}
                                 </span>
@@ -235,7 +267,7 @@ pub fn log_viewer(LogViewerProps { logs }: &LogViewerProps) -> Html {
             </div>
             <div class="card details-card">
                 <div class="actions">
-                    <a href="/api/logfile" download="leap_logs.json" class="btn-primary no-underline">{ "Download logfile" }</a>
+                    <a href="api/logfile" download="leap_logs.json" class="btn-primary no-underline">{ "Download logfile" }</a>
                 </div>
             </div>
         </div>
@@ -243,7 +275,7 @@ pub fn log_viewer(LogViewerProps { logs }: &LogViewerProps) -> Html {
 }
 
 async fn fetch_version_info() -> anyhow::Result<BuildInfo> {
-    let resp = Request::get("/api/version").send().await?;
+    let resp = Request::get("api/version").send().await?;
 
     if !resp.ok() {
         anyhow::bail!("Response is not successful: {}", resp.status());
@@ -254,30 +286,29 @@ async fn fetch_version_info() -> anyhow::Result<BuildInfo> {
 }
 
 async fn fetch_logs() -> anyhow::Result<Vec<LogEntry>> {
-    let mut new_logs = vec![];
-    let resp = Request::get("/api/logfile").send().await?;
+    // The file might be very large, so the server only returns the last X logs.
+    // More would not be that useful either, so we just provide a way to
+    // download them if needed
+    const MAX_LOGS: usize = 200;
+    let resp = Request::get(&format!("api/logfile?tail={MAX_LOGS}"))
+        .send()
+        .await?;
 
     if !resp.ok() {
         anyhow::bail!("Response is not successful: {}", resp.status());
     }
 
     let text = resp.text().await?;
-
-    // The file might be very large, so take only the last X logs.
-    // More would not be that useful either, so we just provide a way to
-    // download them if needed
-    const MAX_LOGS: usize = 200;
-    for log in text.lines().rev().take(MAX_LOGS) {
-        let log = serde_json::from_str(log)?;
-        let log: LogEntry = log;
+    let mut new_logs = vec![];
+    for log in text.lines() {
+        let log: LogEntry = serde_json::from_str(log)?;
         new_logs.push(log);
     }
-    new_logs.reverse();
     Ok(new_logs)
 }
 
 async fn fetch_manifest_info() -> anyhow::Result<Option<(String, ManifestInfo)>> {
-    let resp = Request::get("/api/manifest/latest").send().await?;
+    let resp = Request::get("api/manifest/latest").send().await?;
 
     if !resp.ok() {
         anyhow::bail!("Response is not successful: {}", resp.status());
@@ -292,7 +323,11 @@ async fn fetch_manifest_info() -> anyhow::Result<Option<(String, ManifestInfo)>>
 }
 
 async fn trigger_manifest_update_check() -> anyhow::Result<()> {
-    let resp = Request::post("/api/manifest/fetch").send().await?;
+    let resp = Request::post("api/manifest/fetch").send().await?;
+    if resp.status() == 429 {
+        let retry_after = resp.headers().get("Retry-After").unwrap_or_default();
+        anyhow::bail!("Updates were checked recently. Please try again in {retry_after} seconds");
+    }
     if !resp.ok() {
         anyhow::bail!("Response is not successful: {}", resp.status());
     }
@@ -315,6 +350,12 @@ pub fn version_info(VersionInfoProps { version }: &VersionInfoProps) -> Html {
                         <span class="label">{ "Name: " }</span>
                         <span class="value">{ &version.name }</span>
                     </div>
+                    if let Some(instance_name) = &version.instance_name {
+                        <div class="row">
+                            <span class="label">{ "Instance: " }</span>
+                            <span class="value">{ instance_name }</span>
+                        </div>
+                    }
                     <div class="row">
                         <span class="label">{ "Version: " }</span>
                         <span class="value">{ &version.version }</span>
@@ -353,6 +394,12 @@ pub fn version_info(VersionInfoProps { version }: &VersionInfoProps) -> Html {
                         <span class="label">{ "Features: " }</span>
                         <span class="value">{ &version.features }</span>
                     </div>
+                    if let Some(range) = &version.supported_manifest_versions {
+                        <div class="row">
+                            <span class="label">{ "Manifest versions: " }</span>
+                            <span class="value">{ format!("v{} to v{}", range.min_major, range.max_major) }</span>
+                        </div>
+                    }
                 </div>
             </div>
         </div>
@@ -365,8 +412,9 @@ pub fn status_dashboard() -> Html {
 
     let context = use_context::<ContentContextHandle>().expect("ContentContext not found");
     let sections_loaded = context.sections.is_some();
+    let server_status = use_context::<ServerStatusHandle>().expect("ServerStatus not found");
 
-    use_effect_with(sections_loaded, {
+    use_effect_with((sections_loaded, server_status.attempt), {
         let context = context.clone();
         let state_data = state_data.clone();
         move |_| {
@@ -380,6 +428,7 @@ pub fn status_dashboard() -> Html {
                             web_sys::console::log_1(
                                 &format!("Error while fetching LEAP version: {e}").into(),
                             );
+                            server_status.dispatch(ServerStatusAction::FetchFailed);
                             return;
                         }
                     };
@@ -390,6 +439,7 @@ pub fn status_dashboard() -> Html {
                             web_sys::console::log_1(
                                 &format!("Error while fetching LEAP logs: {e}").into(),
                             );
+                            server_status.dispatch(ServerStatusAction::FetchFailed);
                             return;
                         }
                     };
@@ -400,11 +450,12 @@ pub fn status_dashboard() -> Html {
                             web_sys::console::log_1(
                                 &format!("Error while fetching manifest information: {e}").into(),
                             );
+                            server_status.dispatch(ServerStatusAction::FetchFailed);
                             return;
                         }
                     };
 
-                    let pending_downloads = sections
+                    let mut pending_downloads: Vec<_> = sections
                         .iter()
                         .flat_map(|s| &s.content)
                         .filter(|v| v.status != VideoStatus::Downloaded)
@@ -414,7 +465,9 @@ pub fn status_dashboard() -> Html {
                             status: v.status.clone(),
                         })
                         .collect();
+                    sort_downloads(&mut pending_downloads);
 
+                    server_status.dispatch(ServerStatusAction::FetchSucceeded);
                     state_data.set(Some(Status {
                         version,
                         logs,
@@ -462,3 +515,52 @@ pub fn status_dashboard() -> Html {
         </div>
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use googletest::prelude::*;
+    use leap_api::types::Progress;
+
+    fn item(name: &str, status: VideoStatus) -> DownloadItem {
+        DownloadItem {
+            id: format!("{name}-id"),
+            name: name.to_string(),
+            status,
+        }
+    }
+
+    #[googletest::test]
+    fn test_sort_downloads() {
+        let failed = || VideoStatus::Failed {
+            message: "error".to_string(),
+            kind: FailureKind::Network,
+            attempts: 1,
+        };
+        let mut downloads = vec![
+            item("b-failed", failed()),
+            item("b-pending", VideoStatus::Pending),
+            item("slow", VideoStatus::Downloading(Progress(0.1))),
+            item("a-failed", failed()),
+            item("b-fast", VideoStatus::Downloading(Progress(0.9))),
+            item("a-pending", VideoStatus::Pending),
+            item("a-fast", VideoStatus::Downloading(Progress(0.9))),
+        ];
+
+        sort_downloads(&mut downloads);
+
+        let names: Vec<_> = downloads.iter().map(|d| d.name.as_str()).collect();
+        expect_that!(
+            names,
+            elements_are![
+                eq(&"a-fast"),
+                eq(&"b-fast"),
+                eq(&"slow"),
+                eq(&"a-pending"),
+                eq(&"b-pending"),
+                eq(&"a-failed"),
+                eq(&"b-failed"),
+            ]
+        );
+    }
+}